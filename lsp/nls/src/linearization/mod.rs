@@ -249,6 +249,27 @@ impl<'a> Linearizer for AnalysisHost<'a> {
                     });
                 }
             }
+            Term::LetBlock(bindings, ..) => {
+                for (ident, _) in bindings.iter() {
+                    let id = ItemId {
+                        file_id: self.file,
+                        index: id_gen.get_and_advance(),
+                    };
+                    self.env.insert(ident.to_owned(), id);
+                    lin.push(LinearizationItem {
+                        env: self.env.clone(),
+                        id,
+                        ty: UnifType::Concrete(TypeF::Dyn),
+                        pos: ident.pos,
+                        kind: TermKind::Declaration(
+                            ident.to_owned(),
+                            Vec::new(),
+                            ValueState::Unknown,
+                        ),
+                        meta: None,
+                    });
+                }
+            }
             Term::Let(ident, ..) | Term::Fun(ident, ..) => {
                 let value_ptr = match term {
                     Term::Let(..) => {
@@ -1,13 +1,17 @@
 //! Entry point of the program.
+use nickel_lang::debugger::{Breakpoint, Debugger};
 use nickel_lang::error::{Error, IOError};
 use nickel_lang::eval::cache::CBNCache;
+use nickel_lang::profiler::{Profile, Profiler};
 use nickel_lang::program::{ColorOpt, Program};
 use nickel_lang::repl::query_print;
 #[cfg(feature = "repl")]
 use nickel_lang::repl::rustyline_frontend;
 use nickel_lang::term::{RichTerm, Term};
 use nickel_lang::{serialize, serialize::ExportFormat};
+use std::cell::RefCell;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::{
     fs::{self, File},
     process,
@@ -35,6 +39,10 @@ struct Opt {
     #[structopt(long, global = true, case_insensitive = true, default_value = "auto")]
     color: ColorOpt,
 
+    /// Prints the time spent in each phase and other statistics on the standard error
+    #[structopt(long, global = true)]
+    stats: bool,
+
     #[structopt(subcommand)]
     command: Option<Command>,
 }
@@ -58,6 +66,14 @@ enum Command {
         #[structopt(short = "o", long)]
         #[structopt(parse(from_os_str))]
         output: Option<PathBuf>,
+        /// Profiles the evaluation, and prints the cost of each top-level field and of each
+        /// contract on the standard error
+        #[structopt(long)]
+        profile: bool,
+        /// Writes the profile as folded stacks to the given file, to be rendered as a flamegraph.
+        /// Implies `--profile`
+        #[structopt(long, parse(from_os_str))]
+        profile_folded: Option<PathBuf>,
     },
     /// Prints the metadata attached to an attribute, given as a path
     Query {
@@ -75,6 +91,13 @@ enum Command {
     },
     /// Typechecks the program but do not run it
     Typecheck,
+    /// Evaluates the program step by step in an interactive debugger
+    Debug {
+        /// Breakpoints, either as `<file>:<line>` or as a field path `a.b.c`. If no breakpoint
+        /// is given, the debugger stops before the first reduction step
+        #[structopt(short = "b", long = "break")]
+        breakpoints: Vec<Breakpoint>,
+    },
     /// Starts an REPL session
     Repl {
         #[structopt(long)]
@@ -133,7 +156,12 @@ fn main() {
                 &mut std::io::BufWriter::new(Box::new(std::io::stdout())),
                 transform,
             ),
-            Some(Command::Export { format, output }) => export(&mut program, format, output),
+            Some(Command::Export {
+                format,
+                output,
+                profile,
+                profile_folded,
+            }) => export(&mut program, format, output, profile, profile_folded),
             Some(Command::Query {
                 path,
                 doc,
@@ -160,6 +188,24 @@ fn main() {
                 })
             }
             Some(Command::Typecheck) => program.typecheck(),
+            Some(Command::Debug { breakpoints }) => {
+                // The debugger reads its commands from the standard input, which thus can't be
+                // used to provide the program.
+                if opts.file.is_none() {
+                    eprintln!("error: the debugger requires an input file (`--file`)");
+                    process::exit(1);
+                }
+
+                let debugger = Debugger::new(
+                    Box::new(std::io::BufReader::new(std::io::stdin())),
+                    Box::new(std::io::stdout()),
+                    breakpoints,
+                );
+                program.set_eval_hooks(Some(Box::new(debugger)));
+                program
+                    .eval_full()
+                    .map(|t| println!("{}", Term::from(t).deep_repr()))
+            }
             Some(Command::Repl { .. }) => unreachable!(),
             #[cfg(feature = "doc")]
             Some(Command::Doc { ref output }) => output
@@ -212,6 +258,10 @@ fn main() {
                 .map(|t| println!("{}", Term::from(t).deep_repr())),
         };
 
+        if opts.stats {
+            eprint!("{}", program.stats());
+        }
+
         if let Err(err) = result {
             program.report(err);
             process::exit(1)
@@ -223,8 +273,33 @@ fn export(
     program: &mut Program<EC>,
     format: Option<ExportFormat>,
     output: Option<PathBuf>,
+    profile: bool,
+    profile_folded: Option<PathBuf>,
 ) -> Result<(), Error> {
-    let rt = program.eval_full().map(RichTerm::from)?;
+    let profile = if profile || profile_folded.is_some() {
+        let profile = Rc::new(RefCell::new(Profile::default()));
+        program.set_eval_hooks(Some(Box::new(Profiler::new(profile.clone()))));
+        Some(profile)
+    } else {
+        None
+    };
+
+    let rt = program.eval_full().map(RichTerm::from);
+
+    if let Some(profile) = profile {
+        program.set_eval_hooks(None);
+        let profile = profile.borrow();
+        eprint!("{}", profile);
+
+        if let Some(file) = profile_folded {
+            let file = fs::File::create(file).map_err(IOError::from)?;
+            profile
+                .write_folded(std::io::BufWriter::new(file))
+                .map_err(IOError::from)?;
+        }
+    }
+
+    let rt = rt?;
     let format = format.unwrap_or_default();
 
     serialize::validate(format, &rt)?;
@@ -3,7 +3,9 @@
 use crate::error::{Error, ImportError, ParseError, ParseErrors, TypecheckError};
 use crate::eval::cache::Cache as EvalCache;
 use crate::parser::lexer::Lexer;
-use crate::position::TermPos;
+use crate::position::{RawSpan, TermPos};
+use crate::serialize;
+use crate::stats::Stats;
 use crate::stdlib::{self as nickel_stdlib, StdlibModule};
 use crate::term::record::RecordData;
 use crate::term::{RichTerm, SharedTerm, Term};
@@ -21,7 +23,7 @@ use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::result::Result;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 use void::Void;
 
 /// Supported input formats.
@@ -74,6 +76,10 @@ pub struct Cache {
     wildcards: HashMap<FileId, Wildcards>,
     /// Whether processing should try to continue even in case of errors. Needed by the NLS.
     error_tolerance: ErrorTolerance,
+    /// The restrictions applied to imports.
+    import_policy: ImportPolicy,
+    /// Timing and counters of the preparation phases.
+    stats: Stats,
 
     #[cfg(debug_assertions)]
     /// Skip loading the stdlib, used for debugging purpose
@@ -88,6 +94,71 @@ pub enum ErrorTolerance {
     Strict,
 }
 
+/// Restrictions on the files that can be imported by a program.
+///
+/// The default policy doesn't restrict anything. A restricted policy is useful when evaluating
+/// untrusted configurations, which shouldn't be able to read arbitrary files from the host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportPolicy {
+    /// If not empty, only files located under one of these directories can be imported.
+    pub roots: Vec<PathBuf>,
+    /// Reject imports specified by an absolute path.
+    pub deny_absolute: bool,
+    /// Reject imports which are located under one of the roots, but which resolve to a file
+    /// outside of all the roots once symbolic links have been followed.
+    pub deny_symlink_escape: bool,
+    /// Only allow the import of Nickel sources, and reject other formats such as JSON, YAML or
+    /// TOML.
+    pub nickel_only: bool,
+}
+
+impl ImportPolicy {
+    /// Check that importing `path`, as originally written in the source as `import_path` and
+    /// detected to be of format `format`, is allowed. Return the reason of the denial otherwise.
+    fn check(&self, import_path: &OsStr, path: &Path, format: InputFormat) -> Result<(), String> {
+        if self.nickel_only && format != InputFormat::Nickel {
+            return Err(String::from("only Nickel sources can be imported"));
+        }
+
+        if self.deny_absolute && Path::new(import_path).is_absolute() {
+            return Err(String::from("absolute import paths are not allowed"));
+        }
+
+        if self.roots.is_empty() {
+            return Ok(());
+        }
+
+        // The roots are compared both in their lexical and canonical forms, such that a root
+        // which itself contains a symbolic link is still handled correctly.
+        let is_under_roots = |path: &Path| {
+            self.roots.iter().any(|root| {
+                path.starts_with(lexical_normalize(root))
+                    || matches!(root.canonicalize(), Ok(ref root) if path.starts_with(root))
+            })
+        };
+
+        if !is_under_roots(&lexical_normalize(path)) {
+            return Err(String::from(
+                "the file is outside of the allowed import roots",
+            ));
+        }
+
+        if self.deny_symlink_escape {
+            // If the file doesn't exist, there is nothing to escape to: the subsequent IO error is
+            // more informative.
+            if let Ok(canonical) = path.canonicalize() {
+                if !is_under_roots(&canonical) {
+                    return Err(String::from(
+                        "the file resolves to a location outside of the allowed import roots",
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// The different environments maintained during the REPL session for evaluation and typechecking.
 #[derive(Debug, Clone)]
 pub struct Envs {
@@ -221,12 +292,29 @@ impl Cache {
             imports: HashMap::new(),
             stdlib_ids: None,
             error_tolerance,
+            import_policy: ImportPolicy::default(),
+            stats: Stats::default(),
 
             #[cfg(debug_assertions)]
             skip_stdlib: false,
         }
     }
 
+    /// Set the restrictions applied to imports.
+    pub fn set_import_policy(&mut self, policy: ImportPolicy) {
+        self.import_policy = policy;
+    }
+
+    /// Return the restrictions applied to imports.
+    pub fn import_policy(&self) -> &ImportPolicy {
+        &self.import_policy
+    }
+
+    /// Return the statistics of the preparation phases performed so far.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
     /// Load a file in the file database. Do not insert an entry in the name-id table.
     fn load_file(&mut self, path: impl Into<OsString>) -> io::Result<FileId> {
         let path = path.into();
@@ -358,7 +446,11 @@ impl Cache {
         if let Some(CachedTerm { parse_errs, .. }) = self.terms.get(&file_id) {
             Ok(CacheOp::Cached(parse_errs.clone()))
         } else {
+            let start = Instant::now();
             let (term, parse_errs) = self.parse_nocache(file_id)?;
+            self.stats.parse += start.elapsed();
+            self.stats.files_loaded += 1;
+
             self.terms.insert(
                 file_id,
                 CachedTerm {
@@ -399,7 +491,11 @@ impl Cache {
         if let Some(CachedTerm { parse_errs, .. }) = self.terms.get(&file_id) {
             Ok(CacheOp::Cached(parse_errs.clone()))
         } else {
+            let start = Instant::now();
             let (term, parse_errs) = self.parse_nocache_multi(file_id, format)?;
+            self.stats.parse += start.elapsed();
+            self.stats.files_loaded += 1;
+
             self.terms.insert(
                 file_id,
                 CachedTerm {
@@ -459,7 +555,7 @@ impl Cache {
             InputFormat::Yaml => serde_yaml::from_str(self.files.source(file_id))
                 .map(|t| (t, ParseErrors::default()))
                 .map_err(|err| (ParseError::from_serde_yaml(err, file_id))),
-            InputFormat::Toml => toml::from_str(self.files.source(file_id))
+            InputFormat::Toml => serialize::from_toml_str(self.files.source(file_id))
                 .map(|t| (t, ParseErrors::default()))
                 .map_err(|err| (ParseError::from_toml(err, file_id, &self.files))),
         }
@@ -479,7 +575,9 @@ impl Cache {
             }
             Some(CachedTerm { term, state, .. }) if *state >= EntryState::Parsed => {
                 if *state < EntryState::Typechecking {
+                    let start = Instant::now();
                     let wildcards = type_check(term, initial_ctxt.clone(), self)?;
+                    self.stats.typecheck += start.elapsed();
                     self.update_state(file_id, EntryState::Typechecking);
                     self.wildcards.insert(file_id, wildcards);
                 }
@@ -512,7 +610,10 @@ impl Cache {
                     let CachedTerm {
                         term, parse_errs, ..
                     } = self.terms.remove(&file_id).unwrap();
+                    let start = Instant::now();
                     let term = transform::transform(term, self.wildcards.get(&file_id))?;
+                    self.stats.transform += start.elapsed();
+                    self.stats.terms_transformed += 1;
                     self.terms.insert(
                         file_id,
                         CachedTerm {
@@ -566,6 +667,7 @@ impl Cache {
                 let wildcards = self.wildcards.get(&file_id);
 
                 if state < EntryState::Transforming {
+                    let start = Instant::now();
                     let pos = term.pos;
 
                     match SharedTerm::make_mut(&mut term.term) {
@@ -607,6 +709,9 @@ impl Cache {
                         _ => panic!("cache::transform_inner(): not a record"),
                     }
 
+                    self.stats.transform += start.elapsed();
+                    self.stats.terms_transformed += 1;
+
                     self.terms.insert(
                         file_id,
                         CachedTerm {
@@ -868,6 +973,10 @@ impl Cache {
             return Ok(CacheOp::Cached(()));
         }
 
+        // The parsing of the stdlib is accounted as stdlib preparation.
+        let start = Instant::now();
+        let parse = self.stats.parse;
+
         let file_ids: HashMap<StdlibModule, FileId> = nickel_stdlib::modules()
             .into_iter()
             .map(|module| {
@@ -884,6 +993,10 @@ impl Cache {
             self.parse(*file_id)?;
         }
         self.stdlib_ids.replace(file_ids);
+
+        self.stats.parse = parse;
+        self.stats.stdlib += start.elapsed();
+
         Ok(CacheOp::Done(()))
     }
 
@@ -933,6 +1046,10 @@ impl Cache {
             return Ok(Envs::new());
         }
         self.load_stdlib()?;
+
+        // The transformations of the stdlib are accounted as stdlib preparation.
+        let start = Instant::now();
+        let transform = self.stats.transform;
         let type_ctxt = self.mk_type_ctxt().unwrap();
 
         self.stdlib_ids
@@ -946,6 +1063,10 @@ impl Cache {
                     .unwrap_error("cache::prepare_stdlib(): expected standard library to be parsed")
             })?;
         let eval_env = self.mk_eval_env(eval_cache).unwrap();
+
+        self.stats.transform = transform;
+        self.stats.stdlib += start.elapsed();
+
         Ok(Envs {
             eval_env,
             type_ctxt,
@@ -1036,6 +1157,17 @@ pub trait ImportResolver {
     fn get(&self, file_id: FileId) -> Option<RichTerm>;
 
     fn get_path(&self, file_id: FileId) -> &OsStr;
+
+    /// Return the line and the column, both starting at 1, of the start of a span, if the
+    /// corresponding source is available.
+    fn get_location(&self, _span: &RawSpan) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// Return `true` if the file is a module of the standard library.
+    fn is_stdlib(&self, _file_id: FileId) -> bool {
+        false
+    }
 }
 
 impl ImportResolver for Cache {
@@ -1047,6 +1179,11 @@ impl ImportResolver for Cache {
     ) -> Result<(ResolvedTerm, FileId), ImportError> {
         let path_buf = with_parent(path, parent.clone());
         let format = InputFormat::from_path_buf(&path_buf).unwrap_or(InputFormat::Nickel);
+        self.import_policy
+            .check(path, &path_buf, format)
+            .map_err(|reason| {
+                ImportError::Denied(path_buf.to_string_lossy().into_owned(), reason, *pos)
+            })?;
         let id_op = self.get_or_add_file(&path_buf).map_err(|err| {
             ImportError::IOError(
                 path_buf.to_string_lossy().into_owned(),
@@ -1089,6 +1226,15 @@ impl ImportResolver for Cache {
     fn get_path(&self, file_id: FileId) -> &OsStr {
         self.files.name(file_id)
     }
+
+    fn get_location(&self, span: &RawSpan) -> Option<(usize, usize)> {
+        let location = self.files.location(span.src_id, span.start).ok()?;
+        Some((location.line.to_usize() + 1, location.column.to_usize() + 1))
+    }
+
+    fn is_stdlib(&self, file_id: FileId) -> bool {
+        matches!(&self.stdlib_ids, Some(ids) if ids.values().any(|id| *id == file_id))
+    }
 }
 
 /// Compute the path of a file relatively to a parent.
@@ -1106,6 +1252,30 @@ pub fn normalize_path(path: &Path) -> io::Result<OsString> {
     path.canonicalize().map(|p_| p_.as_os_str().to_os_string())
 }
 
+/// Make a path absolute and remove the `.` and `..` components, without accessing the file
+/// system. Contrary to [normalize_path], symbolic links are not resolved.
+fn lexical_normalize(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let path = if path.is_relative() {
+        std::env::current_dir().unwrap_or_default().join(path)
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
 /// Return the timestamp of a file. Return `None` if an IO error occurred.
 pub fn timestamp(path: impl AsRef<OsStr>) -> io::Result<SystemTime> {
     fs::metadata(path.as_ref())?.modified()
@@ -0,0 +1,432 @@
+//! Interactive step debugger for the Nickel abstract machine.
+//!
+//! The debugger is implemented as a set of [evaluation hooks][crate::eval::hooks]. It stops right
+//! before a reduction step, either because the user asked to single-step or because the current
+//! term hit a breakpoint. While stopped, commands are read from an input stream. They allow to
+//! inspect the current term, the bindings of its environment together with the state of the
+//! corresponding thunks, and the call stack.
+//!
+//! Because the hooks only observe the evaluation, quitting the debugger doesn't abort it: the
+//! program is then evaluated to the end without stopping anymore.
+use crate::cache::ImportResolver;
+use crate::eval::callstack::{FieldPaths, StackElem};
+use crate::eval::hooks::{EvalHooks, Step};
+use crate::eval::lazy::{Thunk, ThunkState};
+use crate::identifier::Ident;
+use crate::position::TermPos;
+use crate::term::RichTerm;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// The maximum number of lines used to print a term when the debugger stops.
+const MAX_TERM_LINES: usize = 10;
+
+const HELP: &str = "\
+Available commands:
+  step [n], s [n]      perform one (or n) reduction steps
+  continue, c          run until the next breakpoint
+  break <loc>, b <loc> add a breakpoint, either `<file>:<line>` or a field path `a.b.c`
+  delete <n>           remove the breakpoint number n
+  breakpoints, bl      list the breakpoints
+  term, t              print the current term
+  env, e               list the bindings of the current environment and the state of their thunks
+  print <var>, p <var> print the state and the content of the thunk bound to a variable
+  callstack, bt        print the call stack, most recent element first
+  quit, q              stop debugging and run the evaluation to the end
+  help, h              print this message";
+
+/// A breakpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Stop when a term starting at a given line of a file is about to be reduced. The components
+    /// of the file path are matched against the last components of the path of the source, such
+    /// that `main.ncl:3` matches `/path/to/main.ncl`, but not `/path/to/domain.ncl`.
+    Position { file: String, line: usize },
+    /// Stop when entering the record field with the given full path, such as `a.b.c`.
+    Field(Vec<Ident>),
+}
+
+/// Error raised when parsing an invalid breakpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBreakpoint(pub String);
+
+impl fmt::Display for InvalidBreakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid breakpoint `{}`: expected `<file>:<line>` or a field path `a.b.c`",
+            self.0
+        )
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = InvalidBreakpoint;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some((file, line)) = s.rsplit_once(':') {
+            return match line.parse::<usize>() {
+                Ok(line) if !file.is_empty() => Ok(Breakpoint::Position {
+                    file: String::from(file),
+                    line,
+                }),
+                _ => Err(InvalidBreakpoint(String::from(s))),
+            };
+        }
+
+        let path: Vec<&str> = s.split('.').collect();
+        if path.iter().any(|id| id.is_empty()) {
+            Err(InvalidBreakpoint(String::from(s)))
+        } else {
+            Ok(Breakpoint::Field(
+                path.into_iter().map(Ident::from).collect(),
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Position { file, line } => write!(f, "{}:{}", file, line),
+            Breakpoint::Field(path) => {
+                let path: Vec<&str> = path.iter().map(Ident::label).collect();
+                write!(f, "{}", path.join("."))
+            }
+        }
+    }
+}
+
+impl Breakpoint {
+    /// Determine if the breakpoint is hit by a reduction step. `field_path` is the path of the
+    /// innermost field being evaluated, if any.
+    fn is_hit(&self, step: &Step, field_path: Option<&[Ident]>) -> bool {
+        match self {
+            Breakpoint::Position { file, line } => match step.closure.body.pos {
+                TermPos::Original(span) => {
+                    // Ignore the `.` components, such that `./main.ncl:3` matches `main.ncl`.
+                    let file: PathBuf = Path::new(file)
+                        .components()
+                        .filter(|c| *c != Component::CurDir)
+                        .collect();
+
+                    Path::new(step.resolver.get_path(span.src_id)).ends_with(file)
+                        && matches!(step.resolver.get_location(&span), Some((l, _)) if l == *line)
+                }
+                _ => false,
+            },
+            Breakpoint::Field(path) => field_path == Some(path.as_slice()),
+        }
+    }
+}
+
+/// The execution mode of the debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Stop after the given number of steps.
+    Step(usize),
+    /// Only stop at breakpoints.
+    Continue,
+    /// Never stop again.
+    Detached,
+}
+
+/// An interactive step debugger.
+pub struct Debugger {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+    /// The number of reduction steps performed so far.
+    steps: usize,
+    /// The indices of the breakpoints hit at the previous step. A breakpoint only stops the
+    /// debugger when it starts being hit, and not at each reduction step of the same location.
+    last_hits: Vec<usize>,
+    /// The full paths of the record fields entered so far.
+    field_paths: FieldPaths,
+}
+
+impl Debugger {
+    /// Create a new debugger reading its commands from `input` and writing to `output`. If no
+    /// breakpoint is provided, the debugger stops before the first reduction step.
+    pub fn new(
+        input: Box<dyn BufRead>,
+        output: Box<dyn Write>,
+        breakpoints: Vec<Breakpoint>,
+    ) -> Self {
+        let mode = if breakpoints.is_empty() {
+            Mode::Step(1)
+        } else {
+            Mode::Continue
+        };
+
+        Debugger {
+            input,
+            output,
+            breakpoints,
+            mode,
+            steps: 0,
+            last_hits: Vec::new(),
+            field_paths: FieldPaths::new(),
+        }
+    }
+
+    /// Stop before a reduction step, and process commands until one of them resumes the
+    /// evaluation.
+    fn stop(&mut self, step: &Step, hits: &[usize]) -> io::Result<()> {
+        for idx in hits {
+            writeln!(
+                self.output,
+                "breakpoint #{} hit: {}",
+                idx, self.breakpoints[*idx]
+            )?;
+        }
+
+        writeln!(
+            self.output,
+            "step {} at {}",
+            self.steps,
+            format_pos(step.resolver, step.closure.body.pos)
+        )?;
+        self.print_term(&step.closure.body, Some(MAX_TERM_LINES))?;
+
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                self.mode = Mode::Detached;
+                return Ok(());
+            }
+
+            let line = line.trim();
+            let (cmd, arg) = line.split_once(' ').unwrap_or((line, ""));
+            let arg = arg.trim();
+
+            match cmd {
+                "step" | "s" | "" => {
+                    let count = if arg.is_empty() {
+                        Ok(1)
+                    } else {
+                        arg.parse::<usize>()
+                    };
+
+                    match count {
+                        Ok(count) if count > 0 => {
+                            self.mode = Mode::Step(count);
+                            return Ok(());
+                        }
+                        _ => writeln!(self.output, "invalid number of steps `{}`", arg)?,
+                    }
+                }
+                "continue" | "c" => {
+                    self.mode = Mode::Continue;
+                    return Ok(());
+                }
+                "quit" | "q" => {
+                    self.mode = Mode::Detached;
+                    return Ok(());
+                }
+                "break" | "b" => match arg.parse::<Breakpoint>() {
+                    Ok(bp) => {
+                        writeln!(
+                            self.output,
+                            "breakpoint #{}: {}",
+                            self.breakpoints.len(),
+                            bp
+                        )?;
+                        self.breakpoints.push(bp);
+                    }
+                    Err(err) => writeln!(self.output, "{}", err)?,
+                },
+                "delete" => match arg.parse::<usize>() {
+                    Ok(idx) if idx < self.breakpoints.len() => {
+                        let bp = self.breakpoints.remove(idx);
+                        self.last_hits.clear();
+                        writeln!(self.output, "deleted breakpoint #{}: {}", idx, bp)?;
+                    }
+                    _ => writeln!(self.output, "no breakpoint `{}`", arg)?,
+                },
+                "breakpoints" | "bl" => {
+                    if self.breakpoints.is_empty() {
+                        writeln!(self.output, "no breakpoints")?;
+                    }
+
+                    for (idx, bp) in self.breakpoints.iter().enumerate() {
+                        writeln!(self.output, "#{}: {}", idx, bp)?;
+                    }
+                }
+                "term" | "t" => self.print_term(&step.closure.body, None)?,
+                "env" | "e" => {
+                    let mut bindings: Vec<(&Ident, &Thunk)> = step
+                        .closure
+                        .env
+                        .iter()
+                        .filter(|(id, _)| !id.is_generated())
+                        .collect();
+                    bindings.sort_by(|(id1, _), (id2, _)| id1.label().cmp(id2.label()));
+
+                    if bindings.is_empty() {
+                        writeln!(self.output, "empty local environment")?;
+                    }
+
+                    for (id, thunk) in bindings {
+                        writeln!(
+                            self.output,
+                            "{} [{}] = {}",
+                            id,
+                            format_state(thunk.state()),
+                            thunk.borrow().body.as_ref().shallow_repr()
+                        )?;
+                    }
+                }
+                "print" | "p" => {
+                    let id = Ident::from(arg);
+                    let thunk = step
+                        .closure
+                        .env
+                        .get(&id)
+                        .or_else(|| step.initial_env.get(&id));
+
+                    match thunk {
+                        Some(thunk) => {
+                            let body = thunk.borrow().body.clone();
+                            writeln!(
+                                self.output,
+                                "{} [{}] defined at {}",
+                                id,
+                                format_state(thunk.state()),
+                                format_pos(step.resolver, body.pos)
+                            )?;
+                            self.print_term(&body, None)?;
+                        }
+                        None => writeln!(self.output, "unbound variable `{}`", arg)?,
+                    }
+                }
+                "callstack" | "bt" => {
+                    if step.call_stack.is_empty() {
+                        writeln!(self.output, "empty call stack")?;
+                    }
+
+                    for elem in step.call_stack.0.iter().rev() {
+                        let descr = match elem {
+                            StackElem::Var { id, pos, .. } => {
+                                format!("variable `{}` at {}", id, format_pos(step.resolver, *pos))
+                            }
+                            StackElem::Field { id, pos_access, .. } => format!(
+                                "field `{}` at {}",
+                                id,
+                                format_pos(step.resolver, *pos_access)
+                            ),
+                            StackElem::App(pos) => {
+                                format!("application at {}", format_pos(step.resolver, *pos))
+                            }
+                            StackElem::Fun(pos) => {
+                                format!("function called at {}", format_pos(step.resolver, *pos))
+                            }
+                        };
+                        writeln!(self.output, "  {}", descr)?;
+                    }
+                }
+                "help" | "h" => writeln!(self.output, "{}", HELP)?,
+                _ => writeln!(
+                    self.output,
+                    "unknown command `{}` (type `help` for the list of commands)",
+                    cmd
+                )?,
+            }
+        }
+    }
+
+    /// Print a term, keeping at most `max_lines` lines if specified.
+    fn print_term(&mut self, rt: &RichTerm, max_lines: Option<usize>) -> io::Result<()> {
+        let printed = rt.to_string();
+        let mut lines = printed.lines();
+
+        for line in lines.by_ref().take(max_lines.unwrap_or(usize::MAX)) {
+            writeln!(self.output, "  {}", line)?;
+        }
+
+        if lines.next().is_some() {
+            writeln!(self.output, "  ... (use `term` to print the whole term)")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl EvalHooks for Debugger {
+    fn on_step(&mut self, step: &Step) {
+        if self.mode == Mode::Detached {
+            return;
+        }
+
+        self.steps += 1;
+
+        let field_path = if self
+            .breakpoints
+            .iter()
+            .any(|bp| matches!(bp, Breakpoint::Field(_)))
+        {
+            self.field_paths.current(step.call_stack)
+        } else {
+            None
+        };
+
+        let hits: Vec<usize> = self
+            .breakpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, bp)| bp.is_hit(step, field_path.as_deref()).then_some(idx))
+            .collect();
+        let new_hits: Vec<usize> = hits
+            .iter()
+            .copied()
+            .filter(|idx| !self.last_hits.contains(idx))
+            .collect();
+        self.last_hits = hits;
+
+        let should_stop = match self.mode {
+            Mode::Step(1) => true,
+            Mode::Step(n) => {
+                self.mode = Mode::Step(n - 1);
+                !new_hits.is_empty()
+            }
+            Mode::Continue => !new_hits.is_empty(),
+            Mode::Detached => false,
+        };
+
+        // If the output isn't writable anymore, there's no point in continuing to debug.
+        if should_stop && self.stop(step, &new_hits).is_err() {
+            self.mode = Mode::Detached;
+        }
+    }
+}
+
+/// Render a position as `<file>:<line>:<column>`.
+pub(crate) fn format_pos(resolver: &dyn ImportResolver, pos: TermPos) -> String {
+    match pos {
+        TermPos::Original(span) | TermPos::Inherited(span) => {
+            let path = resolver.get_path(span.src_id).to_string_lossy();
+
+            match resolver.get_location(&span) {
+                Some((line, col)) => format!("{}:{}:{}", path, line, col),
+                None => format!("{}", path),
+            }
+        }
+        TermPos::None => String::from("<generated>"),
+    }
+}
+
+fn format_state(state: ThunkState) -> &'static str {
+    match state {
+        ThunkState::Blackholed => "being evaluated",
+        ThunkState::Suspended => "suspended",
+        ThunkState::Evaluated => "evaluated",
+    }
+}
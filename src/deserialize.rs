@@ -8,8 +8,12 @@ use serde::de::{
     VariantAccess, Visitor,
 };
 
+use malachite_base::num::conversion::traits::RoundingFrom;
+use malachite_base::rounding_modes::RoundingMode;
+
 use crate::identifier::Ident;
 use crate::term::array::{self, Array};
+use crate::term::{datetime, number};
 use crate::term::{MetaValue, RichTerm, Term};
 
 macro_rules! deserialize_number {
@@ -19,7 +23,7 @@ macro_rules! deserialize_number {
             V: Visitor<'de>,
         {
             match unwrap_term(self)? {
-                Term::Num(n) => visitor.$visit(n as $type),
+                Term::Num(n) => visitor.$visit(number::to_f64(&n) as $type),
                 other => Err(RustDeserializationError::InvalidType {
                     expected: "Num".to_string(),
                     occurred: other.type_of().unwrap_or_else(|| "Other".to_string()),
@@ -36,7 +40,7 @@ macro_rules! deserialize_number_round {
             V: Visitor<'de>,
         {
             match unwrap_term(self)? {
-                Term::Num(n) => visitor.$visit(n.round() as $type),
+                Term::Num(n) => visitor.$visit($type::rounding_from(&n, RoundingMode::Nearest).0),
                 other => Err(RustDeserializationError::InvalidType {
                     expected: "Num".to_string(),
                     occurred: other.type_of().unwrap_or_else(|| "Other".to_string()),
@@ -69,12 +73,20 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
         match unwrap_term(self)? {
             Term::Null => visitor.visit_unit(),
             Term::Bool(v) => visitor.visit_bool(v),
-            Term::Num(v) => visitor.visit_f64(v),
+            Term::Num(v) => visitor.visit_f64(number::to_f64(&v)),
             Term::Str(v) => visitor.visit_string(v),
+            Term::DateTime(v) => {
+                visitor.visit_string(datetime::format(&v).map_err(RustDeserializationError::Other)?)
+            }
+            Term::Duration(v) => visitor.visit_string(datetime::format_duration(&v)),
             Term::Enum(v) => visitor.visit_enum(EnumDeserializer {
                 variant: v.into_label(),
                 rich_term: None,
             }),
+            Term::EnumVariant { tag, arg } => visitor.visit_enum(EnumDeserializer {
+                variant: tag.into_label(),
+                rich_term: Some(arg),
+            }),
             Term::Record(record) => visit_record(record.fields, visitor),
             Term::Array(v, _) => visit_array(v, visitor),
             Term::MetaValue(_) => visitor.visit_unit(),
@@ -108,7 +120,8 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
         }
     }
 
-    /// deserialize `RichTerm::Enum` tags or `RichTerm::Record`s with a single item.
+    /// deserialize `RichTerm::Enum` tags, `RichTerm::EnumVariant`s or `RichTerm::Record`s with a
+    /// single item.
     fn deserialize_enum<V>(
         self,
         _name: &str,
@@ -120,6 +133,7 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
     {
         let (variant, rich_term) = match unwrap_term(self)? {
             Term::Enum(ident) => (ident.into_label(), None),
+            Term::EnumVariant { tag, arg } => (tag.into_label(), Some(arg)),
             Term::Record(record) => {
                 let mut iter = record.fields.into_iter();
                 let (variant, value) = match iter.next() {
@@ -192,13 +206,17 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
         self.deserialize_string(visitor)
     }
 
-    /// Deserialize `RichTerm::Str` as String
+    /// Deserialize `RichTerm::Str`, `RichTerm::DateTime` or `RichTerm::Duration` as String
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         match unwrap_term(self)? {
             Term::Str(v) => visitor.visit_string(v),
+            Term::DateTime(v) => {
+                visitor.visit_string(datetime::format(&v).map_err(RustDeserializationError::Other)?)
+            }
+            Term::Duration(v) => visitor.visit_string(datetime::format_duration(&v)),
             other => Err(RustDeserializationError::InvalidType {
                 expected: "Str".to_string(),
                 occurred: other.type_of().unwrap_or_else(|| "Other".to_string()),
@@ -1,9 +1,15 @@
 //! In this module, you have the main structures used in the destructuring feature of nickel.
 //! Also, there are implementation managing the generation of a contract from a pattern.
+//!
+//! Destructuring patterns are also used, together with enum tags, constants and or-patterns, in
+//! the branches of a `match` expression (see [BranchPattern]).
 
 use crate::identifier::Ident;
 use crate::label::Label;
+use crate::mk_app;
 use crate::position::RawSpan;
+use crate::stdlib::contract;
+use crate::term::number::Number;
 use crate::term::record::RecordData;
 use crate::term::{record::RecordAttrs, Contract, MetaValue, RichTerm, Term};
 use crate::types::{TypeF, Types};
@@ -32,18 +38,39 @@ pub enum LastMatch {
     Ellipsis(Option<Ident>),
 }
 
+/// An element of an array pattern: either a variable, a nested pattern, or a nested pattern bound
+/// to a variable with the `x @` form.
+pub type ElemMatch = (Option<Ident>, Destruct);
+
+/// Last element of an array `Destruct`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LastElem {
+    /// The last element is a normal match. In this case the pattern is "closed" and the array
+    /// must have exactly as many elements as the pattern.
+    Match(Box<ElemMatch>),
+    /// The pattern is "open" `, ..]`. Optionally you can bind an array containing the remaining
+    /// elements to an `Identifier` using the syntax `, ..y]`.
+    Ellipsis(Option<Ident>),
+}
+
 /// A destructuring pattern without the `x @` part.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Destruct {
-    /// A record pattern, the only one implemented for now.
+    /// A record pattern.
     Record {
         matches: Vec<Match>,
         open: bool,
         rest: Option<Ident>,
         span: RawSpan,
     },
-    /// An array destructuring. Not implemented.
-    Array { matches: Vec<Match>, span: RawSpan },
+    /// An array pattern. The i-th element of the pattern is matched against the i-th element of
+    /// the array.
+    Array {
+        matches: Vec<ElemMatch>,
+        open: bool,
+        rest: Option<Ident>,
+        span: RawSpan,
+    },
     /// An empty destructuring. In this case, the pattern is a clasical `let var = something in
     /// body` form.
     Empty,
@@ -58,30 +85,40 @@ impl Destruct {
 
     fn into_contract_with_lbl(self, label: Label) -> MetaValue {
         let open = self.is_open();
+        let contract = match self {
+            // The contract of an array pattern only checks the length of the array. The contracts
+            // of nested patterns are applied to each element during the desugaring.
+            Destruct::Array { matches, .. } => mk_app!(
+                contract::array_pattern(),
+                Term::Num(Number::from(matches.len())),
+                Term::Bool(open)
+            ),
+            _ => Term::Record(RecordData::new(
+                self.inner()
+                    .into_iter()
+                    .map(|m| m.as_meta_field())
+                    .collect(),
+                RecordAttrs { open },
+                None,
+            ))
+            .into(),
+        };
+
         MetaValue {
             contracts: vec![Contract {
-                types: Types(TypeF::Flat(
-                    Term::Record(RecordData::new(
-                        self.inner()
-                            .into_iter()
-                            .map(|m| m.as_meta_field())
-                            .collect(),
-                        RecordAttrs { open },
-                        None,
-                    ))
-                    .into(),
-                )),
+                types: Types(TypeF::Flat(contract)),
                 label,
             }],
             ..Default::default()
         }
     }
 
-    /// Get the inner vector of `Matches` of the pattern. If `Empty` return a empty vector.
+    /// Get the inner vector of `Matches` of a record pattern. If `Empty` or an array pattern,
+    /// return a empty vector.
     pub fn inner(self) -> Vec<Match> {
         match self {
-            Destruct::Record { matches, .. } | Destruct::Array { matches, .. } => matches,
-            Destruct::Empty => vec![],
+            Destruct::Record { matches, .. } => matches,
+            Destruct::Array { .. } | Destruct::Empty => vec![],
         }
     }
 
@@ -96,15 +133,98 @@ impl Destruct {
         }
     }
 
-    /// Is this pattern open? Does it finish with `, ..}` form?
+    /// Is this pattern open? Does it finish with `, ..}` or `, ..]` form?
     pub fn is_open(&self) -> bool {
-        matches!(self, Destruct::Record { open: true, .. })
+        matches!(
+            self,
+            Destruct::Record { open: true, .. } | Destruct::Array { open: true, .. }
+        )
     }
 
     /// check if the pattern is empty.
     pub fn is_empty(&self) -> bool {
         matches!(self, Destruct::Empty)
     }
+
+    /// Push the variables bound by this pattern, including the rest bindings `..y`, to `vars`.
+    pub fn bound_vars(&self, vars: &mut Vec<Ident>) {
+        match self {
+            Destruct::Record { matches, rest, .. } => {
+                for m in matches {
+                    match m {
+                        Match::Simple(id, _) => vars.push(*id),
+                        Match::Assign(_, _, (id, pat)) => {
+                            vars.extend(id);
+                            pat.bound_vars(vars);
+                        }
+                    }
+                }
+                vars.extend(rest);
+            }
+            Destruct::Array { matches, rest, .. } => {
+                for (id, pat) in matches {
+                    vars.extend(id);
+                    pat.bound_vars(vars);
+                }
+                vars.extend(rest);
+            }
+            Destruct::Empty => (),
+        }
+    }
+}
+
+/// The pattern of a branch of a `match` expression.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BranchPattern {
+    /// `_`, which matches any value without binding it.
+    Wildcard,
+    /// An enum tag.
+    Enum(Ident),
+    /// An enum variant `` `tag pat ``, which matches the enum variants with this tag carrying an
+    /// argument matching the pattern `pat`.
+    Variant(Ident, Box<BranchPattern>),
+    /// A number, a string, a boolean or `null`, which matches the values equal to it.
+    Constant(RichTerm),
+    /// A destructuring pattern, optionally bound with the `x @` form. A destructuring pattern
+    /// matches a value if the value has the expected shape: a record with the fields of the pattern
+    /// (some fields being optional if they have a default value), or an array of the right length.
+    /// A single variable matches any value.
+    Destruct(Option<Ident>, Destruct),
+    /// Alternatives `p1 | p2 | ..`, tried from left to right. All the alternatives must bind the
+    /// same variables.
+    Or(Vec<BranchPattern>),
+}
+
+impl BranchPattern {
+    /// Return the variables bound by this pattern, sorted and without duplicates. For an
+    /// or-pattern, return the variables bound by the first alternative.
+    pub fn bound_vars(&self) -> Vec<Ident> {
+        let mut vars = Vec::new();
+
+        match self {
+            BranchPattern::Destruct(id, pat) => {
+                vars.extend(id);
+                pat.bound_vars(&mut vars);
+            }
+            BranchPattern::Variant(_, arg) => return arg.bound_vars(),
+            BranchPattern::Or(alts) => return alts.first().map_or(vars, |p| p.bound_vars()),
+            BranchPattern::Wildcard | BranchPattern::Enum(_) | BranchPattern::Constant(_) => (),
+        }
+
+        vars.sort_by(|id1, id2| id1.label().cmp(id2.label()));
+        vars.dedup();
+        vars
+    }
+}
+
+/// A branch `pattern if guard => body` of a `match` expression.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchBranch {
+    pub pattern: BranchPattern,
+    /// A boolean expression which must evaluate to `true` for the branch to be selected. It can
+    /// refer to the variables bound by the pattern.
+    pub guard: Option<RichTerm>,
+    pub body: RichTerm,
 }
 
 impl Match {
@@ -121,7 +241,7 @@ impl Match {
             //
             // - extra field on the destructuring `d`
             // - missing field on the `id`
-            Match::Assign(id, m, (_, d @ Destruct::Record { .. })) => {
+            Match::Assign(id, m, (_, d @ (Destruct::Record { .. } | Destruct::Array { .. }))) => {
                 let label @ Label { span, .. } = d.label();
                 let span = RawSpan::fuse(id.pos.unwrap(), span).unwrap();
                 let label = Label { span, ..label };
@@ -130,7 +250,6 @@ impl Match {
                     Term::MetaValue(MetaValue::flatten(m, d.into_contract_with_lbl(label))).into(),
                 )
             }
-            Match::Assign(_id, _m, (_, _d @ Destruct::Array { .. })) => unimplemented!(),
         }
     }
 }
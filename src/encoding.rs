@@ -0,0 +1,157 @@
+//! Binary-to-text encodings of strings, used by the `%encode%` and `%decode%` primitive
+//! operations.
+//!
+//! Strings are encoded as their UTF-8 representation. Decoding fails if the input isn't valid in
+//! the given encoding, or if the decoded bytes aren't valid UTF-8, since Nickel strings can't hold
+//! arbitrary bytes.
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+
+/// The characters escaped by URL encoding: everything but the unreserved characters of
+/// [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-2.3).
+const URL_ESCAPED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// An encoding of strings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Base64 with the standard alphabet and padding.
+    Base64,
+    /// Base64 with the URL and filename safe alphabet and padding.
+    Base64Url,
+    /// Lowercase hexadecimal.
+    Hex,
+    /// Percent-encoding of a URL component.
+    Url,
+}
+
+impl Encoding {
+    /// Return the encoding corresponding to an enum tag of the stdlib, such as `Base64`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "Base64" => Some(Encoding::Base64),
+            "Base64Url" => Some(Encoding::Base64Url),
+            "Hex" => Some(Encoding::Hex),
+            "Url" => Some(Encoding::Url),
+            _ => None,
+        }
+    }
+
+    pub fn encode(self, s: &str) -> String {
+        match self {
+            Encoding::Base64 => base64::encode_config(s, base64::STANDARD),
+            Encoding::Base64Url => base64::encode_config(s, base64::URL_SAFE),
+            Encoding::Hex => s.bytes().map(|b| format!("{:02x}", b)).collect(),
+            Encoding::Url => percent_encoding::utf8_percent_encode(s, URL_ESCAPED).to_string(),
+        }
+    }
+
+    /// Decode a string. On error, return a description of the problem.
+    pub fn decode(self, s: &str) -> Result<String, String> {
+        let bytes = match self {
+            Encoding::Base64 => base64::decode_config(s, base64::STANDARD)
+                .map_err(|err| format!("invalid base64 input: {}", err))?,
+            Encoding::Base64Url => base64::decode_config(s, base64::URL_SAFE)
+                .map_err(|err| format!("invalid URL-safe base64 input: {}", err))?,
+            Encoding::Hex => decode_hex(s)?,
+            Encoding::Url => decode_url(s)?,
+        };
+
+        String::from_utf8(bytes).map_err(|_| String::from("the decoded bytes are not valid UTF-8"))
+    }
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() % 2 == 1 {
+        return Err(String::from(
+            "invalid hexadecimal input: odd number of digits",
+        ));
+    }
+
+    s.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, digits)| {
+            // `from_str_radix` accepts a leading sign, which isn't a valid hexadecimal digit
+            digits
+                .iter()
+                .all(u8::is_ascii_hexdigit)
+                .then(|| std::str::from_utf8(digits).ok())
+                .flatten()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "invalid hexadecimal input: invalid digits at offset {}",
+                        2 * i
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Decode a percent-encoded string. Contrary to [percent_encoding::percent_decode_str], which
+/// leaves them as is, reject `%` signs which aren't followed by two hexadecimal digits.
+fn decode_url(s: &str) -> Result<Vec<u8>, String> {
+    let bytes = s.as_bytes();
+
+    for (i, _) in s.match_indices('%') {
+        let valid = matches!(
+            bytes.get(i + 1..i + 3),
+            Some(digits) if digits.iter().all(u8::is_ascii_hexdigit)
+        );
+
+        if !valid {
+            return Err(format!(
+                "invalid URL-encoded input: malformed escape sequence at offset {}",
+                i
+            ));
+        }
+    }
+
+    Ok(percent_encoding::percent_decode(bytes).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let inputs = ["", "hello, world!", "été ☃", "a+b/c?d=e&f~g"];
+        let encodings = [
+            Encoding::Base64,
+            Encoding::Base64Url,
+            Encoding::Hex,
+            Encoding::Url,
+        ];
+
+        for encoding in encodings {
+            for input in inputs {
+                assert_eq!(encoding.decode(&encoding.encode(input)).unwrap(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(Encoding::Base64.encode("hello?>"), "aGVsbG8/Pg==");
+        assert_eq!(Encoding::Base64Url.encode("hello?>"), "aGVsbG8_Pg==");
+        assert_eq!(Encoding::Hex.encode("Hé"), "48c3a9");
+        assert_eq!(Encoding::Url.encode("a b/é~"), "a%20b%2F%C3%A9~");
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(Encoding::Base64.decode("a$==").is_err());
+        assert!(Encoding::Base64.decode("aGVsbG8_Pg==").is_err());
+        assert!(Encoding::Hex.decode("abc").is_err());
+        assert!(Encoding::Hex.decode("zz").is_err());
+        assert!(Encoding::Hex.decode("+a+b").is_err());
+        assert!(Encoding::Hex.decode("ff").is_err());
+        assert!(Encoding::Url.decode("100%").is_err());
+        assert!(Encoding::Url.decode("%zz").is_err());
+        assert_eq!(Encoding::Url.decode("a%2fb").unwrap(), "a/b");
+    }
+}
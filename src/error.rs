@@ -105,6 +105,13 @@ pub enum EvalError {
     },
     /// A non-equatable term was compared for equality.
     EqError { eq_pos: TermPos, term: RichTerm },
+    /// A value didn't match any branch of a match expression.
+    NonExhaustiveMatch {
+        /// The value being matched on.
+        value: RichTerm,
+        /// The position of the match expression.
+        pos: TermPos,
+    },
     /// An unexpected internal error.
     InternalError(String, TermPos),
     /// Errors occurring rarely enough to not deserve a dedicated variant.
@@ -128,6 +135,16 @@ impl IllegalPolymorphicTailAction {
     }
 }
 
+/// The origin of a type inferred during typechecking: the expression whose typechecking fixed
+/// this type. Used to explain type errors.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeOrigin {
+    /// The inferred type.
+    pub ty: Types,
+    /// The position of the expression which caused this type to be inferred.
+    pub span: RawSpan,
+}
+
 /// An error occurring during the static typechecking phase.
 #[derive(Debug, PartialEq, Clone)]
 pub enum TypecheckError {
@@ -159,6 +176,13 @@ pub enum TypecheckError {
         /* the inferred/annotated type */ Types,
         TermPos,
     ),
+    /// An enum row was expected to carry an argument but didn't, or the other way around.
+    EnumRowArgMismatch(
+        Ident,
+        /* the expected type */ Types,
+        /* the inferred/annotated type */ Types,
+        TermPos,
+    ),
 
     /// An unbound type variable was referenced.
     UnboundTypeVariable(Ident, TermPos),
@@ -167,6 +191,8 @@ pub enum TypecheckError {
     TypeMismatch(
         /* the expected type */ Types,
         /* the actual type */ Types,
+        /* the origins of the expected and actual types, if they were inferred */
+        Vec<TypeOrigin>,
         TermPos,
     ),
     /// Two incompatible kind (enum vs record) have been deduced for the same identifier of a row type.
@@ -182,6 +208,8 @@ pub enum TypecheckError {
         /* the expected row type (whole) */ Types,
         /* the actual row type (whole) */ Types,
         /* error at the given row */ Box<TypecheckError>,
+        /* the origins of the expected and actual row types, if they were inferred */
+        Vec<TypeOrigin>,
         TermPos,
     ),
     /// Two incompatible types have been deduced for the same identifier of a row type.
@@ -321,6 +349,8 @@ pub enum ParseError {
     InvalidEscapeSequence(RawSpan),
     /// Invalid ASCII escape code in a string literal.
     InvalidAsciiEscapeCode(RawSpan),
+    /// Invalid number literal, such as a literal with invalid digits for its radix.
+    InvalidNumLiteral(RawSpan, String /* error message */),
     /// Error when parsing an external format such as JSON, YAML, etc.
     ExternalFormatError(
         String, /* format */
@@ -347,6 +377,12 @@ pub enum ParseError {
     /// A recursive let pattern was encountered. They are not currently supported because we
     /// decided it was too involved to implement them.
     RecursiveLetPattern(RawSpan),
+    /// The alternatives of an or-pattern in a match expression don't bind the same variables.
+    InvalidOrPattern(RawSpan),
+    /// The same variable is bound several times in a let-block `let x = .., x = .. in ..`.
+    DuplicateLetBinding(Ident, RawSpan),
+    /// A default value was given to a dynamic field access, as in `record."%{field}" or default`.
+    DynamicAccessOr(RawSpan),
     /// A type variable is used in ways that imply it has muiltiple different kinds.
     ///
     /// This can happen in several situations, for example:
@@ -355,6 +391,21 @@ pub enum ParseError {
     /// - a variable is used as both a record and enum row variable, e.g. in the
     ///   signature `forall r. [| ; r |] -> { ; r }`.
     TypeVariableKindMismatch { ty_var: Ident, span: RawSpan },
+    /// A type alias was applied to a number of arguments different from its number of parameters.
+    TypeAliasArityMismatch {
+        alias: Ident,
+        expected: usize,
+        found: usize,
+        span: RawSpan,
+    },
+    /// A parameter of a type alias is used as the tail of record rows or enum rows, as in `type R
+    /// r = {foo : Num ; r}`, but the alias is applied to an argument which isn't a record type,
+    /// respectively an enum type, or a type variable.
+    TypeAliasInvalidRowTail {
+        alias: Ident,
+        param: Ident,
+        span: RawSpan,
+    },
 }
 
 /// An error occurring during the resolution of an import.
@@ -371,6 +422,12 @@ pub enum ImportError {
         /* error */ ParseErrors,
         /* import position */ TermPos,
     ),
+    /// The import was rejected by the import policy of the cache.
+    Denied(
+        /* imported file */ String,
+        /* reason */ String,
+        /* import position */ TermPos,
+    ),
 }
 
 /// An error occurred during serialization.
@@ -505,6 +562,9 @@ impl ParseError {
                 InternalParseError::Lexical(LexicalError::InvalidAsciiEscapeCode(location)) => {
                     ParseError::InvalidAsciiEscapeCode(mk_span(file_id, location, location + 2))
                 }
+                InternalParseError::Lexical(LexicalError::InvalidNumLiteral(start, end, msg)) => {
+                    ParseError::InvalidNumLiteral(mk_span(file_id, start, end), msg)
+                }
                 InternalParseError::UnboundTypeVariables(idents, span) => {
                     ParseError::UnboundTypeVariables(idents, span)
                 }
@@ -514,9 +574,28 @@ impl ParseError {
                 InternalParseError::RecursiveLetPattern(pos) => {
                     ParseError::RecursiveLetPattern(pos)
                 }
+                InternalParseError::InvalidOrPattern(pos) => ParseError::InvalidOrPattern(pos),
+                InternalParseError::DuplicateLetBinding(id, pos) => {
+                    ParseError::DuplicateLetBinding(id, pos)
+                }
+                InternalParseError::DynamicAccessOr(pos) => ParseError::DynamicAccessOr(pos),
                 InternalParseError::TypeVariableKindMismatch { ty_var, span } => {
                     ParseError::TypeVariableKindMismatch { ty_var, span }
                 }
+                InternalParseError::TypeAliasArityMismatch {
+                    alias,
+                    expected,
+                    found,
+                    span,
+                } => ParseError::TypeAliasArityMismatch {
+                    alias,
+                    expected,
+                    found,
+                    span,
+                },
+                InternalParseError::TypeAliasInvalidRowTail { alias, param, span } => {
+                    ParseError::TypeAliasInvalidRowTail { alias, param, span }
+                }
             },
         }
     }
@@ -1034,6 +1113,22 @@ impl ToDiagnostic<FileId> for EvalError {
                     .with_message("cannot compare values for equality")
                     .with_labels(labels)]
             }
+            EvalError::NonExhaustiveMatch { value, pos } => {
+                let mut labels =
+                    vec![primary_term(value, files)
+                        .with_message("this value doesn't match any branch")];
+
+                if let Some(span) = pos.as_opt_ref() {
+                    labels.push(secondary(span).with_message("in this match expression"));
+                }
+
+                vec![Diagnostic::error()
+                    .with_message("unmatched pattern")
+                    .with_labels(labels)
+                    .with_notes(vec![String::from(
+                        "Add a branch for this value, or a catch-all `_` branch",
+                    )])]
+            }
             EvalError::IllegalPolymorphicTailAccess {
                 action,
                 label: l,
@@ -1365,6 +1460,9 @@ impl ToDiagnostic<FileId> for ParseError {
             ParseError::InvalidAsciiEscapeCode(span) => Diagnostic::error()
                 .with_message("invalid ascii escape code")
                 .with_labels(vec![primary(span)]),
+            ParseError::InvalidNumLiteral(span, msg) => Diagnostic::error()
+                .with_message("invalid number literal")
+                .with_labels(vec![primary(span).with_message(msg)]),
             ParseError::ExternalFormatError(format, msg, span_opt) => {
                 let labels = span_opt
                     .as_ref()
@@ -1405,6 +1503,31 @@ impl ToDiagnostic<FileId> for ParseError {
                     String::from("A destructuring let-binding can't be recursive. Try removing the `rec` from `let rec`."),
                     String::from("Note: you can reference other fields of a record recursively from within a field, so you might not need the recursive let."),
                 ]),
+            ParseError::InvalidOrPattern(span) => Diagnostic::error()
+                .with_message("the alternatives of an or-pattern bind different variables")
+                .with_labels(vec![
+                    primary(span),
+                ])
+                .with_notes(vec![
+                    String::from("Each alternative of an or-pattern `p1 | p2` must bind the same variables, so that they are defined in the branch whichever alternative matches."),
+                ]),
+            ParseError::DuplicateLetBinding(id, span) => Diagnostic::error()
+                .with_message(format!("the variable `{}` is bound several times", id))
+                .with_labels(vec![
+                    primary(span),
+                ])
+                .with_notes(vec![
+                    String::from("The bindings of a let-block `let x1 = e1, .., xn = en in body` must bind distinct variables."),
+                ]),
+            ParseError::DynamicAccessOr(span) => Diagnostic::error()
+                .with_message("default values are not supported for dynamic field accesses")
+                .with_labels(vec![
+                    primary(span),
+                ])
+                .with_notes(vec![
+                    String::from("The field accessed by `record.field or default` must be statically known."),
+                    String::from("You can check for the presence of a dynamic field with `record.has_field` instead."),
+                ]),
             ParseError::TypeVariableKindMismatch { ty_var, span } => Diagnostic::error()
                 .with_message(format!("the type variable {} is used in conflicting ways", ty_var))
                 .with_labels(vec![
@@ -1414,6 +1537,23 @@ impl ToDiagnostic<FileId> for ParseError {
                     String::from("Type variables may be used either as types, polymorphic record tails, or polymorphic enum tails."),
                     String::from("Using the same variable as more than one of these is not permitted.")
                 ]),
+            ParseError::TypeAliasArityMismatch { alias, expected, found, span } => Diagnostic::error()
+                .with_message(format!("the type alias `{}` is applied to {} argument(s), but expects {}", alias, found, expected))
+                .with_labels(vec![
+                    primary(span),
+                ])
+                .with_notes(vec![
+                    String::from("A type alias must be applied to exactly as many types as it has parameters."),
+                ]),
+            ParseError::TypeAliasInvalidRowTail { alias, param, span } => Diagnostic::error()
+                .with_message(format!("invalid argument for the parameter `{}` of the type alias `{}`", param, alias))
+                .with_labels(vec![
+                    primary(span),
+                ])
+                .with_notes(vec![
+                    format!("`{}` is used as the tail of a record type or of an enum type in the definition of `{}`.", param, alias),
+                    String::from("The corresponding argument must be a record type, respectively an enum type, or a type variable."),
+                ]),
         };
 
         vec![diagnostic]
@@ -1433,6 +1573,27 @@ impl ToDiagnostic<FileId> for TypecheckError {
                 .unwrap_or_default()
         }
 
+        // Explain where the types of a mismatch come from. Origins which point to the expression
+        // being reported are skipped, as they don't add anything to the primary label.
+        fn mk_origin_labels(origins: &[TypeOrigin], span_opt: &TermPos) -> Vec<Label<FileId>> {
+            let mut spans: Vec<&RawSpan> = span_opt.as_opt_ref().into_iter().collect();
+
+            origins
+                .iter()
+                .filter_map(|TypeOrigin { ty, span }| {
+                    if spans.contains(&span) {
+                        None
+                    } else {
+                        spans.push(span);
+                        Some(
+                            secondary(span)
+                                .with_message(format!("inferred as `{}` because of this use", ty)),
+                        )
+                    }
+                })
+                .collect()
+        }
+
         match self {
             TypecheckError::UnboundIdentifier(ident, pos_opt) =>
             // Use the same diagnostic as `EvalError::UnboundIdentifier` for consistency.
@@ -1477,6 +1638,16 @@ impl ToDiagnostic<FileId> for TypecheckError {
                         format!("The type of the expression was inferred to be `{}`, which contains the extra tail `| Dyn`", actual),
                     ])]
             ,
+            TypecheckError::EnumRowArgMismatch(ident, expd, actual, span_opt) =>
+                vec![Diagnostic::error()
+                    .with_message(format!("type error: mismatched argument for the enum tag `{}`", ident))
+                    .with_labels(mk_expr_label(span_opt))
+                    .with_notes(vec![
+                        format!("The type of the expression was expected to be `{}`", expd),
+                        format!("The type of the expression was inferred to be `{}`", actual),
+                        format!("One of these types has `{}` carrying an argument while the other does not", ident),
+                    ])]
+            ,
 
             TypecheckError::UnboundTypeVariable(ident, span_opt) =>
                 vec![Diagnostic::error()
@@ -1486,7 +1657,7 @@ impl ToDiagnostic<FileId> for TypecheckError {
                         format!("Maybe you forgot to put a `forall {}.` somewhere in the enclosing type ?", ident),
                     ])]
             ,
-            TypecheckError::TypeMismatch(expd, actual, span_opt) => {
+            TypecheckError::TypeMismatch(expd, actual, origins, span_opt) => {
                 fn addendum(ty: &Types) -> &str {
                     if ty.0.is_flat() {
                         " (a contract)"
@@ -1500,10 +1671,13 @@ impl ToDiagnostic<FileId> for TypecheckError {
                     "These types are not compatible"
                 };
 
+                let mut labels = mk_expr_label(span_opt);
+                labels.extend(mk_origin_labels(origins, span_opt));
+
                 vec![
                     Diagnostic::error()
                         .with_message("incompatible types")
-                        .with_labels(mk_expr_label(span_opt))
+                        .with_labels(labels)
                         .with_notes(vec![
                             format!("The type of the expression was expected to be `{}`{}", expd, addendum(expd)),
                             format!("The type of the expression was inferred to be `{}`{}", actual, addendum(actual)),
@@ -1526,7 +1700,7 @@ impl ToDiagnostic<FileId> for TypecheckError {
                             String::from("Enum row types and record row types are not compatible"),
                         ])]
             }
-            TypecheckError::RowMismatch(ident, expd, actual, err_, span_opt) => {
+            TypecheckError::RowMismatch(ident, expd, actual, err_, origins, span_opt) => {
                 // If the unification error is on a nested field, we will have a succession of
                 // `RowMismatch` errors wrapping the underlying error. In this case, instead of
                 // showing a cascade of similar error messages, we determine the full path of the
@@ -1534,9 +1708,11 @@ impl ToDiagnostic<FileId> for TypecheckError {
                 // error followed by the underlying error.
                 let mut err = (*err_).clone();
                 let mut path = vec![*ident];
+                let mut origins = origins.clone();
 
-                while let TypecheckError::RowMismatch(id_next, _, _, next, _) = *err {
+                while let TypecheckError::RowMismatch(id_next, _, _, next, next_origins, _) = *err {
                     path.push(id_next);
+                    origins.extend(next_origins);
                     err = next;
                 }
 
@@ -1566,9 +1742,12 @@ impl ToDiagnostic<FileId> for TypecheckError {
                     default_msg("inferred", &expd)
                 };
 
+                let mut labels = mk_expr_label(span_opt);
+                labels.extend(mk_origin_labels(&origins, span_opt));
+
                 let mut diags = vec![Diagnostic::error()
                     .with_message("incompatible rows declaration")
-                    .with_labels(mk_expr_label(span_opt))
+                    .with_labels(labels)
                     .with_notes(vec![
                         note1,
                         note2,
@@ -1615,6 +1794,12 @@ impl ToDiagnostic<FileId> for TypecheckError {
                 ];
                 labels.extend(mk_expr_label(span_opt));
 
+                // If the underlying error is a type mismatch, we still explain where its types
+                // come from.
+                if let TypecheckError::TypeMismatch(_, _, origins, _) = err.as_ref() {
+                    labels.extend(mk_origin_labels(origins, span_opt));
+                }
+
                 let mut diags = vec![Diagnostic::error()
                     .with_message("function types mismatch")
                     .with_labels(labels)
@@ -1631,7 +1816,7 @@ impl ToDiagnostic<FileId> for TypecheckError {
                 match err.as_ref() {
                     // If the underlying error is a type mismatch, printing won't add any useful
                     // information, so we just ignore it.
-                    TypecheckError::TypeMismatch(_, _, _) => (),
+                    TypecheckError::TypeMismatch(..) => (),
                     err => {
                         diags.extend(err.to_diagnostic(files, contract_id).into_iter()
                             .map(|mut diag| {
@@ -1696,6 +1881,17 @@ impl ToDiagnostic<FileId> for ImportError {
 
                 diagnostic
             }
+            ImportError::Denied(path, reason, span_opt) => {
+                let labels = span_opt
+                    .as_opt_ref()
+                    .map(|span| vec![primary(span).with_message("imported here")])
+                    .unwrap_or_default();
+
+                vec![Diagnostic::error()
+                    .with_message(format!("import of {} denied", path))
+                    .with_labels(labels)
+                    .with_notes(vec![reason.clone()])]
+            }
         }
     }
 }
@@ -2,9 +2,13 @@ use std::fmt::Debug;
 
 use super::{
     lazy::{BlackholedError, Thunk, ThunkState, ThunkUpdateFrame},
-    IdentKind,
+    Environment, IdentKind,
+};
+use crate::{
+    eval::Closure,
+    identifier::Ident,
+    term::{BindingType, RichTerm},
 };
-use crate::{eval::Closure, identifier::Ident, term::BindingType};
 
 pub trait Cache: Clone {
     type UpdateIndex; // Temporary: we won't really need that once an alternative caching mechanism gets implemented
@@ -24,10 +28,22 @@ pub trait Cache: Clone {
         -> CacheIndex;
     // TODO: Needs a better name
     fn build_cached(&mut self, idx: &mut CacheIndex, rec_env: &[(Ident, CacheIndex)]);
+    fn saturate<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
+        &mut self,
+        idx: CacheIndex,
+        env: &mut Environment,
+        fields: I,
+    ) -> RichTerm;
+    /// Return the number of elements allocated in this cache so far. Used to gather statistics
+    /// (see [crate::stats]).
+    fn thunks_created(&self) -> usize;
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct CBNCache {}
+pub struct CBNCache {
+    /// The number of thunks allocated through this cache.
+    thunks_created: usize,
+}
 
 pub type CacheIndex = Thunk;
 
@@ -57,6 +73,7 @@ impl Cache for CBNCache {
     }
 
     fn add(&mut self, clos: Closure, kind: IdentKind, bty: BindingType) -> CacheIndex {
+        self.thunks_created += 1;
         match bty {
             BindingType::Normal => Thunk::new(clos, kind),
             BindingType::Revertible(deps) => Thunk::new_rev(clos, kind, deps),
@@ -76,7 +93,7 @@ impl Cache for CBNCache {
     }
 
     fn new() -> Self {
-        CBNCache {}
+        CBNCache { thunks_created: 0 }
     }
 
     fn reset_index_state(&self, idx: &mut Self::UpdateIndex) {
@@ -88,10 +105,25 @@ impl Cache for CBNCache {
         idx: &CacheIndex,
         f: F,
     ) -> CacheIndex {
+        self.thunks_created += 1;
         idx.map(f)
     }
 
     fn build_cached(&mut self, idx: &mut CacheIndex, rec_env: &[(Ident, CacheIndex)]) {
         idx.build_cached(rec_env)
     }
+
+    fn saturate<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
+        &mut self,
+        idx: CacheIndex,
+        env: &mut Environment,
+        fields: I,
+    ) -> RichTerm {
+        self.thunks_created += 1;
+        idx.saturate(env, fields)
+    }
+
+    fn thunks_created(&self) -> usize {
+        self.thunks_created
+    }
 }
@@ -4,7 +4,7 @@
 use super::IdentKind;
 use crate::{
     identifier::Ident,
-    position::{RawSpan, TermPos},
+    position::{RawSpan, SpanMap, TermPos},
 };
 use codespan::FileId;
 
@@ -237,3 +237,86 @@ impl From<CallStack> for Vec<StackElem> {
         cs.0
     }
 }
+
+/// Reconstruct the full paths of the record fields being evaluated from the call stack.
+///
+/// The call stack only records the fields being currently evaluated, which isn't always the whole
+/// path: when a record is deeply evaluated, as for an export, its subfields are entered after the
+/// parent field has been popped. The path of a field is thus reconstructed from the path of the
+/// field whose definition contains the record it belongs to, which requires to remember the paths
+/// of the fields entered so far, indexed by the span of their definition.
+#[derive(Clone, Default, Debug)]
+pub struct FieldPaths(SpanMap<Vec<Ident>>);
+
+impl FieldPaths {
+    pub fn new() -> Self {
+        FieldPaths(SpanMap::new())
+    }
+
+    /// Return the path of the innermost field being evaluated, if any, and record the paths of the
+    /// fields currently on the call stack.
+    pub fn current(&mut self, call_stack: &CallStack) -> Option<Vec<Ident>> {
+        self.current_filtered(call_stack, |_| true)
+    }
+
+    /// Same as [Self::current], but only consider the fields whose definition satisfies `keep`.
+    /// Fields without position are always considered.
+    pub fn current_filtered<F>(&mut self, call_stack: &CallStack, keep: F) -> Option<Vec<Ident>>
+    where
+        F: Fn(&RawSpan) -> bool,
+    {
+        let kept = |pos_field: &TermPos| match pos_field.as_opt_ref() {
+            Some(span) => keep(span),
+            None => true,
+        };
+
+        // Fast path: the innermost field has already been seen.
+        let innermost = call_stack.0.iter().rev().find_map(|elem| match elem {
+            StackElem::Field { pos_field, .. } if kept(pos_field) => Some(pos_field),
+            _ => None,
+        })?;
+
+        if let Some(path) = innermost.as_opt_ref().and_then(|span| self.0.get(span)) {
+            return Some(path.clone());
+        }
+
+        let mut current: Option<Vec<Ident>> = None;
+
+        for elem in call_stack.0.iter() {
+            if let StackElem::Field {
+                id,
+                pos_record,
+                pos_field,
+                ..
+            } = elem
+            {
+                if !kept(pos_field) {
+                    continue;
+                }
+
+                if let Some(path) = pos_field.as_opt_ref().and_then(|span| self.0.get(span)) {
+                    current = Some(path.clone());
+                    continue;
+                }
+
+                // The parent is the innermost known field whose definition contains the record.
+                let parent = pos_record.as_opt_ref().and_then(|span_record| {
+                    self.0
+                        .innermost_containing(span_record)
+                        .map(|(_, path)| path.clone())
+                });
+
+                let mut path = parent.or_else(|| current.take()).unwrap_or_default();
+                path.push(*id);
+
+                if let Some(span) = pos_field.as_opt_ref() {
+                    self.0.insert(*span, path.clone());
+                }
+
+                current = Some(path);
+            }
+        }
+
+        current
+    }
+}
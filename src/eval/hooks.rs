@@ -0,0 +1,41 @@
+//! Hooks into the main loop of the virtual machine.
+//!
+//! Hooks are used by tools which need to observe the evaluation step by step, such as the
+//! interactive debugger (see [crate::debugger]) or the profiler (see [crate::profiler]). They are
+//! only called when installed via [super::VirtualMachine::set_hooks], and don't have any effect on
+//! the evaluation itself.
+use super::{callstack::CallStack, lazy::ThunkState, Closure, Environment};
+use crate::{cache::ImportResolver, identifier::Ident, label::Label, position::TermPos};
+
+/// A snapshot of the state of the virtual machine, right before a reduction step.
+pub struct Step<'a> {
+    /// The closure about to be reduced.
+    pub closure: &'a Closure,
+    /// The initial environment, holding the standard library.
+    pub initial_env: &'a Environment,
+    /// The current call stack.
+    pub call_stack: &'a CallStack,
+    /// The import resolver, which can be used to map positions back to the sources.
+    pub resolver: &'a dyn ImportResolver,
+}
+
+/// Callbacks invoked by the virtual machine during evaluation.
+pub trait EvalHooks {
+    /// Called at each iteration of the main loop of
+    /// [super::VirtualMachine::eval_closure], before reducing the current closure.
+    fn on_step(&mut self, step: &Step);
+
+    /// Called when a variable is about to be entered, with the state of its thunk before the
+    /// access. An evaluated thunk means that the value is taken from the cache.
+    fn on_thunk_access(&mut self, _id: Ident, _state: ThunkState) {}
+
+    /// Called when a contract is applied, with the label of the application and the position of
+    /// the contract.
+    fn on_contract(
+        &mut self,
+        _label: &Label,
+        _contract_pos: TermPos,
+        _resolver: &dyn ImportResolver,
+    ) {
+    }
+}
@@ -59,7 +59,7 @@ use crate::term::record::{self, RecordData};
 use crate::term::{
     make as mk_term,
     record::{FieldDeps, RecordAttrs},
-    BinaryOp, Contract, MetaValue, RichTerm, SharedTerm, Term,
+    BinaryOp, BindingType, Contract, MetaValue, RichTerm, SharedTerm, Term,
 };
 use crate::transform::Closurizable;
 use std::collections::HashMap;
@@ -150,7 +150,7 @@ pub fn merge<C: Cache>(
             }
         }
         (Term::Num(n1), Term::Num(n2)) => {
-            if (n1 - n2).abs() < f64::EPSILON {
+            if n1 == n2 {
                 Ok(Closure::atomic_closure(RichTerm::new(
                     Term::Num(n1),
                     pos_op.into_inherited(),
@@ -434,7 +434,15 @@ pub fn merge<C: Cache>(
             for (field, (t1, t2)) in center.into_iter() {
                 m.insert(
                     field,
-                    fields_merge_closurize(&mut env, t1, &env1, t2, &env2, field_names.iter())?,
+                    fields_merge_closurize(
+                        cache,
+                        &mut env,
+                        t1,
+                        &env1,
+                        t2,
+                        &env2,
+                        field_names.iter(),
+                    )?,
                 );
             }
 
@@ -488,7 +496,7 @@ pub fn merge<C: Cache>(
 ///
 /// - the term is given by `t1` in its environment `env1`
 /// - the contracts are given as an iterator `it2` together with their environment `env2`
-fn cross_apply_contracts<'a, C: Cache>(
+pub(crate) fn cross_apply_contracts<'a, C: Cache>(
     cache: &mut C,
     t1: RichTerm,
     env1: &Environment,
@@ -529,7 +537,8 @@ fn merge_doc(doc1: Option<String>, doc2: Option<String>) -> Option<String> {
 /// If the expression is not a variable referring to a thunk (this can happen e.g. for numeric
 /// constants), we just return the term as it is, which falls into the zero dependencies special
 /// case.
-fn saturate<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
+fn saturate<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone, C: Cache>(
+    cache: &mut C,
     rt: RichTerm,
     env: &mut Environment,
     local_env: &Environment,
@@ -541,7 +550,7 @@ fn saturate<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
             .cloned()
             .ok_or(EvalError::UnboundIdentifier(*var_id, rt.pos))?;
 
-        Ok(thunk.saturate(env, fields).with_pos(rt.pos))
+        Ok(cache.saturate(thunk, env, fields).with_pos(rt.pos))
     } else {
         Ok(rt)
     }
@@ -568,7 +577,8 @@ fn field_deps(rt: &RichTerm, local_env: &Environment) -> Result<FieldDeps, EvalE
 ///
 /// The fields are saturated (see [saturate]) to properly propagate recursive dependencies down to
 /// `t1` and `t2` in the final, merged record.
-fn fields_merge_closurize<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
+fn fields_merge_closurize<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone, C: Cache>(
+    cache: &mut C,
     env: &mut Environment,
     t1: RichTerm,
     env1: &Environment,
@@ -581,8 +591,8 @@ fn fields_merge_closurize<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
     let combined_deps = field_deps(&t1, env1)?.union(field_deps(&t2, env2)?);
     let body = RichTerm::from(Term::Op2(
         BinaryOp::Merge(),
-        saturate(t1, &mut local_env, env1, fields.clone())?,
-        saturate(t2, &mut local_env, env2, fields)?,
+        saturate(cache, t1, &mut local_env, env1, fields.clone())?,
+        saturate(cache, t2, &mut local_env, env2, fields)?,
     ));
 
     // We closurize the final result in a thunk with appropriate dependencies
@@ -595,7 +605,11 @@ fn fields_merge_closurize<'a, I: DoubleEndedIterator<Item = &'a Ident> + Clone>(
     // new_rev takes care of not creating a revertible thunk if the dependencies are empty.
     env.insert(
         fresh_var,
-        Thunk::new_rev(closure, IdentKind::Record, combined_deps),
+        cache.add(
+            closure,
+            IdentKind::Record,
+            BindingType::Revertible(combined_deps),
+        ),
     );
 
     Ok(RichTerm::from(Term::Var(fresh_var)))
@@ -105,16 +105,20 @@ use crate::{
 pub mod cache;
 pub mod callstack;
 pub mod fixpoint;
+pub mod hooks;
 pub mod lazy;
 pub mod merge;
 pub mod operation;
 pub mod stack;
+pub mod trace;
 
 use callstack::*;
 use codespan::FileId;
+use hooks::{EvalHooks, Step};
 use lazy::*;
 use operation::OperationCont;
 use stack::Stack;
+use trace::TraceSink;
 
 use self::cache::{Cache, CacheIndex};
 
@@ -148,6 +152,12 @@ pub struct VirtualMachine<R: ImportResolver, C: Cache> {
     import_resolver: R,
     // The evaluation cache.
     pub cache: C,
+    // The destination of the messages emitted by the `trace` primitive operation.
+    trace_sink: TraceSink,
+    // Callbacks invoked during evaluation, used by the debugger.
+    hooks: Option<Box<dyn EvalHooks>>,
+    // The maximum size reached by the stack since the last reset.
+    peak_stack_size: usize,
 }
 
 impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
@@ -158,6 +168,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             call_stack: Default::default(),
             stack: Stack::new(),
             cache: Cache::new(),
+            trace_sink: trace::stderr_sink(),
+            hooks: None,
+            peak_stack_size: 0,
         }
     }
 
@@ -168,6 +181,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             call_stack: Default::default(),
             stack: Stack::new(),
             cache,
+            trace_sink: trace::stderr_sink(),
+            hooks: None,
+            peak_stack_size: 0,
         }
     }
 
@@ -177,6 +193,17 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         self.call_stack.0.clear();
         self.stack.reset(&mut self.cache);
         self.cache = Cache::new();
+        self.peak_stack_size = 0;
+    }
+
+    /// Return the maximum size reached by the stack since the last reset.
+    pub fn peak_stack_size(&self) -> usize {
+        self.peak_stack_size
+    }
+
+    /// Return the number of thunks created since the last reset.
+    pub fn thunks_created(&self) -> usize {
+        self.cache.thunks_created()
     }
 
     fn set_mode(&mut self, new_mode: EvalMode) {
@@ -187,6 +214,16 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         self.eval_mode = new_mode;
     }
 
+    /// Set the destination of the messages emitted by the `trace` primitive operation.
+    pub fn set_trace_sink(&mut self, sink: TraceSink) {
+        self.trace_sink = sink;
+    }
+
+    /// Install callbacks to be invoked during evaluation, or remove them if `hooks` is `None`.
+    pub fn set_hooks(&mut self, hooks: Option<Box<dyn EvalHooks>>) {
+        self.hooks = hooks;
+    }
+
     pub fn import_resolver(&self) -> &R {
         &self.import_resolver
     }
@@ -280,6 +317,17 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         initial_env: &Environment,
     ) -> Result<(RichTerm, Environment), EvalError> {
         loop {
+            self.peak_stack_size = self.peak_stack_size.max(self.stack.len());
+
+            if let Some(hooks) = self.hooks.as_mut() {
+                hooks.on_step(&Step {
+                    closure: &clos,
+                    initial_env,
+                    call_stack: &self.call_stack,
+                    resolver: &self.import_resolver,
+                });
+            }
+
             let Closure {
                 body:
                     RichTerm {
@@ -337,6 +385,10 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         .ok_or(EvalError::UnboundIdentifier(*x, pos))?;
                     std::mem::drop(env); // idx may be a 1RC pointer
 
+                    if let Some(hooks) = self.hooks.as_mut() {
+                        hooks.on_thunk_access(*x, idx.state());
+                    }
+
                     match self.cache.get_update_index(&mut idx) {
                         Ok(Some(idx_upd)) => self.stack.push_update_index(idx_upd),
                         Ok(None) => {}
@@ -386,6 +438,43 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         env,
                     }
                 }
+                Term::LetBlock(bindings, t, LetAttrs { binding_type, rec }) => {
+                    let indices: Vec<(Ident, CacheIndex)> = bindings
+                        .iter()
+                        .map(|(x, s)| {
+                            let closure = Closure {
+                                body: s.clone(),
+                                env: env.clone(),
+                            };
+
+                            (
+                                *x,
+                                self.cache
+                                    .add(closure, IdentKind::Let, binding_type.clone()),
+                            )
+                        })
+                        .collect();
+
+                    // Patch the environment of each binding with all the bindings of the block
+                    if *rec {
+                        for (_, idx) in indices.iter() {
+                            self.cache.patch(idx.clone(), |cl| {
+                                for (x, idx_) in indices.iter() {
+                                    cl.env.insert(*x, idx_.clone());
+                                }
+                            });
+                        }
+                    }
+
+                    for (x, idx) in indices {
+                        env.insert(x, idx);
+                    }
+
+                    Closure {
+                        body: t.clone(),
+                        env,
+                    }
+                }
 
                 Term::Op1(op, t) => {
                     self.set_mode(op.eval_mode());
@@ -653,6 +742,27 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         return Ok((RichTerm::new(Term::Fun(*x, t.clone()), pos), env));
                     }
                 }
+                // Applying an enum tag to an argument builds an enum variant carrying this
+                // argument. The argument isn't evaluated, but bound to a fresh variable, as for
+                // a function call.
+                Term::Enum(tag) => {
+                    if let Some((idx, _)) = self.stack.pop_arg_as_idx(&mut self.cache) {
+                        let fresh_var = Ident::fresh();
+                        env.insert(fresh_var, idx);
+                        Closure {
+                            body: RichTerm::new(
+                                Term::EnumVariant {
+                                    tag: *tag,
+                                    arg: RichTerm::new(Term::Var(fresh_var), pos.into_inherited()),
+                                },
+                                pos,
+                            ),
+                            env,
+                        }
+                    } else {
+                        return Ok((RichTerm::new(Term::Enum(*tag), pos), env));
+                    }
+                }
                 // A match expression acts as a function (in Nickel, a match expression corresponds
                 // to the cases, and doesn't include the examined value).
                 //
@@ -852,6 +962,8 @@ pub fn subst<C: Cache>(
         | v @ Term::ParseError(_)
         | v @ Term::Bool(_)
         | v @ Term::Num(_)
+        | v @ Term::DateTime(_)
+        | v @ Term::Duration(_)
         | v @ Term::Str(_)
         // Do not substitute under lambdas: mutually recursive function could cause an infinite
         // loop. Although avoidable, this requires some care and is not currently needed.
@@ -867,8 +979,18 @@ pub fn subst<C: Cache>(
 
             RichTerm::new(Term::Let(id, t1, t2, attrs), pos)
         }
+        Term::LetBlock(bindings, t, attrs) => {
+            let bindings = bindings
+                .into_iter()
+                .map(|(id, t)| (id, subst(cache, t, initial_env, env)))
+                .collect();
+            let t = subst(cache, t, initial_env, env);
+
+            RichTerm::new(Term::LetBlock(bindings, t, attrs), pos)
+        }
         p @ Term::LetPattern(..) => panic!("Pattern {:?} has not been transformed before evaluation", p),
         p @ Term::FunPattern(..) => panic!("Pattern {:?} has not been transformed before evaluation", p),
+        p @ Term::MatchPattern(..) => panic!("Pattern {:?} has not been transformed before evaluation", p),
         Term::App(t1, t2) => {
             let t1 = subst(cache, t1, initial_env, env);
             let t2 = subst(cache, t2, initial_env, env);
@@ -890,6 +1012,11 @@ pub fn subst<C: Cache>(
 
             RichTerm::new(Term::Match {cases, default}, pos)
         }
+        Term::EnumVariant { tag, arg } => {
+            let arg = subst(cache, arg, initial_env, env);
+
+            RichTerm::new(Term::EnumVariant { tag, arg }, pos)
+        }
         Term::Op1(op, t) => {
             let t = subst(cache, t, initial_env, env);
 
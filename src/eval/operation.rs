@@ -9,31 +9,38 @@
 use super::{
     callstack, is_empty_optional, merge,
     merge::{merge, MergeMode},
-    subst, Closure, Environment, ImportResolver, VirtualMachine,
+    subst,
+    trace::TraceEvent,
+    Closure, Environment, ImportResolver, VirtualMachine,
 };
 
 use crate::{
+    encoding::Encoding,
     error::{EvalError, IllegalPolymorphicTailAction},
     eval,
     eval::Cache,
+    format::{self, FormatArgs},
+    hash::{self, HashAlgorithm},
     identifier::Ident,
     label::ty_path,
     match_sharedterm, mk_app, mk_fun, mk_opn, mk_record,
-    position::TermPos,
+    position::{RawSpan, TermPos},
     serialize,
     serialize::ExportFormat,
     stdlib::internals,
     term::{
-        array::{Array, ArrayAttrs},
-        make as mk_term,
+        array::{Array, ArrayAttrs, ArrayMaskOp},
+        datetime, make as mk_term,
+        number::{self, Number},
         record::{self, RecordData},
-        BinaryOp, MergePriority, MetaValue, NAryOp, PendingContract, RichTerm, SharedTerm,
-        StrChunk, Term, UnaryOp,
+        BinaryOp, CompiledRegex, MergePriority, MetaValue, NAryOp, PendingContract, RichTerm,
+        SharedTerm, StrChunk, Term, UnaryOp,
     },
     transform::{apply_contracts::apply_contracts, Closurizable},
 };
 
-use md5::digest::Digest;
+use codespan::ByteOffset;
+use malachite_base::num::conversion::traits::IsInteger;
 
 use simple_counter::*;
 use unicode_segmentation::UnicodeSegmentation;
@@ -57,6 +64,18 @@ enum EqResult {
     Eqs(RichTerm, RichTerm, Vec<(Closure, Closure)>),
 }
 
+/// Result of the comparison of two terms.
+///
+/// Similar to [`EqResult`], the comparison of two arrays or two records generates the
+/// comparisons of their elements: the first one is encoded as the two first parameters of
+/// `Cmps`, while the (potentially empty) vector holds the following ones, in order.
+///
+/// See [`compare`].
+enum CmpResult {
+    Ordering(std::cmp::Ordering),
+    Cmps(RichTerm, RichTerm, Vec<(Closure, Closure)>),
+}
+
 /// An operation continuation as stored on the stack.
 #[derive(PartialEq, Clone)]
 pub enum OperationCont {
@@ -197,7 +216,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Term::Num(_) => "Num",
                     Term::Bool(_) => "Bool",
                     Term::Str(_) => "Str",
-                    Term::Enum(_) => "Enum",
+                    Term::DateTime(_) => "DateTime",
+                    Term::Duration(_) => "Duration",
+                    Term::Enum(_) | Term::EnumVariant { .. } => "Enum",
                     Term::Fun(..) | Term::Match { .. } => "Fun",
                     Term::Array(..) => "Array",
                     Term::Record(..) | Term::RecRecord(..) => "Record",
@@ -289,7 +310,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
             },
             UnaryOp::Embed(_id) => {
-                if let Term::Enum(_) = &*t {
+                if let Term::Enum(_) | Term::EnumVariant { .. } = &*t {
                     Ok(Closure::atomic_closure(RichTerm {
                         term: t,
                         pos: pos_op_inh,
@@ -318,6 +339,10 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 } else {
                     None
                 };
+                let cases_is_empty = matches!(
+                    cases_closure.body.as_ref(),
+                    Term::Record(r) if r.fields.is_empty()
+                );
 
                 if let Term::Enum(en) = &*t {
                     let Closure {
@@ -340,19 +365,20 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             env: cases_env,
                         })
                         .or(default)
-                        .ok_or_else(||
-                        // ? We should have a dedicated error for unmatched pattern
-                        EvalError::TypeError(
-                            String::from("Enum"),
-                            String::from("match"),
-                            arg_pos,
-                            RichTerm {
-                                term: t,
-                                pos,
-                            },
-                        ))
+                        .ok_or_else(|| EvalError::NonExhaustiveMatch {
+                            value: RichTerm { term: t, pos },
+                            pos: pos_op,
+                        })
                 } else if let Some(clos) = default {
                     Ok(clos)
+                } else if cases_is_empty || matches!(&*t, Term::EnumVariant { .. }) {
+                    // A match expression without any enum case is the fallback of a general
+                    // pattern matching, whose branches have already been tried. Enum variants
+                    // carrying an argument never match a bare tag case.
+                    Err(EvalError::NonExhaustiveMatch {
+                        value: RichTerm { term: t, pos },
+                        pos: pos_op,
+                    })
                 } else {
                     Err(EvalError::TypeError(
                         String::from("Enum"),
@@ -475,6 +501,53 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
+            UnaryOp::StaticAccessOr(path) => {
+                let (default, default_pos) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from("(.) or"), pos_op))?;
+
+                if let Term::Record(record) = &*t {
+                    let (id, rest) = path
+                        .split_first()
+                        .expect("a field path with default must be non-empty");
+
+                    match record.fields.get(id) {
+                        Some(e) if !is_empty_optional(&self.cache, e, &env) => {
+                            self.call_stack.enter_field(*id, pos, e.pos, pos_op);
+
+                            if rest.is_empty() {
+                                Ok(Closure {
+                                    body: e.clone(),
+                                    env,
+                                })
+                            } else {
+                                // Continue with the rest of the path once the field has been
+                                // evaluated.
+                                self.stack.push_arg(default, default_pos);
+                                Ok(Closure {
+                                    body: RichTerm::new(
+                                        Term::Op1(
+                                            UnaryOp::StaticAccessOr(rest.to_vec()),
+                                            e.clone(),
+                                        ),
+                                        pos_op,
+                                    ),
+                                    env,
+                                })
+                            }
+                        }
+                        _ => Ok(default),
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Record"),
+                        String::from("field access"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
             UnaryOp::FieldsOf() => match_sharedterm! {t, with {
                     Term::Record(record) => {
                         let mut fields: Vec<String> = record.fields
@@ -579,17 +652,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     .pop_arg(&self.cache)
                     .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from("generate"), pos_op))?;
 
-                if let Term::Num(n) = *t {
-                    let n_int = n as usize;
-                    if n < 0.0 || n.fract() != 0.0 {
-                        Err(EvalError::Other(
-                            format!(
-                            "generate: expected the 1st agument to be a positive integer, got {}",
-                            n
-                        ),
-                            pos_op,
-                        ))
-                    } else {
+                if let Term::Num(n) = &*t {
+                    if let Ok(n_int) = usize::try_from(n) {
                         let mut shared_env = Environment::new();
                         let f_as_var = f.body.closurize(&mut self.cache, &mut env, f.env);
 
@@ -598,7 +662,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         // currently, variables).
                         let ts = (0..n_int)
                             .map(|n| {
-                                mk_app!(f_as_var.clone(), Term::Num(n as f64)).closurize(
+                                mk_app!(f_as_var.clone(), Term::Num(Number::from(n))).closurize(
                                     &mut self.cache,
                                     &mut shared_env,
                                     env.clone(),
@@ -613,6 +677,14 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             ),
                             env: shared_env,
                         })
+                    } else {
+                        Err(EvalError::Other(
+                            format!(
+                            "generate: expected the 1st agument to be a positive integer, got {}",
+                            number::format(n)
+                        ),
+                            pos_op,
+                        ))
                     }
                 } else {
                     Err(EvalError::TypeError(
@@ -623,195 +695,638 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
-            UnaryOp::RecordMap() => {
-                let (f, ..) = self.stack.pop_arg(&self.cache).ok_or_else(|| {
-                    EvalError::NotEnoughArgs(2, String::from("recordMap"), pos_op)
-                })?;
+            UnaryOp::ArrayReverse() => {
+                if let Term::Array(ts, attrs) = &*t {
+                    let ts = ts.iter().rev().cloned().collect();
 
-                match_sharedterm! {t, with {
-                        Term::Record(record) => {
-                            // While it's certainly possible to allow mapping over
-                            // a record with a sealed tail, it's not entirely obvious
-                            // how that should behave. It's also not clear that this
-                            // is something users will actually need to do, so we've
-                            // decided to prevent this until we have a clearer idea
-                            // of potential use-cases.
-                            if let Some(record::SealedTail { label, .. }) = record.sealed_tail {
-                                return Err(EvalError::IllegalPolymorphicTailAccess {
-                                    action: IllegalPolymorphicTailAction::Map,
-                                    label,
-                                    call_stack: std::mem::take(&mut self.call_stack),
-                                })
-                            }
+                    Ok(Closure {
+                        body: RichTerm::new(Term::Array(ts, attrs.clone()), pos_op_inh),
+                        env,
+                    })
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Array"),
+                        String::from("reverse"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::ArrayFlatten() => {
+                if let Term::Array(ts, attrs) = &*t {
+                    // The inner arrays can be concatenated directly if each element is bound to an
+                    // array and there's no pending contract to apply to the elements first.
+                    let inner_arrays: Option<Vec<_>> = if attrs.pending_contracts.is_empty() {
+                        ts.iter()
+                            .map(|elem| match elem.as_ref() {
+                                Term::Var(id) => env.get(id).cloned(),
+                                _ => None,
+                            })
+                            .map(|idx| {
+                                let Closure { body, env } = self.cache.get(idx?);
+                                match body.term.into_owned() {
+                                    Term::Array(ts, attrs) => Some((ts, attrs, env, body.pos)),
+                                    _ => None,
+                                }
+                            })
+                            .collect()
+                    } else {
+                        None
+                    };
 
-                            let mut shared_env = Environment::new();
-                            let f_as_var = f.body.closurize(&mut self.cache, &mut env, f.env);
+                    if let Some(inner_arrays) = inner_arrays {
+                        // As for `ArrayConcat`, the contracts common to all the inner arrays stay
+                        // pending, while the other ones are applied to the elements.
+                        let ctrs_common: Vec<PendingContract> = match inner_arrays.split_first() {
+                            Some(((_, first_attrs, ..), rest)) => first_attrs
+                                .pending_contracts
+                                .iter()
+                                .filter(|ctr| {
+                                    rest.iter()
+                                        .all(|(_, attrs, ..)| attrs.pending_contracts.contains(ctr))
+                                })
+                                .cloned()
+                                .collect(),
+                            None => Vec::new(),
+                        };
 
-                            // As for `ArrayMap` (see above), we closurize the content of fields
-                            let record = record.map_fields_without_optionals(&mut self.cache, &mut shared_env, &env, |id, t| {
-                                let pos = t.pos.into_inherited();
+                        let mut shared_env = Environment::new();
+                        let mut result = Vec::new();
 
-                                mk_app!(f_as_var.clone(), mk_term::string(id.label()), t)
-                                    .with_pos(pos)
-                            });
+                        for (inner_ts, inner_attrs, inner_env, inner_pos) in inner_arrays {
+                            let ctrs: Vec<_> = inner_attrs
+                                .pending_contracts
+                                .into_iter()
+                                .filter(|ctr| !ctrs_common.contains(ctr))
+                                .collect();
 
-                            Ok(Closure {
-                                body: RichTerm::new(Term::Record(record), pos_op_inh),
-                                env: shared_env,
-                            })
+                            result.extend(inner_ts.into_iter().map(|t| {
+                                apply_contracts(t, ctrs.iter().cloned(), inner_pos.into_inherited())
+                                    .closurize(&mut self.cache, &mut shared_env, inner_env.clone())
+                            }));
                         }
-                    } else {
-                        Err(EvalError::TypeError(
-                            String::from("Record"),
-                            String::from("map on record"),
-                            arg_pos,
-                            RichTerm { term: t, pos },
-                        ))
-                    }
-                }
-            }
-            UnaryOp::Seq() => {
-                if self.stack.count_args() >= 1 {
-                    let (next, ..) = self
-                        .stack
-                        .pop_arg(&self.cache)
-                        .expect("Condition already checked.");
-                    Ok(next)
-                } else {
-                    Err(EvalError::NotEnoughArgs(2, String::from("seq"), pos_op))
-                }
-            }
-            UnaryOp::DeepSeq(_) => {
-                /// Build a RichTerm that forces a given list of terms, and at the end resumes the
-                /// evaluation of the argument on the top of the stack. The argument must iterate over
-                /// a tuple, which first element is an optional call stack element to add to the
-                /// callstack before starting evaluation. This is a temporary fix to have reasonable
-                /// missing definition error when deepsequing a record.
-                ///
-                /// Requires its first argument to be non-empty.
-                fn seq_terms<I>(mut it: I, pos_op_inh: TermPos) -> RichTerm
-                where
-                    I: Iterator<Item = (Option<callstack::StackElem>, RichTerm)>,
-                {
-                    let (first_elem, first) = it
-                        .next()
-                        .expect("expected the argument to be a non-empty iterator");
 
-                    it.fold(
-                        mk_term::op1(UnaryOp::DeepSeq(first_elem), first).with_pos(pos_op_inh),
-                        |acc, (elem, t)| {
-                            mk_app!(mk_term::op1(UnaryOp::DeepSeq(elem), t), acc)
-                                .with_pos(pos_op_inh)
-                        },
-                    )
-                }
+                        let attrs = ArrayAttrs::new()
+                            .closurized()
+                            .with_extra_contracts(ctrs_common);
 
-                match t.into_owned() {
-                    Term::Record(record) if !record.fields.is_empty() => {
-                        let pos_record = pos;
-                        let pos_access = pos_op;
-                        let terms = record
-                            .fields
-                            .into_iter()
-                            // We ignore empty optional fields
-                            .filter(|(_, t)| !is_empty_optional(&self.cache, t, &env))
-                            .map(|(id, t)| {
-                                (
-                                    Some(callstack::StackElem::Field {
-                                        id,
-                                        pos_record,
-                                        pos_field: t.pos,
-                                        pos_access,
-                                    }),
-                                    t,
-                                )
-                            });
                         Ok(Closure {
-                            body: seq_terms(terms, pos_op),
-                            env,
+                            body: RichTerm::new(
+                                Term::Array(Array::new(Rc::from(result)), attrs),
+                                pos_op_inh,
+                            ),
+                            env: shared_env,
                         })
-                    }
-                    Term::Array(ts, attrs) if !ts.is_empty() => {
+                    } else {
+                        // Otherwise, we force each element to an array using `@ []`, which always
+                        // stores a bare array in the thunk of its result (whereas forcing the
+                        // element directly could leave metadata around), and flatten again.
                         let mut shared_env = Environment::new();
-                        let terms =
-                            seq_terms(
-                                ts.into_iter().map(|t| {
-                                    let t_with_ctr = apply_contracts(
-                                        t,
+                        let ts: Array =
+                            ts.iter()
+                                .map(|t| {
+                                    // `ArrayConcat` copies the environment of its second operand,
+                                    // so we give each wrapper a minimal one.
+                                    let mut elem_env = Environment::new();
+                                    let t_with_ctrs = apply_contracts(
+                                        t.clone(),
                                         attrs.pending_contracts.iter().cloned(),
                                         pos.into_inherited(),
                                     )
-                                    .closurize(&mut self.cache, &mut shared_env, env.clone());
-                                    (None, t_with_ctr)
-                                }),
-                                pos_op,
-                            );
+                                    .closurize(&mut self.cache, &mut elem_env, env.clone());
+
+                                    mk_term::op2(
+                                        BinaryOp::ArrayConcat(),
+                                        t_with_ctrs,
+                                        Term::Array(
+                                            Array::default(),
+                                            ArrayAttrs::new().closurized(),
+                                        ),
+                                    )
+                                    .closurize(
+                                        &mut self.cache,
+                                        &mut shared_env,
+                                        elem_env,
+                                    )
+                                })
+                                .collect();
 
-                        Ok(Closure {
-                            body: terms,
-                            env: shared_env,
-                        })
-                    }
-                    _ => {
-                        if let Some((next, ..)) = self.stack.pop_arg(&self.cache) {
-                            Ok(next)
-                        } else {
-                            Err(EvalError::NotEnoughArgs(2, String::from("deepSeq"), pos_op))
-                        }
-                    }
-                }
-            }
-            UnaryOp::ArrayHead() => {
-                if let Term::Array(ts, attrs) = &*t {
-                    if let Some(head) = ts.get(0) {
-                        let head_with_ctr = apply_contracts(
-                            head.clone(),
-                            attrs.pending_contracts.iter().cloned(),
-                            pos.into_inherited(),
+                        let cont = mk_term::op1(
+                            UnaryOp::ArrayFlatten(),
+                            Term::Array(ts.clone(), ArrayAttrs::new().closurized()),
                         );
+                        let body = ts
+                            .into_iter()
+                            .fold(cont, |acc, t| mk_app!(mk_term::op1(UnaryOp::Seq(), t), acc))
+                            .with_pos(pos_op_inh);
 
                         Ok(Closure {
-                            body: head_with_ctr,
-                            env,
+                            body,
+                            env: shared_env,
                         })
-                    } else {
-                        Err(EvalError::Other(String::from("head: empty array"), pos_op))
                     }
                 } else {
                     Err(EvalError::TypeError(
                         String::from("Array"),
-                        String::from("head"),
+                        String::from("flatten"),
                         arg_pos,
                         RichTerm { term: t, pos },
                     ))
                 }
             }
-            UnaryOp::ArrayTail() => match_sharedterm! {t, with {
+            UnaryOp::ArrayFoldl() => {
+                let (f, _) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(3, String::from("foldl"), pos_op))?;
+                let (acc, _) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(3, String::from("foldl"), pos_op))?;
+
+                match_sharedterm! {t, with {
                         Term::Array(ts, attrs) => {
-                            if !ts.is_empty() {
-                                Ok(Closure {
-                                    body: RichTerm::new(Term::Array(ts.advance_by(1), attrs), pos_op_inh),
-                                    env,
-                                })
-                            } else {
-                                Err(EvalError::Other(String::from("tail: empty array"), pos_op))
+                            if ts.is_empty() {
+                                return Ok(acc);
                             }
+
+                            // We perform one step of the fold, that is we evaluate to
+                            // `%seq% acc' (%array_foldl% tail f acc')` where `acc' = f acc head`.
+                            // The environment is built from scratch at each step, so that it
+                            // doesn't grow with the length of the array.
+                            let mut step_env = Environment::new();
+
+                            let head = apply_contracts(
+                                ts.get(0).unwrap().clone(),
+                                attrs.pending_contracts.iter().cloned(),
+                                pos.into_inherited(),
+                            )
+                            .closurize(&mut self.cache, &mut step_env, env.clone());
+                            let tail = RichTerm::new(Term::Array(ts.advance_by(1), attrs), pos)
+                                .closurize(&mut self.cache, &mut step_env, env);
+                            let f_as_var = f.body.closurize(&mut self.cache, &mut step_env, f.env);
+                            let acc_as_var = acc.body.closurize(&mut self.cache, &mut step_env, acc.env);
+
+                            let with_env = step_env.clone();
+                            let next_acc = mk_app!(f_as_var.clone(), acc_as_var, head)
+                                .closurize(&mut self.cache, &mut step_env, with_env);
+
+                            Ok(Closure {
+                                body: mk_app!(
+                                    mk_term::op1(UnaryOp::Seq(), next_acc.clone()),
+                                    mk_app!(mk_term::op1(UnaryOp::ArrayFoldl(), tail), f_as_var, next_acc)
+                                )
+                                .with_pos(pos_op_inh),
+                                env: step_env,
+                            })
                         }
                     } else {
                         Err(EvalError::TypeError(
                             String::from("Array"),
-                            String::from("tail"),
+                            String::from("foldl, 3rd argument"),
                             arg_pos,
                             RichTerm { term: t, pos },
                         ))
                     }
-            },
-            UnaryOp::ArrayLength() => {
-                if let Term::Array(ts, _) = &*t {
-                    // A num does not have any free variable so we can drop the environment
-                    Ok(Closure {
-                        body: RichTerm::new(Term::Num(ts.len() as f64), pos_op_inh),
-                        env: Environment::new(),
+                }
+            }
+            UnaryOp::ArrayFilter() | UnaryOp::ArrayPartition() => {
+                let (op_name, mask_op) = if let UnaryOp::ArrayFilter() = u_op {
+                    ("filter", ArrayMaskOp::Filter)
+                } else {
+                    ("partition", ArrayMaskOp::Partition)
+                };
+
+                let (pred, _) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from(op_name), pos_op))?;
+
+                match_sharedterm! {t, with {
+                        Term::Array(ts, attrs) => {
+                            let pred_as_var = pred.body.closurize(&mut self.cache, &mut env, pred.env);
+                            let mut mask_env = Environment::new();
+
+                            let mask = ts
+                                .iter()
+                                .map(|t| {
+                                    let t_with_ctrs = apply_contracts(
+                                        t.clone(),
+                                        attrs.pending_contracts.iter().cloned(),
+                                        pos.into_inherited(),
+                                    );
+
+                                    mk_app!(pred_as_var.clone(), t_with_ctrs)
+                                        .closurize(&mut self.cache, &mut mask_env, env.clone())
+                                })
+                                .collect();
+
+                            Ok(mk_array_masked(
+                                &mut self.cache,
+                                mask_op,
+                                pred_as_var,
+                                RichTerm::new(Term::Array(ts, attrs), pos),
+                                env,
+                                mask,
+                                mask_env,
+                                pos_op_inh,
+                            ))
+                        }
+                    } else {
+                        Err(EvalError::TypeError(
+                            String::from("Array"),
+                            format!("{op_name}, 2nd argument"),
+                            arg_pos,
+                            RichTerm { term: t, pos },
+                        ))
+                    }
+                }
+            }
+            UnaryOp::ArraySort() => {
+                let (cmp, _) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from("sort"), pos_op))?;
+
+                match_sharedterm! {t, with {
+                        Term::Array(ts, attrs) => {
+                            if ts.len() <= 1 {
+                                return Ok(Closure {
+                                    body: RichTerm::new(Term::Array(ts, attrs), pos),
+                                    env,
+                                });
+                            }
+
+                            let cmp_as_var = cmp.body.closurize(&mut self.cache, &mut env, cmp.env);
+                            let segments = vec![(0, ts.len())];
+                            let (mask, mask_env) = mk_sort_mask(
+                                &mut self.cache,
+                                &cmp_as_var,
+                                &ts,
+                                &attrs,
+                                &env,
+                                &segments,
+                                pos,
+                            );
+
+                            Ok(mk_array_masked(
+                                &mut self.cache,
+                                ArrayMaskOp::Sort(segments),
+                                cmp_as_var,
+                                RichTerm::new(Term::Array(ts, attrs), pos),
+                                env,
+                                mask,
+                                mask_env,
+                                pos_op_inh,
+                            ))
+                        }
+                    } else {
+                        Err(EvalError::TypeError(
+                            String::from("Array"),
+                            String::from("sort, 2nd argument"),
+                            arg_pos,
+                            RichTerm { term: t, pos },
+                        ))
+                    }
+                }
+            }
+            UnaryOp::ArraySortBy() => {
+                let (f, _) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from("sort_by"), pos_op))?;
+
+                match_sharedterm! {t, with {
+                        Term::Array(ts, attrs) => {
+                            // Sort with the comparison function `fun x y => %compare% (f x) (f y)`.
+                            let f_as_var = f.body.closurize(&mut self.cache, &mut env, f.env);
+                            let (x, y) = (Ident::fresh(), Ident::fresh());
+                            let cmp = mk_fun!(
+                                x,
+                                y,
+                                mk_term::op2(
+                                    BinaryOp::Compare(),
+                                    mk_app!(f_as_var.clone(), Term::Var(x)),
+                                    mk_app!(f_as_var, Term::Var(y))
+                                )
+                            );
+
+                            Ok(Closure {
+                                body: mk_app!(
+                                    mk_term::op1(
+                                        UnaryOp::ArraySort(),
+                                        RichTerm::new(Term::Array(ts, attrs), pos)
+                                    ),
+                                    cmp
+                                )
+                                .with_pos(pos_op_inh),
+                                env,
+                            })
+                        }
+                    } else {
+                        Err(EvalError::TypeError(
+                            String::from("Array"),
+                            String::from("sort_by, 2nd argument"),
+                            arg_pos,
+                            RichTerm { term: t, pos },
+                        ))
+                    }
+                }
+            }
+            UnaryOp::ArraySortOn() | UnaryOp::ArrayMinBy() | UnaryOp::ArrayMaxBy() => {
+                let (op_name, mask_op) = match u_op {
+                    UnaryOp::ArraySortOn() => ("sort_on", ArrayMaskOp::SortOn),
+                    UnaryOp::ArrayMinBy() => ("min_by", ArrayMaskOp::MinBy),
+                    _ => ("max_by", ArrayMaskOp::MaxBy),
+                };
+
+                let (f, _) = self
+                    .stack
+                    .pop_arg(&self.cache)
+                    .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from(op_name), pos_op))?;
+
+                match_sharedterm! {t, with {
+                        Term::Array(ts, attrs) => {
+                            if ts.is_empty() && mask_op != ArrayMaskOp::SortOn {
+                                return Err(EvalError::Other(
+                                    format!("{op_name}: empty array"),
+                                    pos_op,
+                                ));
+                            }
+
+                            // The mask holds the key of each element, which is fully evaluated
+                            // before the second stage compares the keys.
+                            let f_as_var = f.body.closurize(&mut self.cache, &mut env, f.env);
+                            let mut mask_env = Environment::new();
+
+                            let mask = ts
+                                .iter()
+                                .map(|t| {
+                                    let t_with_ctrs = apply_contracts(
+                                        t.clone(),
+                                        attrs.pending_contracts.iter().cloned(),
+                                        pos.into_inherited(),
+                                    );
+
+                                    mk_app!(f_as_var.clone(), t_with_ctrs)
+                                        .closurize(&mut self.cache, &mut mask_env, env.clone())
+                                })
+                                .collect();
+
+                            Ok(mk_array_masked(
+                                &mut self.cache,
+                                mask_op,
+                                f_as_var,
+                                RichTerm::new(Term::Array(ts, attrs), pos),
+                                env,
+                                mask,
+                                mask_env,
+                                pos_op_inh,
+                            ))
+                        }
+                    } else {
+                        Err(EvalError::TypeError(
+                            String::from("Array"),
+                            format!("{op_name}, 2nd argument"),
+                            arg_pos,
+                            RichTerm { term: t, pos },
+                        ))
+                    }
+                }
+            }
+            UnaryOp::RecordMap() => {
+                let (f, ..) = self.stack.pop_arg(&self.cache).ok_or_else(|| {
+                    EvalError::NotEnoughArgs(2, String::from("recordMap"), pos_op)
+                })?;
+
+                match_sharedterm! {t, with {
+                        Term::Record(record) => {
+                            // While it's certainly possible to allow mapping over
+                            // a record with a sealed tail, it's not entirely obvious
+                            // how that should behave. It's also not clear that this
+                            // is something users will actually need to do, so we've
+                            // decided to prevent this until we have a clearer idea
+                            // of potential use-cases.
+                            if let Some(record::SealedTail { label, .. }) = record.sealed_tail {
+                                return Err(EvalError::IllegalPolymorphicTailAccess {
+                                    action: IllegalPolymorphicTailAction::Map,
+                                    label,
+                                    call_stack: std::mem::take(&mut self.call_stack),
+                                })
+                            }
+
+                            let mut shared_env = Environment::new();
+                            let f_as_var = f.body.closurize(&mut self.cache, &mut env, f.env);
+
+                            // As for `ArrayMap` (see above), we closurize the content of fields
+                            let record = record.map_fields_without_optionals(&mut self.cache, &mut shared_env, &env, |id, t| {
+                                let pos = t.pos.into_inherited();
+
+                                mk_app!(f_as_var.clone(), mk_term::string(id.label()), t)
+                                    .with_pos(pos)
+                            });
+
+                            Ok(Closure {
+                                body: RichTerm::new(Term::Record(record), pos_op_inh),
+                                env: shared_env,
+                            })
+                        }
+                    } else {
+                        Err(EvalError::TypeError(
+                            String::from("Record"),
+                            String::from("map on record"),
+                            arg_pos,
+                            RichTerm { term: t, pos },
+                        ))
+                    }
+                }
+            }
+            UnaryOp::Seq() => {
+                if self.stack.count_args() >= 1 {
+                    let (next, ..) = self
+                        .stack
+                        .pop_arg(&self.cache)
+                        .expect("Condition already checked.");
+                    Ok(next)
+                } else {
+                    Err(EvalError::NotEnoughArgs(2, String::from("seq"), pos_op))
+                }
+            }
+            UnaryOp::Trace() => {
+                if let Term::Str(message) = &*t {
+                    if self.stack.count_args() >= 1 {
+                        let (next, ..) = self
+                            .stack
+                            .pop_arg(&self.cache)
+                            .expect("Condition already checked.");
+
+                        // The message usually comes from the caller, while the operator is
+                        // located in the stdlib: the position of the former is more relevant.
+                        let pos = if pos.as_opt_ref().is_some() {
+                            pos
+                        } else {
+                            pos_op
+                        };
+                        let location = pos.as_opt_ref().and_then(|span| {
+                            let (line, col) = self.import_resolver.get_location(span)?;
+                            let path = self.import_resolver.get_path(span.src_id);
+                            Some(format!("{}:{}:{}", path.to_string_lossy(), line, col))
+                        });
+
+                        (self.trace_sink)(TraceEvent {
+                            message: message.clone(),
+                            pos,
+                            location,
+                        });
+
+                        Ok(next)
+                    } else {
+                        Err(EvalError::NotEnoughArgs(2, String::from("trace"), pos_op))
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("trace, 1st argument"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::DeepSeq(_) => {
+                /// Build a RichTerm that forces a given list of terms, and at the end resumes the
+                /// evaluation of the argument on the top of the stack. The argument must iterate over
+                /// a tuple, which first element is an optional call stack element to add to the
+                /// callstack before starting evaluation. This is a temporary fix to have reasonable
+                /// missing definition error when deepsequing a record.
+                ///
+                /// Requires its first argument to be non-empty.
+                fn seq_terms<I>(mut it: I, pos_op_inh: TermPos) -> RichTerm
+                where
+                    I: Iterator<Item = (Option<callstack::StackElem>, RichTerm)>,
+                {
+                    let (first_elem, first) = it
+                        .next()
+                        .expect("expected the argument to be a non-empty iterator");
+
+                    it.fold(
+                        mk_term::op1(UnaryOp::DeepSeq(first_elem), first).with_pos(pos_op_inh),
+                        |acc, (elem, t)| {
+                            mk_app!(mk_term::op1(UnaryOp::DeepSeq(elem), t), acc)
+                                .with_pos(pos_op_inh)
+                        },
+                    )
+                }
+
+                match t.into_owned() {
+                    Term::Record(record) if !record.fields.is_empty() => {
+                        let pos_record = pos;
+                        let pos_access = pos_op;
+                        let terms = record
+                            .fields
+                            .into_iter()
+                            // We ignore empty optional fields
+                            .filter(|(_, t)| !is_empty_optional(&self.cache, t, &env))
+                            .map(|(id, t)| {
+                                (
+                                    Some(callstack::StackElem::Field {
+                                        id,
+                                        pos_record,
+                                        pos_field: t.pos,
+                                        pos_access,
+                                    }),
+                                    t,
+                                )
+                            });
+                        Ok(Closure {
+                            body: seq_terms(terms, pos_op),
+                            env,
+                        })
+                    }
+                    Term::Array(ts, attrs) if !ts.is_empty() => {
+                        let mut shared_env = Environment::new();
+                        let terms =
+                            seq_terms(
+                                ts.into_iter().map(|t| {
+                                    let t_with_ctr = apply_contracts(
+                                        t,
+                                        attrs.pending_contracts.iter().cloned(),
+                                        pos.into_inherited(),
+                                    )
+                                    .closurize(&mut self.cache, &mut shared_env, env.clone());
+                                    (None, t_with_ctr)
+                                }),
+                                pos_op,
+                            );
+
+                        Ok(Closure {
+                            body: terms,
+                            env: shared_env,
+                        })
+                    }
+                    Term::EnumVariant { arg, .. } => Ok(Closure {
+                        body: seq_terms(std::iter::once((None, arg)), pos_op),
+                        env,
+                    }),
+                    _ => {
+                        if let Some((next, ..)) = self.stack.pop_arg(&self.cache) {
+                            Ok(next)
+                        } else {
+                            Err(EvalError::NotEnoughArgs(2, String::from("deepSeq"), pos_op))
+                        }
+                    }
+                }
+            }
+            UnaryOp::ArrayHead() => {
+                if let Term::Array(ts, attrs) = &*t {
+                    if let Some(head) = ts.get(0) {
+                        let head_with_ctr = apply_contracts(
+                            head.clone(),
+                            attrs.pending_contracts.iter().cloned(),
+                            pos.into_inherited(),
+                        );
+
+                        Ok(Closure {
+                            body: head_with_ctr,
+                            env,
+                        })
+                    } else {
+                        Err(EvalError::Other(String::from("head: empty array"), pos_op))
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Array"),
+                        String::from("head"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::ArrayTail() => match_sharedterm! {t, with {
+                        Term::Array(ts, attrs) => {
+                            if !ts.is_empty() {
+                                Ok(Closure {
+                                    body: RichTerm::new(Term::Array(ts.advance_by(1), attrs), pos_op_inh),
+                                    env,
+                                })
+                            } else {
+                                Err(EvalError::Other(String::from("tail: empty array"), pos_op))
+                            }
+                        }
+                    } else {
+                        Err(EvalError::TypeError(
+                            String::from("Array"),
+                            String::from("tail"),
+                            arg_pos,
+                            RichTerm { term: t, pos },
+                        ))
+                    }
+            },
+            UnaryOp::ArrayLength() => {
+                if let Term::Array(ts, _) = &*t {
+                    // A num does not have any free variable so we can drop the environment
+                    Ok(Closure {
+                        body: RichTerm::new(Term::Num(Number::from(ts.len())), pos_op_inh),
+                        env: Environment::new(),
                     })
                 } else {
                     Err(EvalError::TypeError(
@@ -856,98 +1371,252 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Str(acc),
                             pos_op_inh,
-                        )))
+                        )))
+                    }
+                } else {
+                    // Since the error halts the evaluation, we don't bother cleaning the stack of the
+                    // remaining string chunks.
+                    Err(EvalError::TypeError(
+                        String::from("String"),
+                        String::from("interpolated string"),
+                        pos_op,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::StrTrim() => {
+                if let Term::Str(s) = &*t {
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Str(String::from(s.trim())),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("trim"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::StrChars() => {
+                if let Term::Str(s) = &*t {
+                    let ts = s
+                        .chars()
+                        .map(|c| RichTerm::from(Term::Str(c.to_string())))
+                        .collect();
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Array(ts, ArrayAttrs::new().closurized()),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("chars"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::CharCode() => {
+                if let Term::Str(s) = &*t {
+                    if s.len() == 1 {
+                        let code = s.chars().next().unwrap() as u32;
+                        Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Num(Number::from(code)),
+                            pos_op_inh,
+                        )))
+                    } else {
+                        Err(EvalError::Other(
+                            format!("charCode: expected 1-char string, got `{}`", s.len()),
+                            pos,
+                        ))
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("charCode"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::CharFromCode() => {
+                if let Term::Num(code) = &*t {
+                    if !code.is_integer() {
+                        Err(EvalError::Other(format!("charFromCode: expected the agument to be an integer, got the non-integer value {}", number::format(code)), pos_op))
+                    } else {
+                        match u32::try_from(code).map(std::char::from_u32) {
+                            Err(_) => Err(EvalError::Other(format!("charFromCode: code out of bounds. Expected a value between 0 and {}, got {}", u32::MAX, code), pos_op)),
+                            Ok(Some(car)) => Ok(Closure::atomic_closure(RichTerm::new(
+                                Term::Str(String::from(car)),
+                                pos_op_inh,
+                            ))),
+                            Ok(None) => Err(EvalError::Other(
+                                format!("charFromCode: invalid character code {}", code),
+                                pos_op,
+                            )),
+                        }
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Num"),
+                        String::from("charFromCode"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::DateTimeParse() => {
+                if let Term::Str(s) = &*t {
+                    match datetime::parse(s) {
+                        Some(dt) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::DateTime(dt),
+                            pos_op_inh,
+                        ))),
+                        None => Err(EvalError::Other(
+                            format!("datetimeParse: invalid RFC 3339 datetime `{}`", s),
+                            pos_op,
+                        )),
                     }
                 } else {
-                    // Since the error halts the evaluation, we don't bother cleaning the stack of the
-                    // remaining string chunks.
                     Err(EvalError::TypeError(
-                        String::from("String"),
-                        String::from("interpolated string"),
-                        pos_op,
+                        String::from("Str"),
+                        String::from("datetimeParse"),
+                        arg_pos,
                         RichTerm { term: t, pos },
                     ))
                 }
             }
-            UnaryOp::StrTrim() => {
-                if let Term::Str(s) = &*t {
-                    Ok(Closure::atomic_closure(RichTerm::new(
-                        Term::Str(String::from(s.trim())),
-                        pos_op_inh,
-                    )))
+            UnaryOp::DateTimeToStr() => {
+                if let Term::DateTime(dt) = &*t {
+                    match datetime::format(dt) {
+                        Ok(s) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Str(s),
+                            pos_op_inh,
+                        ))),
+                        Err(err) => {
+                            Err(EvalError::Other(format!("datetimeToStr: {}", err), pos_op))
+                        }
+                    }
                 } else {
                     Err(EvalError::TypeError(
-                        String::from("Str"),
-                        String::from("trim"),
+                        String::from("DateTime"),
+                        String::from("datetimeToStr"),
                         arg_pos,
                         RichTerm { term: t, pos },
                     ))
                 }
             }
-            UnaryOp::StrChars() => {
-                if let Term::Str(s) = &*t {
-                    let ts = s
-                        .chars()
-                        .map(|c| RichTerm::from(Term::Str(c.to_string())))
-                        .collect();
-
+            UnaryOp::DateTimeToTimestamp() => {
+                if let Term::DateTime(dt) = &*t {
                     Ok(Closure::atomic_closure(RichTerm::new(
-                        Term::Array(ts, ArrayAttrs::new().closurized()),
+                        Term::Num(datetime::to_timestamp(dt)),
                         pos_op_inh,
                     )))
                 } else {
                     Err(EvalError::TypeError(
-                        String::from("Str"),
-                        String::from("chars"),
+                        String::from("DateTime"),
+                        String::from("datetimeToTimestamp"),
                         arg_pos,
                         RichTerm { term: t, pos },
                     ))
                 }
             }
-            UnaryOp::CharCode() => {
+            UnaryOp::DateTimeFromTimestamp() => {
+                if let Term::Num(n) = &*t {
+                    match datetime::from_timestamp(n) {
+                        Some(dt) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::DateTime(dt),
+                            pos_op_inh,
+                        ))),
+                        None => Err(EvalError::Other(
+                            format!(
+                                "datetimeFromTimestamp: timestamp {} is out of range",
+                                number::format(n)
+                            ),
+                            pos_op,
+                        )),
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Num"),
+                        String::from("datetimeFromTimestamp"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::DurationParse() => {
                 if let Term::Str(s) = &*t {
-                    if s.len() == 1 {
-                        let code = (s.chars().next().unwrap() as u32) as f64;
-                        Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Num(code),
+                    match datetime::parse_duration(s) {
+                        Ok(d) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Duration(d),
                             pos_op_inh,
-                        )))
-                    } else {
-                        Err(EvalError::Other(
-                            format!("charCode: expected 1-char string, got `{}`", s.len()),
-                            pos,
-                        ))
+                        ))),
+                        Err(err) => {
+                            Err(EvalError::Other(format!("durationParse: {}", err), pos_op))
+                        }
                     }
                 } else {
                     Err(EvalError::TypeError(
                         String::from("Str"),
-                        String::from("charCode"),
+                        String::from("durationParse"),
                         arg_pos,
                         RichTerm { term: t, pos },
                     ))
                 }
             }
-            UnaryOp::CharFromCode() => {
-                if let Term::Num(code) = *t {
-                    if code.fract() != 0.0 {
-                        Err(EvalError::Other(format!("charFromCode: expected the agument to be an integer, got the floating-point value {}", code), pos_op))
-                    } else if code < 0.0 || code > (u32::MAX as f64) {
-                        Err(EvalError::Other(format!("charFromCode: code out of bounds. Expected a value between 0 and {}, got {}", u32::MAX, code), pos_op))
-                    } else if let Some(car) = std::char::from_u32(code as u32) {
-                        Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Str(String::from(car)),
+            UnaryOp::DurationToStr() => {
+                if let Term::Duration(d) = &*t {
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Str(datetime::format_duration(d)),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Duration"),
+                        String::from("durationToStr"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::DurationToSeconds() => {
+                if let Term::Duration(d) = &*t {
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Num(datetime::duration_to_seconds(d)),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Duration"),
+                        String::from("durationToSeconds"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::DurationFromSeconds() => {
+                if let Term::Num(n) = &*t {
+                    match datetime::duration_from_seconds(n) {
+                        Some(d) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Duration(d),
                             pos_op_inh,
-                        )))
-                    } else {
-                        Err(EvalError::Other(
-                            format!("charFromCode: invalid character code {}", code),
+                        ))),
+                        None => Err(EvalError::Other(
+                            format!(
+                                "durationFromSeconds: {} seconds is out of range",
+                                number::format(n)
+                            ),
                             pos_op,
-                        ))
+                        )),
                     }
                 } else {
                     Err(EvalError::TypeError(
                         String::from("Num"),
-                        String::from("charFromCode"),
+                        String::from("durationFromSeconds"),
                         arg_pos,
                         RichTerm { term: t, pos },
                     ))
@@ -987,7 +1656,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 if let Term::Str(s) = &*t {
                     let length = s.graphemes(true).count();
                     Ok(Closure::atomic_closure(RichTerm::new(
-                        Term::Num(length as f64),
+                        Term::Num(Number::from(length)),
                         pos_op_inh,
                     )))
                 } else {
@@ -1001,7 +1670,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::ToStr() => {
                 let result = match_sharedterm! {t, with {
-                    Term::Num(n) => Ok(Term::Str(n.to_string())),
+                    Term::Num(n) => Ok(Term::Str(number::format(&n))),
                     Term::Str(s) => Ok(Term::Str(s)),
                     Term::Bool(b) => Ok(Term::Str(b.to_string())),
                     Term::Enum(id) => Ok(Term::Str(id.to_string())),
@@ -1019,7 +1688,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::NumFromStr() => {
                 if let Term::Str(s) = &*t {
-                    let n = s.parse::<f64>().map_err(|_| {
+                    let n = number::parse(s).ok_or_else(|| {
                         EvalError::Other(format!("numFrom: invalid num literal `{}`", s), pos)
                     })?;
                     Ok(Closure::atomic_closure(RichTerm::new(
@@ -1035,6 +1704,51 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
+            UnaryOp::EnumGetTag() => match &*t {
+                Term::Enum(_) => Ok(Closure::atomic_closure(RichTerm::new(
+                    t.into_owned(),
+                    pos_op_inh,
+                ))),
+                Term::EnumVariant { tag, .. } => Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Enum(*tag),
+                    pos_op_inh,
+                ))),
+                _ => Err(EvalError::TypeError(
+                    String::from("Enum"),
+                    String::from("enum_get_tag"),
+                    arg_pos,
+                    RichTerm { term: t, pos },
+                )),
+            },
+            UnaryOp::EnumGetArg() => {
+                if let Term::EnumVariant { arg, .. } = &*t {
+                    Ok(Closure {
+                        body: arg.clone(),
+                        env,
+                    })
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Enum"),
+                        String::from("enum_get_arg"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::EnumIsVariant() => match &*t {
+                Term::Enum(_) | Term::EnumVariant { .. } => {
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Bool(matches!(&*t, Term::EnumVariant { .. })),
+                        pos_op_inh,
+                    )))
+                }
+                _ => Err(EvalError::TypeError(
+                    String::from("Enum"),
+                    String::from("enum_is_variant"),
+                    arg_pos,
+                    RichTerm { term: t, pos },
+                )),
+            },
             UnaryOp::EnumFromStr() => {
                 if let Term::Str(s) = &*t {
                     Ok(Closure::atomic_closure(RichTerm::new(
@@ -1052,21 +1766,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::StrIsMatch() => {
                 if let Term::Str(s) = &*t {
-                    let re = regex::Regex::new(s)
+                    let matcher = compile_regex(s, UnaryOp::StrIsMatchCompiled, pos_op_inh)
                         .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
 
-                    let param = Ident::fresh();
-                    let matcher = Term::Fun(
-                        param,
-                        RichTerm::new(
-                            Term::Op1(
-                                UnaryOp::StrIsMatchCompiled(re.into()),
-                                RichTerm::new(Term::Var(param), pos_op_inh),
-                            ),
-                            pos_op_inh,
-                        ),
-                    );
-
                     Ok(Closure::atomic_closure(RichTerm::new(matcher, pos)))
                 } else {
                     Err(EvalError::TypeError(
@@ -1079,21 +1781,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::StrFind() => {
                 if let Term::Str(s) = &*t {
-                    let re = regex::Regex::new(s)
+                    let matcher = compile_regex(s, UnaryOp::StrFindCompiled, pos_op_inh)
                         .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
 
-                    let param = Ident::fresh();
-                    let matcher = Term::Fun(
-                        param,
-                        RichTerm::new(
-                            Term::Op1(
-                                UnaryOp::StrFindCompiled(re.into()),
-                                RichTerm::new(Term::Var(param), pos_op_inh),
-                            ),
-                            pos_op_inh,
-                        ),
-                    );
-
                     Ok(Closure::atomic_closure(RichTerm::new(matcher, pos)))
                 } else {
                     Err(EvalError::TypeError(
@@ -1104,6 +1794,37 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
+            UnaryOp::StrFindAll() => {
+                if let Term::Str(s) = &*t {
+                    let matcher = compile_regex(s, UnaryOp::StrFindAllCompiled, pos_op_inh)
+                        .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
+
+                    Ok(Closure::atomic_closure(RichTerm::new(matcher, pos)))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("str_find_all"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::StrReplaceRegexWith() => {
+                if let Term::Str(s) = &*t {
+                    let replacer =
+                        compile_regex(s, UnaryOp::StrReplaceRegexWithCompiled, pos_op_inh)
+                            .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
+
+                    Ok(Closure::atomic_closure(RichTerm::new(replacer, pos)))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("str_replace_regex_with"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
             UnaryOp::StrIsMatchCompiled(regex) => {
                 if let Term::Str(s) = &*t {
                     Ok(Closure::atomic_closure(RichTerm::new(
@@ -1121,34 +1842,18 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::StrFindCompiled(regex) => {
                 if let Term::Str(s) = &*t {
-                    let capt = regex.captures(s);
-                    let result = if let Some(capt) = capt {
-                        let first_match = capt.get(0).unwrap();
-                        let groups = capt
-                            .iter()
-                            .skip(1)
-                            .filter_map(|s_opt| {
-                                s_opt.map(|s| RichTerm::from(Term::Str(String::from(s.as_str()))))
-                            })
-                            .collect();
-
-                        mk_record!(
-                            ("matched", Term::Str(String::from(first_match.as_str()))),
-                            ("index", Term::Num(first_match.start() as f64)),
-                            (
-                                "groups",
-                                Term::Array(groups, ArrayAttrs::new().closurized())
-                            )
-                        )
+                    let result = if let Some(capt) = regex.captures(s) {
+                        mk_match_record(&regex, &capt)
                     } else {
                         //FIXME: what should we return when there's no match?
                         mk_record!(
                             ("matched", Term::Str(String::new())),
-                            ("index", Term::Num(-1.)),
+                            ("index", Term::Num(Number::from(-1))),
                             (
                                 "groups",
                                 Term::Array(Array::default(), ArrayAttrs::default())
-                            )
+                            ),
+                            ("named", Term::Record(RecordData::empty()))
                         )
                     };
 
@@ -1162,6 +1867,66 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
+            UnaryOp::StrFindAllCompiled(regex) => {
+                if let Term::Str(s) = &*t {
+                    let matches = regex
+                        .captures_iter(s)
+                        .map(|capt| mk_match_record(&regex, &capt))
+                        .collect();
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Array(matches, ArrayAttrs::new().closurized()),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("str_find_all_compiled"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
+            UnaryOp::StrReplaceRegexWithCompiled(regex) => {
+                if let Term::Str(s) = &*t {
+                    // We return a function of the replacer, which is the concatenation of the
+                    // parts of the string that didn't match and of the application of the
+                    // replacer to each match.
+                    let replacer = Ident::fresh();
+                    let mut pieces = Vec::new();
+                    let mut last_end = 0;
+
+                    for capt in regex.captures_iter(s) {
+                        // unwrap(): the group 0 always corresponds to the whole match
+                        let whole = capt.get(0).unwrap();
+                        pieces.push(mk_term::string(&s[last_end..whole.start()]));
+                        pieces.push(mk_app!(
+                            mk_term::var(replacer),
+                            mk_match_record(&regex, &capt)
+                        ));
+                        last_end = whole.end();
+                    }
+
+                    let body = pieces
+                        .into_iter()
+                        .rev()
+                        .fold(mk_term::string(&s[last_end..]), |acc, piece| {
+                            mk_term::op2(BinaryOp::StrConcat(), piece, acc)
+                        });
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Fun(replacer, body),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("str_replace_regex_with_compiled"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            }
             UnaryOp::Force(_) => {
                 /// `Seq` the `terms` iterator and then resume evaluating the `cont` continuation.
                 fn seq_terms<I>(terms: I, pos: TermPos, cont: RichTerm) -> RichTerm
@@ -1224,6 +1989,20 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                                 body: seq_terms(terms, pos_op, cont),
                                 env: shared_env,
                             })
+                        },
+                        Term::EnumVariant { tag, arg } => {
+                            let mut shared_env = Environment::new();
+                            let arg = mk_term::op1(UnaryOp::Force(None), arg)
+                                .closurize(&mut self.cache, &mut shared_env, env);
+                            let cont = RichTerm::new(
+                                Term::EnumVariant { tag, arg: arg.clone() },
+                                pos.into_inherited(),
+                            );
+
+                            Ok(Closure {
+                                body: seq_terms(std::iter::once(arg), pos_op, cont),
+                                env: shared_env,
+                            })
                         }
                     } else Ok(Closure {
                         body: RichTerm { term : t, pos},
@@ -1237,6 +2016,64 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             UnaryOp::RecForce() => {
                 Ok(RecPriority::Top.propagate_in_term(&mut self.cache, t, env, pos))
             }
+            UnaryOp::ReplaceValue() => {
+                // The argument is below the marker restoring the evaluation mode, which has been
+                // pushed before evaluating the operand. We restore the mode right away.
+                if let Some(eval_mode) = self.stack.pop_strictness_marker() {
+                    self.eval_mode = eval_mode;
+                }
+
+                let (value, _) = self.stack.pop_arg(&self.cache).ok_or_else(|| {
+                    EvalError::NotEnoughArgs(3, String::from("record_update"), pos_op)
+                })?;
+
+                match_sharedterm! {t, with {
+                        Term::MetaValue(meta) => {
+                            let MetaValue {
+                                doc,
+                                types,
+                                contracts,
+                                priority,
+                                ..
+                            } = meta;
+
+                            // As when merging, the contracts of the original definition are
+                            // applied to the new value.
+                            let (value, mut value_env) = merge::cross_apply_contracts(
+                                &mut self.cache,
+                                value.body,
+                                &value.env,
+                                types.iter().chain(contracts.iter()),
+                                &env,
+                            )?;
+
+                            let contracts = contracts
+                                .into_iter()
+                                .map(|ctr| ctr.closurize(&mut self.cache, &mut value_env, env.clone()))
+                                .collect();
+                            let types =
+                                types.map(|ctr| ctr.closurize(&mut self.cache, &mut value_env, env));
+
+                            Ok(Closure {
+                                body: RichTerm::new(
+                                    Term::MetaValue(MetaValue {
+                                        doc,
+                                        types,
+                                        contracts,
+                                        opt: false,
+                                        priority,
+                                        value: Some(value),
+                                    }),
+                                    pos_op_inh,
+                                ),
+                                env: value_env,
+                            })
+                        }
+                    } else {
+                        Ok(value)
+                    }
+                }
+            }
             UnaryOp::RecordEmptyWithTail() => match_sharedterm! { t,
                 with {
                     Term::Record(r) => {
@@ -1320,8 +2157,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::Plus() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Num(n1 + n2),
                             pos_op_inh,
@@ -1350,8 +2187,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::Sub() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Num(n1 - n2),
                             pos_op_inh,
@@ -1380,8 +2217,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::Mult() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Num(n1 * n2),
                             pos_op_inh,
@@ -1410,9 +2247,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::Div() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
-                        if n2 == 0.0 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
+                        if *n2 == 0u8 {
                             Err(EvalError::Other(String::from("division by zero"), pos_op))
                         } else {
                             Ok(Closure::atomic_closure(RichTerm::new(
@@ -1444,12 +2281,15 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::Modulo() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
-                        Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Num(n1 % n2),
-                            pos_op_inh,
-                        )))
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
+                        match number::rem(n1, n2) {
+                            Some(result) => Ok(Closure::atomic_closure(RichTerm::new(
+                                Term::Num(result),
+                                pos_op_inh,
+                            ))),
+                            None => Err(EvalError::Other(String::from("division by zero"), pos_op)),
+                        }
                     } else {
                         Err(EvalError::TypeError(
                             String::from("Num"),
@@ -1474,12 +2314,33 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::Pow() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
-                        Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Num(n1.powf(n2)),
-                            pos_op_inh,
-                        )))
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
+                        if number::exceeds_max_exponent(n2) {
+                            return Err(EvalError::Other(
+                                format!(
+                                    "pow: the exponent {} is too large (the maximum is {})",
+                                    number::format(n2),
+                                    number::MAX_EXPONENT
+                                ),
+                                pos_op,
+                            ));
+                        }
+
+                        match number::pow(n1, n2) {
+                            Some(result) => Ok(Closure::atomic_closure(RichTerm::new(
+                                Term::Num(result),
+                                pos_op_inh,
+                            ))),
+                            None => Err(EvalError::Other(
+                                format!(
+                                    "pow: {} to the power {} is not a number",
+                                    number::format(n1),
+                                    number::format(n2)
+                                ),
+                                pos_op,
+                            )),
+                        }
                     } else {
                         Err(EvalError::TypeError(
                             String::from("Num"),
@@ -1545,6 +2406,10 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     l.arg_pos = self.cache.get_then(idx.clone(), |c| c.body.pos);
                     l.arg_idx = Some(idx);
 
+                    if let Some(hooks) = self.hooks.as_mut() {
+                        hooks.on_contract(&l, pos1, &self.import_resolver);
+                    }
+
                     self.stack.push_arg(
                         Closure::atomic_closure(RichTerm::new(Term::Lbl(l), pos2.into_inherited())),
                         pos2.into_inherited(),
@@ -1707,24 +2572,79 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             let t2 = c2.body.closurize(&mut self.cache, &mut env, c2.env);
 
                             Ok(Closure {
-                                body: RichTerm::new(Term::Op2(BinaryOp::Eq(), t1, t2), pos_op),
+                                body: RichTerm::new(Term::Op2(BinaryOp::Eq(), t1, t2), pos_op),
+                                env,
+                            })
+                        }
+                    },
+                    EqResult::Eqs(t1, t2, subeqs) => {
+                        self.stack.push_eqs(subeqs.into_iter());
+
+                        Ok(Closure {
+                            body: RichTerm::new(Term::Op2(BinaryOp::Eq(), t1, t2), pos_op),
+                            env,
+                        })
+                    }
+                }
+            }
+            BinaryOp::Compare() => {
+                let mut env = Environment::new();
+
+                let c1 = Closure {
+                    body: RichTerm {
+                        term: t1,
+                        pos: pos1,
+                    },
+                    env: env1,
+                };
+                let c2 = Closure {
+                    body: RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                    env: env2,
+                };
+
+                match compare(&mut self.cache, &mut env, c1, c2, pos_op_inh)? {
+                    CmpResult::Ordering(ord) => match (ord, self.stack.pop_cmp()) {
+                        (std::cmp::Ordering::Equal, Some((c1, c2))) => {
+                            let t1 = c1.body.closurize(&mut self.cache, &mut env, c1.env);
+                            let t2 = c2.body.closurize(&mut self.cache, &mut env, c2.env);
+
+                            Ok(Closure {
+                                body: RichTerm::new(Term::Op2(BinaryOp::Compare(), t1, t2), pos_op),
                                 env,
                             })
                         }
+                        (ord, _) => {
+                            self.stack.clear_cmps();
+                            let tag = match ord {
+                                std::cmp::Ordering::Less => "Lesser",
+                                std::cmp::Ordering::Equal => "Equal",
+                                std::cmp::Ordering::Greater => "Greater",
+                            };
+
+                            Ok(Closure::atomic_closure(RichTerm::new(
+                                Term::Enum(Ident::from(tag)),
+                                pos_op_inh,
+                            )))
+                        }
                     },
-                    EqResult::Eqs(t1, t2, subeqs) => {
-                        self.stack.push_eqs(subeqs.into_iter());
+                    CmpResult::Cmps(t1, t2, subcmps) => {
+                        // The comparisons are popped from the top of the stack, so we push them in
+                        // reverse order.
+                        self.stack.push_cmps(subcmps.into_iter().rev());
 
                         Ok(Closure {
-                            body: RichTerm::new(Term::Op2(BinaryOp::Eq(), t1, t2), pos_op),
+                            body: RichTerm::new(Term::Op2(BinaryOp::Compare(), t1, t2), pos_op),
                             env,
                         })
                     }
                 }
             }
             BinaryOp::LessThan() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Bool(n1 < n2),
                             pos_op_inh,
@@ -1753,8 +2673,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::LessOrEq() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Bool(n1 <= n2),
                             pos_op_inh,
@@ -1783,8 +2703,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::GreaterThan() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Bool(n1 > n2),
                             pos_op_inh,
@@ -1813,8 +2733,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 }
             }
             BinaryOp::GreaterOrEq() => {
-                if let Term::Num(n1) = *t1 {
-                    if let Term::Num(n2) = *t2 {
+                if let Term::Num(n1) = &*t1 {
+                    if let Term::Num(n2) = &*t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
                             Term::Bool(n1 >= n2),
                             pos_op_inh,
@@ -2014,6 +2934,62 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             },
+            BinaryOp::DynUpdate() => {
+                let (clos, _) = self.stack.pop_arg(&self.cache).ok_or_else(|| {
+                    EvalError::NotEnoughArgs(3, String::from("record_update"), pos_op)
+                })?;
+
+                if let Term::Str(id) = &*t1 {
+                    match_sharedterm! {t2, with {
+                            Term::Record(record) => {
+                                let mut fields = record.fields;
+                                let id = Ident::from(id);
+                                let value = clos.body.closurize(&mut self.cache, &mut env2, clos.env);
+
+                                // If the field is already defined, the new value is wrapped so
+                                // that it inherits the metadata of the previous definition.
+                                let value = match fields.remove(&id) {
+                                    Some(prev) => {
+                                        let with_env = env2.clone();
+                                        mk_app!(mk_term::op1(UnaryOp::ReplaceValue(), prev), value)
+                                            .closurize(&mut self.cache, &mut env2, with_env)
+                                    }
+                                    None => value,
+                                };
+
+                                fields.insert(id, value);
+                                Ok(Closure {
+                                    body: RichTerm::new(
+                                        Term::Record(RecordData { fields, ..record }),
+                                        pos_op_inh,
+                                    ),
+                                    env: env2,
+                                })
+                            }
+                        } else {
+                            Err(EvalError::TypeError(
+                                String::from("Record"),
+                                String::from("record_update"),
+                                snd_pos,
+                                RichTerm {
+                                    term: t2,
+                                    pos: pos2,
+                                },
+                            ))
+                        }
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("record_update"),
+                        fst_pos,
+                        RichTerm {
+                            term: t1,
+                            pos: pos1,
+                        },
+                    ))
+                }
+            }
             BinaryOp::HasField() => match_sharedterm! {t1, with {
                     Term::Str(id) => {
                         if let Term::Record(record) = &*t2 {
@@ -2135,10 +3111,11 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             },
             BinaryOp::ArrayElemAt() => match (&*t1, &*t2) {
                 (Term::Array(ts, attrs), Term::Num(n)) => {
-                    let n_int = *n as usize;
-                    if n.fract() != 0.0 {
-                        Err(EvalError::Other(format!("elemAt: expected the 2nd agument to be an integer, got the floating-point value {}", n), pos_op))
-                    } else if *n < 0.0 || n_int >= ts.len() {
+                    // Negative or too large indices are out of bounds anyway.
+                    let n_int = usize::try_from(n).unwrap_or(usize::MAX);
+                    if !n.is_integer() {
+                        Err(EvalError::Other(format!("elemAt: expected the 2nd agument to be an integer, got the non-integer value {}", number::format(n)), pos_op))
+                    } else if n_int >= ts.len() {
                         Err(EvalError::Other(format!("elemAt: index out of bounds. Expected a value between 0 and {}, got {}", ts.len(), n), pos_op))
                     } else {
                         let elem_with_ctr = apply_contracts(
@@ -2171,6 +3148,112 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     },
                 )),
             },
+            BinaryOp::ArrayRange() => match (&*t1, &*t2) {
+                (Term::Num(start), Term::Num(end)) => {
+                    if !start.is_integer() {
+                        Err(EvalError::Other(format!("range: expected the 1st argument (start) to be an integer, got the non-integer value {}", number::format(start)), pos_op))
+                    } else if !end.is_integer() {
+                        Err(EvalError::Other(format!("range: expected the 2nd argument (end) to be an integer, got the non-integer value {}", number::format(end)), pos_op))
+                    } else {
+                        let mut ts = Vec::new();
+                        let mut n = start.clone();
+
+                        while n < *end {
+                            ts.push(RichTerm::from(Term::Num(n.clone())));
+                            n += Number::from(1);
+                        }
+
+                        // Numbers are constants, which don't need to be closurized.
+                        Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Array(Array::new(Rc::from(ts)), ArrayAttrs::new().closurized()),
+                            pos_op_inh,
+                        )))
+                    }
+                }
+                (Term::Num(_), _) => Err(EvalError::TypeError(
+                    String::from("Num"),
+                    String::from("range, 2nd argument"),
+                    snd_pos,
+                    RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                )),
+                (_, _) => Err(EvalError::TypeError(
+                    String::from("Num"),
+                    String::from("range, 1st argument"),
+                    fst_pos,
+                    RichTerm {
+                        term: t1,
+                        pos: pos1,
+                    },
+                )),
+            },
+            BinaryOp::ArrayZip() => match (&*t1, &*t2) {
+                (Term::Array(ts1, attrs1), Term::Array(ts2, attrs2)) => {
+                    let mut shared_env = Environment::new();
+
+                    let ts = ts1
+                        .iter()
+                        .zip(ts2.iter())
+                        .map(|(fst, snd)| {
+                            let mut pair_env = Environment::new();
+                            let fst = apply_contracts(
+                                fst.clone(),
+                                attrs1.pending_contracts.iter().cloned(),
+                                pos1.into_inherited(),
+                            )
+                            .closurize(
+                                &mut self.cache,
+                                &mut pair_env,
+                                env1.clone(),
+                            );
+                            let snd = apply_contracts(
+                                snd.clone(),
+                                attrs2.pending_contracts.iter().cloned(),
+                                pos2.into_inherited(),
+                            )
+                            .closurize(
+                                &mut self.cache,
+                                &mut pair_env,
+                                env2.clone(),
+                            );
+
+                            mk_record!(("fst", fst), ("snd", snd)).closurize(
+                                &mut self.cache,
+                                &mut shared_env,
+                                pair_env,
+                            )
+                        })
+                        .collect();
+
+                    Ok(Closure {
+                        body: RichTerm::new(
+                            Term::Array(ts, ArrayAttrs::new().closurized()),
+                            pos_op_inh,
+                        ),
+                        env: shared_env,
+                    })
+                }
+                (Term::Array(..), _) => Err(EvalError::TypeError(
+                    String::from("Array"),
+                    String::from("zip, 2nd argument"),
+                    snd_pos,
+                    RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                )),
+                (_, _) => Err(EvalError::TypeError(
+                    String::from("Array"),
+                    String::from("zip, 1st argument"),
+                    fst_pos,
+                    RichTerm {
+                        term: t1,
+                        pos: pos1,
+                    },
+                )),
+            },
             BinaryOp::Merge() => merge(
                 &mut self.cache,
                 RichTerm {
@@ -2188,51 +3271,37 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 &mut self.call_stack,
             ),
 
-            BinaryOp::Hash() => {
-                let mk_err_fst = |t1| {
-                    Err(EvalError::TypeError(
-                        String::from("Enum <Md5, Sha1, Sha256, Sha512>"),
-                        String::from("hash, 1st argument"),
-                        fst_pos,
-                        RichTerm {
-                            term: t1,
-                            pos: pos1,
-                        },
-                    ))
+            BinaryOp::Hash() | BinaryOp::HashValue() => {
+                let op_name = if let BinaryOp::Hash() = b_op {
+                    "hash"
+                } else {
+                    "hash_value"
                 };
 
-                if let Term::Enum(id) = &*t1 {
-                    if let Term::Str(s) = &*t2 {
-                        let result = match id.as_ref() {
-                            "Md5" => {
-                                let mut hasher = md5::Md5::new();
-                                hasher.update(s);
-                                format!("{:x}", hasher.finalize())
-                            }
-                            "Sha1" => {
-                                let mut hasher = sha1::Sha1::new();
-                                hasher.update(s);
-                                format!("{:x}", hasher.finalize())
-                            }
-                            "Sha256" => {
-                                let mut hasher = sha2::Sha256::new();
-                                hasher.update(s);
-                                format!("{:x}", hasher.finalize())
-                            }
-                            "Sha512" => {
-                                let mut hasher = sha2::Sha512::new();
-                                hasher.update(s);
-                                format!("{:x}", hasher.finalize())
-                            }
-                            _ => return mk_err_fst(t1),
-                        };
+                let algorithm = match &*t1 {
+                    Term::Enum(id) => HashAlgorithm::from_tag(id.label()),
+                    _ => None,
+                };
 
-                        Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Str(result),
-                            pos_op_inh,
-                        )))
-                    } else {
-                        Err(EvalError::TypeError(
+                let algorithm = match algorithm {
+                    Some(algorithm) => algorithm,
+                    None => {
+                        return Err(EvalError::TypeError(
+                            String::from("Enum <Md5, Sha1, Sha256, Sha512, Blake3>"),
+                            format!("{}, 1st argument", op_name),
+                            fst_pos,
+                            RichTerm {
+                                term: t1,
+                                pos: pos1,
+                            },
+                        ))
+                    }
+                };
+
+                let result = match (b_op, &*t2) {
+                    (BinaryOp::Hash(), Term::Str(s)) => algorithm.digest(s),
+                    (BinaryOp::Hash(), _) => {
+                        return Err(EvalError::TypeError(
                             String::from("Str"),
                             String::from("hash, 2nd argument"),
                             snd_pos,
@@ -2242,8 +3311,82 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             },
                         ))
                     }
+                    _ => {
+                        // Hashing needs the value to be fully substituted, as for serialization
+                        let rt2 = subst(
+                            &self.cache,
+                            RichTerm {
+                                term: t2,
+                                pos: pos2,
+                            },
+                            &Environment::new(),
+                            &env2,
+                        );
+                        let bytes = hash::canonical_encoding(&rt2).map_err(|rt| {
+                            EvalError::Other(
+                                format!(
+                                    "hash_value: can't hash a value of type {}",
+                                    rt.as_ref()
+                                        .type_of()
+                                        .unwrap_or_else(|| String::from("Other"))
+                                ),
+                                pos_op,
+                            )
+                        })?;
+                        algorithm.digest(bytes)
+                    }
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Str(result),
+                    pos_op_inh,
+                )))
+            }
+            BinaryOp::Encode() | BinaryOp::Decode() => {
+                let op_name = if let BinaryOp::Encode() = b_op {
+                    "encode"
                 } else {
-                    mk_err_fst(t1)
+                    "decode"
+                };
+
+                let encoding = match &*t1 {
+                    Term::Enum(id) => Encoding::from_tag(id.label()),
+                    _ => None,
+                };
+
+                match (encoding, &*t2) {
+                    (Some(encoding), Term::Str(s)) => {
+                        let result = if let BinaryOp::Encode() = b_op {
+                            encoding.encode(s)
+                        } else {
+                            encoding.decode(s).map_err(|msg| {
+                                EvalError::Other(format!("{}: {}", op_name, msg), pos_op)
+                            })?
+                        };
+
+                        Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Str(result),
+                            pos_op_inh,
+                        )))
+                    }
+                    (Some(_), _) => Err(EvalError::TypeError(
+                        String::from("Str"),
+                        format!("{}, 2nd argument", op_name),
+                        snd_pos,
+                        RichTerm {
+                            term: t2,
+                            pos: pos2,
+                        },
+                    )),
+                    (None, _) => Err(EvalError::TypeError(
+                        String::from("Enum <Base64, Base64Url, Hex, Url>"),
+                        format!("{}, 1st argument", op_name),
+                        fst_pos,
+                        RichTerm {
+                            term: t1,
+                            pos: pos1,
+                        },
+                    )),
                 }
             }
             BinaryOp::Serialize() => {
@@ -2318,7 +3461,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                                     pos_op,
                                 )
                             })?,
-                            "Toml" => toml::from_str(s).map_err(|err| {
+                            "Toml" => serialize::from_toml_str(s).map_err(|err| {
                                 EvalError::DeserializationError(
                                     String::from("toml"),
                                     format!("{}", err),
@@ -2375,14 +3518,193 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     },
                 )),
             },
-            BinaryOp::StrContains() => match (&*t1, &*t2) {
-                (Term::Str(s1), Term::Str(s2)) => Ok(Closure::atomic_closure(RichTerm::new(
-                    Term::Bool(s1.contains(s2)),
-                    pos_op_inh,
-                ))),
-                (Term::Str(_), _) => Err(EvalError::TypeError(
-                    String::from("Str"),
-                    String::from("strContains, 2nd argument"),
+            BinaryOp::StrContains() => match (&*t1, &*t2) {
+                (Term::Str(s1), Term::Str(s2)) => Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Bool(s1.contains(s2)),
+                    pos_op_inh,
+                ))),
+                (Term::Str(_), _) => Err(EvalError::TypeError(
+                    String::from("Str"),
+                    String::from("strContains, 2nd argument"),
+                    snd_pos,
+                    RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                )),
+                (_, _) => Err(EvalError::TypeError(
+                    String::from("Str"),
+                    String::from("strContains, 1st argument"),
+                    fst_pos,
+                    RichTerm {
+                        term: t1,
+                        pos: pos1,
+                    },
+                )),
+            },
+            BinaryOp::DateTimeFormat() => match (&*t1, &*t2) {
+                (Term::Str(description), Term::DateTime(dt)) => {
+                    match datetime::format_with(dt, description) {
+                        Ok(s) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Str(s),
+                            pos_op_inh,
+                        ))),
+                        Err(err) => {
+                            Err(EvalError::Other(format!("datetimeFormat: {}", err), pos_op))
+                        }
+                    }
+                }
+                (Term::Str(_), _) => Err(EvalError::TypeError(
+                    String::from("DateTime"),
+                    String::from("datetimeFormat, 2nd argument"),
+                    snd_pos,
+                    RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                )),
+                (_, _) => Err(EvalError::TypeError(
+                    String::from("Str"),
+                    String::from("datetimeFormat, 1st argument"),
+                    fst_pos,
+                    RichTerm {
+                        term: t1,
+                        pos: pos1,
+                    },
+                )),
+            },
+            BinaryOp::DateTimeAdd() => match (&*t1, &*t2) {
+                (Term::DateTime(dt), Term::Duration(d)) => match dt.checked_add(*d) {
+                    Some(result) => Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::DateTime(result),
+                        pos_op_inh,
+                    ))),
+                    None => Err(EvalError::Other(
+                        format!(
+                            "datetimeAdd: adding {} to {} is out of range",
+                            datetime::format_duration(d),
+                            datetime::format(dt).unwrap_or_else(|_| dt.to_string())
+                        ),
+                        pos_op,
+                    )),
+                },
+                (Term::DateTime(_), _) => Err(EvalError::TypeError(
+                    String::from("Duration"),
+                    String::from("datetimeAdd, 2nd argument"),
+                    snd_pos,
+                    RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                )),
+                (_, _) => Err(EvalError::TypeError(
+                    String::from("DateTime"),
+                    String::from("datetimeAdd, 1st argument"),
+                    fst_pos,
+                    RichTerm {
+                        term: t1,
+                        pos: pos1,
+                    },
+                )),
+            },
+            BinaryOp::StrFormat() => {
+                if let Term::Str(fmt) = &*t1 {
+                    // The arguments need all their variables to be substituted.
+                    let args = subst(
+                        &self.cache,
+                        RichTerm {
+                            term: t2,
+                            pos: pos2,
+                        },
+                        &Environment::new(),
+                        &env2,
+                    );
+
+                    let result = match args.as_ref() {
+                        Term::Array(array, _) => format::format(fmt, FormatArgs::Positional(array)),
+                        Term::Record(record) => {
+                            format::format(fmt, FormatArgs::Named(&record.fields))
+                        }
+                        _ => {
+                            return Err(EvalError::TypeError(
+                                String::from("Array or Record"),
+                                String::from("strFormat, 2nd argument"),
+                                snd_pos,
+                                args,
+                            ))
+                        }
+                    };
+
+                    match result {
+                        Ok(s) => Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Str(s),
+                            pos_op_inh,
+                        ))),
+                        Err(err) => {
+                            // If the format string is a literal without escape sequences, the
+                            // length of its source is the length of its content plus the two
+                            // delimiting quotes, and we can report the exact location of the
+                            // error. Otherwise, we report the location of the whole format string.
+                            let pos = match pos1.into_opt() {
+                                Some(span)
+                                    if (span.end - span.start).to_usize() == fmt.len() + 2 =>
+                                {
+                                    TermPos::Original(RawSpan {
+                                        src_id: span.src_id,
+                                        start: span.start + ByteOffset::from(err.start as i64 + 1),
+                                        end: span.start + ByteOffset::from(err.end as i64 + 1),
+                                    })
+                                }
+                                Some(_) => pos1,
+                                None => pos_op,
+                            };
+
+                            Err(EvalError::Other(format!("format: {}", err.message), pos))
+                        }
+                    }
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("strFormat, 1st argument"),
+                        fst_pos,
+                        RichTerm {
+                            term: t1,
+                            pos: pos1,
+                        },
+                    ))
+                }
+            }
+            BinaryOp::ToStrRadix() => match (&*t1, &*t2) {
+                (Term::Num(radix), Term::Num(n)) => {
+                    let base = u8::try_from(radix)
+                        .ok()
+                        .filter(|base| (2..=36).contains(base));
+
+                    match (base, n.is_integer()) {
+                        (None, _) => Err(EvalError::Other(
+                            format!(
+                                "toStrRadix: expected the 1st argument to be an integer between 2 and 36, got {}",
+                                number::format(radix)
+                            ),
+                            pos_op,
+                        )),
+                        (_, false) => Err(EvalError::Other(
+                            format!(
+                                "toStrRadix: expected the 2nd argument to be an integer, got the non-integer value {}",
+                                number::format(n)
+                            ),
+                            pos_op,
+                        )),
+                        (Some(base), true) => Ok(Closure::atomic_closure(RichTerm::new(
+                            // unwrap(): we checked that `n` is an integer
+                            Term::Str(number::format_base(n, base).unwrap()),
+                            pos_op_inh,
+                        ))),
+                    }
+                }
+                (Term::Num(_), _) => Err(EvalError::TypeError(
+                    String::from("Num"),
+                    String::from("toStrRadix, 2nd argument"),
                     snd_pos,
                     RichTerm {
                         term: t2,
@@ -2390,8 +3712,8 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     },
                 )),
                 (_, _) => Err(EvalError::TypeError(
-                    String::from("Str"),
-                    String::from("strContains, 1st argument"),
+                    String::from("Num"),
+                    String::from("toStrRadix, 1st argument"),
                     fst_pos,
                     RichTerm {
                         term: t1,
@@ -2533,17 +3855,18 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
 
                 match (&*fst, &*snd, &*thd) {
                     (Term::Str(s), Term::Num(start), Term::Num(end)) => {
-                        let start_int = *start as usize;
-                        let end_int = *end as usize;
+                        // Negative or too large indices are out of bounds anyway.
+                        let start_int = usize::try_from(start).unwrap_or(usize::MAX);
+                        let end_int = usize::try_from(end).unwrap_or(usize::MAX);
 
-                        if start.fract() != 0.0 {
-                            Err(EvalError::Other(format!("substring: expected the 2nd agument (start) to be an integer, got the floating-point value {}", start), pos_op))
+                        if !start.is_integer() {
+                            Err(EvalError::Other(format!("substring: expected the 2nd agument (start) to be an integer, got the non-integer value {}", number::format(start)), pos_op))
                         } else if !s.is_char_boundary(start_int) {
                             Err(EvalError::Other(format!("substring: index out of bounds. Expected the 2nd argument (start) to be between 0 and {}, got {}", s.len(), start), pos_op))
-                        } else if end.fract() != 0.0 {
-                            Err(EvalError::Other(format!("substring: expected the 3nd argument (end) to be an integer, got the floating-point value {}", end), pos_op))
+                        } else if !end.is_integer() {
+                            Err(EvalError::Other(format!("substring: expected the 3nd argument (end) to be an integer, got the non-integer value {}", number::format(end)), pos_op))
                         } else if end <= start || !s.is_char_boundary(end_int) {
-                            Err(EvalError::Other(format!("substring: index out of bounds. Expected the 3rd argument (end) to be between {} and {}, got {}", start+1., s.len(), end), pos_op))
+                            Err(EvalError::Other(format!("substring: index out of bounds. Expected the 3rd argument (end) to be between {} and {}, got {}", start + Number::from(1), s.len(), end), pos_op))
                         } else {
                             Ok(Closure::atomic_closure(RichTerm::new(
                                 Term::Str(s[start_int..end_int].to_owned()),
@@ -2551,27 +3874,351 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             )))
                         }
                     }
-                    (Term::Str(_), Term::Num(_), _) => Err(EvalError::TypeError(
+                    (Term::Str(_), Term::Num(_), _) => Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("strReplace, 3rd argument"),
+                        thd_pos,
+                        RichTerm {
+                            term: thd,
+                            pos: pos3,
+                        },
+                    )),
+                    (Term::Str(_), _, _) => Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("strReplace, 2nd argument"),
+                        snd_pos,
+                        RichTerm {
+                            term: snd,
+                            pos: pos2,
+                        },
+                    )),
+                    (_, _, _) => Err(EvalError::TypeError(
+                        String::from("Str"),
+                        String::from("strReplace, 1st argument"),
+                        fst_pos,
+                        RichTerm {
+                            term: fst,
+                            pos: pos1,
+                        },
+                    )),
+                }
+            }
+            NAryOp::StrPadLeft() | NAryOp::StrPadRight() => {
+                let mut args_wo_env = args
+                    .into_iter()
+                    .map(|(clos, pos)| (clos.body.term, clos.body.pos, pos));
+                let (fst, pos1, fst_pos) = args_wo_env.next().unwrap();
+                let (snd, pos2, snd_pos) = args_wo_env.next().unwrap();
+                let (thd, pos3, thd_pos) = args_wo_env.next().unwrap();
+                debug_assert!(args_wo_env.next().is_none());
+
+                let op_name = if let NAryOp::StrPadLeft() = n_op {
+                    "padLeft"
+                } else {
+                    "padRight"
+                };
+
+                match (&*fst, &*snd, &*thd) {
+                    (Term::Str(s), Term::Num(width), Term::Str(fill)) => {
+                        if !width.is_integer() || *width < 0 {
+                            Err(EvalError::Other(
+                                format!(
+                                    "{}: expected the width to be a natural number, got {}",
+                                    op_name,
+                                    number::format(width)
+                                ),
+                                pos_op,
+                            ))
+                        } else if *width > format::MAX_WIDTH {
+                            Err(EvalError::Other(
+                                format!(
+                                    "{}: the width {} is larger than the maximum width {}",
+                                    op_name,
+                                    number::format(width),
+                                    format::MAX_WIDTH
+                                ),
+                                pos_op,
+                            ))
+                        } else if fill.graphemes(true).count() != 1 {
+                            Err(EvalError::Other(format!("{}: expected the fill string to be a single character, got \"{}\"", op_name, fill), pos_op))
+                        } else {
+                            // unwrap(): the width is a natural number smaller than `MAX_WIDTH`
+                            let width = usize::try_from(width).unwrap();
+                            let padded =
+                                format::pad(s, width, fill, matches!(n_op, NAryOp::StrPadLeft()));
+
+                            Ok(Closure::atomic_closure(RichTerm::new(
+                                Term::Str(padded),
+                                pos_op_inh,
+                            )))
+                        }
+                    }
+                    (Term::Str(_), Term::Num(_), _) => Err(EvalError::TypeError(
+                        String::from("Str"),
+                        format!("{}, 3rd argument", op_name),
+                        thd_pos,
+                        RichTerm {
+                            term: thd,
+                            pos: pos3,
+                        },
+                    )),
+                    (Term::Str(_), _, _) => Err(EvalError::TypeError(
+                        String::from("Num"),
+                        format!("{}, 2nd argument", op_name),
+                        snd_pos,
+                        RichTerm {
+                            term: snd,
+                            pos: pos2,
+                        },
+                    )),
+                    (_, _, _) => Err(EvalError::TypeError(
+                        String::from("Str"),
+                        format!("{}, 1st argument", op_name),
+                        fst_pos,
+                        RichTerm {
+                            term: fst,
+                            pos: pos1,
+                        },
+                    )),
+                }
+            }
+            NAryOp::ArraySlice() => {
+                let mut args = args.into_iter();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: fst,
+                                pos: pos1,
+                            },
+                        env: env1,
+                    },
+                    fst_pos,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: snd,
+                                pos: pos2,
+                            },
+                        ..
+                    },
+                    snd_pos,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: thd,
+                                pos: pos3,
+                            },
+                        ..
+                    },
+                    thd_pos,
+                ) = args.next().unwrap();
+                debug_assert!(args.next().is_none());
+
+                match (&*fst, &*snd, &*thd) {
+                    (Term::Array(ts, attrs), Term::Num(start), Term::Num(end)) => {
+                        // Negative or too large indices are out of bounds anyway.
+                        let start_int = usize::try_from(start).unwrap_or(usize::MAX);
+                        let end_int = usize::try_from(end).unwrap_or(usize::MAX);
+
+                        if !start.is_integer() {
+                            Err(EvalError::Other(format!("slice: expected the 2nd agument (start) to be an integer, got the non-integer value {}", number::format(start)), pos_op))
+                        } else if start_int > ts.len() {
+                            Err(EvalError::Other(format!("slice: index out of bounds. Expected the 2nd argument (start) to be between 0 and {}, got {}", ts.len(), start), pos_op))
+                        } else if !end.is_integer() {
+                            Err(EvalError::Other(format!("slice: expected the 3nd argument (end) to be an integer, got the non-integer value {}", number::format(end)), pos_op))
+                        } else if end_int < start_int || end_int > ts.len() {
+                            Err(EvalError::Other(format!("slice: index out of bounds. Expected the 3rd argument (end) to be between {} and {}, got {}", start, ts.len(), end), pos_op))
+                        } else {
+                            Ok(Closure {
+                                body: RichTerm::new(
+                                    Term::Array(
+                                        ts.clone().slice(start_int, end_int),
+                                        attrs.clone(),
+                                    ),
+                                    pos_op_inh,
+                                ),
+                                env: env1,
+                            })
+                        }
+                    }
+                    (Term::Array(..), Term::Num(_), _) => Err(EvalError::TypeError(
+                        String::from("Num"),
+                        String::from("slice, 3rd argument"),
+                        thd_pos,
+                        RichTerm {
+                            term: thd,
+                            pos: pos3,
+                        },
+                    )),
+                    (Term::Array(..), _, _) => Err(EvalError::TypeError(
+                        String::from("Num"),
+                        String::from("slice, 2nd argument"),
+                        snd_pos,
+                        RichTerm {
+                            term: snd,
+                            pos: pos2,
+                        },
+                    )),
+                    (_, _, _) => Err(EvalError::TypeError(
+                        String::from("Array"),
+                        String::from("slice, 1st argument"),
+                        fst_pos,
+                        RichTerm {
+                            term: fst,
+                            pos: pos1,
+                        },
+                    )),
+                }
+            }
+            NAryOp::RecordCopyField() => {
+                let mut args = args.into_iter();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: fst,
+                                pos: pos1,
+                            },
+                        ..
+                    },
+                    fst_pos,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: snd,
+                                pos: pos2,
+                            },
+                        env: env2,
+                    },
+                    snd_pos,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: thd,
+                                pos: pos3,
+                            },
+                        ..
+                    },
+                    thd_pos,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: fourth,
+                                pos: pos4,
+                            },
+                        env: mut env4,
+                    },
+                    fourth_pos,
+                ) = args.next().unwrap();
+                debug_assert!(args.next().is_none());
+
+                match (&*fst, &*snd, &*thd, &*fourth) {
+                    (Term::Str(from), Term::Record(src), Term::Str(to), Term::Record(dst)) => {
+                        let field = match src.fields.get(&Ident::from(from)) {
+                            Some(t) if !is_empty_optional(&self.cache, t, &env2) => t,
+                            _ => {
+                                return Err(EvalError::FieldMissing(
+                                    from.clone(),
+                                    String::from("record_copy_field"),
+                                    RichTerm {
+                                        term: snd,
+                                        pos: pos2,
+                                    },
+                                    pos_op,
+                                ))
+                            }
+                        };
+
+                        // The copy doesn't share the thunk of the original field, which may be a
+                        // revertible thunk depending on other fields of the source record. Such
+                        // fields don't necessarily exist in the target record, which would break
+                        // a later merge. The copy is rather a fresh thunk holding the current
+                        // content of the original one.
+                        let field = match field.as_ref() {
+                            Term::Var(id) => {
+                                let idx = env2
+                                    .get(id)
+                                    .cloned()
+                                    .ok_or(EvalError::UnboundIdentifier(*id, field.pos))?;
+                                let content = self.cache.get(idx);
+                                let fresh_id = Ident::fresh();
+                                env4.insert(
+                                    fresh_id,
+                                    self.cache.add(
+                                        content,
+                                        eval::IdentKind::Record,
+                                        crate::term::BindingType::Normal,
+                                    ),
+                                );
+                                RichTerm::new(Term::Var(fresh_id), field.pos)
+                            }
+                            _ => field.clone().closurize(&mut self.cache, &mut env4, env2),
+                        };
+
+                        let mut fields = dst.fields.clone();
+                        match fields.insert(Ident::from(to), field) {
+                            Some(t) if !is_empty_optional(&self.cache, &t, &env4) => {
+                                Err(EvalError::Other(
+                                    format!(
+                                        "record_copy_field: tried to extend record with the field {}, but it already exists",
+                                        to
+                                    ),
+                                    pos_op,
+                                ))
+                            }
+                            _ => Ok(Closure {
+                                body: RichTerm::new(
+                                    Term::Record(RecordData {
+                                        fields,
+                                        ..dst.clone()
+                                    }),
+                                    pos_op_inh,
+                                ),
+                                env: env4,
+                            }),
+                        }
+                    }
+                    (Term::Str(_), Term::Record(_), Term::Str(_), _) => Err(EvalError::TypeError(
+                        String::from("Record"),
+                        String::from("record_copy_field, 4th argument"),
+                        fourth_pos,
+                        RichTerm {
+                            term: fourth,
+                            pos: pos4,
+                        },
+                    )),
+                    (Term::Str(_), Term::Record(_), _, _) => Err(EvalError::TypeError(
                         String::from("Str"),
-                        String::from("strReplace, 3rd argument"),
+                        String::from("record_copy_field, 3rd argument"),
                         thd_pos,
                         RichTerm {
                             term: thd,
                             pos: pos3,
                         },
                     )),
-                    (Term::Str(_), _, _) => Err(EvalError::TypeError(
-                        String::from("Str"),
-                        String::from("strReplace, 2nd argument"),
+                    (Term::Str(_), _, _, _) => Err(EvalError::TypeError(
+                        String::from("Record"),
+                        String::from("record_copy_field, 2nd argument"),
                         snd_pos,
                         RichTerm {
                             term: snd,
                             pos: pos2,
                         },
                     )),
-                    (_, _, _) => Err(EvalError::TypeError(
+                    (_, _, _, _) => Err(EvalError::TypeError(
                         String::from("Str"),
-                        String::from("strReplace, 1st argument"),
+                        String::from("record_copy_field, 1st argument"),
                         fst_pos,
                         RichTerm {
                             term: fst,
@@ -2580,6 +4227,241 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     )),
                 }
             }
+            NAryOp::ArrayMasked(mask_op) => {
+                let mut args = args.into_iter();
+                let (f, _) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: array,
+                                pos: pos_array,
+                            },
+                        env: array_env,
+                    },
+                    _,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body: RichTerm { term: mask, .. },
+                        env: mask_env,
+                    },
+                    _,
+                ) = args.next().unwrap();
+                debug_assert!(args.next().is_none());
+
+                // Both the array and the mask are produced by the first stage of the operation.
+                let (ts, attrs) = match array.into_owned() {
+                    Term::Array(ts, attrs) => (ts, attrs),
+                    _ => panic!("array mask primop: expected an array as the 2nd argument"),
+                };
+                let mask: Vec<Term> = match &*mask {
+                    Term::Array(mask, _) => mask
+                        .iter()
+                        .map(|t| {
+                            subst(&self.cache, t.clone(), &Environment::new(), &mask_env)
+                                .term
+                                .into_owned()
+                        })
+                        .collect(),
+                    _ => panic!("array mask primop: expected an array as the 3rd argument"),
+                };
+
+                match mask_op {
+                    ArrayMaskOp::Filter | ArrayMaskOp::Partition => {
+                        let op_name = if let ArrayMaskOp::Filter = mask_op {
+                            "filter"
+                        } else {
+                            "partition"
+                        };
+
+                        let mut right = Vec::new();
+                        let mut wrong = Vec::new();
+
+                        for (t, b) in ts.iter().zip(mask) {
+                            match b {
+                                Term::Bool(true) => right.push(t.clone()),
+                                Term::Bool(false) => wrong.push(t.clone()),
+                                b => {
+                                    return Err(EvalError::TypeError(
+                                        String::from("Bool"),
+                                        format!("{op_name}, return value of the predicate"),
+                                        pos_op,
+                                        RichTerm::new(b, pos_op_inh),
+                                    ))
+                                }
+                            }
+                        }
+
+                        // The elements are left untouched, so the contracts pending on the
+                        // original array remain pending on the results.
+                        let right = Term::Array(Array::new(Rc::from(right)), attrs.clone());
+
+                        if let ArrayMaskOp::Filter = mask_op {
+                            return Ok(Closure {
+                                body: RichTerm::new(right, pos_op_inh),
+                                env: array_env,
+                            });
+                        }
+
+                        let wrong = Term::Array(Array::new(Rc::from(wrong)), attrs);
+                        let mut shared_env = Environment::new();
+                        let right = RichTerm::new(right, pos_op_inh).closurize(
+                            &mut self.cache,
+                            &mut shared_env,
+                            array_env.clone(),
+                        );
+                        let wrong = RichTerm::new(wrong, pos_op_inh).closurize(
+                            &mut self.cache,
+                            &mut shared_env,
+                            array_env,
+                        );
+
+                        Ok(Closure {
+                            body: mk_record!(("right", right), ("wrong", wrong))
+                                .with_pos(pos_op_inh),
+                            env: shared_env,
+                        })
+                    }
+                    ArrayMaskOp::SortOn => {
+                        let mut keyed: Vec<_> = ts.iter().cloned().zip(mask).collect();
+                        let mut error = None;
+
+                        // `sort_by` is stable. Once an error occurs, the remaining comparisons are
+                        // irrelevant.
+                        keyed.sort_by(|(_, k1), (_, k2)| {
+                            if error.is_some() {
+                                return std::cmp::Ordering::Equal;
+                            }
+
+                            compare_values(k1, k2, pos_op).unwrap_or_else(|err| {
+                                error = Some(err);
+                                std::cmp::Ordering::Equal
+                            })
+                        });
+
+                        if let Some(err) = error {
+                            return Err(err);
+                        }
+
+                        let sorted = keyed.into_iter().map(|(t, _)| t).collect();
+
+                        // The elements are left untouched, so the contracts pending on the
+                        // original array remain pending on the result.
+                        Ok(Closure {
+                            body: RichTerm::new(Term::Array(sorted, attrs), pos_op_inh),
+                            env: array_env,
+                        })
+                    }
+                    ArrayMaskOp::MinBy | ArrayMaskOp::MaxBy => {
+                        // The element replacing the current one is strictly lesser (or greater),
+                        // so that the first of the equal elements is returned.
+                        let replace = if let ArrayMaskOp::MinBy = mask_op {
+                            std::cmp::Ordering::Less
+                        } else {
+                            std::cmp::Ordering::Greater
+                        };
+
+                        let mut keys = mask.iter().enumerate();
+                        // unwrap(): the first stage fails on an empty array
+                        let (mut best, mut best_key) = keys.next().unwrap();
+
+                        for (idx, key) in keys {
+                            if compare_values(key, best_key, pos_op)? == replace {
+                                best = idx;
+                                best_key = key;
+                            }
+                        }
+
+                        // unwrap(): the mask has one entry per element of the array
+                        let elt = ts.get(best).unwrap().clone();
+                        let pos = elt.pos.into_inherited();
+
+                        Ok(Closure {
+                            body: apply_contracts(elt, attrs.pending_contracts.into_iter(), pos),
+                            env: array_env,
+                        })
+                    }
+                    ArrayMaskOp::Sort(segments) => {
+                        let mut sorted: Vec<RichTerm> = ts.iter().cloned().collect();
+                        let mut next_segments = Vec::new();
+                        let mut mask = mask.into_iter();
+
+                        for (start, end) in segments {
+                            let mut lesser = Vec::new();
+                            let mut equal = Vec::new();
+                            let mut greater = Vec::new();
+
+                            for t in &sorted[start..end] {
+                                // unwrap(): the mask has one entry per element of the segments
+                                match mask.next().unwrap() {
+                                    Term::Enum(id) if id.label() == "Lesser" => lesser.push(t.clone()),
+                                    Term::Enum(id) if id.label() == "Equal" => equal.push(t.clone()),
+                                    Term::Enum(id) if id.label() == "Greater" => greater.push(t.clone()),
+                                    other => {
+                                        return Err(EvalError::Other(
+                                            format!(
+                                                "sort: expected the comparison function to return `Lesser, `Equal or `Greater, got {}",
+                                                other.shallow_repr()
+                                            ),
+                                            pos_op,
+                                        ))
+                                    }
+                                }
+                            }
+
+                            let mid = start + lesser.len();
+                            let upper = mid + equal.len();
+
+                            if lesser.len() > 1 {
+                                next_segments.push((start, mid));
+                            }
+                            if greater.len() > 1 {
+                                next_segments.push((upper, end));
+                            }
+
+                            for (slot, t) in sorted[start..end]
+                                .iter_mut()
+                                .zip(lesser.into_iter().chain(equal).chain(greater))
+                            {
+                                *slot = t;
+                            }
+                        }
+
+                        let ts = Array::new(Rc::from(sorted));
+
+                        if next_segments.is_empty() {
+                            return Ok(Closure {
+                                body: RichTerm::new(Term::Array(ts, attrs), pos_op_inh),
+                                env: array_env,
+                            });
+                        }
+
+                        let mut env = array_env;
+                        let cmp = f.body.closurize(&mut self.cache, &mut env, f.env);
+                        let (mask, mask_env) = mk_sort_mask(
+                            &mut self.cache,
+                            &cmp,
+                            &ts,
+                            &attrs,
+                            &env,
+                            &next_segments,
+                            pos_array,
+                        );
+
+                        Ok(mk_array_masked(
+                            &mut self.cache,
+                            ArrayMaskOp::Sort(next_segments),
+                            cmp,
+                            RichTerm::new(Term::Array(ts, attrs), pos_array),
+                            env,
+                            mask,
+                            mask_env,
+                            pos_op_inh,
+                        ))
+                    }
+                }
+            }
             NAryOp::MergeContract() => {
                 let mut args_iter = args.into_iter();
                 let (
@@ -3079,9 +4961,33 @@ fn eq<C: Cache>(
         (Term::Bool(b1), Term::Bool(b2)) => Ok(EqResult::Bool(b1 == b2)),
         (Term::Num(n1), Term::Num(n2)) => Ok(EqResult::Bool(n1 == n2)),
         (Term::Str(s1), Term::Str(s2)) => Ok(EqResult::Bool(s1 == s2)),
+        (Term::DateTime(dt1), Term::DateTime(dt2)) => Ok(EqResult::Bool(dt1 == dt2)),
+        (Term::Duration(d1), Term::Duration(d2)) => Ok(EqResult::Bool(d1 == d2)),
         (Term::Lbl(l1), Term::Lbl(l2)) => Ok(EqResult::Bool(l1 == l2)),
         (Term::SealingKey(s1), Term::SealingKey(s2)) => Ok(EqResult::Bool(s1 == s2)),
         (Term::Enum(id1), Term::Enum(id2)) => Ok(EqResult::Bool(id1 == id2)),
+        (
+            Term::EnumVariant {
+                tag: tag1,
+                arg: arg1,
+            },
+            Term::EnumVariant {
+                tag: tag2,
+                arg: arg2,
+            },
+        ) => {
+            if tag1 == tag2 {
+                Ok(gen_eqs(
+                    cache,
+                    std::iter::once((arg1, arg2)),
+                    env,
+                    env1,
+                    env2,
+                ))
+            } else {
+                Ok(EqResult::Bool(false))
+            }
+        }
         (Term::Record(r1), Term::Record(r2)) => {
             let merge::hashmap::SplitResult {
                 left,
@@ -3171,6 +5077,447 @@ fn eq<C: Cache>(
     }
 }
 
+/// Compute the structural comparison of two terms, see [`BinaryOp::Compare`].
+///
+/// Values which can be compared directly are. For arrays and records, the comparisons of the
+/// elements are generated and returned as a [`CmpResult::Cmps`], to be evaluated in order by the
+/// caller. The comparison of the lengths of two arrays comes last, so that a prefix of an array is
+/// lesser than the array.
+///
+/// # Return
+///
+/// If the terms are comparable, returns either an ordering or the comparisons to perform,
+/// otherwise returns an [`EvalError`] indicating that the values cannot be compared.
+#[allow(clippy::result_large_err)]
+fn compare<C: Cache>(
+    cache: &mut C,
+    env: &mut Environment,
+    c1: Closure,
+    c2: Closure,
+    pos_op: TermPos,
+) -> Result<CmpResult, EvalError> {
+    use std::cmp::Ordering;
+
+    let Closure {
+        body: RichTerm { term: t1, .. },
+        env: env1,
+    } = c1;
+    let Closure {
+        body: RichTerm { term: t2, .. },
+        env: env2,
+    } = c2;
+
+    // Take a list of subcomparisons, and either return `ordering` if it is empty, or generate
+    // an approriate `CmpResult::Cmps` variant with closurized terms in it.
+    fn gen_cmps<C: Cache>(
+        cache: &mut C,
+        cmps: Vec<(Closure, Closure)>,
+        env: &mut Environment,
+        ordering: Ordering,
+    ) -> CmpResult {
+        let mut cmps = cmps.into_iter();
+
+        if let Some((c1, c2)) = cmps.next() {
+            CmpResult::Cmps(
+                c1.body.closurize(cache, env, c1.env),
+                c2.body.closurize(cache, env, c2.env),
+                cmps.collect(),
+            )
+        } else {
+            CmpResult::Ordering(ordering)
+        }
+    }
+
+    match (t1.into_owned(), t2.into_owned()) {
+        (Term::Null, Term::Null) => Ok(CmpResult::Ordering(Ordering::Equal)),
+        (Term::Bool(b1), Term::Bool(b2)) => Ok(CmpResult::Ordering(b1.cmp(&b2))),
+        (Term::Num(n1), Term::Num(n2)) => Ok(CmpResult::Ordering(n1.cmp(&n2))),
+        (Term::Str(s1), Term::Str(s2)) => Ok(CmpResult::Ordering(s1.cmp(&s2))),
+        (Term::Enum(id1), Term::Enum(id2)) => Ok(CmpResult::Ordering(id1.label().cmp(id2.label()))),
+        // A bare tag is lesser than a variant with the same tag carrying an argument.
+        (Term::Enum(id1), Term::EnumVariant { tag: id2, .. }) => Ok(CmpResult::Ordering(
+            id1.label().cmp(id2.label()).then(Ordering::Less),
+        )),
+        (Term::EnumVariant { tag: id1, .. }, Term::Enum(id2)) => Ok(CmpResult::Ordering(
+            id1.label().cmp(id2.label()).then(Ordering::Greater),
+        )),
+        (
+            Term::EnumVariant {
+                tag: tag1,
+                arg: arg1,
+            },
+            Term::EnumVariant {
+                tag: tag2,
+                arg: arg2,
+            },
+        ) => {
+            let tags_ord = tag1.label().cmp(tag2.label());
+
+            if tags_ord != Ordering::Equal {
+                Ok(CmpResult::Ordering(tags_ord))
+            } else {
+                let cmps = vec![(
+                    Closure {
+                        body: arg1,
+                        env: env1,
+                    },
+                    Closure {
+                        body: arg2,
+                        env: env2,
+                    },
+                )];
+
+                Ok(gen_cmps(cache, cmps, env, Ordering::Equal))
+            }
+        }
+        (Term::DateTime(dt1), Term::DateTime(dt2)) => Ok(CmpResult::Ordering(dt1.cmp(&dt2))),
+        (Term::Duration(d1), Term::Duration(d2)) => Ok(CmpResult::Ordering(d1.cmp(&d2))),
+        (Term::Array(l1, a1), Term::Array(l2, a2)) => {
+            // As for equality, we apply the pending contracts before comparing the elements.
+            let mut shared_env1 = env1.clone();
+            let mut shared_env2 = env2.clone();
+            let (len1, len2) = (l1.len(), l2.len());
+
+            let elems1: Vec<_> = l1
+                .into_iter()
+                .map(|t| {
+                    let pos = t.pos.into_inherited();
+                    apply_contracts(t, a1.pending_contracts.iter().cloned(), pos).closurize(
+                        cache,
+                        &mut shared_env1,
+                        env1.clone(),
+                    )
+                })
+                .collect();
+            let elems2: Vec<_> = l2
+                .into_iter()
+                .map(|t| {
+                    let pos = t.pos.into_inherited();
+                    apply_contracts(t, a2.pending_contracts.iter().cloned(), pos).closurize(
+                        cache,
+                        &mut shared_env2,
+                        env2.clone(),
+                    )
+                })
+                .collect();
+
+            let mut cmps: Vec<_> = elems1
+                .into_iter()
+                .zip(elems2)
+                .map(|(t1, t2)| {
+                    (
+                        Closure {
+                            body: t1,
+                            env: shared_env1.clone(),
+                        },
+                        Closure {
+                            body: t2,
+                            env: shared_env2.clone(),
+                        },
+                    )
+                })
+                .collect();
+
+            if len1 != len2 && !cmps.is_empty() {
+                let length = |n: usize| Closure::atomic_closure(Term::Num(Number::from(n)).into());
+                cmps.push((length(len1), length(len2)));
+            }
+
+            Ok(gen_cmps(cache, cmps, env, len1.cmp(&len2)))
+        }
+        (Term::Record(r1), Term::Record(r2)) => {
+            // As for other record operations, we ignore optional fields without a definition.
+            let sorted_fields = |fields: std::collections::HashMap<Ident, RichTerm>,
+                                 env: &Environment| {
+                let mut fields: Vec<_> = fields
+                    .into_iter()
+                    .filter(|(_, t)| !is_empty_optional(cache, t, env))
+                    .collect();
+                fields.sort_by(|(id1, _), (id2, _)| id1.label().cmp(id2.label()));
+                fields
+            };
+            let fields1 = sorted_fields(r1.fields, &env1);
+            let fields2 = sorted_fields(r2.fields, &env2);
+
+            let names_ord = fields1
+                .iter()
+                .map(|(id, _)| id.label())
+                .cmp(fields2.iter().map(|(id, _)| id.label()));
+
+            if names_ord != Ordering::Equal {
+                Ok(CmpResult::Ordering(names_ord))
+            } else {
+                let cmps = fields1
+                    .into_iter()
+                    .zip(fields2)
+                    .map(|((_, t1), (_, t2))| {
+                        (
+                            Closure {
+                                body: t1,
+                                env: env1.clone(),
+                            },
+                            Closure {
+                                body: t2,
+                                env: env2.clone(),
+                            },
+                        )
+                    })
+                    .collect();
+
+                Ok(gen_cmps(cache, cmps, env, Ordering::Equal))
+            }
+        }
+        (Term::Fun(..), _) | (_, Term::Fun(..)) => Err(EvalError::Other(
+            String::from("compare: functions can't be compared"),
+            pos_op,
+        )),
+        (t1, t2) => Err(EvalError::Other(
+            format!(
+                "compare: can't compare a value of type {} with a value of type {}",
+                t1.type_of().unwrap_or_else(|| String::from("Other")),
+                t2.type_of().unwrap_or_else(|| String::from("Other")),
+            ),
+            pos_op,
+        )),
+    }
+}
+
+/// Compare two fully evaluated values, as [`BinaryOp::Compare`] does. Used to compare the keys
+/// computed by the masked array operations, see [`ArrayMaskOp::SortOn`].
+#[allow(clippy::result_large_err)]
+fn compare_values(t1: &Term, t2: &Term, pos_op: TermPos) -> Result<std::cmp::Ordering, EvalError> {
+    use std::cmp::Ordering;
+
+    // Compare pairs of subterms in order, up to the first difference.
+    fn compare_all<'a>(
+        pairs: impl Iterator<Item = (&'a RichTerm, &'a RichTerm)>,
+        pos_op: TermPos,
+    ) -> Result<Ordering, EvalError> {
+        for (t1, t2) in pairs {
+            let ord = compare_values(&t1.term, &t2.term, pos_op)?;
+
+            if ord != Ordering::Equal {
+                return Ok(ord);
+            }
+        }
+
+        Ok(Ordering::Equal)
+    }
+
+    match (t1, t2) {
+        (
+            Term::MetaValue(MetaValue {
+                value: Some(t1), ..
+            }),
+            t2,
+        ) => compare_values(&t1.term, t2, pos_op),
+        (
+            t1,
+            Term::MetaValue(MetaValue {
+                value: Some(t2), ..
+            }),
+        ) => compare_values(t1, &t2.term, pos_op),
+        (Term::Null, Term::Null) => Ok(Ordering::Equal),
+        (Term::Bool(b1), Term::Bool(b2)) => Ok(b1.cmp(b2)),
+        (Term::Num(n1), Term::Num(n2)) => Ok(n1.cmp(n2)),
+        (Term::Str(s1), Term::Str(s2)) => Ok(s1.cmp(s2)),
+        (Term::Enum(id1), Term::Enum(id2)) => Ok(id1.label().cmp(id2.label())),
+        (Term::Enum(id1), Term::EnumVariant { tag: id2, .. }) => {
+            Ok(id1.label().cmp(id2.label()).then(Ordering::Less))
+        }
+        (Term::EnumVariant { tag: id1, .. }, Term::Enum(id2)) => {
+            Ok(id1.label().cmp(id2.label()).then(Ordering::Greater))
+        }
+        (
+            Term::EnumVariant {
+                tag: tag1,
+                arg: arg1,
+            },
+            Term::EnumVariant {
+                tag: tag2,
+                arg: arg2,
+            },
+        ) => match tag1.label().cmp(tag2.label()) {
+            Ordering::Equal => compare_values(&arg1.term, &arg2.term, pos_op),
+            ord => Ok(ord),
+        },
+        (Term::DateTime(dt1), Term::DateTime(dt2)) => Ok(dt1.cmp(dt2)),
+        (Term::Duration(d1), Term::Duration(d2)) => Ok(d1.cmp(d2)),
+        (Term::Array(l1, _), Term::Array(l2, _)) => {
+            Ok(compare_all(l1.iter().zip(l2.iter()), pos_op)?.then(l1.len().cmp(&l2.len())))
+        }
+        (Term::Record(r1), Term::Record(r2)) => {
+            // Optional fields without a definition have been removed by the evaluation of the
+            // keys.
+            let mut fields1: Vec<_> = r1.fields.iter().collect();
+            let mut fields2: Vec<_> = r2.fields.iter().collect();
+            fields1.sort_by(|(id1, _), (id2, _)| id1.label().cmp(id2.label()));
+            fields2.sort_by(|(id1, _), (id2, _)| id1.label().cmp(id2.label()));
+
+            let names_ord = fields1
+                .iter()
+                .map(|(id, _)| id.label())
+                .cmp(fields2.iter().map(|(id, _)| id.label()));
+
+            if names_ord != Ordering::Equal {
+                Ok(names_ord)
+            } else {
+                compare_all(
+                    fields1
+                        .into_iter()
+                        .zip(fields2)
+                        .map(|((_, t1), (_, t2))| (t1, t2)),
+                    pos_op,
+                )
+            }
+        }
+        (Term::Fun(..), _) | (_, Term::Fun(..)) => Err(EvalError::Other(
+            String::from("compare: functions can't be compared"),
+            pos_op,
+        )),
+        (t1, t2) => Err(EvalError::Other(
+            format!(
+                "compare: can't compare a value of type {} with a value of type {}",
+                t1.type_of().unwrap_or_else(|| String::from("Other")),
+                t2.type_of().unwrap_or_else(|| String::from("Other")),
+            ),
+            pos_op,
+        )),
+    }
+}
+
+/// Build the second stage of an array operation inspecting the results of a function `f` on the
+/// elements of an array, that is `%array_masked% f array (%force% mask)`. The mask is fully
+/// evaluated beforehand, so that its entries can be read directly from the cache.
+#[allow(clippy::too_many_arguments)]
+fn mk_array_masked<C: Cache>(
+    cache: &mut C,
+    op: ArrayMaskOp,
+    f: RichTerm,
+    array: RichTerm,
+    array_env: Environment,
+    mask: Array,
+    mut mask_env: Environment,
+    pos_op: TermPos,
+) -> Closure {
+    let f = f.closurize(cache, &mut mask_env, array_env.clone());
+    let array = array.closurize(cache, &mut mask_env, array_env);
+    let mask = RichTerm::new(Term::Array(mask, ArrayAttrs::new().closurized()), pos_op);
+
+    Closure {
+        body: mk_opn!(
+            NAryOp::ArrayMasked(op),
+            f,
+            array,
+            mk_term::op1(UnaryOp::Force(None), mask)
+        )
+        .with_pos(pos_op),
+        env: mask_env,
+    }
+}
+
+/// Build the mask of a round of sorting, which compares each element of the given segments of
+/// the array with the pivot of its segment using `cmp`. The pivot is compared to itself without
+/// calling `cmp`, which guarantees that each round makes progress.
+fn mk_sort_mask<C: Cache>(
+    cache: &mut C,
+    cmp: &RichTerm,
+    ts: &Array,
+    attrs: &ArrayAttrs,
+    env: &Environment,
+    segments: &[(usize, usize)],
+    pos: TermPos,
+) -> (Array, Environment) {
+    let mut mask_env = Environment::new();
+    let mut mask = Vec::new();
+
+    for &(start, end) in segments {
+        let pivot_idx = start + (end - start) / 2;
+        let mut pivot_env = env.clone();
+        // unwrap(): the segments are always within the bounds of the array
+        let pivot = apply_contracts(
+            ts.get(pivot_idx).unwrap().clone(),
+            attrs.pending_contracts.iter().cloned(),
+            pos.into_inherited(),
+        )
+        .closurize(cache, &mut pivot_env, env.clone());
+
+        mask.extend((start..end).map(|idx| {
+            if idx == pivot_idx {
+                RichTerm::from(Term::Enum(Ident::from("Equal")))
+            } else {
+                let t_with_ctrs = apply_contracts(
+                    ts.get(idx).unwrap().clone(),
+                    attrs.pending_contracts.iter().cloned(),
+                    pos.into_inherited(),
+                );
+
+                mk_app!(cmp.clone(), t_with_ctrs, pivot.clone()).closurize(
+                    cache,
+                    &mut mask_env,
+                    pivot_env.clone(),
+                )
+            }
+        }));
+    }
+
+    (Array::new(Rc::from(mask)), mask_env)
+}
+
+/// Compile a regex, and return a function wrapping the operation built by `mk_op` from the compiled
+/// regex. This allows to share the compiled regex between the calls of the resulting function.
+fn compile_regex(
+    pattern: &str,
+    mk_op: impl FnOnce(CompiledRegex) -> UnaryOp,
+    pos: TermPos,
+) -> Result<Term, regex::Error> {
+    let re = regex::Regex::new(pattern)?;
+    let param = Ident::fresh();
+
+    Ok(Term::Fun(
+        param,
+        RichTerm::new(
+            Term::Op1(mk_op(re.into()), RichTerm::new(Term::Var(param), pos)),
+            pos,
+        ),
+    ))
+}
+
+/// Build the record representing a match of a regex: the part of the string that matched, the
+/// index of the match, the array of the capture groups which participated in the match, and the
+/// record of the named capture groups which participated in the match.
+fn mk_match_record(regex: &regex::Regex, capt: &regex::Captures) -> RichTerm {
+    // unwrap(): the group 0 always corresponds to the whole match
+    let whole = capt.get(0).unwrap();
+    let groups = capt
+        .iter()
+        .skip(1)
+        .filter_map(|s_opt| s_opt.map(|s| RichTerm::from(Term::Str(String::from(s.as_str())))))
+        .collect();
+    let named = regex
+        .capture_names()
+        .flatten()
+        .filter_map(|name| {
+            capt.name(name).map(|s| {
+                (
+                    Ident::from(name),
+                    RichTerm::from(Term::Str(String::from(s.as_str()))),
+                )
+            })
+        })
+        .collect();
+
+    mk_record!(
+        ("matched", Term::Str(String::from(whole.as_str()))),
+        ("index", Term::Num(Number::from(whole.start()))),
+        (
+            "groups",
+            Term::Array(groups, ArrayAttrs::new().closurized())
+        ),
+        ("named", Term::Record(RecordData::with_fields(named)))
+    )
+}
+
 trait RecordDataExt {
     fn map_fields_without_optionals<F, C: Cache>(
         self,
@@ -3228,11 +5575,11 @@ mod tests {
         let mut vm: VirtualMachine<DummyResolver, EC> = VirtualMachine::new(DummyResolver {});
 
         vm.stack.push_arg(
-            Closure::atomic_closure(Term::Num(5.0).into()),
+            Closure::atomic_closure(Term::Num(Number::from(5)).into()),
             TermPos::None,
         );
         vm.stack.push_arg(
-            Closure::atomic_closure(Term::Num(46.0).into()),
+            Closure::atomic_closure(Term::Num(Number::from(46)).into()),
             TermPos::None,
         );
 
@@ -3248,7 +5595,7 @@ mod tests {
         assert_eq!(
             clos,
             Closure {
-                body: Term::Num(46.0).into(),
+                body: Term::Num(Number::from(46)).into(),
                 env: Environment::new()
             }
         );
@@ -3260,14 +5607,14 @@ mod tests {
         let cont = OperationCont::Op2First(
             BinaryOp::Plus(),
             Closure {
-                body: Term::Num(6.0).into(),
+                body: Term::Num(Number::from(6)).into(),
                 env: Environment::new(),
             },
             TermPos::None,
         );
 
         let mut clos = Closure {
-            body: Term::Num(7.0).into(),
+            body: Term::Num(Number::from(7)).into(),
             env: Environment::new(),
         };
         let mut vm = VirtualMachine::new(DummyResolver {});
@@ -3278,7 +5625,7 @@ mod tests {
         assert_eq!(
             clos,
             Closure {
-                body: Term::Num(6.0).into(),
+                body: Term::Num(Number::from(6)).into(),
                 env: Environment::new()
             }
         );
@@ -3289,7 +5636,7 @@ mod tests {
                 OperationCont::Op2Second(
                     BinaryOp::Plus(),
                     Closure {
-                        body: Term::Num(7.0).into(),
+                        body: Term::Num(Number::from(7)).into(),
                         env: Environment::new(),
                     },
                     TermPos::None,
@@ -3307,7 +5654,7 @@ mod tests {
         let cont: OperationCont = OperationCont::Op2Second(
             BinaryOp::Plus(),
             Closure {
-                body: Term::Num(7.0).into(),
+                body: Term::Num(Number::from(7)).into(),
                 env: Environment::new(),
             },
             TermPos::None,
@@ -3316,7 +5663,7 @@ mod tests {
 
         let mut vm: VirtualMachine<DummyResolver, EC> = VirtualMachine::new(DummyResolver {});
         let mut clos = Closure {
-            body: Term::Num(6.0).into(),
+            body: Term::Num(Number::from(6)).into(),
             env: Environment::new(),
         };
         vm.stack.push_op_cont(cont, 0, TermPos::None);
@@ -3326,7 +5673,7 @@ mod tests {
         assert_eq!(
             clos,
             Closure {
-                body: Term::Num(13.0).into(),
+                body: Term::Num(Number::from(13)).into(),
                 env: Environment::new()
             }
         );
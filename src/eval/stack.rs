@@ -18,6 +18,12 @@ pub enum Marker<C: Cache> {
     /// computation - are put on the stack as `Eq` elements. If an equality evaluates to `false` at
     /// some point, all the consecutive `Eq` elements at the top of the stack are discarded.
     Eq(Closure, Closure),
+    /// A comparison to perform.
+    ///
+    /// Similar to `Eq`, when comparing two arrays or two records, the abstract machine generates
+    /// the comparisons of their elements, which are put on the stack as `Cmp` elements, and
+    /// evaluated in order as long as the result is `Equal.
+    Cmp(Closure, Closure),
     /// An argument of an application.
     Arg(Closure, TermPos),
     /// A tracked argument. Behave the same as a standard argument, but is given directly as a thunk, such that
@@ -54,6 +60,7 @@ impl<C: Cache> std::fmt::Debug for Marker<C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Marker::Eq(_, _) => write!(f, "Eq"),
+            Marker::Cmp(_, _) => write!(f, "Cmp"),
             Marker::Arg(_, _) => write!(f, "Arg"),
             Marker::TrackedArg(_, _) => write!(f, "TrackedArg"),
             Marker::UpdateIndex(_) => write!(f, "UpdateIndex"),
@@ -82,6 +89,10 @@ impl<C: Cache> Marker<C> {
         matches!(*self, Marker::Eq(..))
     }
 
+    pub fn is_cmp(&self) -> bool {
+        matches!(*self, Marker::Cmp(..))
+    }
+
     pub fn is_str_chunk(&self) -> bool {
         matches!(*self, Marker::StrChunk(..))
     }
@@ -129,6 +140,15 @@ impl<C: Cache> Stack<C> {
         count
     }
 
+    /// Return the number of elements on the stack.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Pops all items in the stack and resets the state of the thunks it encounters.
     pub fn reset(&mut self, cache: &mut C) {
         while let Some(marker) = self.0.pop() {
@@ -167,6 +187,14 @@ impl<C: Cache> Stack<C> {
         self.0.extend(it.map(|(t1, t2)| Marker::Eq(t1, t2)));
     }
 
+    /// Push a sequence of comparisons on the stack.
+    pub fn push_cmps<I>(&mut self, it: I)
+    where
+        I: Iterator<Item = (Closure, Closure)>,
+    {
+        self.0.extend(it.map(|(t1, t2)| Marker::Cmp(t1, t2)));
+    }
+
     /// Push a sequence of string chunks on the stack.
     pub fn push_str_chunks<I>(&mut self, it: I)
     where
@@ -270,6 +298,19 @@ impl<C: Cache> Stack<C> {
         }
     }
 
+    /// Try to pop a comparison from the top of the stack. If `None` is returned, the top element
+    /// was not a comparison and the stack is left unchanged.
+    pub fn pop_cmp(&mut self) -> Option<(Closure, Closure)> {
+        if self.0.last().map(Marker::is_cmp).unwrap_or(false) {
+            match self.0.pop() {
+                Some(Marker::Cmp(c1, c2)) => Some((c1, c2)),
+                _ => panic!(),
+            }
+        } else {
+            None
+        }
+    }
+
     /// Try to pop the a string accumulator from the stack. If `None` is returned, the top element
     /// was not a string accumulator and the stack is left unchanged.
     pub fn pop_str_acc(&mut self) -> Option<(String, usize, Environment)> {
@@ -323,6 +364,12 @@ impl<C: Cache> Stack<C> {
         while self.pop_eq().is_some() {}
     }
 
+    /// Discard all the consecutive comparisons from the top of the stack. This drops the
+    /// continuation of the comparison being currently evaluated.
+    pub fn clear_cmps(&mut self) {
+        while self.pop_cmp().is_some() {}
+    }
+
     /// Turning the top element of the stack into a tracked arg if it was not already. Returns the
     /// corresponding thunk, or `None` if the top element wasn't an argument.
     pub fn track_arg(&mut self, cache: &mut C) -> Option<CacheIndex> {
@@ -408,7 +455,7 @@ mod tests {
         assert_eq!(2, s.count_args());
         assert_eq!(
             some_closure(),
-            s.pop_arg(&CBNCache {}).expect("Already checked").0
+            s.pop_arg(&CBNCache::new()).expect("Already checked").0
         );
         assert_eq!(1, s.count_args());
     }
@@ -457,7 +504,7 @@ mod tests {
     #[test]
     fn pushing_and_poping_strictness_markers() {
         let mut s = Stack::new();
-        let cache = CBNCache {};
+        let cache = CBNCache::new();
         assert_eq!(0, s.count_args());
 
         s.push_strictness(EvalMode::UnwrapMeta);
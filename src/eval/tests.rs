@@ -5,7 +5,7 @@ use crate::eval::cache::CBNCache;
 use crate::label::Label;
 use crate::parser::{grammar, lexer};
 use crate::term::make as mk_term;
-use crate::term::{BinaryOp, StrChunk, UnaryOp};
+use crate::term::{number::Number, BinaryOp, StrChunk, UnaryOp};
 use crate::transform::import_resolution::resolve_imports;
 use crate::{mk_app, mk_fun};
 use codespan::Files;
@@ -31,7 +31,7 @@ fn parse(s: &str) -> Option<RichTerm> {
 
 #[test]
 fn identity_over_values() {
-    let num = Term::Num(45.3);
+    let num = Term::Num(Number::from_signeds(453, 10));
     assert_eq!(Ok(num.clone()), eval_no_import(num.into()));
 
     let boolean = Term::Bool(true);
@@ -61,36 +61,47 @@ fn lone_var_panics() {
 
 #[test]
 fn only_fun_are_applicable() {
-    eval_no_import(mk_app!(Term::Bool(true), Term::Num(45.))).unwrap_err();
+    eval_no_import(mk_app!(Term::Bool(true), Term::Num(Number::from(45)))).unwrap_err();
 }
 
 #[test]
 fn simple_app() {
-    let t = mk_app!(mk_term::id(), Term::Num(5.0));
-    assert_eq!(Ok(Term::Num(5.0)), eval_no_import(t));
+    let t = mk_app!(mk_term::id(), Term::Num(Number::from(5)));
+    assert_eq!(Ok(Term::Num(Number::from(5))), eval_no_import(t));
 }
 
 #[test]
 fn simple_let() {
-    let t = mk_term::let_in("x", Term::Num(5.0), mk_term::var("x"));
-    assert_eq!(Ok(Term::Num(5.0)), eval_no_import(t));
+    let t = mk_term::let_in("x", Term::Num(Number::from(5)), mk_term::var("x"));
+    assert_eq!(Ok(Term::Num(Number::from(5))), eval_no_import(t));
 }
 
 #[test]
 fn simple_ite() {
-    let t = mk_term::if_then_else(Term::Bool(true), Term::Num(5.0), Term::Bool(false));
-    assert_eq!(Ok(Term::Num(5.0)), eval_no_import(t));
+    let t = mk_term::if_then_else(
+        Term::Bool(true),
+        Term::Num(Number::from(5)),
+        Term::Bool(false),
+    );
+    assert_eq!(Ok(Term::Num(Number::from(5))), eval_no_import(t));
 }
 
 #[test]
 fn simple_plus() {
-    let t = mk_term::op2(BinaryOp::Plus(), Term::Num(5.0), Term::Num(7.5));
-    assert_eq!(Ok(Term::Num(12.5)), eval_no_import(t));
+    let t = mk_term::op2(
+        BinaryOp::Plus(),
+        Term::Num(Number::from(5)),
+        Term::Num(Number::from_signeds(15, 2)),
+    );
+    assert_eq!(
+        Ok(Term::Num(Number::from_signeds(25, 2))),
+        eval_no_import(t)
+    );
 }
 
 #[test]
 fn asking_for_various_types() {
-    let num = mk_term::op1(UnaryOp::Typeof(), Term::Num(45.3));
+    let num = mk_term::op1(UnaryOp::Typeof(), Term::Num(Number::from_signeds(453, 10)));
     assert_eq!(Ok(Term::Enum("Num".into())), eval_no_import(num));
 
     let boolean = mk_term::op1(UnaryOp::Typeof(), Term::Bool(true));
@@ -131,18 +142,18 @@ fn enriched_terms_unwrapping() {
 fn merge_enriched_default() {
     let t = mk_term::op2(
         BinaryOp::Merge(),
-        Term::Num(1.0),
-        mk_default(Term::Num(2.0).into()),
+        Term::Num(Number::from(1)),
+        mk_default(Term::Num(Number::from(2)).into()),
     );
-    assert_eq!(Ok(Term::Num(1.0)), eval_no_import(t));
+    assert_eq!(Ok(Term::Num(Number::from(1))), eval_no_import(t));
 }
 
 #[test]
 fn merge_incompatible_defaults() {
     let t = mk_term::op2(
         BinaryOp::Merge(),
-        mk_default(Term::Num(1.0).into()),
-        mk_default(Term::Num(2.0).into()),
+        mk_default(Term::Num(Number::from(1)).into()),
+        mk_default(Term::Num(Number::from(2)).into()),
     );
 
     eval_no_import(t).unwrap_err();
@@ -205,7 +216,7 @@ fn imports() {
         vm.eval(mk_import_two, &Environment::new(),)
             .map(Term::from)
             .unwrap(),
-        Term::Num(2.0)
+        Term::Num(Number::from(2))
     );
 
     // let x = import "lib" in x.f
@@ -291,35 +302,35 @@ fn initial_env() {
     initial_env.insert(
         Ident::from("g"),
         eval_cache.add(
-            Closure::atomic_closure(Term::Num(1.0).into()),
+            Closure::atomic_closure(Term::Num(Number::from(1)).into()),
             IdentKind::Let,
             BindingType::Normal,
         ),
     );
 
-    let t = mk_term::let_in("x", Term::Num(2.0), mk_term::var("x"));
+    let t = mk_term::let_in("x", Term::Num(Number::from(2)), mk_term::var("x"));
     assert_eq!(
         VirtualMachine::new_with_cache(DummyResolver {}, eval_cache.clone())
             .eval(t, &initial_env)
             .map(Term::from),
-        Ok(Term::Num(2.0))
+        Ok(Term::Num(Number::from(2)))
     );
 
-    let t = mk_term::let_in("x", Term::Num(2.0), mk_term::var("g"));
+    let t = mk_term::let_in("x", Term::Num(Number::from(2)), mk_term::var("g"));
     assert_eq!(
         VirtualMachine::new_with_cache(DummyResolver {}, eval_cache.clone())
             .eval(t, &initial_env)
             .map(Term::from),
-        Ok(Term::Num(1.0))
+        Ok(Term::Num(Number::from(1)))
     );
 
     // Shadowing of the initial environment
-    let t = mk_term::let_in("g", Term::Num(2.0), mk_term::var("g"));
+    let t = mk_term::let_in("g", Term::Num(Number::from(2)), mk_term::var("g"));
     assert_eq!(
         VirtualMachine::new_with_cache(DummyResolver {}, eval_cache.clone())
             .eval(t, &initial_env)
             .map(Term::from),
-        Ok(Term::Num(2.0))
+        Ok(Term::Num(Number::from(2)))
     );
 }
 
@@ -344,7 +355,7 @@ fn substitution() {
     let mut eval_cache = EC::new();
     let initial_env = mk_env(
         vec![
-            ("glob1", Term::Num(1.0).into()),
+            ("glob1", Term::Num(Number::from(1)).into()),
             ("glob2", parse("\"Glob2\"").unwrap()),
             ("glob3", Term::Bool(false).into()),
         ],
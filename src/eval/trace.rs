@@ -0,0 +1,37 @@
+//! Output of the `trace` primitive operation.
+//!
+//! `builtin.trace msg value` emits `msg`, together with the position it was issued from, to a
+//! [TraceSink] owned by the virtual machine, and then proceeds with `value`. By default, traces
+//! are printed on the standard error. Embedders (such as the WASM REPL) can install their own sink
+//! to collect them instead.
+use crate::position::TermPos;
+use std::fmt;
+
+/// A message emitted by the `trace` primitive operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// The traced message.
+    pub message: String,
+    /// The position of the message in the source.
+    pub pos: TermPos,
+    /// A human-readable rendering of `pos`, in the form `<file>:<line>:<column>`, if the import
+    /// resolver was able to compute it.
+    pub location: Option<String>,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "trace [{}]: {}", location, self.message),
+            None => write!(f, "trace: {}", self.message),
+        }
+    }
+}
+
+/// The destination of trace messages.
+pub type TraceSink = Box<dyn FnMut(TraceEvent)>;
+
+/// The default sink, printing the trace messages on the standard error.
+pub fn stderr_sink() -> TraceSink {
+    Box::new(|event| eprintln!("{}", event))
+}
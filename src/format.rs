@@ -0,0 +1,460 @@
+//! String formatting, used by the `%str_format%` primitive operation.
+//!
+//! A format string is a string containing placeholders delimited by braces, which are replaced by
+//! the formatted arguments. The syntax is close to the one of Rust's `format!`:
+//!
+//! - `{}` is replaced by the next positional argument, `{0}` by the first positional argument and
+//!   `{name}` by the named argument `name`. Literal braces are written `{{` and `}}`.
+//! - A placeholder may be followed by a format specifier, as in `{:>8}` or `{name:08.2}`, of the
+//!   form `[[fill]align][sign]['#']['0'][width]['.' precision][radix]`, where:
+//!     - `align` is one of `<` (left), `^` (center) or `>` (right), and `fill` is the character
+//!       used to pad the value to `width` (a space by default). Strings are left-aligned and
+//!       numbers right-aligned by default.
+//!     - `sign` is `+`, to print the sign of non-negative numbers as well.
+//!     - `#` prefixes numbers printed in a radix with `0x`, `0o` or `0b`.
+//!     - `0` pads numbers with zeros after the sign and the prefix.
+//!     - `precision` is the number of digits after the decimal point of a number, or the maximum
+//!       length of a string.
+//!     - `radix` is one of `x` (lowercase hexadecimal), `X` (uppercase hexadecimal), `o` (octal)
+//!       or `b` (binary), which only apply to integers.
+//!
+//! Lengths are measured in extended grapheme clusters, as for `string.length`. Widths and
+//! precisions are limited to [`MAX_WIDTH`], as format strings may come from untrusted sources.
+use crate::identifier::Ident;
+use crate::term::{array::Array, number, RichTerm, Term};
+use malachite_base::num::conversion::traits::IsInteger;
+use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The maximum width or precision accepted in a format specifier, and by the padding primitive
+/// operations. Larger values are rejected instead of trying to allocate arbitrary large strings.
+pub const MAX_WIDTH: usize = 100_000;
+
+/// The arguments substituted in a format string.
+pub enum FormatArgs<'a> {
+    Positional(&'a Array),
+    Named(&'a HashMap<Ident, RichTerm>),
+}
+
+/// An error while formatting, located at a range of byte offsets of the format string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+impl FormatError {
+    fn new(start: usize, end: usize, message: impl Into<String>) -> Self {
+        FormatError {
+            start,
+            end,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// A parsed format specifier.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    radix: Option<char>,
+}
+
+/// Format a string with the given arguments.
+pub fn format(fmt: &str, args: FormatArgs) -> Result<String, FormatError> {
+    let mut result = String::with_capacity(fmt.len());
+    let mut next_positional = 0;
+    let mut chars = fmt.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' if matches!(chars.peek(), Some((_, '{'))) => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if matches!(chars.peek(), Some((_, '}'))) => {
+                chars.next();
+                result.push('}');
+            }
+            '}' => return Err(FormatError::new(start, start + 1, "unmatched `}`")),
+            '{' => {
+                let end = fmt[start..]
+                    .find('}')
+                    .map(|len| start + len)
+                    .ok_or_else(|| FormatError::new(start, fmt.len(), "unclosed placeholder"))?;
+                let placeholder = &fmt[start + 1..end];
+                let (arg, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+                let spec = parse_spec(spec).map_err(|msg| FormatError::new(start, end + 1, msg))?;
+
+                let value = lookup(arg, &args, &mut next_positional)
+                    .map_err(|msg| FormatError::new(start, end + 1, msg))?;
+                let formatted = format_value(value, &spec)
+                    .map_err(|msg| FormatError::new(start, end + 1, msg))?;
+                result.push_str(&formatted);
+
+                // Skip the placeholder.
+                while matches!(chars.next(), Some((i, _)) if i < end) {}
+            }
+            c => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Pad a string to `width` grapheme clusters, with `fill` either on the left or on the right.
+pub fn pad(s: &str, width: usize, fill: &str, left: bool) -> String {
+    let missing = width.saturating_sub(s.graphemes(true).count());
+    let padding = fill.repeat(missing);
+
+    if left {
+        padding + s
+    } else {
+        String::from(s) + &padding
+    }
+}
+
+/// Return the argument referenced by a placeholder.
+fn lookup<'a>(
+    arg: &str,
+    args: &FormatArgs<'a>,
+    next_positional: &mut usize,
+) -> Result<&'a RichTerm, String> {
+    match args {
+        FormatArgs::Positional(array) => {
+            let index = if arg.is_empty() {
+                *next_positional += 1;
+                *next_positional - 1
+            } else {
+                arg.parse::<usize>().map_err(|_| {
+                    format!(
+                        "the named placeholder `{{{}}}` requires a record of arguments",
+                        arg
+                    )
+                })?
+            };
+
+            array.get(index).ok_or_else(|| {
+                format!(
+                    "missing argument: the placeholder refers to the argument {}, but only {} were provided",
+                    index,
+                    array.len()
+                )
+            })
+        }
+        FormatArgs::Named(fields) => {
+            if arg.is_empty() || arg.parse::<usize>().is_ok() {
+                return Err(String::from(
+                    "positional placeholders require an array of arguments",
+                ));
+            }
+
+            fields
+                .get(&Ident::from(arg))
+                .ok_or_else(|| format!("missing argument `{}`", arg))
+        }
+    }
+}
+
+fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let mut result = Spec::default();
+    let mut chars: Vec<char> = spec.chars().collect();
+    let invalid = || format!("invalid format specifier `{}`", spec);
+
+    let align_of = |c: char| match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    };
+
+    if let Some(align) = chars.get(1).copied().and_then(align_of) {
+        result.fill = Some(chars[0]);
+        result.align = Some(align);
+        chars.drain(..2);
+    } else if let Some(align) = chars.first().copied().and_then(align_of) {
+        result.align = Some(align);
+        chars.remove(0);
+    }
+
+    let mut rest = chars.as_slice();
+
+    if let ['+', tail @ ..] = rest {
+        result.plus = true;
+        rest = tail;
+    }
+
+    if let ['#', tail @ ..] = rest {
+        result.alternate = true;
+        rest = tail;
+    }
+
+    if let ['0', tail @ ..] = rest {
+        result.zero = true;
+        rest = tail;
+    }
+
+    let too_large = || {
+        format!(
+            "invalid format specifier `{}`: widths and precisions are limited to {}",
+            spec, MAX_WIDTH
+        )
+    };
+
+    let (width, tail) = take_number(rest).ok_or_else(too_large)?;
+    result.width = width.unwrap_or(0);
+    rest = tail;
+
+    if let ['.', tail @ ..] = rest {
+        let (precision, tail) = take_number(tail).ok_or_else(too_large)?;
+        result.precision = Some(precision.ok_or_else(invalid)?);
+        rest = tail;
+    }
+
+    if result.width > MAX_WIDTH || result.precision.unwrap_or(0) > MAX_WIDTH {
+        return Err(too_large());
+    }
+
+    match rest {
+        [] => (),
+        [radix @ ('x' | 'X' | 'o' | 'b')] => result.radix = Some(*radix),
+        _ => return Err(invalid()),
+    }
+
+    if result.radix.is_some() && result.precision.is_some() {
+        return Err(format!(
+            "invalid format specifier `{}`: a precision can't be used together with a radix",
+            spec
+        ));
+    }
+
+    Ok(result)
+}
+
+/// Parse a decimal number at the start of `chars`, if any. Return `None` on overflow.
+fn take_number(chars: &[char]) -> Option<(Option<usize>, &[char])> {
+    let len = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+
+    if len == 0 {
+        return Some((None, chars));
+    }
+
+    let n = chars[..len].iter().collect::<String>().parse().ok()?;
+    Some((Some(n), &chars[len..]))
+}
+
+fn format_value(value: &RichTerm, spec: &Spec) -> Result<String, String> {
+    match value.as_ref() {
+        Term::Num(n) => format_num(n, spec),
+        Term::MetaValue(meta) if meta.value.is_some() => {
+            // unwrap(): we checked that the value is some in the guard
+            format_value(meta.value.as_ref().unwrap(), spec)
+        }
+        t => {
+            if spec.plus || spec.alternate || spec.zero || spec.radix.is_some() {
+                return Err(String::from(
+                    "the flags `+`, `#`, `0` and the radix only apply to numbers",
+                ));
+            }
+
+            let s = match t {
+                Term::Str(s) => s.clone(),
+                Term::Bool(b) => b.to_string(),
+                Term::Null => String::from("null"),
+                Term::Enum(id) => id.to_string(),
+                t => {
+                    return Err(format!(
+                        "can't format a value of type {}",
+                        t.type_of().unwrap_or_else(|| String::from("Other"))
+                    ))
+                }
+            };
+
+            let s = match spec.precision {
+                Some(precision) => s.graphemes(true).take(precision).collect(),
+                None => s,
+            };
+
+            Ok(align(&s, spec, Align::Left))
+        }
+    }
+}
+
+fn format_num(n: &number::Number, spec: &Spec) -> Result<String, String> {
+    let (digits, prefix) = match spec.radix {
+        Some(radix) => {
+            let (base, prefix) = match radix {
+                'x' | 'X' => (16, "0x"),
+                'o' => (8, "0o"),
+                _ => (2, "0b"),
+            };
+
+            if !n.is_integer() {
+                return Err(format!(
+                    "the radix `{}` only applies to integers, got {}",
+                    radix,
+                    number::format(n)
+                ));
+            }
+
+            // unwrap(): we checked that `n` is an integer
+            let digits = number::format_base(n, base).unwrap();
+            let digits = if radix == 'X' {
+                digits.to_uppercase()
+            } else {
+                digits
+            };
+
+            (digits, if spec.alternate { prefix } else { "" })
+        }
+        None => {
+            let digits = match spec.precision {
+                Some(precision) => number::format_fixed(n, precision),
+                None => number::format(n),
+            };
+
+            (digits, "")
+        }
+    };
+
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None if spec.plus => ("+", digits.as_str()),
+        None => ("", digits.as_str()),
+    };
+
+    if spec.zero {
+        let head = format!("{}{}", sign, prefix);
+        let width = spec.width.saturating_sub(head.len());
+        Ok(format!("{}{:0>width$}", head, digits, width = width))
+    } else {
+        Ok(align(
+            &format!("{}{}{}", sign, prefix, digits),
+            spec,
+            Align::Right,
+        ))
+    }
+}
+
+fn align(s: &str, spec: &Spec, default: Align) -> String {
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    let missing = spec.width.saturating_sub(s.graphemes(true).count());
+
+    match spec.align.unwrap_or(default) {
+        Align::Left => pad(s, spec.width, &fill, false),
+        Align::Right => pad(s, spec.width, &fill, true),
+        Align::Center => {
+            let left = missing / 2;
+            format!("{}{}{}", fill.repeat(left), s, fill.repeat(missing - left))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::term::{make as mk_term, number::Number, Term};
+    use std::rc::Rc;
+
+    fn positional(fmt: &str, args: Vec<RichTerm>) -> Result<String, FormatError> {
+        let array = Array::new(Rc::from(args));
+        format(fmt, FormatArgs::Positional(&array))
+    }
+
+    fn num(s: &str) -> RichTerm {
+        Term::Num(number::parse(s).unwrap()).into()
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            positional("{} + {} = {2}", vec![num("1"), num("2"), num("3")]).unwrap(),
+            "1 + 2 = 3"
+        );
+        assert_eq!(
+            positional("{{{}}}", vec![mk_term::string("x")]).unwrap(),
+            "{x}"
+        );
+
+        let fields = HashMap::from([
+            (Ident::from("name"), mk_term::string("nickel")),
+            (Ident::from("version"), num("0.3")),
+        ]);
+        assert_eq!(
+            format("{name} v{version}", FormatArgs::Named(&fields)).unwrap(),
+            "nickel v0.3"
+        );
+    }
+
+    #[test]
+    fn specifiers() {
+        let check = |fmt: &str, arg: RichTerm, expected: &str| {
+            assert_eq!(positional(fmt, vec![arg]).unwrap(), expected);
+        };
+
+        check("{:5}|", mk_term::string("ab"), "ab   |");
+        check("{:>5}|", mk_term::string("ab"), "   ab|");
+        check("{:*^6}|", mk_term::string("ab"), "**ab**|");
+        check("{:.2}", mk_term::string("abcdef"), "ab");
+        check("{:5}", num("42"), "   42");
+        check("{:<5}|", num("42"), "42   |");
+        check("{:05}", num("-42"), "-0042");
+        check("{:+}", num("42"), "+42");
+        check("{:.2}", num("3.14159"), "3.14");
+        check("{:08.3}", num("-3.14159"), "-003.142");
+        check("{:x}", num("255"), "ff");
+        check("{:#X}", num("255"), "0xFF");
+        check("{:#010b}", num("5"), "0b00000101");
+        check("{:o}", num("-8"), "-10");
+        check(
+            "{}",
+            Term::Num(Number::from(1) / Number::from(4)).into(),
+            "0.25",
+        );
+        check("{:>4}", Term::Bool(true).into(), "true");
+        check("{:3}|", mk_term::string("é☃"), "é☃ |");
+    }
+
+    #[test]
+    fn errors() {
+        let error_at = |fmt: &str, args: Vec<RichTerm>| {
+            let err = positional(fmt, args).unwrap_err();
+            (err.start, err.end)
+        };
+
+        assert_eq!(error_at("ab {", vec![]), (3, 4));
+        assert_eq!(error_at("a } b", vec![]), (2, 3));
+        assert_eq!(error_at("{} {}", vec![num("1")]), (3, 5));
+        assert_eq!(error_at("{name}", vec![num("1")]), (0, 6));
+        assert_eq!(error_at("x{:x}", vec![num("1.5")]), (1, 5));
+        assert_eq!(error_at("{:.2x}", vec![num("1")]), (0, 6));
+        assert_eq!(error_at("{:q}", vec![num("1")]), (0, 4));
+        assert_eq!(error_at("{:+}", vec![mk_term::string("a")]), (0, 4));
+        assert_eq!(error_at("{:99999999999}", vec![num("1")]), (0, 14));
+        assert_eq!(error_at("{:.100001}", vec![num("1")]), (0, 10));
+        assert_eq!(
+            error_at(
+                "{}",
+                vec![Term::Array(Array::default(), Default::default()).into()]
+            ),
+            (0, 2)
+        );
+
+        let fields = HashMap::new();
+        assert!(format("{}", FormatArgs::Named(&fields)).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Hashing of strings and of arbitrary values, used by the `%hash%` and `%hash_value%` primitive
+//! operations.
+//!
+//! Values are hashed structurally: a fully evaluated value is first turned into a canonical byte
+//! encoding, which is then fed to the chosen hash function. The encoding doesn't depend on the
+//! order in which record fields were defined nor on how numbers were written, so that two values
+//! which are equal with respect to `==` always have the same hash.
+use crate::term::{datetime, MetaValue, RichTerm, Term};
+use md5::digest::Digest;
+
+/// A hash function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Return the algorithm corresponding to an enum tag of the stdlib, such as `Sha256`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "Md5" => Some(HashAlgorithm::Md5),
+            "Sha1" => Some(HashAlgorithm::Sha1),
+            "Sha256" => Some(HashAlgorithm::Sha256),
+            "Sha512" => Some(HashAlgorithm::Sha512),
+            "Blake3" => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Hash a sequence of bytes, and return the digest as a lowercase hexadecimal string.
+    pub fn digest(self, bytes: impl AsRef<[u8]>) -> String {
+        fn hex_digest<D: Digest>(bytes: &[u8]) -> String {
+            let mut hasher = D::new();
+            hasher.update(bytes);
+            hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect()
+        }
+
+        let bytes = bytes.as_ref();
+
+        match self {
+            HashAlgorithm::Md5 => hex_digest::<md5::Md5>(bytes),
+            HashAlgorithm::Sha1 => hex_digest::<sha1::Sha1>(bytes),
+            HashAlgorithm::Sha256 => hex_digest::<sha2::Sha256>(bytes),
+            HashAlgorithm::Sha512 => hex_digest::<sha2::Sha512>(bytes),
+            HashAlgorithm::Blake3 => blake3::hash(bytes).to_hex().to_string(),
+        }
+    }
+}
+
+/// Compute the canonical encoding of a fully evaluated and substituted value.
+///
+/// Each value is encoded as a one-byte tag followed by its content, where strings and collections
+/// are prefixed by their length. Record fields are sorted by name and empty optional fields are
+/// skipped, while numbers are encoded as normalized fractions.
+///
+/// On error, return the first subterm which can't be hashed, such as a function.
+pub fn canonical_encoding(rt: &RichTerm) -> Result<Vec<u8>, RichTerm> {
+    let mut buf = Vec::new();
+    encode(rt, &mut buf)?;
+    Ok(buf)
+}
+
+fn encode_len(len: usize, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+fn encode_str(s: &str, buf: &mut Vec<u8>) {
+    encode_len(s.len(), buf);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn encode(rt: &RichTerm, buf: &mut Vec<u8>) -> Result<(), RichTerm> {
+    match rt.as_ref() {
+        Term::Null => buf.push(b'n'),
+        Term::Bool(true) => buf.push(b't'),
+        Term::Bool(false) => buf.push(b'f'),
+        Term::Num(n) => {
+            buf.push(b'#');
+            encode_str(&n.to_string(), buf);
+        }
+        Term::Str(s) => {
+            buf.push(b's');
+            encode_str(s, buf);
+        }
+        Term::Enum(id) => {
+            buf.push(b'e');
+            encode_str(id.label(), buf);
+        }
+        Term::EnumVariant { tag, arg } => {
+            buf.push(b'v');
+            encode_str(tag.label(), buf);
+            encode(arg, buf)?;
+        }
+        Term::DateTime(dt) => {
+            buf.push(b'd');
+            encode_str(&datetime::to_timestamp(dt).to_string(), buf);
+        }
+        Term::Duration(d) => {
+            buf.push(b'u');
+            encode_str(&datetime::duration_to_seconds(d).to_string(), buf);
+        }
+        Term::Array(ts, _) => {
+            buf.push(b'a');
+            encode_len(ts.len(), buf);
+            for t in ts.iter() {
+                encode(t, buf)?;
+            }
+        }
+        Term::Record(record) => {
+            let mut fields: Vec<_> = record
+                .fields
+                .iter()
+                .filter(|(_, t)| !is_empty_optional(t))
+                .collect();
+            fields.sort_by(|(id1, _), (id2, _)| id1.label().cmp(id2.label()));
+
+            buf.push(b'r');
+            encode_len(fields.len(), buf);
+            for (id, t) in fields {
+                encode_str(id.label(), buf);
+                encode(t, buf)?;
+            }
+        }
+        Term::MetaValue(MetaValue {
+            value: Some(ref t), ..
+        }) => encode(t, buf)?,
+        _ => return Err(rt.clone()),
+    }
+
+    Ok(())
+}
+
+/// Determine if a record field is an optional field without definition, which is ignored when
+/// hashing, as it is when serializing. Values being hashed are fully evaluated, so only the shape
+/// of the term is inspected.
+fn is_empty_optional(rt: &RichTerm) -> bool {
+    let mut is_opt = false;
+    let mut rt = rt;
+
+    loop {
+        match rt.as_ref() {
+            Term::MetaValue(meta) => {
+                is_opt = is_opt || meta.opt;
+
+                match meta.value {
+                    Some(ref next) => rt = next,
+                    None => return is_opt,
+                }
+            }
+            _ => return false,
+        }
+    }
+}
@@ -1,17 +1,23 @@
 pub mod cache;
+pub mod debugger;
 pub mod deserialize;
 pub mod destruct;
+pub mod encoding;
 pub mod environment;
 pub mod error;
 pub mod eval;
+pub mod format;
+pub mod hash;
 pub mod identifier;
 pub mod label;
 pub mod parser;
 pub mod position;
 pub mod pretty;
+pub mod profiler;
 pub mod program;
 pub mod repl;
 pub mod serialize;
+pub mod stats;
 pub mod stdlib;
 pub mod term;
 pub mod transform;
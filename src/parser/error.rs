@@ -8,6 +8,8 @@ pub enum LexicalError {
     InvalidEscapeSequence(usize),
     /// Invalid escape ASCII code in a string literal.
     InvalidAsciiEscapeCode(usize),
+    /// Invalid number literal, such as a literal with invalid digits for its radix.
+    InvalidNumLiteral(usize, usize, String),
     /// Generic lexer error
     Generic(usize, usize),
 }
@@ -36,6 +38,12 @@ pub enum ParseError {
     /// A recursive let pattern was encountered. They are not currently supported because we
     /// decided it was too involved to implement them.
     RecursiveLetPattern(RawSpan),
+    /// The alternatives of an or-pattern in a match expression don't bind the same variables.
+    InvalidOrPattern(RawSpan),
+    /// The same variable is bound several times in a let-block `let x = .., x = .. in ..`.
+    DuplicateLetBinding(Ident, RawSpan),
+    /// A default value was given to a dynamic field access, as in `record."%{field}" or default`.
+    DynamicAccessOr(RawSpan),
     /// A type variable is used in ways that imply it has muiltiple different kinds.
     ///
     /// This can happen in several situations, for example:
@@ -44,4 +52,19 @@ pub enum ParseError {
     /// - a variable is used as both a record and enum row variable, e.g. in the
     ///   signature `forall r. [| ; r |] -> { ; r }`.
     TypeVariableKindMismatch { ty_var: Ident, span: RawSpan },
+    /// A type alias was applied to a number of arguments different from its number of parameters.
+    TypeAliasArityMismatch {
+        alias: Ident,
+        expected: usize,
+        found: usize,
+        span: RawSpan,
+    },
+    /// A parameter of a type alias is used as the tail of record rows or enum rows, as in `type R
+    /// r = {foo : Num ; r}`, but the alias is applied to an argument which isn't a record type,
+    /// respectively an enum type, or a type variable.
+    TypeAliasInvalidRowTail {
+        alias: Ident,
+        param: Ident,
+        span: RawSpan,
+    },
 }
@@ -30,6 +30,7 @@
 //! go back to string mode. In our example, this is the second `}`: at this point, the lexer knows
 //! that the coming characters must be lexed as string tokens, and not as normal tokens.
 use crate::parser::error::{LexicalError, ParseError};
+use crate::term::number::{self, Number, Radix};
 use logos::Logos;
 use std::ops::Range;
 
@@ -53,8 +54,14 @@ pub enum NormalToken<'input> {
     // regex for checking identifiers at ../lsp/nls/src/requests/completion.rs
     #[regex("_?[a-zA-Z][_a-zA-Z0-9-']*")]
     Identifier(&'input str),
-    #[regex("[0-9]*\\.?[0-9]+", |lex| lex.slice().parse())]
-    NumLiteral(f64),
+    /// A candidate number literal, in decimal notation or prefixed by `0x`, `0o` or `0b`, and
+    /// possibly containing `_` digit separators. The modal lexer validates it and turns it into a
+    /// `NumLiteral`, or reports an invalid literal.
+    #[regex("([0-9][0-9_]*)?\\.?[0-9][0-9_]*")]
+    #[regex("0[xob][0-9a-zA-Z_]*")]
+    CandidateNumLiteral(&'input str),
+    /// A number literal. Emitted by the modal lexer from a `CandidateNumLiteral`.
+    NumLiteral(Number),
 
     // **IMPORTANT**
     // This regex should be kept in sync with the one for Identifier above.
@@ -71,6 +78,10 @@ pub enum NormalToken<'input> {
     Bool,
     #[token("Str")]
     Str,
+    #[token("DateTime")]
+    DateTime,
+    #[token("Duration")]
+    Duration,
     #[token("Array")]
     Array,
 
@@ -88,8 +99,17 @@ pub enum NormalToken<'input> {
     Let,
     #[token("rec")]
     Rec,
+    /// The `type` keyword, which only starts a type alias definition `type Name params = ...`.
+    /// Anywhere else, `type` is an ordinary identifier: see [`Lexer::starts_type_alias`].
+    #[token("type")]
+    Type,
     #[token("match")]
     Match,
+    /// The `or` keyword, which introduces the default value of a field access `r.foo or default`.
+    /// It is only lexed as a keyword right after a field access: anywhere else, `or` is an
+    /// ordinary identifier.
+    #[token("or")]
+    Or,
 
     #[token("null")]
     Null,
@@ -199,6 +219,10 @@ pub enum NormalToken<'input> {
     RecordInsert,
     #[token("%record_remove%")]
     RecordRemove,
+    #[token("%record_update%")]
+    RecordUpdate,
+    #[token("%record_copy_field%")]
+    RecordCopyField,
     #[token("%record_empty_with_tail%")]
     RecordEmptyWithTail,
     #[token("%record_seal_tail%")]
@@ -209,6 +233,8 @@ pub enum NormalToken<'input> {
     Seq,
     #[token("%deep_seq%")]
     DeepSeq,
+    #[token("%trace%")]
+    Trace,
     #[token("%force%")]
     OpForce,
     #[token("%head%")]
@@ -232,6 +258,32 @@ pub enum NormalToken<'input> {
     ElemAt,
     #[token("%generate%")]
     ArrayGen,
+    #[token("%array_reverse%")]
+    ArrayReverse,
+    #[token("%array_flatten%")]
+    ArrayFlatten,
+    #[token("%array_foldl%")]
+    ArrayFoldl,
+    #[token("%array_filter%")]
+    ArrayFilter,
+    #[token("%array_partition%")]
+    ArrayPartition,
+    #[token("%array_sort%")]
+    ArraySort,
+    #[token("%array_sort_by%")]
+    ArraySortBy,
+    #[token("%array_sort_on%")]
+    ArraySortOn,
+    #[token("%array_min_by%")]
+    ArrayMinBy,
+    #[token("%array_max_by%")]
+    ArrayMaxBy,
+    #[token("%array_range%")]
+    ArrayRange,
+    #[token("%array_zip%")]
+    ArrayZip,
+    #[token("%array_slice%")]
+    ArraySlice,
     #[token("%rec_force%")]
     RecForceOp,
     #[token("%rec_default%")]
@@ -252,6 +304,14 @@ pub enum NormalToken<'input> {
 
     #[token("%hash%")]
     OpHash,
+    #[token("%hash_value%")]
+    HashValue,
+    #[token("%compare%")]
+    Compare,
+    #[token("%encode%")]
+    Encode,
+    #[token("%decode%")]
+    Decode,
     #[token("%serialize%")]
     Serialize,
     #[token("%deserialize%")]
@@ -266,12 +326,34 @@ pub enum NormalToken<'input> {
     CharCode,
     #[token("%char_from_code%")]
     CharFromCode,
+    #[token("%datetime_parse%")]
+    DateTimeParse,
+    #[token("%datetime_to_str%")]
+    DateTimeToStr,
+    #[token("%datetime_to_timestamp%")]
+    DateTimeToTimestamp,
+    #[token("%datetime_from_timestamp%")]
+    DateTimeFromTimestamp,
+    #[token("%duration_parse%")]
+    DurationParse,
+    #[token("%duration_to_str%")]
+    DurationToStr,
+    #[token("%duration_to_seconds%")]
+    DurationToSeconds,
+    #[token("%duration_from_seconds%")]
+    DurationFromSeconds,
+    #[token("%datetime_format%")]
+    DateTimeFormat,
+    #[token("%datetime_add%")]
+    DateTimeAdd,
     #[token("%str_uppercase%")]
     StrUppercase,
     #[token("%str_lowercase%")]
     StrLowercase,
     #[token("%str_contains%")]
     StrContains,
+    #[token("%to_str_radix%")]
+    ToStrRadix,
     #[token("%str_replace%")]
     StrReplace,
     #[token("%str_replace_regex%")]
@@ -280,16 +362,32 @@ pub enum NormalToken<'input> {
     StrIsMatch,
     #[token("%str_find%")]
     StrFind,
+    #[token("%str_find_all%")]
+    StrFindAll,
+    #[token("%str_replace_regex_with%")]
+    StrReplaceRegexWith,
     #[token("%str_length%")]
     StrLength,
     #[token("%str_substr%")]
     StrSubstr,
+    #[token("%str_pad_left%")]
+    StrPadLeft,
+    #[token("%str_pad_right%")]
+    StrPadRight,
+    #[token("%str_format%")]
+    StrFormat,
     #[token("%to_str%")]
     ToStr,
     #[token("%num_from_str%")]
     NumFromStr,
     #[token("%enum_from_str%")]
     EnumFromStr,
+    #[token("%enum_get_tag%")]
+    EnumGetTag,
+    #[token("%enum_get_arg%")]
+    EnumGetArg,
+    #[token("%enum_is_variant%")]
+    EnumIsVariant,
 
     #[token("{")]
     LBrace,
@@ -320,9 +418,9 @@ pub enum NormalToken<'input> {
 }
 
 pub const KEYWORDS: &[&str] = &[
-    "Dyn", "Num", "Bool", "Str", "Array", "if", "then", "else", "forall", "in", "let", "rec",
-    "match", "null", "true", "false", "fun", "import", "merge", "default", "doc", "optional",
-    "priority", "force",
+    "Dyn", "Num", "Bool", "Str", "DateTime", "Duration", "Array", "if", "then", "else", "forall",
+    "in", "let", "rec", "match", "null", "true", "false", "fun", "import", "merge", "default",
+    "doc", "optional", "priority", "force",
 ];
 
 /// The tokens in string mode.
@@ -457,6 +555,14 @@ pub struct Lexer<'input> {
     /// made necessary by an issue of Logos (<https://github.com/maciejhirsz/logos/issues/200>). See
     /// [`MultiStringToken::QuotesCandidateInterpolation`].
     pub buffer: Option<(Token<'input>, Range<usize>)>,
+    /// Whether the previous token is a `.`.
+    prev_dot: bool,
+    /// Whether the previous token ends a field access, such as `r.foo` or `r."foo"`. An `or`
+    /// token is only lexed as a keyword in this case.
+    after_field_access: bool,
+    /// For each string being lexed, whether it started right after a `.`, that is if it is the
+    /// name of the field of a field access such as `r."foo"`.
+    field_strings: Vec<bool>,
 }
 
 impl<'input> Lexer<'input> {
@@ -466,6 +572,9 @@ impl<'input> Lexer<'input> {
             stack: Vec::new(),
             count: 0,
             buffer: None,
+            prev_dot: false,
+            after_field_access: false,
+            field_strings: Vec::new(),
         }
     }
 
@@ -478,6 +587,7 @@ impl<'input> Lexer<'input> {
             //  `Normal`
             Some(ModalLexer::Normal(lexer)) => {
                 self.stack.push(ModeElt::Normal(self.count));
+                self.field_strings.push(self.prev_dot);
                 self.lexer = Some(morph(lexer));
             }
             _ => panic!("lexer::enter_strlike"),
@@ -511,7 +621,9 @@ impl<'input> Lexer<'input> {
         self.count = 0;
     }
 
-    fn leave_str(&mut self) {
+    /// Leave string mode, and return `true` if the string was the name of the field of a field
+    /// access.
+    fn leave_str(&mut self) -> bool {
         match self.lexer.take() {
             Some(ModalLexer::Str(lexer)) => {
                 // We can only enter string mode from normal mode
@@ -524,9 +636,13 @@ impl<'input> Lexer<'input> {
             }
             _ => panic!("lexer::leave_str"),
         }
+
+        self.field_strings.pop().unwrap_or(false)
     }
 
-    fn leave_indstr(&mut self) {
+    /// Leave multiline string mode, and return `true` if the string was the name of the field of a
+    /// field access.
+    fn leave_indstr(&mut self) -> bool {
         match self.lexer.take() {
             Some(ModalLexer::MultiStr(lexer)) => {
                 // We can only enter string mode from normal mode
@@ -539,6 +655,8 @@ impl<'input> Lexer<'input> {
             }
             _ => panic!("lexer::leave_str"),
         }
+
+        self.field_strings.pop().unwrap_or(false)
     }
 
     fn leave_normal(&mut self) {
@@ -586,6 +704,25 @@ impl<'input> Lexer<'input> {
 
         (token, span)
     }
+
+    /// Determine if a `type` token that has just been lexed starts a type alias definition, that
+    /// is if it is followed by at least one identifier and an `=`. `type` is a contextual
+    /// keyword: in any other position, such as `{type = "a"}` or `let {type, ..} = r in type`,
+    /// it is lexed as an identifier.
+    fn starts_type_alias(&self) -> bool {
+        let mut lexer = match self.lexer.as_ref() {
+            Some(ModalLexer::Normal(lexer)) => lexer.clone(),
+            _ => return false,
+        };
+        let mut tokens =
+            std::iter::from_fn(|| lexer.next()).filter(|t| *t != NormalToken::LineComment);
+
+        if !matches!(tokens.next(), Some(NormalToken::Identifier(_))) {
+            return false;
+        }
+
+        tokens.find(|t| !matches!(t, NormalToken::Identifier(_))) == Some(NormalToken::Equals)
+    }
 }
 
 impl<'input> Iterator for Lexer<'input> {
@@ -602,6 +739,7 @@ impl<'input> Iterator for Lexer<'input> {
             let span = lexer.span();
             (token, span)
         };
+        let mut closes_field_access = false;
 
         match token.as_ref() {
             Some(Normal(NormalToken::DoubleQuote | NormalToken::StrEnumTagBegin)) => {
@@ -632,7 +770,7 @@ impl<'input> Iterator for Lexer<'input> {
                 }
             }
             Some(Str(StringToken::DoubleQuote)) => {
-                self.leave_str();
+                closes_field_access = self.leave_str();
                 // To make things simpler on the parser side, we only return one variant for
                 // `DoubleQuote`, namely the the normal one.
                 token = Some(Normal(NormalToken::DoubleQuote));
@@ -708,13 +846,23 @@ impl<'input> Iterator for Lexer<'input> {
             // starting token then it is the end of a multiline string
             Some(MultiStr(MultiStringToken::CandidateEnd(s))) if s.len() == self.count => {
                 token = Some(MultiStr(MultiStringToken::End));
-                self.leave_indstr()
+                closes_field_access = self.leave_indstr();
             }
             // Otherwise, it is just part of the string, so we transform the token into a
             // `Literal` one
             Some(MultiStr(MultiStringToken::CandidateEnd(s))) => {
                 token = Some(MultiStr(MultiStringToken::Literal(s)))
             }
+            Some(Normal(NormalToken::CandidateNumLiteral(s))) => match parse_num_literal(s) {
+                Ok(lit) => token = Some(Normal(NormalToken::NumLiteral(lit))),
+                Err((range, msg)) => {
+                    return Some(Err(ParseError::Lexical(LexicalError::InvalidNumLiteral(
+                        span.start + range.start,
+                        span.start + range.end,
+                        msg,
+                    ))))
+                }
+            },
             // Early report errors for now. This could change in the future
             Some(Normal(NormalToken::Error))
             | Some(Str(StringToken::Error))
@@ -723,15 +871,78 @@ impl<'input> Iterator for Lexer<'input> {
                     span.start, span.end,
                 ))))
             }
+            Some(Normal(NormalToken::Type)) if !self.starts_type_alias() => {
+                token = Some(Normal(NormalToken::Identifier("type")))
+            }
+            Some(Normal(NormalToken::Or)) if !self.after_field_access => {
+                token = Some(Normal(NormalToken::Identifier("or")))
+            }
             // Ignore comment
             Some(Normal(NormalToken::LineComment)) => return self.next(),
             _ => (),
         };
 
+        if let Some(token) = token.as_ref() {
+            self.after_field_access = closes_field_access
+                || (self.prev_dot && matches!(token, Normal(NormalToken::Identifier(_))));
+            self.prev_dot = matches!(token, Normal(NormalToken::Dot));
+        }
+
         token.map(|t| Ok((span.start, t, span.end)))
     }
 }
 
+/// Parse a number literal, in decimal notation or prefixed by `0x`, `0o` or `0b`. Digits can be
+/// separated by `_`. On error, return the range of the offending characters relatively to the
+/// start of the literal, together with a description of the problem.
+pub(crate) fn parse_num_literal(literal: &str) -> Result<Number, (Range<usize>, String)> {
+    let (radix, start) = match literal.get(..2) {
+        Some("0x") => (Radix::Hexadecimal, 2),
+        Some("0o") => (Radix::Octal, 2),
+        Some("0b") => (Radix::Binary, 2),
+        _ => (Radix::Decimal, 0),
+    };
+
+    if literal.len() == start {
+        return Err((
+            0..literal.len(),
+            format!("expected {} digits after `{}`", radix, radix.prefix()),
+        ));
+    }
+
+    let bytes = literal.as_bytes();
+    let is_digit_at = |i: usize| matches!(bytes.get(i), Some(c) if c.is_ascii_alphanumeric());
+    let mut digits = String::with_capacity(literal.len() - start);
+
+    for (i, c) in literal.char_indices().skip(start) {
+        match c {
+            '_' if i > start && is_digit_at(i - 1) && is_digit_at(i + 1) => (),
+            '_' => {
+                return Err((
+                    i..i + 1,
+                    String::from("digit separators must be placed between two digits"),
+                ))
+            }
+            '.' => digits.push(c),
+            c if c.is_digit(radix.base() as u32) => digits.push(c),
+            c => {
+                return Err((
+                    i..i + c.len_utf8(),
+                    format!("invalid digit `{}` in {} literal", c, radix),
+                ))
+            }
+        }
+    }
+
+    let value = match radix {
+        Radix::Decimal => number::parse(&digits),
+        _ => number::parse_base(&digits, radix.base()),
+    };
+
+    // The regexes of `CandidateNumLiteral` and the checks above ensure that `digits` is valid.
+    Ok(value.unwrap())
+}
+
 /// Generate the character corresponding to an escape char.
 fn escape_char(chr: char) -> Option<char> {
     match chr {
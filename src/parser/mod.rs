@@ -39,8 +39,15 @@ impl grammar::ExtendedTermParser {
     ) -> Result<(ExtendedTerm, ParseErrors), ParseError> {
         let mut parse_errors = Vec::new();
         let mut next_wildcard_id = 0;
+        let mut type_aliases = uniterm::TypeAliasEnv::new();
         let result = self
-            .parse(file_id, &mut parse_errors, &mut next_wildcard_id, lexer)
+            .parse(
+                file_id,
+                &mut parse_errors,
+                &mut next_wildcard_id,
+                &mut type_aliases,
+                lexer,
+            )
             .map_err(|err| ParseError::from_lalrpop(err, file_id));
 
         let parse_errors = ParseErrors::from_recoverable(parse_errors, file_id);
@@ -74,8 +81,15 @@ impl grammar::TermParser {
     ) -> Result<(RichTerm, ParseErrors), ParseError> {
         let mut parse_errors = Vec::new();
         let mut wildcard_id = 0;
+        let mut type_aliases = uniterm::TypeAliasEnv::new();
         let result = self
-            .parse(file_id, &mut parse_errors, &mut wildcard_id, lexer)
+            .parse(
+                file_id,
+                &mut parse_errors,
+                &mut wildcard_id,
+                &mut type_aliases,
+                lexer,
+            )
             .map_err(|err| ParseError::from_lalrpop(err, file_id));
 
         let parse_errors = ParseErrors::from_recoverable(parse_errors, file_id);
@@ -4,7 +4,7 @@ use crate::identifier::Ident;
 use crate::parser::error::ParseError as InternalParseError;
 use crate::term::make as mk_term;
 use crate::term::Term::*;
-use crate::term::{record, BinaryOp, RichTerm, StrChunk, UnaryOp};
+use crate::term::{number::Number, record, BinaryOp, RichTerm, StrChunk, UnaryOp};
 use crate::{mk_app, mk_match};
 use assert_matches::assert_matches;
 use codespan::Files;
@@ -36,11 +36,51 @@ fn mk_single_chunk(s: &str) -> RichTerm {
 
 #[test]
 fn numbers() {
-    assert_eq!(parse_without_pos("22"), Num(22.0).into());
-    assert_eq!(parse_without_pos("22.0"), Num(22.0).into());
-    assert_eq!(parse_without_pos("22.22"), Num(22.22).into());
-    assert_eq!(parse_without_pos("(22)"), Num(22.0).into());
-    assert_eq!(parse_without_pos("((22))"), Num(22.0).into());
+    assert_eq!(parse_without_pos("22"), Num(Number::from(22)).into());
+    assert_eq!(parse_without_pos("22.0"), Num(Number::from(22)).into());
+    assert_eq!(
+        parse_without_pos("22.22"),
+        Num(Number::from_signeds(2222, 100)).into()
+    );
+    assert_eq!(parse_without_pos("(22)"), Num(Number::from(22)).into());
+    assert_eq!(parse_without_pos("((22))"), Num(Number::from(22)).into());
+}
+
+#[test]
+fn num_literals() {
+    assert_eq!(
+        parse_without_pos("0xFF00"),
+        Num(Number::from(0xff00)).into()
+    );
+    assert_eq!(
+        parse_without_pos("0xff00"),
+        Num(Number::from(0xff00)).into()
+    );
+    assert_eq!(parse_without_pos("0o644"), Num(Number::from(0o644)).into());
+    assert_eq!(
+        parse_without_pos("0b1010_1010"),
+        Num(Number::from(0b1010_1010)).into()
+    );
+    assert_eq!(
+        parse_without_pos("1_000_000"),
+        Num(Number::from(1_000_000)).into()
+    );
+    assert_eq!(
+        parse_without_pos("1_000.000_5"),
+        Num(Number::from_signeds(10_000_005, 10_000)).into()
+    );
+}
+
+#[test]
+fn invalid_num_literals() {
+    assert_matches!(parse("0o19"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("0xFG"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("0b102"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("0x"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("1__000"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("1000_"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("1_.5"), Err(ParseError::InvalidNumLiteral(..)));
+    assert_matches!(parse("0x_FF"), Err(ParseError::InvalidNumLiteral(..)));
 }
 
 #[test]
@@ -78,14 +118,19 @@ fn strings() {
 fn plus() {
     assert_eq!(
         parse_without_pos("3 + 4"),
-        Op2(BinaryOp::Plus(), Num(3.0).into(), Num(4.).into()).into()
+        Op2(
+            BinaryOp::Plus(),
+            Num(Number::from(3)).into(),
+            Num(Number::from(4)).into()
+        )
+        .into()
     );
     assert_eq!(
         parse_without_pos("(true + false) + 4"),
         Op2(
             BinaryOp::Plus(),
             Op2(BinaryOp::Plus(), Bool(true).into(), Bool(false).into()).into(),
-            Num(4.).into(),
+            Num(Number::from(4)).into(),
         )
         .into()
     );
@@ -101,7 +146,11 @@ fn booleans() {
 fn ite() {
     assert_eq!(
         parse_without_pos("if true then 3 else 4"),
-        mk_app!(mk_term::op1(UnaryOp::Ite(), Bool(true)), Num(3.0), Num(4.0))
+        mk_app!(
+            mk_term::op1(UnaryOp::Ite(), Bool(true)),
+            Num(Number::from(3)),
+            Num(Number::from(4))
+        )
     );
 }
 
@@ -109,12 +158,16 @@ fn ite() {
 fn applications() {
     assert_eq!(
         parse_without_pos("1 true 2"),
-        mk_app!(Num(1.0), Bool(true), Num(2.0))
+        mk_app!(Num(Number::from(1)), Bool(true), Num(Number::from(2)))
     );
 
     assert_eq!(
         parse_without_pos("1 (2 3) 4"),
-        mk_app!(Num(1.0), mk_app!(Num(2.0), Num(3.0)), Num(4.0))
+        mk_app!(
+            Num(Number::from(1)),
+            mk_app!(Num(Number::from(2)), Num(Number::from(3))),
+            Num(Number::from(4))
+        )
     );
 }
 
@@ -180,16 +233,16 @@ fn enum_terms() {
             "match with raw tags",
             "match { `foo => true, `bar => false, _ => 456, } 123",
             mk_app!(
-                mk_match!(("foo", Bool(true)), ("bar", Bool(false)) ; Num(456.)),
-                Num(123.)
+                mk_match!(("foo", Bool(true)), ("bar", Bool(false)) ; Num(Number::from(456))),
+                Num(Number::from(123))
             ),
         ),
         (
             "match with string tags",
             "match { `\"one:two\" => true, `\"three four\" => false, _ => 13 } 1",
             mk_app!(
-                mk_match!(("one:two", Bool(true)), ("three four", Bool(false)) ; Num(13.)),
-                Num(1.)
+                mk_match!(("one:two", Bool(true)), ("three four", Bool(false)) ; Num(Number::from(13))),
+                Num(Number::from(1))
             ),
         ),
     ];
@@ -220,9 +273,9 @@ fn record_terms() {
         RecRecord(
             record::RecordData::with_fields(
                 vec![
-                    (Ident::from("a"), Num(1.).into()),
-                    (Ident::from("b"), Num(2.).into()),
-                    (Ident::from("c"), Num(3.).into()),
+                    (Ident::from("a"), Num(Number::from(1)).into()),
+                    (Ident::from("b"), Num(Number::from(2)).into()),
+                    (Ident::from("c"), Num(Number::from(3)).into()),
                 ]
                 .into_iter()
                 .collect()
@@ -238,15 +291,19 @@ fn record_terms() {
         RecRecord(
             record::RecordData::with_fields(
                 vec![
-                    (Ident::from("a"), Num(1.).into()),
-                    (Ident::from("d"), Num(42.).into()),
+                    (Ident::from("a"), Num(Number::from(1)).into()),
+                    (Ident::from("d"), Num(Number::from(42)).into()),
                 ]
                 .into_iter()
                 .collect()
             ),
             vec![(
-                StrChunks(vec![StrChunk::expr(RichTerm::from(Num(123.)))]).into(),
-                mk_app!(mk_term::op1(UnaryOp::Ite(), Num(4.)), Num(5.), Num(6.))
+                StrChunks(vec![StrChunk::expr(RichTerm::from(Num(Number::from(123))))]).into(),
+                mk_app!(
+                    mk_term::op1(UnaryOp::Ite(), Num(Number::from(4))),
+                    Num(Number::from(5)),
+                    Num(Number::from(6))
+                )
             )],
             None,
         )
@@ -258,8 +315,8 @@ fn record_terms() {
         RecRecord(
             record::RecordData::with_fields(
                 vec![
-                    (Ident::from("a"), Num(1.).into()),
-                    (Ident::from("\"%}%"), Num(2.).into()),
+                    (Ident::from("a"), Num(Number::from(1)).into()),
+                    (Ident::from("\"%}%"), Num(Number::from(2)).into()),
                 ]
                 .into_iter()
                 .collect()
@@ -320,7 +377,7 @@ fn string_lexing() {
                 Token::Normal(NormalToken::DoubleQuote),
                 Token::Str(StringToken::Literal("1 + ")),
                 Token::Str(StringToken::Interpolation),
-                Token::Normal(NormalToken::NumLiteral(1.0)),
+                Token::Normal(NormalToken::NumLiteral(Number::from(1))),
                 Token::Normal(NormalToken::RBrace),
                 Token::Str(StringToken::Literal(" + 2")),
                 Token::Normal(NormalToken::DoubleQuote),
@@ -335,7 +392,7 @@ fn string_lexing() {
                 Token::Str(StringToken::Interpolation),
                 Token::Normal(NormalToken::DoubleQuote),
                 Token::Str(StringToken::Interpolation),
-                Token::Normal(NormalToken::NumLiteral(1.0)),
+                Token::Normal(NormalToken::NumLiteral(Number::from(1))),
                 Token::Normal(NormalToken::RBrace),
                 Token::Normal(NormalToken::DoubleQuote),
                 Token::Normal(NormalToken::RBrace),
@@ -367,7 +424,7 @@ fn string_lexing() {
                 Token::Normal(NormalToken::SymbolicStringStart(3)),
                 Token::MultiStr(MultiStringToken::Literal("text ")),
                 Token::MultiStr(MultiStringToken::Interpolation),
-                Token::Normal(NormalToken::NumLiteral(1.0)),
+                Token::Normal(NormalToken::NumLiteral(Number::from(1))),
                 Token::Normal(NormalToken::RBrace),
                 Token::MultiStr(MultiStringToken::Literal(" etc.")),
                 Token::MultiStr(MultiStringToken::End),
@@ -468,6 +525,101 @@ fn line_comments() {
     );
 }
 
+#[test]
+fn contextual_type_keyword() {
+    use NormalToken::{Equals, Identifier, Type};
+
+    let lex_normal = |s| -> Vec<NormalToken> {
+        lex_without_pos(s)
+            .unwrap()
+            .into_iter()
+            .map(|tok| match tok {
+                Token::Normal(tok) => tok,
+                tok => panic!("unexpected token {:?}", tok),
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        lex_normal("type Port = "),
+        vec![Type, Identifier("Port"), Equals]
+    );
+    assert_eq!(
+        lex_normal("type Pair a # comment\n b ="),
+        vec![
+            Type,
+            Identifier("Pair"),
+            Identifier("a"),
+            Identifier("b"),
+            Equals
+        ]
+    );
+    assert_eq!(lex_normal("type = "), vec![Identifier("type"), Equals]);
+    assert_eq!(
+        lex_normal("type x == "),
+        vec![Identifier("type"), Identifier("x"), NormalToken::DoubleEq]
+    );
+    assert_eq!(lex_normal("type"), vec![Identifier("type")]);
+}
+
+#[test]
+fn contextual_or_keyword() {
+    use NormalToken::{Dot, DoubleQuote, Identifier, Or};
+
+    let lex = |s| lex_without_pos(s).unwrap();
+
+    assert_eq!(
+        lex("r.foo or"),
+        vec![
+            Token::Normal(Identifier("r")),
+            Token::Normal(Dot),
+            Token::Normal(Identifier("foo")),
+            Token::Normal(Or)
+        ]
+    );
+    assert_eq!(
+        lex("r.\"foo\" or"),
+        vec![
+            Token::Normal(Identifier("r")),
+            Token::Normal(Dot),
+            Token::Normal(DoubleQuote),
+            Token::Str(StringToken::Literal("foo")),
+            Token::Normal(DoubleQuote),
+            Token::Normal(Or)
+        ]
+    );
+    assert_eq!(
+        lex("let or = r.or in or"),
+        vec![
+            Token::Normal(NormalToken::Let),
+            Token::Normal(Identifier("or")),
+            Token::Normal(NormalToken::Equals),
+            Token::Normal(Identifier("r")),
+            Token::Normal(Dot),
+            Token::Normal(Identifier("or")),
+            Token::Normal(NormalToken::In),
+            Token::Normal(Identifier("or"))
+        ]
+    );
+}
+
+#[test]
+fn forall_shadows_type_alias() {
+    let annot_type = |s| match parse_without_pos(s).as_ref() {
+        MetaValue(meta) => meta.types.as_ref().unwrap().types.to_string(),
+        t => panic!("expected a type annotation, got {:?}", t),
+    };
+
+    assert_eq!(
+        annot_type("type A = Num in (1 : forall A. A -> A)"),
+        "forall A. A -> A"
+    );
+    assert_eq!(
+        annot_type("type A = Num in (1 : {f : forall A. A -> A, g : A})"),
+        annot_type("(1 : {f : forall A. A -> A, g : Num})")
+    );
+}
+
 /// Regression test for [#942](https://github.com/tweag/nickel/issues/942).
 #[test]
 fn ty_var_kind_mismatch() {
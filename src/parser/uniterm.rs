@@ -8,12 +8,12 @@ use crate::{
     position::{RawSpan, TermPos},
     term::{record::RecordAttrs, Contract, MergePriority, MetaValue, RichTerm, SharedTerm, Term},
     types::{
-        EnumRows, EnumRowsIteratorItem, RecordRow, RecordRows, RecordRowsF, TypeF, Types,
-        UnboundTypeVariableError, VarKind,
+        EnumRows, EnumRowsF, RecordRow, RecordRows, RecordRowsF, RowTailSubstError, TypeAlias,
+        TypeF, Types, UnboundTypeVariableError, VarKind,
     },
 };
 
-use std::{cell::RefCell, convert::TryFrom};
+use std::{cell::RefCell, convert::TryFrom, rc::Rc};
 
 /// A node of the uniterm AST. We only define new variants for those constructs that are common to
 /// types and terms. Otherwise, we piggyback on the existing ASTs to avoid duplicating methods and
@@ -47,6 +47,11 @@ pub enum UniTermNode {
     Term(RichTerm),
     /// A uniterm that has been determined to be a type.
     Types(Types),
+    /// An identifier which refers to a type alias in scope, applied to the arguments parsed so
+    /// far. If it ends up being used as a type, the alias is expanded, and must then have been
+    /// applied to exactly as many arguments as it has parameters. If it is used as a term, aliases
+    /// play no role: the identifier is a variable, and the arguments are regular applications.
+    TypeAlias(Rc<TypeAlias>, Ident, Vec<UniTerm>),
 }
 
 /// A uniterm with positional information.
@@ -69,6 +74,102 @@ impl UniTerm {
         self.pos = pos;
         self
     }
+
+    /// Apply a uniterm to an argument. If `self` is a type alias, the argument is added to the
+    /// arguments of the alias, until we know if it is used as a type or as a term. Otherwise, this
+    /// is a standard application (see [`utils::mk_app_or_variant`]).
+    pub fn app(self, arg: UniTerm) -> Result<UniTerm, ParseError> {
+        match self.node {
+            UniTermNode::TypeAlias(alias, id, mut args) => {
+                args.push(arg);
+                Ok(UniTerm::from(UniTermNode::TypeAlias(alias, id, args)))
+            }
+            node => {
+                let head = RichTerm::try_from(UniTerm {
+                    node,
+                    pos: self.pos,
+                })?;
+                Ok(UniTerm::from(utils::mk_app_or_variant(
+                    head,
+                    RichTerm::try_from(arg)?,
+                )))
+            }
+        }
+    }
+}
+
+/// Expand a type alias applied to arguments, or return an error if the number of arguments doesn't
+/// match the number of parameters.
+fn expand_alias(alias: &TypeAlias, args: Vec<UniTerm>, pos: TermPos) -> Result<Types, ParseError> {
+    if args.len() == alias.params.len() {
+        let spans: Vec<TermPos> = args.iter().map(|arg| arg.pos).collect();
+        let args = args
+            .into_iter()
+            .map(Types::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        alias.instantiate(args).map_err(|RowTailSubstError(param)| {
+            let index = alias.params.iter().position(|p| *p == param).unwrap();
+            ParseError::TypeAliasInvalidRowTail {
+                alias: alias.name,
+                param,
+                span: spans[index].into_opt().unwrap_or_else(|| pos.unwrap()),
+            }
+        })
+    } else {
+        Err(ParseError::TypeAliasArityMismatch {
+            alias: alias.name,
+            expected: alias.params.len(),
+            found: args.len(),
+            span: pos.unwrap(),
+        })
+    }
+}
+
+/// The type aliases in scope at the current point of parsing (see [`TypeAlias`]).
+///
+/// Inside the body of an alias definition, the parameters of the alias shadow the outer aliases of
+/// the same name, and so do the variables bound by a `forall` inside its body: they are recorded
+/// without an associated alias.
+#[derive(Default)]
+pub struct TypeAliasEnv(Vec<(Ident, Option<Rc<TypeAlias>>)>);
+
+impl TypeAliasEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bring the parameters of an alias being defined, or the variables bound by a `forall`, into
+    /// scope.
+    pub fn push_params(&mut self, params: &[Ident]) {
+        self.0.extend(params.iter().map(|param| (*param, None)));
+    }
+
+    /// Bring a type alias into scope.
+    pub fn push_alias(&mut self, alias: TypeAlias) {
+        self.0.push((alias.name, Some(Rc::new(alias))));
+    }
+
+    /// Remove the `n` last parameters or aliases brought into scope.
+    pub fn pop(&mut self, n: usize) {
+        self.0.truncate(self.0.len().saturating_sub(n));
+    }
+
+    /// Create the uniterm corresponding to an identifier: a reference to the corresponding type
+    /// alias if there is one in scope, or a variable otherwise.
+    pub fn var(&self, id: Ident) -> UniTerm {
+        let alias = self
+            .0
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == id)
+            .and_then(|(_, alias)| alias.clone());
+
+        match alias {
+            Some(alias) => UniTerm::from(UniTermNode::TypeAlias(alias, id, Vec::new())),
+            None => UniTerm::from(UniTermNode::Var(id)),
+        }
+    }
 }
 
 impl TryFrom<UniTerm> for Types {
@@ -80,6 +181,7 @@ impl TryFrom<UniTerm> for Types {
             UniTermNode::Record(r) => Types::try_from(r),
             UniTermNode::Types(ty) => Ok(ty),
             UniTermNode::Term(rt) => Ok(Types(TypeF::Flat(rt))),
+            UniTermNode::TypeAlias(alias, _, args) => expand_alias(&alias, args, ut.pos),
         }
     }
 }
@@ -92,21 +194,29 @@ impl TryFrom<UniTerm> for RichTerm {
         let rt = match node {
             UniTermNode::Var(id) => RichTerm::new(Term::Var(id), pos),
             UniTermNode::Record(r) => RichTerm::try_from(r)?,
-            UniTermNode::Types(mut ty) => {
-                ty.fix_type_vars(pos.unwrap())?;
-                ty.contract().map_err(|UnboundTypeVariableError(id)| {
-                    // We unwrap the position of the identifier, which must be set at this stage of parsing
-                    let pos = id.pos;
-                    ParseError::UnboundTypeVariables(vec![id], pos.unwrap())
-                })?
-            }
+            UniTermNode::Types(ty) => types_to_contract(ty, pos)?,
             UniTermNode::Term(rt) => rt,
+            UniTermNode::TypeAlias(_, id, args) => args
+                .into_iter()
+                .try_fold(RichTerm::new(Term::Var(id), id.pos), |head, arg| {
+                    Ok(utils::mk_app_or_variant(head, RichTerm::try_from(arg)?))
+                })?,
         };
 
         Ok(rt.with_pos(pos))
     }
 }
 
+/// Convert a type used in term position to the corresponding contract.
+fn types_to_contract(mut ty: Types, pos: TermPos) -> Result<RichTerm, ParseError> {
+    ty.fix_type_vars(pos.unwrap())?;
+    ty.contract().map_err(|UnboundTypeVariableError(id)| {
+        // We unwrap the position of the identifier, which must be set at this stage of parsing
+        let pos = id.pos;
+        ParseError::UnboundTypeVariables(vec![id], pos.unwrap())
+    })
+}
+
 impl From<RichTerm> for UniTerm {
     fn from(rt: RichTerm) -> Self {
         let pos = rt.pos;
@@ -478,6 +588,8 @@ impl FixTypeVars for Types {
         match self.0 {
             TypeF::Dyn
             | TypeF::Num
+            | TypeF::DateTime
+            | TypeF::Duration
             | TypeF::Bool
             | TypeF::Str
             | TypeF::Sym
@@ -563,26 +675,26 @@ impl FixTypeVars for EnumRows {
         bound_vars: BoundVarEnv,
         span: RawSpan,
     ) -> Result<(), ParseError> {
-        // An enum row doesn't contain any subtypes (beside other enum rows). No term variable can
-        // appear in it, so we don't have to traverse for fixing type variables properly.
-        //
-        // However, the second task of the fix_type_vars phase is to determine the variable kind of
-        // forall binders: here, we do need to check if the fail of this enum is an enum row type
-        // variable.
-        let mut iter = self
-            .iter()
-            .skip_while(|item| matches!(item, EnumRowsIteratorItem::Row(_)));
-        match iter.next() {
-            Some(EnumRowsIteratorItem::TailVar(id)) => {
+        match self.0 {
+            EnumRowsF::Empty => Ok(()),
+            // As for record rows, we don't fix `TailVar`, but we have to set the correct kind for
+            // the corresponding forall binder.
+            EnumRowsF::TailVar(ref id) => {
                 if let Some(cell) = bound_vars.get(id) {
                     cell.set_or_check_equal(VarKind::EnumRows)
                         .map_err(|_| ParseError::TypeVariableKindMismatch { ty_var: *id, span })?;
                 }
                 Ok(())
             }
-            // unreachable(): we consumed all the rows item via the `take_while()` call above
-            Some(EnumRowsIteratorItem::Row(_)) => unreachable!(),
-            None => Ok(()),
+            EnumRowsF::Extend {
+                ref mut row,
+                ref mut tail,
+            } => {
+                if let Some(ref mut ty) = row.types {
+                    ty.fix_type_vars_env(bound_vars.clone(), span)?;
+                }
+                tail.fix_type_vars_env(bound_vars, span)
+            }
         }
     }
 }
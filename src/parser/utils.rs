@@ -1,7 +1,7 @@
 //! Various helpers and companion code for the parser are put here to keep the grammar definition
 //! uncluttered.
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::rc::Rc;
 
@@ -10,7 +10,7 @@ use codespan::FileId;
 use super::error::ParseError;
 
 use crate::{
-    destruct::Destruct,
+    destruct::{BranchPattern, Destruct, MatchBranch},
     eval::operation::RecPriority,
     identifier::Ident,
     label::Label,
@@ -58,13 +58,6 @@ pub enum StringEndDelimiter {
     Special,
 }
 
-/// Distinguish between a normal case `id => exp` and a default case `_ => exp`.
-#[derive(Clone, Debug)]
-pub enum MatchCase {
-    Normal(Ident, RichTerm),
-    Default(RichTerm),
-}
-
 /// Left hand side of a record field declaration.
 #[derive(Clone, Debug)]
 pub enum FieldPathElem {
@@ -266,6 +259,36 @@ pub fn mk_access(access: RichTerm, root: RichTerm) -> RichTerm {
     }
 }
 
+/// Generate the access with default `record.field_1. .. .field_n or default`, given the parsed
+/// access chain `record.field_1. .. .field_n` and the default value.
+///
+/// The longest suffix of static accesses of the chain becomes the path of the access. Fails if the
+/// last access of the chain is dynamic, which isn't supported.
+pub fn mk_access_or(
+    chain: RichTerm,
+    default: RichTerm,
+    span: RawSpan,
+) -> Result<RichTerm, ParseError> {
+    let mut path = Vec::new();
+    let mut record = chain;
+
+    while let Term::Op1(UnaryOp::StaticAccess(id), ref t) = *record.term {
+        path.push(id);
+        let next = t.clone();
+        record = next;
+    }
+
+    if path.is_empty() {
+        return Err(ParseError::DynamicAccessOr(span));
+    }
+
+    path.reverse();
+    Ok(mk_app!(
+        mk_term::op1(UnaryOp::StaticAccessOr(path), record),
+        default
+    ))
+}
+
 /// Elaborate a record field definition specified as a path, like `a.b.c = foo`, into a regular
 /// flat definition `a = {b = {c = foo}}`.
 ///
@@ -488,6 +511,64 @@ pub fn mk_let(
     Ok(result)
 }
 
+/// A binding `<pattern> = <value>` of a let-block with several bindings, as parsed.
+pub struct LetBinding {
+    pub id: Option<Ident>,
+    pub pat: Destruct,
+    pub value: RichTerm,
+    pub span: RawSpan,
+}
+
+/// Generate a `LetBlock` from the parsing of a let definition with several bindings.
+///
+/// Destructuring bindings are bound to a fresh variable in the block, and are destructured at the
+/// beginning of the body. This function fails if the same variable is bound twice, or if the block
+/// is recursive and has a destructuring binding (see [mk_let]).
+pub fn mk_let_block(
+    rec: bool,
+    bindings: Vec<LetBinding>,
+    body: RichTerm,
+    span: RawSpan,
+) -> Result<RichTerm, ParseError> {
+    let mut bound = HashSet::new();
+
+    for binding in bindings.iter() {
+        let mut vars: Vec<Ident> = binding.id.into_iter().collect();
+        binding.pat.bound_vars(&mut vars);
+
+        if let Some(id) = vars.into_iter().find(|id| !bound.insert(*id)) {
+            return Err(ParseError::DuplicateLetBinding(id, binding.span));
+        }
+    }
+
+    let mut block = Vec::with_capacity(bindings.len());
+    let mut destructs = Vec::new();
+
+    for LetBinding { id, pat, value, .. } in bindings {
+        match (id, pat) {
+            (Some(id), Destruct::Empty) => block.push((id, value)),
+            (id, pat) => {
+                if rec {
+                    return Err(ParseError::RecursiveLetPattern(span));
+                }
+
+                let fresh = Ident::fresh();
+                block.push((fresh, value));
+                destructs.push((id, pat, fresh));
+            }
+        }
+    }
+
+    let body = destructs
+        .into_iter()
+        .rev()
+        .fold(body, |body, (id, pat, fresh)| {
+            mk_term::let_pat(id, pat, Term::Var(fresh), body)
+        });
+
+    Ok(mk_term::let_block(rec, block, body))
+}
+
 /// Determine the minimal level of indentation of a multi-line string.
 ///
 /// The result is determined by computing the minimum indentation level among all lines, where the
@@ -709,3 +790,54 @@ pub fn strip_indent_doc(doc: String) -> String {
         .next()
         .expect("expected non-empty chunks after indentation of documentation")
 }
+
+/// Generate the application of `t1` to `t2`. If `t1` is an enum tag, such as in `` `Ok 5 ``,
+/// generate an enum variant carrying `t2` as an argument instead.
+pub fn mk_app_or_variant(t1: RichTerm, t2: RichTerm) -> RichTerm {
+    match t1.as_ref() {
+        Term::Enum(tag) => RichTerm::from(Term::EnumVariant { tag: *tag, arg: t2 }),
+        _ => mk_app!(t1, t2),
+    }
+}
+
+/// Generate a match expression from its branches. If there is no guard and all the patterns are
+/// distinct enum tags, except maybe a last wildcard, this function generates a [Term::Match], which
+/// is evaluated natively. Otherwise, it generates a [Term::MatchPattern], whose branches are tried
+/// in order.
+pub fn mk_match(branches: Vec<MatchBranch>) -> Term {
+    let mut cases = HashMap::with_capacity(branches.len());
+    let mut default = None;
+    let last_index = branches.len().saturating_sub(1);
+
+    for (i, branch) in branches.iter().enumerate() {
+        match branch {
+            MatchBranch {
+                pattern: BranchPattern::Enum(id),
+                guard: None,
+                body,
+            } if !cases.contains_key(id) => {
+                cases.insert(*id, body.clone());
+            }
+            MatchBranch {
+                pattern: BranchPattern::Wildcard,
+                guard: None,
+                body,
+            } if i == last_index => default = Some(body.clone()),
+            _ => return Term::MatchPattern(branches),
+        }
+    }
+
+    Term::Match { cases, default }
+}
+
+/// Generate an or-pattern from its alternatives. This function fails if the alternatives don't
+/// all bind the same variables.
+pub fn mk_or_pattern(alts: Vec<BranchPattern>, span: RawSpan) -> Result<BranchPattern, ParseError> {
+    let vars = alts.first().map(BranchPattern::bound_vars);
+
+    if alts.iter().any(|alt| Some(alt.bound_vars()) != vars) {
+        Err(ParseError::InvalidOrPattern(span))
+    } else {
+        Ok(BranchPattern::Or(alts))
+    }
+}
@@ -4,7 +4,8 @@
 //! raw byte indices.  They are prefixed with Raw to differentiate them from codespan's types and
 //! indicate that they do not store human friendly data like lines and columns.
 use codespan::{ByteIndex, FileId};
-use std::cmp::{max, min, Ordering};
+use std::cmp::{max, min, Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
 
 /// A position identified by a byte offset in a file.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -137,3 +138,58 @@ impl PartialOrd for RawSpan {
         }
     }
 }
+
+/// A map indexed by spans, which can find the innermost span containing a given span without
+/// scanning all the entries.
+///
+/// The spans of a file are expected to be either nested or disjoint, as the spans of the terms of
+/// a parsed source are.
+#[derive(Clone, Debug)]
+pub struct SpanMap<V>(HashMap<FileId, BTreeMap<(ByteIndex, Reverse<ByteIndex>), V>>);
+
+impl<V> Default for SpanMap<V> {
+    fn default() -> Self {
+        SpanMap(HashMap::new())
+    }
+}
+
+impl<V> SpanMap<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, span: RawSpan, value: V) -> Option<V> {
+        self.0
+            .entry(span.src_id)
+            .or_default()
+            .insert((span.start, Reverse(span.end)), value)
+    }
+
+    pub fn get(&self, span: &RawSpan) -> Option<&V> {
+        self.0
+            .get(&span.src_id)?
+            .get(&(span.start, Reverse(span.end)))
+    }
+
+    /// Return the innermost span of the map containing `span`, together with its value.
+    pub fn innermost_containing(&self, span: &RawSpan) -> Option<(RawSpan, &V)> {
+        // Entries are ordered by increasing start, and then by decreasing end. Walking back from
+        // the last entry which starts before `span`, the first one which also ends after it is
+        // thus the innermost.
+        self.0
+            .get(&span.src_id)?
+            .range(..=(span.start, Reverse(ByteIndex(0))))
+            .rev()
+            .find(|((_, Reverse(end)), _)| *end >= span.end)
+            .map(|(&(start, Reverse(end)), value)| {
+                (
+                    RawSpan {
+                        src_id: span.src_id,
+                        start,
+                        end,
+                    },
+                    value,
+                )
+            })
+    }
+}
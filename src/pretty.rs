@@ -1,10 +1,18 @@
-use crate::destruct::{self, Destruct};
-use crate::parser::lexer::KEYWORDS;
-use crate::term::{BinaryOp, MetaValue, RichTerm, Term, UnaryOp};
+use crate::destruct::{self, BranchPattern, Destruct};
+use crate::parser::lexer::{parse_num_literal, KEYWORDS};
+use crate::position::TermPos;
+use crate::term::{
+    datetime,
+    number::{self, Number},
+    BinaryOp, MetaValue, RichTerm, Term, UnaryOp,
+};
 use crate::types::{EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types};
+use codespan::{Files, Span};
+use pretty::{BoxDoc, DocPtr};
 pub use pretty::{DocAllocator, DocBuilder, Pretty};
 use regex::Regex;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Helper to find the min number of `%` sign needed to interpolate a string containing this chunk.
 fn min_interpolate_sign(text: &str) -> usize {
@@ -38,6 +46,61 @@ fn sorted_map<K: Ord, V>(m: &'_ HashMap<K, V>) -> Vec<(&'_ K, &'_ V)> {
 
 impl<'a, A: Clone + 'a> NickelAllocatorExt<'a, A> for pretty::BoxAllocator {}
 
+/// An allocator which prints number literals in their original form, such as `0xFF00` or
+/// `1_000_000`, by looking them up in the sources they come from. Other allocators print numbers
+/// in decimal notation.
+pub struct SourceAllocator<'src> {
+    files: &'src Files<String>,
+}
+
+impl<'src> SourceAllocator<'src> {
+    pub fn new(files: &'src Files<String>) -> Self {
+        SourceAllocator { files }
+    }
+}
+
+impl<'a, A: 'a> DocAllocator<'a, A> for SourceAllocator<'_> {
+    type Doc = BoxDoc<'a, A>;
+
+    fn alloc(&'a self, doc: pretty::Doc<'a, Self::Doc, A>) -> Self::Doc {
+        BoxDoc::new(doc)
+    }
+
+    fn alloc_column_fn(
+        &'a self,
+        f: impl Fn(usize) -> Self::Doc + 'a,
+    ) -> <Self::Doc as DocPtr<'a, A>>::ColumnFn {
+        Rc::new(f)
+    }
+
+    fn alloc_width_fn(
+        &'a self,
+        f: impl Fn(isize) -> Self::Doc + 'a,
+    ) -> <Self::Doc as DocPtr<'a, A>>::WidthFn {
+        Rc::new(f)
+    }
+}
+
+impl<'a, A: Clone + 'a> NickelAllocatorExt<'a, A> for SourceAllocator<'_> {
+    fn num(&'a self, n: &Number, pos: TermPos) -> DocBuilder<'a, Self, A> {
+        // The position of a number may not be the one of its literal, e.g. after a program
+        // transformation: we only use the source if it is a literal denoting the same number.
+        let literal = match pos {
+            TermPos::Original(span) => self
+                .files
+                .source_slice(span.src_id, Span::new(span.start, span.end))
+                .ok()
+                .filter(|literal| parse_num_literal(literal).is_ok_and(|m| m == *n)),
+            _ => None,
+        };
+
+        match literal {
+            Some(literal) => self.text(literal),
+            None => self.text(number::format(n)),
+        }
+    }
+}
+
 trait NickelAllocatorExt<'a, A: 'a>: DocAllocator<'a, A> + Sized
 where
     Self::Doc: Clone,
@@ -52,6 +115,11 @@ where
         }
     }
 
+    /// Print a number, located at `pos`.
+    fn num(&'a self, n: &Number, _pos: TermPos) -> DocBuilder<'a, Self, A> {
+        self.text(number::format(n))
+    }
+
     fn escaped_string(&'a self, s: &str) -> DocBuilder<'a, Self, A> {
         let s = s
             .replace('\\', "\\\\")
@@ -147,6 +215,13 @@ where
             BoolAnd() => allocator.space().append(allocator.text("&&")),
             BoolOr() => allocator.space().append(allocator.text("||")),
             StaticAccess(id) => allocator.text(".").append(allocator.quote_if_needed(id)),
+            StaticAccessOr(path) => allocator
+                .concat(
+                    path.iter()
+                        .map(|id| allocator.text(".").append(allocator.quote_if_needed(id))),
+                )
+                .append(allocator.space())
+                .append(allocator.text("or")),
             Embed(id) => allocator
                 .text("%embed%")
                 .append(allocator.space())
@@ -252,8 +327,86 @@ where
                     allocator.nil()
                 })
                 .braces(),
+            Destruct::Array {
+                matches,
+                open,
+                rest,
+                ..
+            } => allocator
+                .intersperse(
+                    matches.iter().map(|(id, pat)| match (id, pat) {
+                        (Some(id), Destruct::Empty) => allocator.as_string(id),
+                        (Some(id), pat) => allocator
+                            .as_string(id)
+                            .append(allocator.space())
+                            .append(allocator.text("@"))
+                            .append(allocator.space())
+                            .append(pat.pretty(allocator)),
+                        (None, pat) => pat.pretty(allocator),
+                    }),
+                    allocator.text(",").append(allocator.space()),
+                )
+                .append(if *open {
+                    if matches.is_empty() {
+                        allocator.nil()
+                    } else {
+                        allocator.text(",").append(allocator.space())
+                    }
+                    .append(allocator.text(".."))
+                    .append(if let Some(rest) = rest {
+                        allocator.as_string(rest)
+                    } else {
+                        allocator.nil()
+                    })
+                } else {
+                    allocator.nil()
+                })
+                .brackets(),
             Destruct::Empty => allocator.nil(),
-            _ => unimplemented!(),
+        }
+    }
+}
+
+impl<'a, D, A> Pretty<'a, D, A> for &BranchPattern
+where
+    D: NickelAllocatorExt<'a, A>,
+    D::Doc: Clone,
+    A: Clone + 'a,
+{
+    fn pretty(self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        match self {
+            BranchPattern::Wildcard => allocator.text("_"),
+            BranchPattern::Enum(id) => allocator.text("`").append(allocator.quote_if_needed(id)),
+            BranchPattern::Variant(id, arg) => {
+                let arg_doc = match arg.as_ref() {
+                    BranchPattern::Variant(..) | BranchPattern::Or(_) => {
+                        arg.as_ref().pretty(allocator).parens()
+                    }
+                    _ => arg.as_ref().pretty(allocator),
+                };
+
+                allocator
+                    .text("`")
+                    .append(allocator.quote_if_needed(id))
+                    .append(allocator.space())
+                    .append(arg_doc)
+            }
+            BranchPattern::Constant(t) => t.clone().pretty(allocator),
+            BranchPattern::Destruct(Some(id), Destruct::Empty) => allocator.as_string(id),
+            BranchPattern::Destruct(Some(id), pat) => allocator
+                .as_string(id)
+                .append(allocator.space())
+                .append(allocator.text("@"))
+                .append(allocator.space())
+                .append(pat.pretty(allocator)),
+            BranchPattern::Destruct(None, pat) => pat.pretty(allocator),
+            BranchPattern::Or(alts) => allocator.intersperse(
+                alts.iter().map(|alt| alt.pretty(allocator)),
+                allocator
+                    .space()
+                    .append(allocator.text("|"))
+                    .append(allocator.space()),
+            ),
         }
     }
 }
@@ -270,8 +423,28 @@ where
         match self.as_ref() {
             Null => allocator.text("null"),
             Bool(v) => allocator.as_string(v),
-            Num(v) => allocator.as_string(v),
+            Num(v) => allocator.num(v, self.pos),
             Str(v) => allocator.escaped_string(v).double_quotes(),
+            // Datetimes which can't be represented in the RFC 3339 format are printed as a
+            // timestamp, losing their offset.
+            DateTime(v) => match datetime::format(v) {
+                Ok(s) => allocator
+                    .text("datetime.parse")
+                    .append(allocator.space())
+                    .append(allocator.as_string(s).double_quotes()),
+                Err(_) => allocator
+                    .text("datetime.from_timestamp")
+                    .append(allocator.space())
+                    .append(allocator.text(number::format(&datetime::to_timestamp(v)))),
+            },
+            Duration(v) => allocator
+                .text("duration.parse")
+                .append(allocator.space())
+                .append(
+                    allocator
+                        .as_string(datetime::format_duration(v))
+                        .double_quotes(),
+                ),
             StrChunks(chunks) => {
                 let multiline = chunks.len() > 1;
                 let nb_perc = chunks
@@ -393,6 +566,55 @@ where
                 .append(allocator.line())
                 .append(body.to_owned().pretty(allocator))
                 .group(),
+            LetBlock(bindings, body, attrs) => allocator
+                .text("let")
+                .append(allocator.space())
+                .append(if attrs.rec {
+                    allocator.text("rec").append(allocator.space())
+                } else {
+                    allocator.nil()
+                })
+                .append(
+                    allocator
+                        .intersperse(
+                            bindings.iter().map(|(id, rt)| {
+                                allocator
+                                    .as_string(id)
+                                    .append(if let MetaValue(ref mv) = rt.as_ref() {
+                                        allocator.space().append(allocator.metadata(mv, false))
+                                    } else {
+                                        allocator.nil()
+                                    })
+                                    .append(allocator.space())
+                                    .append(allocator.text("="))
+                                    .append(allocator.line())
+                                    .append(
+                                        if let MetaValue(crate::term::MetaValue {
+                                            value: Some(rt),
+                                            ..
+                                        }) = rt.as_ref()
+                                        {
+                                            rt
+                                        } else {
+                                            rt
+                                        }
+                                        .to_owned()
+                                        .pretty(allocator)
+                                        .nest(2),
+                                    )
+                                    .group()
+                            }),
+                            allocator.text(",").append(allocator.line()),
+                        )
+                        .nest(2),
+                )
+                .append(allocator.line())
+                .append(allocator.text("in"))
+                .append(allocator.line_())
+                .group()
+                .append(allocator.line())
+                .append(body.to_owned().pretty(allocator))
+                .group(),
             LetPattern(opt_id, dst, rt, body) => allocator
                 .text("let")
                 .append(allocator.space())
@@ -464,6 +686,12 @@ where
             },
             Var(id) => allocator.as_string(id),
             Enum(id) => allocator.text("`").append(allocator.quote_if_needed(id)),
+            EnumVariant { tag, arg } => allocator
+                .text("`")
+                .append(allocator.quote_if_needed(tag))
+                .append(allocator.line())
+                .append(allocator.atom(arg))
+                .group(),
             Record(record) => allocator
                 .line()
                 .append(allocator.intersperse(
@@ -610,6 +838,33 @@ where
                     .braces()
                     .group(),
             ),
+            MatchPattern(branches) => allocator.text("match").append(allocator.space()).append(
+                allocator
+                    .intersperse(
+                        branches.iter().map(|branch| {
+                            branch
+                                .pattern
+                                .pretty(allocator)
+                                .append(branch.guard.clone().map_or(allocator.nil(), |guard| {
+                                    allocator
+                                        .space()
+                                        .append(allocator.text("if"))
+                                        .append(allocator.space())
+                                        .append(guard.pretty(allocator))
+                                }))
+                                .append(allocator.space())
+                                .append(allocator.text("=>"))
+                                .append(allocator.space())
+                                .append(branch.body.clone().pretty(allocator))
+                                .append(allocator.text(","))
+                        }),
+                        allocator.line(),
+                    )
+                    .nest(2)
+                    .append(allocator.line_())
+                    .braces()
+                    .group(),
+            ),
             Array(fields, _) => allocator
                 // NOTE: the Array attributes are ignored here.
                 .line()
@@ -643,7 +898,7 @@ where
                     .append(op.pretty(allocator))
                     .append(rtl.to_owned().pretty(allocator))
             } else {
-                if (&BinaryOp::Sub(), &Num(0.0)) == (op, rtl.as_ref()) {
+                if (&BinaryOp::Sub(), &Num(Number::from(0))) == (op, rtl.as_ref()) {
                     allocator.text("-")
                 } else if let crate::term::OpPos::Prefix = op.pos() {
                     op.pretty(allocator)
@@ -719,7 +974,18 @@ where
                 .append(allocator.space())
                 .append(allocator.as_string(id)),
             EnumRowsF::Extend { row, tail } => {
-                let builder = allocator.text("`").append(allocator.quote_if_needed(&row));
+                let builder = allocator
+                    .text("`")
+                    .append(allocator.quote_if_needed(&row.id));
+                let builder = match row.types {
+                    Some(ty) if ty.fmt_is_atom() => builder
+                        .append(allocator.space())
+                        .append(ty.pretty(allocator)),
+                    Some(ty) => builder
+                        .append(allocator.space())
+                        .append(ty.pretty(allocator).nest(2).parens()),
+                    None => builder,
+                };
                 let builder = if let EnumRowsF::Extend { .. } = tail.0 {
                     builder
                         .append(allocator.text(","))
@@ -790,6 +1056,8 @@ where
             Num => allocator.text("Num"),
             Bool => allocator.text("Bool"),
             Str => allocator.text("Str"),
+            DateTime => allocator.text("DateTime"),
+            Duration => allocator.text("Duration"),
             Array(ty) => allocator
                 .text("Array")
                 .group()
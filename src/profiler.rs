@@ -0,0 +1,399 @@
+//! Evaluation profiler.
+//!
+//! The profiler is installed as [evaluation hooks][crate::eval::hooks::EvalHooks] and measures,
+//! for each reduction step of the virtual machine, the time it takes. The cost of a step is
+//! attributed to:
+//!
+//! - the top-level field being evaluated, as reconstructed from the call stack (see
+//!   [crate::eval::callstack::FieldPaths]). The fields of the standard library are ignored. Steps
+//!   performed outside of any field are attributed to `<toplevel>`;
+//! - the contract being checked, if the reduced term comes from the definition of a contract which
+//!   has been applied before. Only the steps performed in the code of the contract itself are
+//!   counted, and not the ones performed in the functions it calls.
+//!
+//! The profiler also counts the contract applications and the accesses to the thunk cache: a
+//! variable whose thunk has already been evaluated is a cache hit, while a variable which needs to
+//! be evaluated is a cache miss.
+//!
+//! The results are accumulated in a [Profile], shared with the caller, which can then be rendered
+//! as a table (using its `Display` implementation) or as folded stacks (see
+//! [Profile::write_folded]).
+use crate::cache::ImportResolver;
+use crate::debugger::format_pos;
+use crate::eval::callstack::FieldPaths;
+use crate::eval::hooks::{EvalHooks, Step};
+use crate::eval::lazy::ThunkState;
+use crate::identifier::Ident;
+use crate::label::{ty_path, Label};
+use crate::position::{RawSpan, SpanMap, TermPos};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// The name of the entry gathering the steps performed outside of any field.
+const TOPLEVEL: &str = "<toplevel>";
+
+/// The cost attributed to a top-level field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldCost {
+    /// The number of reduction steps.
+    pub reductions: u64,
+    /// The time spent in the reduction steps.
+    pub time: Duration,
+    /// The number of contracts applied.
+    pub contract_applications: u64,
+    /// The number of variables whose thunk was already evaluated.
+    pub cache_hits: u64,
+    /// The number of variables whose thunk had to be evaluated.
+    pub cache_misses: u64,
+}
+
+/// The cost attributed to a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractCost {
+    /// The type checked by the contract, as written in the annotation of its first application.
+    pub name: String,
+    /// The position of the definition of the contract, as `<file>:<line>:<column>`.
+    pub location: String,
+    /// The number of times the contract has been applied.
+    pub applications: u64,
+    /// The number of reduction steps performed in the code of the contract.
+    pub reductions: u64,
+    /// The time spent in the reduction steps performed in the code of the contract.
+    pub time: Duration,
+}
+
+/// The results of the profiling of an evaluation.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// The total number of reduction steps.
+    pub reductions: u64,
+    /// The total time spent in reduction steps.
+    pub time: Duration,
+    /// The total number of contract applications.
+    pub contract_applications: u64,
+    /// The total number of thunk cache hits.
+    pub cache_hits: u64,
+    /// The total number of thunk cache misses.
+    pub cache_misses: u64,
+    /// The cost per top-level field.
+    pub fields: HashMap<String, FieldCost>,
+    /// The cost per contract, indexed by the span of the contract's definition.
+    pub contracts: HashMap<RawSpan, ContractCost>,
+    /// The number of reduction steps per stack of fields and contracts, each stack being
+    /// represented as a list of frames separated by `;`.
+    pub stacks: HashMap<String, u64>,
+}
+
+impl Profile {
+    /// Write the profile as folded stacks, one line per stack followed by its number of reduction
+    /// steps. This is the input format of flamegraph generators, such as `inferno-flamegraph` or
+    /// `flamegraph.pl`.
+    pub fn write_folded<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut stacks: Vec<_> = self.stacks.iter().collect();
+        stacks.sort();
+
+        for (stack, count) in stacks {
+            writeln!(out, "{} {}", stack, count)?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the fields sorted by decreasing number of reduction steps.
+    pub fn sorted_fields(&self) -> Vec<(&String, &FieldCost)> {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by(|(name1, cost1), (name2, cost2)| {
+            cost2
+                .reductions
+                .cmp(&cost1.reductions)
+                .then_with(|| name1.cmp(name2))
+        });
+        fields
+    }
+
+    /// Return the contracts sorted by decreasing number of reduction steps.
+    pub fn sorted_contracts(&self) -> Vec<&ContractCost> {
+        let mut contracts: Vec<_> = self.contracts.values().collect();
+        contracts.sort_by(|cost1, cost2| {
+            cost2
+                .reductions
+                .cmp(&cost1.reductions)
+                .then_with(|| cost2.applications.cmp(&cost1.applications))
+                .then_with(|| cost1.location.cmp(&cost2.location))
+        });
+        contracts
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} reductions in {:.2?}, {} contract applications, {} cache hits, {} cache misses",
+            self.reductions,
+            self.time,
+            self.contract_applications,
+            self.cache_hits,
+            self.cache_misses
+        )?;
+
+        let fields = self.sorted_fields();
+        let width = fields
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(std::iter::once("field".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<width$}  {:>10}  {:>10}  {:>9}  {:>10}  {:>12}",
+            "field",
+            "reductions",
+            "time",
+            "contracts",
+            "cache hits",
+            "cache misses",
+            width = width
+        )?;
+        for (name, cost) in fields {
+            writeln!(
+                f,
+                "{:<width$}  {:>10}  {:>10}  {:>9}  {:>10}  {:>12}",
+                name,
+                cost.reductions,
+                format!("{:.2?}", cost.time),
+                cost.contract_applications,
+                cost.cache_hits,
+                cost.cache_misses,
+                width = width
+            )?;
+        }
+
+        let contracts = self.sorted_contracts();
+        if contracts.is_empty() {
+            return Ok(());
+        }
+
+        let width = contracts
+            .iter()
+            .map(|cost| cost.name.len())
+            .chain(std::iter::once("contract".len()))
+            .max()
+            .unwrap_or_default();
+        let loc_width = contracts
+            .iter()
+            .map(|cost| cost.location.len())
+            .chain(std::iter::once("defined at".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<width$}  {:<loc_width$}  {:>12}  {:>10}  {:>10}",
+            "contract",
+            "defined at",
+            "applications",
+            "reductions",
+            "time",
+            width = width,
+            loc_width = loc_width
+        )?;
+        for cost in contracts {
+            writeln!(
+                f,
+                "{:<width$}  {:<loc_width$}  {:>12}  {:>10}  {:>10}",
+                cost.name,
+                cost.location,
+                cost.applications,
+                cost.reductions,
+                format!("{:.2?}", cost.time),
+                width = width,
+                loc_width = loc_width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What a reduction step is attributed to.
+struct Attribution {
+    /// The top-level field being evaluated.
+    field: String,
+    /// The contract whose code is being evaluated, if any.
+    contract: Option<RawSpan>,
+}
+
+/// An evaluation profiler, to be installed via
+/// [Program::set_eval_hooks][crate::program::Program::set_eval_hooks].
+///
+/// The duration of the last step is only attributed when the profiler is dropped, which happens
+/// when it is uninstalled: the profile is complete once the profiler has been removed from the
+/// program.
+pub struct Profiler {
+    profile: Rc<RefCell<Profile>>,
+    field_paths: FieldPaths,
+    /// The definitions of the contracts applied so far.
+    contract_spans: SpanMap<()>,
+    /// The previous step, whose duration is only known at the beginning of the next step.
+    last: Option<(Instant, Attribution)>,
+}
+
+impl Profiler {
+    /// Create a new profiler, accumulating its results in `profile`.
+    pub fn new(profile: Rc<RefCell<Profile>>) -> Self {
+        Profiler {
+            profile,
+            field_paths: FieldPaths::new(),
+            contract_spans: SpanMap::new(),
+            last: None,
+        }
+    }
+
+    /// Return the innermost contract definition containing a position, if any.
+    fn contract_at(&self, pos: TermPos) -> Option<RawSpan> {
+        let span = pos.into_opt()?;
+
+        self.contract_spans
+            .innermost_containing(&span)
+            .map(|(contract, _)| contract)
+    }
+
+    /// Attribute the duration of the previous step, if any, and start timing the next one.
+    fn record_time(&mut self, next: Attribution) {
+        let now = self.flush();
+        self.last = Some((now, next));
+    }
+
+    /// Attribute the duration of the previous step, if any, and return the current instant.
+    fn flush(&mut self) -> Instant {
+        let now = Instant::now();
+
+        if let Some((start, attribution)) = self.last.take() {
+            let elapsed = now - start;
+            let mut profile = self.profile.borrow_mut();
+
+            profile.time += elapsed;
+            profile.fields.entry(attribution.field).or_default().time += elapsed;
+            if let Some(contract) = attribution
+                .contract
+                .and_then(|span| profile.contracts.get_mut(&span))
+            {
+                contract.time += elapsed;
+            }
+        }
+
+        now
+    }
+
+    /// The top-level field of the current step.
+    fn current_field(&self) -> String {
+        self.last
+            .as_ref()
+            .map(|(_, attribution)| attribution.field.clone())
+            .unwrap_or_else(|| String::from(TOPLEVEL))
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl EvalHooks for Profiler {
+    fn on_step(&mut self, step: &Step) {
+        // Accesses to the standard library, such as `array.map`, aren't fields of the program.
+        let resolver = step.resolver;
+        let path = self
+            .field_paths
+            .current_filtered(step.call_stack, |span| !resolver.is_stdlib(span.src_id));
+        let contract = self.contract_at(step.closure.body.pos);
+
+        let field = match path.as_ref().and_then(|path| path.first()) {
+            Some(id) => id.to_string(),
+            None => String::from(TOPLEVEL),
+        };
+
+        let mut frames: Vec<String> = match path {
+            Some(path) if !path.is_empty() => path.iter().map(Ident::to_string).collect(),
+            _ => vec![String::from(TOPLEVEL)],
+        };
+
+        {
+            let mut profile = self.profile.borrow_mut();
+            profile.reductions += 1;
+            profile.fields.entry(field.clone()).or_default().reductions += 1;
+
+            if let Some(cost) = contract.and_then(|span| profile.contracts.get_mut(&span)) {
+                cost.reductions += 1;
+                frames.push(format!("contract {}", cost.name));
+            }
+
+            *profile.stacks.entry(frames.join(";")).or_default() += 1;
+        }
+
+        self.record_time(Attribution { field, contract });
+    }
+
+    fn on_thunk_access(&mut self, _id: Ident, state: ThunkState) {
+        let field = self.current_field();
+        let mut profile = self.profile.borrow_mut();
+        let cost = profile.fields.entry(field).or_default();
+
+        match state {
+            ThunkState::Evaluated => {
+                cost.cache_hits += 1;
+                profile.cache_hits += 1;
+            }
+            ThunkState::Suspended | ThunkState::Blackholed => {
+                cost.cache_misses += 1;
+                profile.cache_misses += 1;
+            }
+        }
+    }
+
+    fn on_contract(&mut self, label: &Label, contract_pos: TermPos, resolver: &dyn ImportResolver) {
+        // Contracts generated by the interpreter don't have a position: we use the position of
+        // the annotation instead.
+        let span = contract_pos.into_opt().unwrap_or(label.span);
+        let field = self.current_field();
+        let mut profile = self.profile.borrow_mut();
+
+        profile.contract_applications += 1;
+        profile
+            .fields
+            .entry(field)
+            .or_default()
+            .contract_applications += 1;
+
+        profile
+            .contracts
+            .entry(span)
+            .or_insert_with(|| {
+                self.contract_spans.insert(span, ());
+
+                // The label of a subcontract, such as `Num` in `Array Num`, is the label of the
+                // original annotation, together with a path locating the subcontract inside it.
+                let repr = label.types.to_string();
+                let ty_path::PathSpan { start, end, .. } =
+                    ty_path::span(label.path.iter().peekable(), &label.types);
+
+                ContractCost {
+                    name: repr.get(start..end).unwrap_or(&repr).to_owned(),
+                    location: format_pos(resolver, TermPos::Original(span)),
+                    applications: 0,
+                    reductions: 0,
+                    time: Duration::ZERO,
+                }
+            })
+            .applications += 1;
+    }
+}
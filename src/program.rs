@@ -26,6 +26,7 @@ use crate::eval::cache::Cache as EvalCache;
 use crate::eval::VirtualMachine;
 use crate::identifier::Ident;
 use crate::parser::lexer::Lexer;
+use crate::stats::Stats;
 use crate::term::{RichTerm, Term};
 use crate::{eval, parser};
 use codespan::FileId;
@@ -33,6 +34,7 @@ use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
 use std::ffi::OsString;
 use std::io::{self, Read};
 use std::result::Result;
+use std::time::Instant;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ColorOpt {
@@ -65,6 +67,9 @@ pub struct Program<EC: EvalCache> {
     vm: VirtualMachine<Cache, EC>,
     /// The color option to use when reporting errors.
     color_opt: ColorOpt,
+    /// The statistics of the evaluation phase. The statistics of the other phases are kept by the
+    /// cache.
+    eval_stats: Stats,
 }
 
 impl<EC: EvalCache> Program<EC> {
@@ -82,6 +87,7 @@ impl<EC: EvalCache> Program<EC> {
             main_id,
             vm,
             color_opt: ColorOpt::Auto,
+            eval_stats: Stats::default(),
         })
     }
 
@@ -99,6 +105,7 @@ impl<EC: EvalCache> Program<EC> {
             main_id,
             vm,
             color_opt: ColorOpt::Auto,
+            eval_stats: Stats::default(),
         })
     }
 
@@ -108,25 +115,55 @@ impl<EC: EvalCache> Program<EC> {
         self.vm.prepare_eval(self.main_id)
     }
 
+    /// Record the statistics of an evaluation which started at `start`.
+    fn record_eval_stats(&mut self, start: Instant) {
+        self.eval_stats.eval += start.elapsed();
+        self.eval_stats.thunks_created += self.vm.thunks_created();
+        self.eval_stats.peak_stack_size = self
+            .eval_stats
+            .peak_stack_size
+            .max(self.vm.peak_stack_size());
+    }
+
     /// Parse if necessary, typecheck and then evaluate the program.
     pub fn eval(&mut self) -> Result<RichTerm, Error> {
         let (t, initial_env) = self.prepare_eval()?;
         self.vm.reset();
-        self.vm.eval(t, &initial_env).map_err(|e| e.into())
+        let start = Instant::now();
+        let result = self.vm.eval(t, &initial_env);
+        self.record_eval_stats(start);
+        result.map_err(|e| e.into())
     }
 
     /// Same as `eval`, but proceeds to a full evaluation.
     pub fn eval_full(&mut self) -> Result<RichTerm, Error> {
         let (t, initial_env) = self.prepare_eval()?;
         self.vm.reset();
-        self.vm.eval_full(t, &initial_env).map_err(|e| e.into())
+        let start = Instant::now();
+        let result = self.vm.eval_full(t, &initial_env);
+        self.record_eval_stats(start);
+        result.map_err(|e| e.into())
     }
 
     /// Same as `eval_full`, but does not substitute all variables.
     pub fn eval_deep(&mut self) -> Result<RichTerm, Error> {
         let (t, initial_env) = self.prepare_eval()?;
         self.vm.reset();
-        self.vm.eval_deep(t, &initial_env).map_err(|e| e.into())
+        let start = Instant::now();
+        let result = self.vm.eval_deep(t, &initial_env);
+        self.record_eval_stats(start);
+        result.map_err(|e| e.into())
+    }
+
+    /// Return the timing of each phase and other statistics about the processing of the program
+    /// so far.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            eval: self.eval_stats.eval,
+            thunks_created: self.eval_stats.thunks_created,
+            peak_stack_size: self.eval_stats.peak_stack_size,
+            ..self.vm.import_resolver().stats().clone()
+        }
     }
 
     /// Wrapper for [`query`].
@@ -178,18 +215,33 @@ impl<EC: EvalCache> Program<EC> {
         self.color_opt = c;
     }
 
+    /// Set the destination of the messages emitted by `builtin.trace`. See [eval::trace].
+    pub fn set_trace_sink(&mut self, sink: eval::trace::TraceSink) {
+        self.vm.set_trace_sink(sink);
+    }
+
+    /// Install callbacks to be invoked during evaluation, such as the ones of the
+    /// [debugger][crate::debugger], or remove them if `hooks` is `None`.
+    pub fn set_eval_hooks(&mut self, hooks: Option<Box<dyn eval::hooks::EvalHooks>>) {
+        self.vm.set_hooks(hooks);
+    }
+
+    /// Restrict the files that can be imported by the program. See [ImportPolicy].
+    pub fn set_import_policy(&mut self, policy: ImportPolicy) {
+        self.vm.import_resolver_mut().set_import_policy(policy);
+    }
+
     pub fn pprint_ast(
         &mut self,
         out: &mut std::io::BufWriter<Box<dyn std::io::Write>>,
         apply_transforms: bool,
     ) -> Result<(), Error> {
         use crate::pretty::*;
-        use pretty::BoxAllocator;
 
         let Program {
             ref main_id, vm, ..
         } = self;
-        let allocator = BoxAllocator;
+        let allocator = SourceAllocator::new(vm.import_resolver().files());
 
         let rt = vm.import_resolver().parse_nocache(*main_id)?.0;
         let rt = if apply_transforms {
@@ -414,6 +466,7 @@ mod tests {
     use crate::eval::cache::CBNCache;
     use crate::position::TermPos;
     use crate::term::array::ArrayAttrs;
+    use crate::term::number::Number;
     use assert_matches::assert_matches;
     use std::io::Cursor;
 
@@ -451,11 +504,11 @@ mod tests {
 
         // [2, "ab", [1, [3]]]
         let expd = mk_array!(
-            Term::Num(2_f64),
+            Term::Num(Number::from(2)),
             Term::Str(String::from("ab")),
             mk_array!(
-                Term::Num(1_f64),
-                mk_array!(Term::Num(3_f64); ArrayAttrs::new().closurized());
+                Term::Num(Number::from(1)),
+                mk_array!(Term::Num(Number::from(3)); ArrayAttrs::new().closurized());
                 ArrayAttrs::new().closurized()
             );
             ArrayAttrs::new().closurized()
@@ -467,7 +520,7 @@ mod tests {
         // Records are parsed as RecRecords, so we need to build one by hand
         let expd = mk_record!((
             "foo",
-            mk_record!(("bar", mk_record!(("baz", Term::Num(2.0)))))
+            mk_record!(("bar", mk_record!(("baz", Term::Num(Number::from(2))))))
         ));
         assert_eq!(t.without_pos(), expd);
 
@@ -59,7 +59,9 @@ pub trait Repl {
     fn eval_full(&mut self, exp: &str) -> Result<EvalResult, Error>;
     /// Load the content of a file in the environment. Return the loaded record.
     fn load(&mut self, path: impl AsRef<OsStr>) -> Result<RichTerm, Error>;
-    /// Typecheck an expression and return its [apparent type][crate::typecheck::ApparentType].
+    /// Typecheck an expression and return its [apparent type][crate::typecheck::ApparentType]. If
+    /// the expression isn't annotated, return instead its inferred type, generalized, when the
+    /// expression is well-typed in strict mode.
     fn typecheck(&mut self, exp: &str) -> Result<Types, Error>;
     /// Query the metadata of an expression.
     fn query(&mut self, exp: &str) -> Result<Term, Error>;
@@ -92,6 +94,11 @@ impl<EC: EvalCache> ReplImpl<EC> {
         }
     }
 
+    /// Set the destination of the messages emitted by `builtin.trace`.
+    pub fn set_trace_sink(&mut self, sink: eval::trace::TraceSink) {
+        self.vm.set_trace_sink(sink);
+    }
+
     /// Load and process the stdlib, and use it to populate the eval environment as well as the
     /// typing environment.
     pub fn load_stdlib(&mut self) -> Result<(), Error> {
@@ -283,12 +290,22 @@ impl<EC: EvalCache> Repl for ReplImpl<EC> {
             )
             .unwrap();
 
-        Ok(typecheck::apparent_type(
+        match typecheck::apparent_type(
             term.as_ref(),
             Some(&self.env.type_ctxt.type_env),
             Some(self.vm.import_resolver()),
-        )
-        .into())
+        ) {
+            // If the expression isn't annotated, try to infer its most general type. This fails
+            // if the expression isn't well-typed in strict mode, in which case we fall back to the
+            // apparent type.
+            ty @ typecheck::ApparentType::Approximated(_) => Ok(typecheck::infer_type(
+                &term,
+                self.env.type_ctxt.clone(),
+                self.vm.import_resolver(),
+            )
+            .unwrap_or_else(|_| ty.into())),
+            ty => Ok(ty.into()),
+        }
     }
 
     fn query(&mut self, exp: &str) -> Result<Term, Error> {
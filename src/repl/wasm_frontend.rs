@@ -331,3 +331,14 @@ pub fn repl_serialize(
         .map(WasmInputResult::from)
         .unwrap_or_else(|err| WasmInputResult::error(state.0.cache_mut(), err))
 }
+
+/// Redirect the messages emitted by `builtin.trace` to a JavaScript callback, which is called with
+/// the rendered message as a string argument. By default, trace messages are printed on the
+/// standard error, which isn't available in the browser.
+#[wasm_bindgen]
+pub fn repl_set_trace_callback(state: &mut ReplState, callback: js_sys::Function) {
+    state.0.set_trace_sink(Box::new(move |event| {
+        // An exception thrown by the callback can't be sensibly reported at this point.
+        let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&event.to_string()));
+    }));
+}
@@ -6,15 +6,20 @@ use crate::{
         cache::{CBNCache, Cache},
         is_empty_optional,
     },
+    identifier::Ident,
     term::{
         array::{Array, ArrayAttrs},
+        datetime::{self, DateTime, Duration},
+        number::{self, Number},
         record::RecordData,
         MetaValue, RichTerm, Term,
     },
 };
 
+use malachite_base::num::conversion::traits::IsInteger;
+
 use serde::{
-    de::{Deserialize, Deserializer},
+    de::{self, Deserialize, DeserializeSeed, Deserializer, Visitor},
     ser::{Error, Serialize, SerializeMap, SerializeSeq, Serializer},
 };
 
@@ -70,23 +75,77 @@ impl FromStr for ExportFormat {
     }
 }
 
-/// Implicitly convert float to integers when possible to avoid trailing zeros. Note this this
-/// only work if the float is in range of either `i64` or `f64`. It seems there's no easy general
-/// solution (working for both YAML, TOML, and JSON) to choose the way floating point values are
-/// formatted.
-pub fn serialize_num<S>(n: &f64, serializer: S) -> Result<S::Ok, S::Error>
+/// Serialize a number. Integers are serialized exactly, as long as they fit in 128 bits (and in 64
+/// bits for most formats), and raise an error otherwise. Other numbers are serialized as the
+/// closest float.
+pub fn serialize_num<S>(n: &Number, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    if n.fract() == 0.0 {
-        if *n < 0.0 && *n >= (i64::MIN as f64) && *n <= (i64::MAX as f64) {
-            return (*n as i64).serialize(serializer);
-        } else if *n >= 0.0 && *n <= (u64::MAX as f64) {
-            return (*n as u64).serialize(serializer);
+    if n.is_integer() {
+        if let Ok(n) = i64::try_from(n) {
+            n.serialize(serializer)
+        } else if let Ok(n) = u64::try_from(n) {
+            n.serialize(serializer)
+        } else if let Ok(n) = i128::try_from(n) {
+            n.serialize(serializer)
+        } else if let Ok(n) = u128::try_from(n) {
+            n.serialize(serializer)
+        } else {
+            Err(Error::custom(format!(
+                "the integer {} is too large to be serialized exactly",
+                n
+            )))
         }
+    } else {
+        number::to_f64(n).serialize(serializer)
     }
+}
 
-    n.serialize(serializer)
+/// Serialize a number to the given format. JSON numbers have an arbitrary precision: numbers with
+/// a finite decimal expansion are serialized exactly, while other numbers, such as `1/3`, are
+/// rounded to the closest float. Numbers are serialized by [serialize_num] in other formats.
+fn serialize_num_to<S>(n: &Number, format: ExportFormat, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match number::format_exact(n) {
+        Some(literal) if format == ExportFormat::Json => serde_json::Number::from_str(&literal)
+            .map_err(Error::custom)?
+            .serialize(serializer),
+        _ => serialize_num(n, serializer),
+    }
+}
+
+/// Serialize a datetime as an RFC 3339 string. Datetimes are serialized as native datetimes when
+/// exporting to TOML (see [Formatted]).
+pub fn serialize_datetime<S>(dt: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    datetime::format(dt)
+        .map_err(Error::custom)?
+        .serialize(serializer)
+}
+
+/// Serialize a datetime as a native TOML datetime. This only makes sense for the serializer of the
+/// `toml` crate, which recognizes the representation of [toml::value::Datetime].
+fn serialize_toml_datetime<S>(dt: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let formatted = datetime::format(dt).map_err(Error::custom)?;
+    toml::value::Datetime::from_str(&formatted)
+        .map_err(Error::custom)?
+        .serialize(serializer)
+}
+
+/// Serialize a duration as an ISO 8601 string.
+pub fn serialize_duration<S>(d: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    datetime::format_duration(d).serialize(serializer)
 }
 
 /// Serializer for metavalues.
@@ -107,31 +166,76 @@ where
 pub fn serialize_record<S>(record: &RecordData, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
+{
+    serialize_record_with(record, serializer, false, |t| t)
+}
+
+/// Same as [serialize_record], but serialize `wrap(t)` for each field value `t`. If `tables_last`
+/// is set, the fields are ordered as required by TOML (see [toml_order]), and alphabetically
+/// within each group.
+fn serialize_record_with<'a, S, T, F>(
+    record: &'a RecordData,
+    serializer: S,
+    tables_last: bool,
+    wrap: F,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+    F: Fn(&'a RichTerm) -> T,
 {
     let mut entries: Vec<(_, _)> = record
         .fields
         .iter()
         // Filtering out optional fields without a definition. All variable should have been
         // substituted at this point, so we pass an empty environment.
-        .filter(|(_, t)| !is_empty_optional(&CBNCache {}, t, &eval::Environment::new()))
+        .filter(|(_, t)| !is_empty_optional(&CBNCache::new(), t, &eval::Environment::new()))
         .collect();
     entries.sort_by_key(|(k, _)| *k);
 
+    if tables_last {
+        // The sort is stable: the fields stay in alphabetical order within each group.
+        entries.sort_by_key(|(_, t)| toml_order(t));
+    }
+
     let mut map_ser = serializer.serialize_map(Some(entries.len()))?;
-    for (id, t) in entries.iter() {
-        map_ser.serialize_entry(&id.to_string(), &t)?
+    for (id, t) in entries {
+        map_ser.serialize_entry(&id.to_string(), &wrap(t))?
     }
 
     map_ser.end()
 }
 
-/// Deserialize for a record. Required to set the record attributes to default.
-pub fn deserialize_record<'de, D>(deserializer: D) -> Result<RecordData, D::Error>
+/// The rank of a field value in a TOML table. The serializer of the `toml` crate can't emit a
+/// plain value after a table, so plain values (rank 0) come first, then arrays of tables (rank 1)
+/// and finally tables (rank 2). This is the order used by `toml::Value`.
+fn toml_order(t: &RichTerm) -> u8 {
+    fn is_table(t: &RichTerm) -> bool {
+        match t.as_ref() {
+            Term::Record(_) | Term::EnumVariant { .. } => true,
+            Term::MetaValue(MetaValue { value: Some(t), .. }) => is_table(t),
+            _ => false,
+        }
+    }
+
+    match t.as_ref() {
+        Term::MetaValue(MetaValue { value: Some(t), .. }) => toml_order(t),
+        _ if is_table(t) => 2,
+        Term::Array(terms, _) if terms.iter().any(is_table) => 1,
+        _ => 0,
+    }
+}
+
+/// Serialize an enum variant carrying an argument in the externally tagged form, that is as a
+/// map with a single entry `{tag: arg}`.
+pub fn serialize_enum_variant<S, T>(tag: &Ident, arg: &T, serializer: S) -> Result<S::Ok, S::Error>
 where
-    D: Deserializer<'de>,
+    S: Serializer,
+    T: Serialize,
 {
-    let fields = HashMap::deserialize(deserializer)?;
-    Ok(RecordData::with_fields(fields))
+    let mut map_ser = serializer.serialize_map(Some(1))?;
+    map_ser.serialize_entry(&tag.to_string(), arg)?;
+    map_ser.end()
 }
 
 /// Serialize for an Array. Required to hide the internal attributes.
@@ -142,22 +246,228 @@ pub fn serialize_array<S>(
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
+{
+    serialize_array_with(terms, serializer, |t| t)
+}
+
+/// Same as [serialize_array], but serialize `wrap(t)` for each element `t`.
+fn serialize_array_with<'a, S, T, F>(
+    terms: &'a Array,
+    serializer: S,
+    wrap: F,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+    F: Fn(&'a RichTerm) -> T,
 {
     let mut seq = serializer.serialize_seq(Some(terms.len()))?;
     for term in terms.iter() {
-        seq.serialize_element(term)?;
+        seq.serialize_element(&wrap(term))?;
     }
 
     seq.end()
 }
 
-/// Deserialize for an Array. Required to set the default attributes.
-pub fn deserialize_array<'de, D>(deserializer: D) -> Result<(Array, ArrayAttrs), D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let terms = Array::new(Rc::from(Vec::deserialize(deserializer)?));
-    Ok((terms, Default::default()))
+/// A term to be serialized to a specific format.
+///
+/// The serialization of some values depends on the target format (see [serialize_num_to] and
+/// [serialize_toml_datetime]), which isn't exposed by serializers. The format is thus carried
+/// along with the term, down to its subterms. The derived implementation of `Serialize` for
+/// [Term] is used for the other values.
+struct Formatted<'a> {
+    term: &'a RichTerm,
+    format: ExportFormat,
+}
+
+impl<'a> Formatted<'a> {
+    fn new(term: &'a RichTerm, format: ExportFormat) -> Self {
+        Formatted { term, format }
+    }
+
+    /// Wrap a subterm, to be serialized to the same format.
+    fn wrap(&self, term: &'a RichTerm) -> Self {
+        Formatted::new(term, self.format)
+    }
+}
+
+impl Serialize for Formatted<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let term: &RichTerm = self.term;
+
+        match term.as_ref() {
+            Term::Num(n) => serialize_num_to(n, self.format, serializer),
+            Term::EnumVariant { tag, arg } => {
+                serialize_enum_variant(tag, &self.wrap(arg), serializer)
+            }
+            Term::DateTime(dt) if self.format == ExportFormat::Toml => {
+                serialize_toml_datetime(dt, serializer)
+            }
+            Term::Record(record) => {
+                serialize_record_with(record, serializer, self.format == ExportFormat::Toml, |t| {
+                    self.wrap(t)
+                })
+            }
+            Term::Array(terms, _) => serialize_array_with(terms, serializer, |t| self.wrap(t)),
+            Term::MetaValue(MetaValue { value: Some(t), .. }) => self.wrap(t).serialize(serializer),
+            _ => term.serialize(serializer),
+        }
+    }
+}
+
+/// The name under which `serde_json` passes numbers which don't fit in 64 bits, when its
+/// `arbitrary_precision` feature is enabled: such numbers are deserialized as a map with a single
+/// entry, whose key is this token and whose value is the literal representation of the number.
+const JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// The name under which the `toml` crate passes datetimes: they are deserialized as a map with a
+/// single entry, whose key is this token and whose value is the literal representation of the
+/// datetime.
+const TOML_DATETIME_TOKEN: &str = "$__toml_private_datetime";
+
+/// Visitor building a term from a deserialized value.
+///
+/// We don't derive `Deserialize` for [Term] as an untagged enum, because serde then buffers the
+/// input in an intermediate representation which doesn't support 128 bits integers. Integers are
+/// converted exactly, while floats are converted to the simplest number which rounds to them, such
+/// that `0.1` is deserialized as exactly `1/10`.
+///
+/// The `toml` crate passes datetimes as maps keyed by [TOML_DATETIME_TOKEN]. Such maps are only
+/// converted to datetimes when `toml` is set, that is when deserializing a TOML document (see
+/// [from_toml_str]), so that a record of another format with such a field is left untouched.
+#[derive(Clone, Copy)]
+struct TermVisitor {
+    toml: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for TermVisitor {
+    type Value = RichTerm;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<RichTerm, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self).map(RichTerm::from)
+    }
+}
+
+impl<'de> Visitor<'de> for TermVisitor {
+    type Value = Term;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("null, a boolean, a number, a string, an array or a record")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Term, E> {
+        Ok(Term::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Term, E> {
+        Ok(Term::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Term, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Term, E> {
+        Ok(Term::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Term, E> {
+        Ok(Term::Num(Number::from(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Term, E> {
+        Ok(Term::Num(Number::from(v)))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Term, E> {
+        Ok(Term::Num(Number::from(v)))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Term, E> {
+        Ok(Term::Num(Number::from(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Term, E> {
+        number::from_f64(v)
+            .map(Term::Num)
+            .ok_or_else(|| E::custom(format!("{} is not a valid Nickel number", v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Term, E> {
+        Ok(Term::Str(String::from(v)))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Term, E> {
+        Ok(Term::Str(v))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Term, A::Error> {
+        let mut terms = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(t) = seq.next_element_seed(self)? {
+            terms.push(t);
+        }
+
+        Ok(Term::Array(
+            Array::new(Rc::from(terms)),
+            ArrayAttrs::default(),
+        ))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Term, A::Error> {
+        let mut fields = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+
+        match map.next_key::<String>()? {
+            Some(key) if key == JSON_NUMBER_TOKEN => {
+                let literal = map.next_value::<String>()?;
+                return number::parse(&literal).map(Term::Num).ok_or_else(|| {
+                    de::Error::custom(format!("{} is not a valid Nickel number", literal))
+                });
+            }
+            // Only TOML offset datetimes denote an instant. Local datetimes, dates and times are
+            // kept as strings.
+            Some(key) if self.toml && key == TOML_DATETIME_TOKEN => {
+                let literal = map.next_value::<String>()?;
+                // TOML allows a space instead of the `T` separating the date and the time.
+                return Ok(datetime::parse(&literal.replacen(' ', "T", 1))
+                    .map(Term::DateTime)
+                    .unwrap_or(Term::Str(literal)));
+            }
+            Some(key) => {
+                fields.insert(Ident::from(key), map.next_value_seed(self)?);
+            }
+            None => (),
+        }
+
+        while let Some(key) = map.next_key::<String>()? {
+            fields.insert(Ident::from(key), map.next_value_seed(self)?);
+        }
+
+        Ok(Term::Record(RecordData::with_fields(fields)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Term {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TermVisitor { toml: false })
+    }
+}
+
+/// Deserialize a TOML document, where TOML offset datetimes are converted to Nickel datetimes.
+pub fn from_toml_str(s: &str) -> Result<RichTerm, toml::de::Error> {
+    let mut deserializer = toml::de::Deserializer::new(s);
+    let term = TermVisitor { toml: true }.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(term)
 }
 
 impl Serialize for RichTerm {
@@ -198,7 +508,8 @@ pub fn validate(format: ExportFormat, t: &RichTerm) -> Result<(), SerializationE
             // TOML doesn't support null values
             Null if format == ExportFormat::Json || format == ExportFormat::Yaml => Ok(()),
             Null => Err(SerializationError::UnsupportedNull(format, t.clone())),
-            Bool(_) | Num(_) | Str(_) | Enum(_) => Ok(()),
+            Bool(_) | Num(_) | DateTime(_) | Duration(_) | Str(_) | Enum(_) => Ok(()),
+            EnumVariant { arg, .. } => validate(format, arg),
             Record(record) => {
                 record
                     .fields
@@ -231,14 +542,16 @@ where
     W: io::Write,
 {
     match format {
-        ExportFormat::Json => serde_json::to_writer_pretty(writer, &rt)
+        ExportFormat::Json => serde_json::to_writer_pretty(writer, &Formatted::new(rt, format))
             .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Yaml => serde_yaml::to_writer(writer, &rt)
+        ExportFormat::Yaml => serde_yaml::to_writer(writer, &Formatted::new(rt, format))
             .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Toml => toml::Value::try_from(rt)
+        ExportFormat::Toml => toml::to_string(&Formatted::new(rt, format))
             .map_err(|err| SerializationError::Other(err.to_string()))
-            .and_then(|v| {
-                write!(writer, "{}", v).map_err(|err| SerializationError::Other(err.to_string()))
+            .and_then(|s| {
+                writer
+                    .write_all(s.as_bytes())
+                    .map_err(|err| SerializationError::Other(err.to_string()))
             }),
         ExportFormat::Raw => match rt.as_ref() {
             Term::Str(s) => writer
@@ -254,13 +567,11 @@ where
 
 pub fn to_string(format: ExportFormat, rt: &RichTerm) -> Result<String, SerializationError> {
     match format {
-        ExportFormat::Json => serde_json::to_string_pretty(&rt)
+        ExportFormat::Json => serde_json::to_string_pretty(&Formatted::new(rt, format))
             .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Yaml => {
-            serde_yaml::to_string(&rt).map_err(|err| SerializationError::Other(err.to_string()))
-        }
-        ExportFormat::Toml => toml::Value::try_from(rt)
-            .map(|v| format!("{}", v))
+        ExportFormat::Yaml => serde_yaml::to_string(&Formatted::new(rt, format))
+            .map_err(|err| SerializationError::Other(err.to_string())),
+        ExportFormat::Toml => toml::to_string(&Formatted::new(rt, format))
             .map_err(|err| SerializationError::Other(err.to_string())),
         ExportFormat::Raw => match rt.as_ref() {
             Term::Str(s) => Ok(s.clone()),
@@ -338,8 +649,7 @@ mod tests {
             let from_yaml: RichTerm =
                 serde_yaml::from_str(&serde_yaml::to_string(&evaluated).unwrap()).unwrap();
             let from_toml: RichTerm =
-                toml::from_str(&format!("{}", &toml::Value::try_from(&evaluated).unwrap()))
-                    .unwrap();
+                from_toml_str(&to_string(ExportFormat::Toml, &evaluated).unwrap()).unwrap();
 
             assert_eq!(
                 VirtualMachine::<_, EC>::new(DummyResolver {})
@@ -453,4 +763,98 @@ mod tests {
         assert_involutory!("{val = [\"a\", 3, []]}");
         assert_involutory!("{a.foo.bar = \"2\", b = false, c = [{d = \"e\"}, {d = \"f\"}]}");
     }
+
+    #[test]
+    fn exact_numbers() {
+        assert_json_eq!("9007199254740993", 9007199254740993u64);
+        assert_json_eq!("-9007199254740993 * 4", -36028797018963972i64);
+        assert_json_eq!("18446744073709551615 * 2", 36893488147419103230u128);
+        assert_json_eq!("0.1 + 0.2", 0.3);
+        assert_involutory!("{val = 9007199254740993, min = -9223372036854775808, dec = 0.1}");
+
+        let expected = Term::Num(Number::from(u128::MAX));
+        let from_json: RichTerm =
+            serde_json::from_str("340282366920938463463374607431768211455").unwrap();
+        assert_eq!(*from_json.term, expected);
+        let from_yaml: RichTerm =
+            serde_yaml::from_str("340282366920938463463374607431768211455").unwrap();
+        assert_eq!(*from_yaml.term, expected);
+
+        let exported = |format, source: &str| {
+            to_string(
+                format,
+                &mk_program(source).and_then(|mut p| p.eval_full()).unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            exported(ExportFormat::Json, "[0.12345678901234567890123, 1 / 3]"),
+            "[\n  0.12345678901234567890123,\n  0.3333333333333333\n]"
+        );
+        assert_eq!(
+            exported(
+                ExportFormat::Json,
+                "{a = -2 * 340282366920938463463374607431768211455}"
+            ),
+            "{\n  \"a\": -680564733841876926926749214863536422910\n}"
+        );
+        assert_eq!(
+            exported(ExportFormat::Yaml, "0.12345678901234567890123"),
+            "---\n0.12345678901234568\n"
+        );
+
+        let from_json: RichTerm = serde_json::from_str("0.1").unwrap();
+        assert_eq!(*from_json.term, Term::Num(Number::from_signeds(1, 10)));
+    }
+
+    #[test]
+    fn datetimes() {
+        let program = r#"{
+            at = datetime.parse "2022-11-03T10:45:00.5+01:00",
+            nested = [{ at = datetime.from_timestamp 0 }],
+        }"#;
+        let evaluated = mk_program(program).and_then(|mut p| p.eval_full()).unwrap();
+
+        assert_json_eq!(
+            program,
+            json!({
+                "at": "2022-11-03T10:45:00.5+01:00",
+                "nested": [{ "at": "1970-01-01T00:00:00Z" }],
+            })
+        );
+        assert_json_eq!("duration.parse \"PT90S\"", "PT1M30S");
+
+        let toml = to_string(ExportFormat::Toml, &evaluated).unwrap();
+        assert!(toml.contains("at = 2022-11-03T10:45:00.5+01:00"));
+        assert!(toml.contains("at = 1970-01-01T00:00:00Z"));
+
+        let from_toml = from_toml_str(&toml).unwrap();
+        assert_eq!(
+            VirtualMachine::<_, EC>::new(DummyResolver {})
+                .eval(
+                    mk_term::op2(BinaryOp::Eq(), from_toml, evaluated),
+                    &Environment::new(),
+                )
+                .map(Term::from),
+            Ok(Term::Bool(true))
+        );
+
+        let local = from_toml_str("date = 1979-05-27").unwrap();
+        assert_eq!(
+            serde_json::to_string(&local).unwrap(),
+            r#"{"date":"1979-05-27"}"#
+        );
+
+        // The representation of datetimes used by the `toml` crate isn't recognized in user data.
+        let record = r#"{"$__toml_private_datetime" = "1979-05-27T07:32:00Z"}"#;
+        let evaluated = mk_program(record).and_then(|mut p| p.eval_full()).unwrap();
+        assert_eq!(
+            to_string(ExportFormat::Toml, &evaluated).unwrap(),
+            "\"$__toml_private_datetime\" = \"1979-05-27T07:32:00Z\"\n"
+        );
+        let from_json: RichTerm =
+            serde_json::from_str(r#"{"$__toml_private_datetime": "1979-05-27T07:32:00Z"}"#)
+                .unwrap();
+        assert!(matches!(from_json.as_ref(), Term::Record(_)));
+    }
 }
@@ -0,0 +1,62 @@
+//! Statistics about the phases of the processing of a program.
+//!
+//! The preparation phases (parsing, typechecking, program transformations and preparation of the
+//! standard library) are measured by the [cache][crate::cache::Cache], while the evaluation is
+//! measured by the [program][crate::program::Program]. The complete statistics are available
+//! through [Program::stats][crate::program::Program::stats].
+use std::fmt;
+use std::time::Duration;
+
+/// Wall-clock time per phase and counters accumulated during the processing of a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Time spent parsing sources, including imports, but excluding the standard library.
+    pub parse: Duration,
+    /// Time spent typechecking.
+    pub typecheck: Duration,
+    /// Time spent applying program transformations, excluding the standard library.
+    pub transform: Duration,
+    /// Time spent preparing the standard library: loading, parsing and transforming its modules,
+    /// and building the initial environments.
+    pub stdlib: Duration,
+    /// Time spent evaluating the program.
+    pub eval: Duration,
+    /// The number of sources parsed, including the modules of the standard library.
+    pub files_loaded: usize,
+    /// The number of terms which went through program transformations.
+    pub terms_transformed: usize,
+    /// The number of thunks created during evaluation.
+    pub thunks_created: usize,
+    /// The maximum size reached by the evaluation stack.
+    pub peak_stack_size: usize,
+}
+
+impl Stats {
+    /// The total time spent in all phases.
+    pub fn total(&self) -> Duration {
+        self.parse + self.typecheck + self.transform + self.stdlib + self.eval
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phases = [
+            ("parse", self.parse),
+            ("typecheck", self.typecheck),
+            ("transform", self.transform),
+            ("stdlib", self.stdlib),
+            ("eval", self.eval),
+            ("total", self.total()),
+        ];
+
+        for (name, time) in phases {
+            writeln!(f, "{:<10} {:>10}", name, format!("{:.2?}", time))?;
+        }
+
+        writeln!(
+            f,
+            "files loaded: {}, terms transformed: {}, thunks created: {}, peak stack size: {}",
+            self.files_loaded, self.terms_transformed, self.thunks_created, self.peak_stack_size
+        )
+    }
+}
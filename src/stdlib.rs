@@ -5,7 +5,7 @@ use crate::term::make as mk_term;
 use crate::term::RichTerm;
 
 /// This is an array containing all the Nickel standard library modules.
-pub fn modules() -> [StdlibModule; 8] {
+pub fn modules() -> [StdlibModule; 10] {
     [
         StdlibModule::Builtin,
         StdlibModule::Contract,
@@ -14,6 +14,8 @@ pub fn modules() -> [StdlibModule; 8] {
         StdlibModule::String,
         StdlibModule::Num,
         StdlibModule::Function,
+        StdlibModule::DateTime,
+        StdlibModule::Duration,
         StdlibModule::Internals,
     ]
 }
@@ -28,6 +30,8 @@ pub enum StdlibModule {
     String,
     Num,
     Function,
+    DateTime,
+    Duration,
     Internals,
 }
 
@@ -41,6 +45,8 @@ impl StdlibModule {
             StdlibModule::String => "<stdlib/string.ncl>",
             StdlibModule::Num => "<stdlib/num.ncl>",
             StdlibModule::Function => "<stdlib/function.ncl>",
+            StdlibModule::DateTime => "<stdlib/datetime.ncl>",
+            StdlibModule::Duration => "<stdlib/duration.ncl>",
             StdlibModule::Internals => "<stdlib/internals.ncl>",
         }
     }
@@ -54,6 +60,8 @@ impl StdlibModule {
             StdlibModule::String => include_str!("../stdlib/string.ncl"),
             StdlibModule::Num => include_str!("../stdlib/num.ncl"),
             StdlibModule::Function => include_str!("../stdlib/function.ncl"),
+            StdlibModule::DateTime => include_str!("../stdlib/datetime.ncl"),
+            StdlibModule::Duration => include_str!("../stdlib/duration.ncl"),
             StdlibModule::Internals => include_str!("../stdlib/internals.ncl"),
         }
     }
@@ -73,6 +81,8 @@ impl TryFrom<Ident> for StdlibModule {
             "string" => StdlibModule::String,
             "num" => StdlibModule::Num,
             "function" => StdlibModule::Function,
+            "datetime" => StdlibModule::DateTime,
+            "duration" => StdlibModule::Duration,
             "internals" => StdlibModule::Internals,
             _ => return Err(UnknownStdlibModule),
         };
@@ -90,6 +100,8 @@ impl From<StdlibModule> for Ident {
             StdlibModule::String => "string",
             StdlibModule::Num => "num",
             StdlibModule::Function => "function",
+            StdlibModule::DateTime => "datetime",
+            StdlibModule::Duration => "duration",
             StdlibModule::Internals => "internals",
         };
         Ident::from(name)
@@ -115,13 +127,18 @@ pub mod contract {
 
     generate_accessor!(num);
     generate_accessor!(bool);
+    generate_accessor!(datetime);
+    generate_accessor!(duration);
     generate_accessor!(string);
     generate_accessor!(array);
+    generate_accessor!(array_pattern);
     generate_accessor!(func);
     generate_accessor!(forall_var);
     generate_accessor!(fail);
     generate_accessor!(enums);
     generate_accessor!(enum_fail);
+    generate_accessor!(enum_tag);
+    generate_accessor!(enum_variant);
     generate_accessor!(record);
     generate_accessor!(dyn_record);
     generate_accessor!(record_extend);
@@ -135,4 +152,9 @@ pub mod internals {
 
     generate_accessor!(rec_default);
     generate_accessor!(rec_force);
+
+    generate_accessor!(match_constant);
+    generate_accessor!(match_record);
+    generate_accessor!(match_array);
+    generate_accessor!(match_variant);
 }
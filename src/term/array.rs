@@ -53,6 +53,26 @@ impl ArrayAttrs {
     }
 }
 
+/// The operation to perform once the mask of an array operation has been computed. See
+/// [`NAryOp::ArrayMasked`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArrayMaskOp {
+    /// Keep the elements whose mask entry is `true`.
+    Filter,
+    /// Split the elements according to their mask entry, `true` going right.
+    Partition,
+    /// A round of sorting. The mask holds the comparison of each element of the given segments
+    /// `(start, end)` of the array with the pivot of its segment, that is the element in the
+    /// middle of the segment.
+    Sort(Vec<(usize, usize)>),
+    /// Sort the elements by their mask entry, which is their fully evaluated key.
+    SortOn,
+    /// Return the first element with the smallest mask entry.
+    MinBy,
+    /// Return the first element with the greatest mask entry.
+    MaxBy,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Array {
     inner: Rc<[RichTerm]>,
@@ -90,6 +110,15 @@ impl Array {
         self
     }
 
+    /// Restricts the array to the terms between `start` (included) and `end` (excluded). Both
+    /// indices are clamped to the length of the array.
+    pub fn slice(mut self, start: usize, end: usize) -> Self {
+        let len = self.len();
+        self.end = self.start + usize::min(end, len);
+        self.start += usize::min(start, self.end - self.start);
+        self
+    }
+
     /// Makes a mutable slice into the given `Array`.
     pub fn make_mut(&mut self) -> &mut [RichTerm] {
         // NOTE: trying to use `Rc::make_mut` will result in the following compiler error:
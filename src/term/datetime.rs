@@ -0,0 +1,243 @@
+//! Datetimes and durations.
+//!
+//! A datetime is an instant together with the UTC offset it is expressed in, as described by
+//! [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339), such as `2022-11-03T10:45:00+01:00`. A
+//! duration is an exact, signed amount of time with a nanosecond precision. Durations are written
+//! in the [ISO 8601](https://en.wikipedia.org/wiki/ISO_8601#Durations) notation, such as
+//! `PT1H30M`. Amounts of years or months depend on the calendar and are thus not durations.
+use super::number::Number;
+use malachite_base::num::conversion::traits::RoundingFrom;
+use malachite_base::rounding_modes::RoundingMode;
+use malachite_nz::integer::Integer;
+use time::format_description::{self, well_known::Rfc3339};
+
+pub use time::{Duration, OffsetDateTime as DateTime};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const SECONDS_PER_MINUTE: i64 = 60;
+const SECONDS_PER_HOUR: i64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: i64 = 24 * SECONDS_PER_HOUR;
+const SECONDS_PER_WEEK: i64 = 7 * SECONDS_PER_DAY;
+
+/// Parse a datetime in the RFC 3339 format.
+pub fn parse(s: &str) -> Option<DateTime> {
+    DateTime::parse(s, &Rfc3339).ok()
+}
+
+/// Render a datetime in the RFC 3339 format. Fail for datetimes which can't be represented in
+/// this format, that is before the year 0.
+pub fn format(dt: &DateTime) -> Result<String, String> {
+    dt.format(&Rfc3339).map_err(|err| err.to_string())
+}
+
+/// Render a datetime according to a format description, such as `[year]-[month]-[day]`. See the
+/// [documentation of the time crate](https://time-rs.github.io/book/api/format-description.html)
+/// for the syntax of format descriptions.
+pub fn format_with(dt: &DateTime, description: &str) -> Result<String, String> {
+    let items = format_description::parse(description).map_err(|err| err.to_string())?;
+    dt.format(&items).map_err(|err| err.to_string())
+}
+
+/// Return the exact number of seconds elapsed since the Unix epoch.
+pub fn to_timestamp(dt: &DateTime) -> Number {
+    Number::from(dt.unix_timestamp_nanos()) / Number::from(NANOS_PER_SECOND)
+}
+
+/// Return the UTC datetime corresponding to a number of seconds since the Unix epoch. Fractions of
+/// nanoseconds are truncated. Return `None` if the result is out of the supported range.
+pub fn from_timestamp(seconds: &Number) -> Option<DateTime> {
+    to_nanos(seconds).and_then(|nanos| DateTime::from_unix_timestamp_nanos(nanos).ok())
+}
+
+/// Return the exact number of seconds of a duration.
+pub fn duration_to_seconds(d: &Duration) -> Number {
+    Number::from(d.whole_nanoseconds()) / Number::from(NANOS_PER_SECOND)
+}
+
+/// Return the duration corresponding to a number of seconds. Fractions of nanoseconds are
+/// truncated. Return `None` if the result is out of the supported range.
+pub fn duration_from_seconds(seconds: &Number) -> Option<Duration> {
+    let nanos = to_nanos(seconds)?;
+    let whole_seconds = i64::try_from(nanos / NANOS_PER_SECOND).ok()?;
+    // The remainder is strictly smaller than one billion in absolute value.
+    Some(Duration::new(
+        whole_seconds,
+        (nanos % NANOS_PER_SECOND) as i32,
+    ))
+}
+
+/// Parse a duration in the ISO 8601 notation, such as `P1DT12H`, `PT0.5S` or `-PT10M`. The
+/// components are weeks (`W`), days (`D`) and, after the `T` separator, hours (`H`), minutes (`M`)
+/// and seconds (`S`). Each component can have a fractional part, and appears at most once, from the
+/// largest unit to the smallest. On error, return a description of the problem.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid ISO 8601 duration `{}`", s);
+
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let components = unsigned.strip_prefix('P').ok_or_else(invalid)?;
+
+    if components.is_empty() || components.ends_with('T') {
+        return Err(invalid());
+    }
+
+    let mut seconds = Number::from(0);
+    let mut in_time = false;
+    let mut start = 0;
+    // The number of seconds of the last unit, which the next one must be strictly smaller than.
+    let mut last_unit_seconds = None;
+
+    for (i, c) in components.char_indices() {
+        match c {
+            'T' if !in_time && i == start => {
+                in_time = true;
+                start = i + 1;
+            }
+            '0'..='9' | '.' | ',' => (),
+            unit => {
+                let unit_seconds = match (unit, in_time) {
+                    ('W', false) => SECONDS_PER_WEEK,
+                    ('D', false) => SECONDS_PER_DAY,
+                    ('H', true) => SECONDS_PER_HOUR,
+                    ('M', true) => SECONDS_PER_MINUTE,
+                    ('S', true) => 1,
+                    ('Y' | 'M', false) => {
+                        return Err(format!(
+                            "{}: years and months don't have a fixed duration",
+                            invalid()
+                        ))
+                    }
+                    _ => return Err(invalid()),
+                };
+
+                if matches!(last_unit_seconds, Some(last) if unit_seconds >= last) {
+                    return Err(format!(
+                        "{}: each unit must appear at most once, from the largest to the smallest",
+                        invalid()
+                    ));
+                }
+                last_unit_seconds = Some(unit_seconds);
+
+                let value = super::number::parse(&components[start..i].replace(',', "."))
+                    .ok_or_else(invalid)?;
+
+                seconds += value * Number::from(unit_seconds);
+                start = i + 1;
+            }
+        }
+    }
+
+    // A trailing number without unit.
+    if start != components.len() {
+        return Err(invalid());
+    }
+
+    if negative {
+        seconds = -seconds;
+    }
+
+    duration_from_seconds(&seconds).ok_or_else(|| format!("duration `{}` is out of range", s))
+}
+
+/// Render a duration in the ISO 8601 notation, using days, hours, minutes and seconds, such as
+/// `P1DT2H0.5S`.
+pub fn format_duration(d: &Duration) -> String {
+    if d.is_zero() {
+        return String::from("PT0S");
+    }
+
+    let sign = if d.is_negative() { "-" } else { "" };
+    let seconds = d.whole_seconds().unsigned_abs();
+    let nanos = d.subsec_nanoseconds().unsigned_abs();
+
+    let days = seconds / SECONDS_PER_DAY as u64;
+    let hours = seconds % SECONDS_PER_DAY as u64 / SECONDS_PER_HOUR as u64;
+    let minutes = seconds % SECONDS_PER_HOUR as u64 / SECONDS_PER_MINUTE as u64;
+    let seconds = seconds % SECONDS_PER_MINUTE as u64;
+
+    let mut result = format!("{}P", sign);
+
+    if days != 0 {
+        result.push_str(&format!("{}D", days));
+    }
+
+    if hours != 0 || minutes != 0 || seconds != 0 || nanos != 0 {
+        result.push('T');
+    }
+
+    if hours != 0 {
+        result.push_str(&format!("{}H", hours));
+    }
+
+    if minutes != 0 {
+        result.push_str(&format!("{}M", minutes));
+    }
+
+    if nanos != 0 {
+        let fraction = format!("{:09}", nanos);
+        result.push_str(&format!("{}.{}S", seconds, fraction.trim_end_matches('0')));
+    } else if seconds != 0 {
+        result.push_str(&format!("{}S", seconds));
+    }
+
+    result
+}
+
+/// Convert a number of seconds to a number of nanoseconds, truncating the fractional part.
+fn to_nanos(seconds: &Number) -> Option<i128> {
+    let nanos =
+        Integer::rounding_from(seconds * Number::from(NANOS_PER_SECOND), RoundingMode::Down).0;
+    i128::try_from(&nanos).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datetimes() {
+        let dt = parse("2022-11-03T10:45:00.5+01:00").unwrap();
+        assert_eq!(format(&dt).unwrap(), "2022-11-03T10:45:00.5+01:00");
+        assert_eq!(
+            to_timestamp(&dt),
+            Number::from(1667468700) + Number::from_signeds(1, 2)
+        );
+        assert_eq!(
+            format(&from_timestamp(&Number::from(0)).unwrap()).unwrap(),
+            "1970-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            format_with(&dt, "[day]/[month]/[year]").unwrap(),
+            "03/11/2022"
+        );
+        assert_eq!(parse("2022-11-03"), None);
+    }
+
+    #[test]
+    fn durations() {
+        let check = |s: &str, seconds: Number, canonical: &str| {
+            let d = parse_duration(s).unwrap();
+            assert_eq!(duration_to_seconds(&d), seconds);
+            assert_eq!(format_duration(&d), canonical);
+        };
+
+        check("PT1H30M", Number::from(5400), "PT1H30M");
+        check("P1W", Number::from(604800), "P7D");
+        check("P1DT0.5S", Number::from_signeds(172801, 2), "P1DT0.5S");
+        check("-PT10M", Number::from(-600), "-PT10M");
+        check("PT0,25H", Number::from(900), "PT15M");
+        check("PT0S", Number::from(0), "PT0S");
+
+        assert!(parse_duration("P1Y").is_err());
+        assert!(parse_duration("P1M").is_err());
+        assert!(parse_duration("PT1S1H").is_err());
+        assert!(parse_duration("PT1H1H").is_err());
+        assert!(parse_duration("PT").is_err());
+        assert!(parse_duration("P").is_err());
+        assert!(parse_duration("PT1").is_err());
+        assert!(parse_duration("1H").is_err());
+        assert!(parse_duration("PT1H1D").is_err());
+    }
+}
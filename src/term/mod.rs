@@ -18,12 +18,14 @@
 //! definitions of contracts, record and metadata all together.
 
 pub mod array;
+pub mod datetime;
+pub mod number;
 pub mod record;
 
-use array::{Array, ArrayAttrs};
+use array::{Array, ArrayAttrs, ArrayMaskOp};
 
 use crate::{
-    destruct::Destruct,
+    destruct::{Destruct, MatchBranch},
     error::ParseError,
     eval::EvalMode,
     identifier::Ident,
@@ -35,7 +37,7 @@ use crate::{
 
 use codespan::FileId;
 
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 use std::{
     cmp::{Ordering, PartialOrd},
@@ -46,6 +48,8 @@ use std::{
     rc::Rc,
 };
 
+use datetime::{DateTime, Duration};
+use number::Number;
 use record::{FieldDeps, RecordData, RecordDeps};
 
 /// The AST of a Nickel expression.
@@ -53,16 +57,22 @@ use record::{FieldDeps, RecordData, RecordDeps};
 /// Parsed terms also need to store their position in the source for error reporting.  This is why
 /// this type is nested with [`RichTerm`].
 ///
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
 #[serde(untagged)]
 pub enum Term {
     /// The null value.
     Null,
     /// A boolean value.
     Bool(bool),
-    /// A floating-point value.
+    /// An arbitrary precision rational number. See [number].
     #[serde(serialize_with = "crate::serialize::serialize_num")]
-    Num(f64),
+    Num(Number),
+    /// A datetime. See [datetime].
+    #[serde(serialize_with = "crate::serialize::serialize_datetime")]
+    DateTime(DateTime),
+    /// A duration. See [datetime].
+    #[serde(serialize_with = "crate::serialize::serialize_duration")]
+    Duration(Duration),
     /// A literal string.
     Str(String),
     /// A string containing interpolated expressions, represented as a list of either literals or
@@ -88,6 +98,10 @@ pub enum Term {
     /// A let binding.
     #[serde(skip)]
     Let(Ident, RichTerm, RichTerm, LetAttrs),
+    /// A block of let-bindings `let [rec] id1 = t1, .., idn = tn in body`. The bindings of a
+    /// recursive block are mutually recursive: each one is in scope in all the others.
+    #[serde(skip)]
+    LetBlock(Vec<(Ident, RichTerm)>, RichTerm, LetAttrs),
     /// A destructuring let-binding.
     #[serde(skip)]
     LetPattern(Option<Ident>, Destruct, RichTerm, RichTerm),
@@ -100,10 +114,14 @@ pub enum Term {
 
     /// An enum variant.
     Enum(Ident),
+    /// An enum variant carrying an argument, such as `` `Ok 5 ``.
+    ///
+    /// Serialized in the externally tagged form, that is as a single-field record `{tag: arg}`.
+    #[serde(serialize_with = "crate::serialize::serialize_enum_variant")]
+    EnumVariant { tag: Ident, arg: RichTerm },
 
     /// A record, mapping identifiers to terms.
     #[serde(serialize_with = "crate::serialize::serialize_record")]
-    #[serde(deserialize_with = "crate::serialize::deserialize_record")]
     Record(RecordData),
     /// A recursive record, where the fields can reference each others.
     #[serde(skip)]
@@ -122,9 +140,14 @@ pub enum Term {
         default: Option<RichTerm>,
     },
 
+    /// A match construct with general patterns (see [crate::destruct::MatchBranch]), whose
+    /// branches are tried in order. Match expressions with only enum tags as patterns are
+    /// represented by [Term::Match] instead. Like let-patterns, it is desugared before evaluation.
+    #[serde(skip)]
+    MatchPattern(Vec<MatchBranch>),
+
     /// An array.
     #[serde(serialize_with = "crate::serialize::serialize_array")]
-    #[serde(deserialize_with = "crate::serialize::deserialize_array")]
     Array(Array, ArrayAttrs),
 
     /// A primitive unary operator.
@@ -165,7 +188,6 @@ pub enum Term {
     Sealed(SealingKey, RichTerm, Label),
 
     #[serde(serialize_with = "crate::serialize::serialize_meta_value")]
-    #[serde(skip_deserializing)]
     MetaValue(MetaValue),
 
     /// An unresolved import.
@@ -495,6 +517,12 @@ impl Term {
                     func(default)
                 }
             }
+            MatchPattern(ref mut branches) => {
+                branches.iter_mut().for_each(|branch| {
+                    branch.guard.iter_mut().for_each(&func);
+                    func(&mut branch.body);
+                });
+            }
             Record(ref mut r) => {
                 r.fields.iter_mut().for_each(|(_, t)| func(t));
             }
@@ -505,10 +533,11 @@ impl Term {
                     func(t2);
                 });
             }
-            Bool(_) | Num(_) | Str(_) | Lbl(_) | Var(_) | SealingKey(_) | Enum(_) | Import(_)
-            | ResolvedImport(_) => {}
+            Bool(_) | Num(_) | DateTime(_) | Duration(_) | Str(_) | Lbl(_) | Var(_)
+            | SealingKey(_) | Enum(_) | Import(_) | ResolvedImport(_) => {}
             Fun(_, ref mut t)
             | FunPattern(_, _, ref mut t)
+            | EnumVariant { arg: ref mut t, .. }
             | Op1(_, ref mut t)
             | Sealed(_, ref mut t, _) => {
                 func(t);
@@ -523,6 +552,10 @@ impl Term {
                     });
                 meta.value.iter_mut().for_each(func);
             }
+            LetBlock(ref mut bindings, ref mut body, _) => {
+                bindings.iter_mut().for_each(|(_, t)| func(t));
+                func(body);
+            }
             Let(_, ref mut t1, ref mut t2, _)
             | LetPattern(_, _, ref mut t1, ref mut t2)
             | App(ref mut t1, ref mut t2)
@@ -552,17 +585,20 @@ impl Term {
             Term::Null => Some("Null"),
             Term::Bool(_) => Some("Bool"),
             Term::Num(_) => Some("Num"),
+            Term::DateTime(_) => Some("DateTime"),
+            Term::Duration(_) => Some("Duration"),
             Term::Str(_) => Some("Str"),
             Term::Fun(_, _) | Term::FunPattern(_, _, _) => Some("Fun"),
-            Term::Match { .. } => Some("MatchExpression"),
+            Term::Match { .. } | Term::MatchPattern(_) => Some("MatchExpression"),
             Term::Lbl(_) => Some("Label"),
-            Term::Enum(_) => Some("Enum"),
+            Term::Enum(_) | Term::EnumVariant { .. } => Some("Enum"),
             Term::Record(..) | Term::RecRecord(..) => Some("Record"),
             Term::Array(..) => Some("Array"),
             Term::SealingKey(_) => Some("SealingKey"),
             Term::Sealed(..) => Some("Sealed"),
             Term::MetaValue(_) => Some("Metavalue"),
             Term::Let(..)
+            | Term::LetBlock(..)
             | Term::LetPattern(..)
             | Term::App(_, _)
             | Term::Var(_)
@@ -583,7 +619,12 @@ impl Term {
             Term::Null => String::from("null"),
             Term::Bool(true) => String::from("true"),
             Term::Bool(false) => String::from("false"),
-            Term::Num(n) => format!("{}", n),
+            Term::Num(n) => number::format(n),
+            Term::DateTime(dt) => match datetime::format(dt) {
+                Ok(s) => format!("<datetime {}>", s),
+                Err(_) => String::from("<datetime>"),
+            },
+            Term::Duration(d) => format!("<duration {}>", datetime::format_duration(d)),
             Term::Str(s) => format!("\"{}\"", s),
             Term::StrChunks(chunks) => {
                 let chunks_str: Vec<String> = chunks
@@ -598,7 +639,7 @@ impl Term {
                 format!("\"{}\"", chunks_str.join(""))
             }
             Term::Fun(_, _) | Term::FunPattern(_, _, _) => String::from("<func>"),
-            Term::Match { .. } => String::from("<func (match expr)>"),
+            Term::Match { .. } | Term::MatchPattern(_) => String::from("<func (match expr)>"),
             Term::Lbl(_) => String::from("<label>"),
             Term::Enum(id) => {
                 let re = regex::Regex::new("_?[a-zA-Z][_a-zA-Z0-9]*").unwrap();
@@ -609,6 +650,9 @@ impl Term {
                     format!("`\"{}\"", s)
                 }
             }
+            Term::EnumVariant { tag, .. } => {
+                format!("{} ...", Term::Enum(*tag).shallow_repr())
+            }
             Term::Record(..) | Term::RecRecord(..) => String::from("{ ... }"),
             Term::Array(..) => String::from("[ ... ]"),
             Term::SealingKey(_) => String::from("<sealing key>"),
@@ -639,6 +683,7 @@ impl Term {
             Term::Var(id) => id.to_string(),
             Term::ParseError(_) => String::from("<parse error>"),
             Term::Let(..)
+            | Term::LetBlock(..)
             | Term::LetPattern(..)
             | Term::App(_, _)
             | Term::Op1(_, _)
@@ -673,6 +718,15 @@ impl Term {
                     .collect();
                 format!("[ {} ]", elements_str.join(", "))
             }
+            Term::EnumVariant { tag, arg } => {
+                let arg_str = arg.as_ref().deep_repr();
+
+                if arg.as_ref().is_atom() {
+                    format!("{} {}", Term::Enum(*tag).shallow_repr(), arg_str)
+                } else {
+                    format!("{} ({})", Term::Enum(*tag).shallow_repr(), arg_str)
+                }
+            }
             _ => self.shallow_repr(),
         }
     }
@@ -683,18 +737,23 @@ impl Term {
             Term::Null
             | Term::Bool(_)
             | Term::Num(_)
+            | Term::DateTime(_)
+            | Term::Duration(_)
             | Term::Str(_)
             | Term::Fun(_, _)
             // match expressions are function
             | Term::Match {..}
             | Term::Lbl(_)
             | Term::Enum(_)
+            | Term::EnumVariant { .. }
             | Term::Record(..)
             | Term::Array(..)
             | Term::SealingKey(_) => true,
             Term::Let(..)
+            | Term::LetBlock(..)
             | Term::LetPattern(..)
             | Term::FunPattern(..)
+            | Term::MatchPattern(_)
             | Term::App(_, _)
             | Term::Var(_)
             | Term::Op1(_, _)
@@ -724,18 +783,23 @@ impl Term {
             Term::Null
             | Term::Bool(_)
             | Term::Num(_)
+            | Term::DateTime(_)
+            | Term::Duration(_)
             | Term::Str(_)
             | Term::Lbl(_)
             | Term::Enum(_)
             | Term::SealingKey(_) => true,
             Term::Let(..)
+            | Term::LetBlock(..)
             | Term::LetPattern(..)
+            | Term::EnumVariant { .. }
             | Term::Record(..)
             | Term::Array(..)
             | Term::Fun(_, _)
             | Term::FunPattern(_, _, _)
             | Term::App(_, _)
             | Term::Match { .. }
+            | Term::MatchPattern(_)
             | Term::Var(_)
             | Term::Op1(_, _)
             | Term::Op2(_, _, _)
@@ -755,7 +819,7 @@ impl Term {
         match self {
             Term::Null
             | Term::Bool(..)
-            | Term::Num(..)
+            | Term::Num(_)
             | Term::Str(..)
             | Term::StrChunks(..)
             | Term::Lbl(..)
@@ -775,16 +839,23 @@ impl Term {
             // We might want a more robust mechanism for pretty printing such operators.
             | Term::Op1(UnaryOp::BoolAnd(), _)
             | Term::Op1(UnaryOp::BoolOr(), _)
+            | Term::Op1(UnaryOp::StaticAccessOr(_), _)
             => true,
             Term::Let(..)
+            | Term::LetBlock(..)
             | Term::Match { .. }
+            | Term::MatchPattern(_)
             | Term::LetPattern(..)
+            | Term::EnumVariant { .. }
             | Term::Fun(..)
             | Term::FunPattern(..)
             | Term::App(..)
             | Term::Op1(..)
             | Term::Op2(..)
             | Term::OpN(..)
+            // Datetimes and durations are printed as the application of a parsing function.
+            | Term::DateTime(_)
+            | Term::Duration(_)
             | Term::Sealed(..)
             | Term::MetaValue(..)
             | Term::Import(..)
@@ -876,10 +947,23 @@ pub enum UnaryOp {
     /// Evaluate a match block applied to an argument.
     Match { has_default: bool },
 
+    /// Return the tag of an enum, stripping the argument if the enum is a variant carrying one.
+    EnumGetTag(),
+    /// Return the argument of an enum variant.
+    EnumGetArg(),
+    /// Determine if an enum is a variant carrying an argument (as opposed to a bare tag).
+    EnumIsVariant(),
+
     /// Static access to a record field.
     ///
     /// Static means that the field identifier is a statically known string inside the source.
     StaticAccess(Ident),
+    /// Static access to a path of nested record fields, with a default value.
+    ///
+    /// Lazily takes the default value as a second argument, which is returned if one of the
+    /// fields on the path is missing or is an optional field without definition. Generated by
+    /// the syntax `record.field1.field2 or default`.
+    StaticAccessOr(Vec<Ident>),
 
     /// Map a function on each element of an array.
     ArrayMap(),
@@ -943,6 +1027,44 @@ pub enum UnaryOp {
     ArrayLength(),
     /// Generate an array of a given length by mapping a `Num -> Num` function onto `[1,..,n]`.
     ArrayGen(),
+    /// Reverse an array.
+    ArrayReverse(),
+    /// Concatenate an array of arrays.
+    ///
+    /// The elements of the outer array are first forced to weak head normal form, before the inner
+    /// arrays are concatenated in a second pass of the same operator.
+    ArrayFlatten(),
+    /// Fold a function on an array from the left, with a strict accumulator. The array is the
+    /// first argument, the function the second one and the initial accumulator the third one.
+    ///
+    /// Each evaluation of `ArrayFoldl` performs one step of the fold and evaluates to the next
+    /// one.
+    ArrayFoldl(),
+    /// Keep the elements of an array satisfying a predicate. The array is the first argument, and
+    /// the predicate the second one.
+    ArrayFilter(),
+    /// Split an array into the elements satisfying a predicate and the other ones. The array is
+    /// the first argument, and the predicate the second one.
+    ArrayPartition(),
+    /// Sort an array given a comparison function. The array is the first argument, and the
+    /// comparison function the second one. The sort is stable.
+    ArraySort(),
+    /// Sort an array by the keys computed by a function, compared with [`BinaryOp::Compare`]. The
+    /// array is the first argument, and the function the second one. The keys are computed again
+    /// for each comparison, which only evaluates them as far as needed. The sort is stable.
+    ArraySortBy(),
+    /// Sort an array by the keys computed by a function, compared with [`BinaryOp::Compare`]. The
+    /// array is the first argument, and the function the second one. The key of each element is
+    /// computed and fully evaluated once. The sort is stable.
+    ArraySortOn(),
+    /// Return the first element of a non-empty array with the smallest key, as computed by a
+    /// function and compared with [`BinaryOp::Compare`]. The array is the first argument, and the
+    /// function the second one.
+    ArrayMinBy(),
+    /// Return the first element of a non-empty array with the greatest key, as computed by a
+    /// function and compared with [`BinaryOp::Compare`]. The array is the first argument, and the
+    /// function the second one.
+    ArrayMaxBy(),
 
     /// Generated by the evaluation of a string with interpolated expressions. `ChunksConcat`
     /// applied to the current chunk to evaluate. As additional state, it uses a string
@@ -963,6 +1085,22 @@ pub enum UnaryOp {
     CharCode(),
     /// Return the character corresponding to a code.
     CharFromCode(),
+    /// Parse a datetime in the RFC 3339 format.
+    DateTimeParse(),
+    /// Render a datetime in the RFC 3339 format.
+    DateTimeToStr(),
+    /// Return the number of seconds elapsed since the Unix epoch.
+    DateTimeToTimestamp(),
+    /// Return the UTC datetime corresponding to a number of seconds since the Unix epoch.
+    DateTimeFromTimestamp(),
+    /// Parse a duration in the ISO 8601 notation.
+    DurationParse(),
+    /// Render a duration in the ISO 8601 notation.
+    DurationToStr(),
+    /// Return the number of seconds of a duration.
+    DurationToSeconds(),
+    /// Return the duration corresponding to a number of seconds.
+    DurationFromSeconds(),
     /// Transform a string to uppercase.
     StrUppercase(),
     /// Transform a string to lowercase.
@@ -978,7 +1116,7 @@ pub enum UnaryOp {
     /// Test if a regex matches a string.
     /// Like [`UnaryOp::StrFind`], this is a unary operator because we would like a way to share the
     /// same "compiled regex" for many matching calls. This is done by returning functions
-    /// wrapping [`UnaryOp::StrIsMatchCompiled`] and [`UnaryOp::StrFindCompiled`]
+    /// wrapping [`UnaryOp::StrIsMatchCompiled`], [`UnaryOp::StrFindCompiled`], etc.
     StrIsMatch(),
     /// Match a regex on a string, and returns the captured groups together, the index of the
     /// match, etc.
@@ -987,6 +1125,15 @@ pub enum UnaryOp {
     StrIsMatchCompiled(CompiledRegex),
     /// Version of [`UnaryOp::StrFind`] which remembers the compiled regex.
     StrFindCompiled(CompiledRegex),
+    /// Match a regex on a string, and return the array of all the non-overlapping matches.
+    StrFindAll(),
+    /// Version of [`UnaryOp::StrFindAll`] which remembers the compiled regex.
+    StrFindAllCompiled(CompiledRegex),
+    /// Replace the matches of a regex in a string by the result of a function applied to each
+    /// match. The string is the first argument, and the replacement function the second one.
+    StrReplaceRegexWith(),
+    /// Version of [`UnaryOp::StrReplaceRegexWith`] which remembers the compiled regex.
+    StrReplaceRegexWithCompiled(CompiledRegex),
     /// Force full evaluation of a term and return it.
     ///
     /// This was added in the context of [`BinaryOp::ArrayLazyAssume`],
@@ -1004,6 +1151,9 @@ pub enum UnaryOp {
     /// It's also worth noting that [`UnaryOp::DeepSeq`] should be, in principle, more efficient that [`UnaryOp::Force`]
     /// as it does less cloning.
     Force(Option<crate::eval::callstack::StackElem>),
+    /// Emit its first argument, a string, to the trace sink of the virtual machine (see
+    /// [crate::eval::trace]) and proceed with the second one.
+    Trace(),
     /// Recursive default priority operator. Recursively propagates a default priority through a
     /// record, stopping whenever a field isn't a record anymore to then turn into a simple
     /// `default`.
@@ -1039,6 +1189,14 @@ pub enum UnaryOp {
     /// As opposed to `RecDefault`, the `force` takes precedence and erase any prior explicit
     /// priority annotation.
     RecForce(),
+    /// Replace the value of a field definition by the expression on top of the stack, keeping
+    /// the metadata of the original definition. The contracts and the type annotation of the
+    /// original definition are applied to the new value. Used to implement
+    /// [BinaryOp::DynUpdate].
+    ///
+    /// As for `RecDefault`, the argument is evaluated without unwrapping metavalues. This operator
+    /// is only produced during evaluation.
+    ReplaceValue(),
 
     /// Creates an "empty" record with the sealed tail of its [`Term::Record`]
     /// argument.
@@ -1086,7 +1244,9 @@ pub enum OpPos {
 impl UnaryOp {
     pub fn eval_mode(&self) -> EvalMode {
         match self {
-            UnaryOp::RecDefault() | UnaryOp::RecForce() => EvalMode::StopAtMeta,
+            UnaryOp::RecDefault() | UnaryOp::RecForce() | UnaryOp::ReplaceValue() => {
+                EvalMode::StopAtMeta
+            }
             _ => EvalMode::default(),
         }
     }
@@ -1094,7 +1254,7 @@ impl UnaryOp {
     pub fn pos(&self) -> OpPos {
         use UnaryOp::*;
         match self {
-            BoolAnd() | BoolOr() | StaticAccess(_) => OpPos::Postfix,
+            BoolAnd() | BoolOr() | StaticAccess(_) | StaticAccessOr(_) => OpPos::Postfix,
             Ite() => OpPos::Special,
             _ => OpPos::Prefix,
         }
@@ -1128,6 +1288,15 @@ pub enum BinaryOp {
     GreaterThan(),
     /// Greater than or equal comparison operator.
     GreaterOrEq(),
+    /// Structural comparison of two values, returning one of the enum tags `Lesser`, `Equal`
+    /// or `Greater`.
+    ///
+    /// Values of the same type are totally ordered: booleans (`false` comes first), numbers,
+    /// strings (lexicographically), enum tags (by name), dates and durations. Arrays are compared
+    /// lexicographically. Records are compared by their sorted list of field names first, and then
+    /// by the values of their fields, in the order of field names. As for equality, arrays and
+    /// records are compared lazily: the comparison stops at the first difference.
+    Compare(),
     /// An assume.
     ///
     /// Apply a contract to a label and a value. The value is is stored on the stack unevaluated,
@@ -1155,6 +1324,11 @@ pub enum BinaryOp {
     DynExtend(),
     /// Remove a field from a record. The field name is given as an arbitrary Nickel expression.
     DynRemove(),
+    /// Set the value of a field of a record, or add it if it is absent. As for `DynExtend`, the
+    /// new value is the expression on top of the stack. If the field already exists, its metadata
+    /// (documentation, priority, type annotation and contracts) is kept, and the contracts are
+    /// applied to the new value.
+    DynUpdate(),
     /// Access the field of record. The field name is given as an arbitrary Nickel expression.
     DynAccess(),
     /// Test if a record has a specific field.
@@ -1163,11 +1337,22 @@ pub enum BinaryOp {
     ArrayConcat(),
     /// Access the n-th element of an array.
     ArrayElemAt(),
+    /// Generate the array of the integers in the range `[start, end)`.
+    ArrayRange(),
+    /// Pair up the elements of two arrays in records `{fst, snd}`. The result is as long as the
+    /// shortest argument.
+    ArrayZip(),
     /// The merge operator (see [crate::eval::merge]).
     Merge(),
 
-    /// Hash a string.
+    /// Hash a string (see [crate::hash]).
     Hash(),
+    /// Hash a fully evaluated value structurally (see [crate::hash]).
+    HashValue(),
+    /// Encode a string (see [crate::encoding]).
+    Encode(),
+    /// Decode a string (see [crate::encoding]).
+    Decode(),
     /// Serialize a value to a string.
     Serialize(),
     /// Deserialize a string to a value.
@@ -1177,6 +1362,15 @@ pub enum BinaryOp {
     StrSplit(),
     /// Determine if a string is a substring of another one.
     StrContains(),
+    /// Render an integer in the given radix.
+    ToStrRadix(),
+    /// Format a string with an array of positional arguments or a record of named arguments (see
+    /// [crate::format]).
+    StrFormat(),
+    /// Render a datetime according to a format description.
+    DateTimeFormat(),
+    /// Add a duration to a datetime.
+    DateTimeAdd(),
     /// Seal a term with a sealing key (see [`Term::Sealed`]).
     Seal(),
 
@@ -1198,7 +1392,7 @@ impl BinaryOp {
         match self {
             Plus() | Sub() | Mult() | Div() | Modulo() | Pow() | StrConcat() | Eq()
             | LessThan() | LessOrEq() | GreaterThan() | GreaterOrEq() | DynExtend()
-            | DynRemove() | DynAccess() | ArrayConcat() | Merge() => OpPos::Infix,
+            | DynRemove() | DynUpdate() | DynAccess() | ArrayConcat() | Merge() => OpPos::Infix,
             _ => OpPos::Prefix,
         }
     }
@@ -1214,6 +1408,23 @@ pub enum NAryOp {
     StrReplaceRegex(),
     /// Return a substring of an original string.
     StrSubstr(),
+    /// Pad a string on the left to a given width with a fill character.
+    StrPadLeft(),
+    /// Pad a string on the right to a given width with a fill character.
+    StrPadRight(),
+    /// Return the sub-array of an array between a start index (included) and an end index
+    /// (excluded).
+    ArraySlice(),
+    /// Second stage of the array operations taking a function returning a value to be inspected
+    /// natively (see [`ArrayMaskOp`]). Takes the function, the original array, and the fully
+    /// evaluated array of the results of the function, called the mask.
+    ///
+    /// This operator is only produced during evaluation.
+    ArrayMasked(ArrayMaskOp),
+    /// Copy a field of a record to another record, possibly under a different name. Takes the name
+    /// of the field to copy, the source record, the new name and the target record. The content
+    /// of the field, including its metadata, is copied as it is.
+    RecordCopyField(),
     /// The merge operator in contract mode (see [crate::eval::merge]). The arguments are in order
     /// the contract's label, the value to check, and the contract as a record.
     MergeContract(),
@@ -1242,9 +1453,13 @@ impl NAryOp {
             NAryOp::StrReplace()
             | NAryOp::StrReplaceRegex()
             | NAryOp::StrSubstr()
+            | NAryOp::StrPadLeft()
+            | NAryOp::StrPadRight()
+            | NAryOp::ArraySlice()
+            | NAryOp::ArrayMasked(_)
             | NAryOp::MergeContract()
             | NAryOp::RecordUnsealTail() => 3,
-            NAryOp::RecordSealTail() => 4,
+            NAryOp::RecordCopyField() | NAryOp::RecordSealTail() => 4,
         }
     }
 
@@ -1259,6 +1474,16 @@ impl fmt::Display for NAryOp {
             NAryOp::StrReplace() => write!(f, "strReplace"),
             NAryOp::StrReplaceRegex() => write!(f, "strReplaceRegex"),
             NAryOp::StrSubstr() => write!(f, "substring"),
+            NAryOp::StrPadLeft() => write!(f, "padLeft"),
+            NAryOp::StrPadRight() => write!(f, "padRight"),
+            NAryOp::ArraySlice() => write!(f, "slice"),
+            NAryOp::ArrayMasked(ArrayMaskOp::Filter) => write!(f, "filter"),
+            NAryOp::ArrayMasked(ArrayMaskOp::Partition) => write!(f, "partition"),
+            NAryOp::ArrayMasked(ArrayMaskOp::Sort(_)) => write!(f, "sort"),
+            NAryOp::ArrayMasked(ArrayMaskOp::SortOn) => write!(f, "sort_on"),
+            NAryOp::ArrayMasked(ArrayMaskOp::MinBy) => write!(f, "min_by"),
+            NAryOp::ArrayMasked(ArrayMaskOp::MaxBy) => write!(f, "max_by"),
+            NAryOp::RecordCopyField() => write!(f, "%record_copy_field%"),
             NAryOp::MergeContract() => write!(f, "mergeContract"),
             NAryOp::RecordSealTail() => write!(f, "%record_seal_tail%"),
             NAryOp::RecordUnsealTail() => write!(f, "%record_unseal_tail%"),
@@ -1349,6 +1574,17 @@ impl RichTerm {
                     pos,
                 )
             },
+            Term::LetBlock(bindings, body, attrs) => {
+                let bindings = bindings
+                    .into_iter()
+                    .map(|(id, t)| Ok((id, t.traverse(f, state, order)?)))
+                    .collect::<Result<Vec<_>, E>>()?;
+                let body = body.traverse(f, state, order)?;
+                RichTerm::new(
+                    Term::LetBlock(bindings, body, attrs),
+                    pos,
+                )
+            },
             Term::LetPattern(id, pat, t1, t2) => {
                 let t1 = t1.traverse(f, state, order)?;
                 let t2 = t2.traverse(f, state, order)?;
@@ -1381,6 +1617,30 @@ impl RichTerm {
                     pos,
                 )
             },
+            Term::MatchPattern(branches) => {
+                let branches_res: Result<Vec<MatchBranch>, E> = branches
+                    .into_iter()
+                    .map(|MatchBranch { pattern, guard, body }| {
+                        Ok(MatchBranch {
+                            pattern,
+                            guard: guard.map(|t| t.traverse(f, state, order)).transpose()?,
+                            body: body.traverse(f, state, order)?,
+                        })
+                    })
+                    .collect();
+
+                RichTerm::new(
+                    Term::MatchPattern(branches_res?),
+                    pos,
+                )
+            },
+            Term::EnumVariant { tag, arg } => {
+                let arg = arg.traverse(f, state, order)?;
+                RichTerm::new(
+                    Term::EnumVariant { tag, arg },
+                    pos,
+                )
+            },
             Term::Op1(op, t) => {
                 let t = t.traverse(f, state, order)?;
                 RichTerm::new(
@@ -1585,10 +1845,10 @@ impl std::fmt::Display for RichTerm {
 /// It is used somehow as a match statement, going from
 /// ```
 /// # use nickel_lang::term::{RichTerm, Term};
-/// let rt = RichTerm::from(Term::Num(5.0));
+/// let rt = RichTerm::from(Term::Bool(true));
 ///
 /// match rt.term.into_owned() {
-///     Term::Num(x) => x as usize,
+///     Term::Bool(b) => b as usize,
 ///     Term::Str(s) => s.len(),
 ///     _ => 42,
 /// };
@@ -1597,10 +1857,10 @@ impl std::fmt::Display for RichTerm {
 /// ```
 /// # use nickel_lang::term::{RichTerm, Term};
 /// # use nickel_lang::match_sharedterm;
-/// let rt = RichTerm::from(Term::Num(5.0));
+/// let rt = RichTerm::from(Term::Bool(true));
 ///
 /// match_sharedterm!{rt.term, with {
-///         Term::Num(x) => x as usize,
+///         Term::Bool(b) => b as usize,
 ///         Term::Str(s) => s.len(),
 ///     } else 42
 /// };
@@ -1765,6 +2025,23 @@ pub mod make {
         let_in_(true, id, t1, t2)
     }
 
+    pub fn let_block<I, T1, T2>(rec: bool, bindings: Vec<(I, T1)>, body: T2) -> RichTerm
+    where
+        T1: Into<RichTerm>,
+        T2: Into<RichTerm>,
+        I: Into<Ident>,
+    {
+        let attrs = LetAttrs {
+            binding_type: BindingType::Normal,
+            rec,
+        };
+        let bindings = bindings
+            .into_iter()
+            .map(|(id, t)| (id.into(), t.into()))
+            .collect();
+        Term::LetBlock(bindings, body.into(), attrs).into()
+    }
+
     pub fn let_pat<I, D, T1, T2>(id: Option<I>, pat: D, t1: T1, t2: T2) -> RichTerm
     where
         T1: Into<RichTerm>,
@@ -1775,7 +2052,6 @@ pub mod make {
         Term::LetPattern(id.map(|i| i.into()), pat.into(), t1.into(), t2.into()).into()
     }
 
-    #[cfg(test)]
     pub fn if_then_else<T1, T2, T3>(cond: T1, t1: T2, t2: T3) -> RichTerm
     where
         T1: Into<RichTerm>,
@@ -1852,4 +2128,32 @@ mod tests {
         let res = MetaValue::flatten(outer, inner);
         assert_ne!(res.types, None);
     }
+
+    #[test]
+    fn enum_variant_deep_repr() {
+        let variant = |tag, arg: Term| Term::EnumVariant {
+            tag: Ident::from(tag),
+            arg: arg.into(),
+        };
+
+        assert_eq!(
+            variant("Ok", Term::Num(Number::from(1))).deep_repr(),
+            "`Ok 1"
+        );
+        assert_eq!(
+            variant(
+                "Ok",
+                Term::Array(
+                    Array::from_iter([Term::Bool(true).into()]),
+                    Default::default()
+                )
+            )
+            .deep_repr(),
+            "`Ok [ true ]"
+        );
+        assert_eq!(
+            variant("Some", variant("Ok", Term::Null)).deep_repr(),
+            "`Some (`Ok null)"
+        );
+    }
 }
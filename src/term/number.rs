@@ -0,0 +1,245 @@
+//! Numbers.
+//!
+//! Nickel numbers are arbitrary precision rationals. Literals, arithmetic operations and
+//! comparisons are exact, so that e.g. 64-bit identifiers or `0.1 + 0.2` aren't subject to
+//! rounding. Numbers are only approximated by floating-point values when an operation has no exact
+//! rational result (such as a non-integer power), when printing a number without a finite decimal
+//! expansion (such as `1/3`), or when exporting a non-integer to another format than JSON.
+use malachite_base::num::arithmetic::traits::{Pow, UnsignedAbs};
+use malachite_base::num::conversion::traits::{
+    FromSciString, FromStringBase, IsInteger, RoundingFrom, ToStringBase,
+};
+use malachite_base::rounding_modes::RoundingMode;
+use malachite_nz::integer::Integer;
+use std::fmt;
+
+pub use malachite_q::Rational as Number;
+
+/// The radix in which a number literal is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Decimal,
+    Hexadecimal,
+    Octal,
+    Binary,
+}
+
+impl Radix {
+    /// The base corresponding to this radix.
+    pub fn base(self) -> u8 {
+        match self {
+            Radix::Decimal => 10,
+            Radix::Hexadecimal => 16,
+            Radix::Octal => 8,
+            Radix::Binary => 2,
+        }
+    }
+
+    /// The prefix of a literal written in this radix.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Radix::Decimal => "",
+            Radix::Hexadecimal => "0x",
+            Radix::Octal => "0o",
+            Radix::Binary => "0b",
+        }
+    }
+}
+
+impl fmt::Display for Radix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Radix::Decimal => write!(f, "decimal"),
+            Radix::Hexadecimal => write!(f, "hexadecimal"),
+            Radix::Octal => write!(f, "octal"),
+            Radix::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// The largest exponent, in absolute value, of a number in scientific notation or of a power. The
+/// size of an exact result grows with the exponent, which must thus be bounded to avoid exhausting
+/// memory and time.
+pub const MAX_EXPONENT: u64 = 100_000;
+
+/// Parse a number in decimal notation, with an optional sign, fractional part and exponent, such
+/// as `-1.5e3`. The conversion is exact. Return `None` if the exponent is larger than
+/// [MAX_EXPONENT] in absolute value.
+pub fn parse(s: &str) -> Option<Number> {
+    if let Some((_, exp)) = s.split_once(['e', 'E']) {
+        match exp.parse::<i64>() {
+            Ok(exp) if exp.unsigned_abs() <= MAX_EXPONENT => (),
+            _ => return None,
+        }
+    }
+
+    Number::from_sci_string(s)
+}
+
+/// Parse a non-negative integer written in the given base, which must be between 2 and 36, without
+/// prefix nor sign.
+pub fn parse_base(s: &str, base: u8) -> Option<Number> {
+    Integer::from_string_base(base, s).map(Number::from)
+}
+
+/// Convert a float to the simplest number which rounds to it, such that `0.1` becomes `1/10`.
+/// Return `None` for infinite and `NaN` values.
+pub fn from_f64(f: f64) -> Option<Number> {
+    Number::try_from_float_simplest(f).ok()
+}
+
+/// Convert a number to the closest float.
+pub fn to_f64(n: &Number) -> f64 {
+    f64::rounding_from(n, RoundingMode::Nearest).0
+}
+
+/// Render a number in decimal notation. The representation is exact if the number has a finite
+/// decimal expansion. Otherwise, the number is rendered as the closest float.
+pub fn format(n: &Number) -> String {
+    format_exact(n).unwrap_or_else(|| to_f64(n).to_string())
+}
+
+/// Render a number exactly in decimal notation. Return `None` if the number doesn't have a finite
+/// decimal expansion, such as `1/3`.
+pub fn format_exact(n: &Number) -> Option<String> {
+    if n.is_integer() {
+        return Some(n.to_string());
+    }
+
+    let scale = n.length_after_point_in_small_base(10)?;
+    let scaled = n * Number::from(10u8).pow(scale);
+    let digits = Integer::rounding_from(&scaled, RoundingMode::Exact)
+        .0
+        .unsigned_abs()
+        .to_string();
+    let scale = scale as usize;
+    // Pad with leading zeros, so that there's at least one digit before the point.
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    let sign = if *n < 0u8 { "-" } else { "" };
+
+    Some(format!("{}{}.{}", sign, int, frac))
+}
+
+/// Render an integer in the given base, which must be between 2 and 36, using lowercase letters
+/// for digits above 9 and without any prefix. Return `None` if the number isn't an integer.
+pub fn format_base(n: &Number, base: u8) -> Option<String> {
+    Integer::try_from(n)
+        .ok()
+        .map(|int| int.to_string_base(base))
+}
+
+/// Render a number in the decimal notation with exactly `precision` digits after the point,
+/// rounding to the nearest representable value (and to the even one in case of a tie).
+pub fn format_fixed(n: &Number, precision: usize) -> String {
+    let scaled = n * Number::from(10u8).pow(precision as u64);
+    let rounded = Integer::rounding_from(&scaled, RoundingMode::Nearest).0;
+    let sign = if rounded < 0u8 { "-" } else { "" };
+    let digits = rounded.unsigned_abs().to_string();
+
+    if precision == 0 {
+        return format!("{}{}", sign, digits);
+    }
+
+    // Pad with leading zeros, so that there's at least one digit before the point.
+    let digits = format!("{:0>width$}", digits, width = precision + 1);
+    let (int, frac) = digits.split_at(digits.len() - precision);
+
+    format!("{}{}.{}", sign, int, frac)
+}
+
+/// Compute the remainder of the division of `n1` by `n2`, where the quotient is rounded toward
+/// zero. The result has thus the same sign as `n1`. Return `None` if `n2` is zero.
+pub fn rem(n1: &Number, n2: &Number) -> Option<Number> {
+    if *n2 == 0u8 {
+        return None;
+    }
+
+    let quotient = Integer::rounding_from(n1 / n2, RoundingMode::Down).0;
+    Some(n1 - n2 * Number::from(quotient))
+}
+
+/// Raise `n1` to the power `n2`. The result is exact if `n2` is an integer, and approximated
+/// otherwise. Return `None` if the result isn't a number, as for `0` raised to a negative power or
+/// for a negative number raised to a non-integer power.
+///
+/// The exponent must not be larger than [MAX_EXPONENT] in absolute value (see
+/// [exceeds_max_exponent]).
+pub fn pow(n1: &Number, n2: &Number) -> Option<Number> {
+    match i64::try_from(n2) {
+        Ok(_) if *n1 == 0u8 && *n2 < 0u8 => None,
+        Ok(exp) => Some(n1.pow(exp)),
+        Err(_) => from_f64(to_f64(n1).powf(to_f64(n2))),
+    }
+}
+
+/// Determine if a number is too large to be used as an exponent, that is, larger than
+/// [MAX_EXPONENT] in absolute value.
+pub fn exceeds_max_exponent(n: &Number) -> bool {
+    *n > MAX_EXPONENT || *n < -(MAX_EXPONENT as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_decimals() {
+        let n = parse("0.1").unwrap() + parse("0.2").unwrap();
+        assert_eq!(n, parse("0.3").unwrap());
+        assert_eq!(format(&n), "0.3");
+        assert_eq!(format(&parse("-0.05").unwrap()), "-0.05");
+        assert_eq!(format(&parse("1.5e3").unwrap()), "1500");
+        assert_eq!(parse("1e999999999"), None);
+        assert_eq!(parse("1e-99999999999999999999999"), None);
+        assert_eq!(format(&Number::from(u64::MAX)), "18446744073709551615");
+        assert_eq!(
+            format(&(Number::from(1u8) / Number::from(3u8))),
+            "0.3333333333333333"
+        );
+        assert_eq!(format_exact(&(Number::from(1u8) / Number::from(3u8))), None);
+    }
+
+    #[test]
+    fn remainder_and_power() {
+        assert_eq!(
+            rem(&Number::from(-7), &Number::from(2)),
+            Some(Number::from(-1))
+        );
+        assert_eq!(rem(&parse("5.5").unwrap(), &Number::from(2)), parse("1.5"));
+        assert_eq!(rem(&Number::from(1), &Number::from(0)), None);
+        assert_eq!(
+            pow(&Number::from(2), &Number::from(64)),
+            Some(Number::from(u64::MAX) + Number::from(1u8))
+        );
+        assert_eq!(pow(&Number::from(2), &Number::from(-1)), parse("0.5"));
+        assert_eq!(
+            pow(&Number::from(4), &parse("0.5").unwrap()),
+            Some(Number::from(2))
+        );
+        assert_eq!(pow(&Number::from(0), &Number::from(-1)), None);
+        assert_eq!(pow(&Number::from(-1), &parse("0.5").unwrap()), None);
+        assert!(exceeds_max_exponent(&Number::from(100_000_000)));
+        assert!(!exceeds_max_exponent(&Number::from(-100_000)));
+        assert!(exceeds_max_exponent(&parse("100000.5").unwrap()));
+    }
+
+    #[test]
+    fn radix() {
+        assert_eq!(parse_base("ff00", 16), Some(Number::from(0xff00)));
+        assert_eq!(parse_base("644", 8), Some(Number::from(0o644)));
+        assert_eq!(parse_base("102", 2), None);
+        assert_eq!(format_base(&Number::from(-255), 16).unwrap(), "-ff");
+        assert_eq!(format_base(&parse("0.5").unwrap(), 16), None);
+    }
+
+    #[test]
+    fn fixed() {
+        let third = Number::from(1u8) / Number::from(3u8);
+        assert_eq!(format_fixed(&third, 3), "0.333");
+        assert_eq!(format_fixed(&parse("-2.345").unwrap(), 2), "-2.34");
+        assert_eq!(format_fixed(&parse("2.5").unwrap(), 0), "2");
+        assert_eq!(format_fixed(&parse("0.0001").unwrap(), 2), "0.00");
+        assert_eq!(format_fixed(&Number::from(42), 2), "42.00");
+    }
+}
@@ -18,6 +18,19 @@
 //! ...
 //! ```
 //!
+//! ## The array pattern:
+//! ```text
+//! let [a, {b}, ..rest] = [1, {b=2}, 3, 4] in ...
+//! ```
+//! will be transformed to:
+//! ```text
+//! let x = [1, {b=2}, 3, 4] in
+//! let rest = %array_slice% x 2 (%length% x) in
+//! let a = %elem_at% x 0 in
+//! let {b} = %elem_at% x 1 in
+//! ...
+//! ```
+//!
 //! ## The function pattern
 //! ```text
 //! let f = fun x@{a, b=c} {d ? 2, ..w} => <do_something> in ...
@@ -30,11 +43,43 @@
 //!     <do_something>
 //! ) in ...
 //! ```
-use crate::destruct::{Destruct, Match};
+//!
+//! ## The match expression
+//! ```text
+//! match { {a, ..} if a > 0 => a, `Foo => 0, _ => -1 }
+//! ```
+//! will be transformed to a function testing each branch in order:
+//! ```text
+//! fun %x% =>
+//!     let %rest1% = (
+//!         let %rest2% = (
+//!             let %rest3% = match {} %x% in
+//!             -1
+//!         ) in
+//!         if $match_constant `Enum `Foo %x% then 0 else %rest2%
+//!     ) in
+//!     if $match_record ["a"] ["a"] true %x% then
+//!         (let {a, ..} = %x% in if a > 0 then a else %rest1%)
+//!     else
+//!         %rest1%
+//! ```
+//! where the empty match `match {}` raises a non-exhaustive match error.
+use std::collections::HashMap;
+
+use crate::destruct::{BranchPattern, Destruct, Match, MatchBranch};
 use crate::identifier::Ident;
-use crate::match_sharedterm;
-use crate::term::make::{op1, op2};
-use crate::term::{BinaryOp::DynRemove, MetaValue, RichTerm, Term, UnaryOp::StaticAccess};
+use crate::stdlib::internals;
+use crate::term::array::{Array, ArrayAttrs};
+use crate::term::make::{if_then_else, let_in, op1, op2, opn};
+use crate::term::number::Number;
+use crate::term::{
+    BinaryOp::{ArrayElemAt, DynRemove, HasField},
+    MetaValue,
+    NAryOp::ArraySlice,
+    RichTerm, Term,
+    UnaryOp::{ArrayLength, BoolAnd, BoolOr, EnumGetArg, StaticAccess},
+};
+use crate::{match_sharedterm, mk_app, mk_fun};
 
 /// Entry point of the patterns desugaring.
 /// It desugar a `RichTerm` if possible (the term is a let pattern or a function with patterns in
@@ -45,6 +90,7 @@ pub fn transform_one(rt: RichTerm) -> RichTerm {
     match *rt.term {
         Term::LetPattern(..) => desugar_with_contract(rt),
         Term::FunPattern(..) => desugar_fun(rt),
+        Term::MatchPattern(..) => desugar_match(rt),
         _ => rt,
     }
 }
@@ -125,7 +171,8 @@ pub fn desugar(rt: RichTerm) -> RichTerm {
 }
 
 /// Wrap `body` in a let construct binding the open part of the pattern to the required value.
-/// Having `let {a,..y} = {a=1, b=2, c=3} in <BODY>` will bind `y` to `{b=2,c=3}` in `BODY`. Here,
+/// Having `let {a,..y} = {a=1, b=2, c=3} in <BODY>` will bind `y` to `{b=2,c=3}` in `BODY`, and
+/// having `let [a,..y] = [1, 2, 3] in <BODY>` will bind `y` to `[2, 3]`. Here,
 /// `x` is the identifier pointing to the full record. If having `val @ {...} = ... in ...` the
 /// variable x should be `Ident("val")` but if we have a `@` binding less form, you will probably
 /// generate a fresh variable.
@@ -143,11 +190,28 @@ fn bind_open_field(x: Ident, pat: &Destruct, body: RichTerm) -> RichTerm {
             rest: None,
             ..
         } => (matches, Ident::fresh()),
+        Destruct::Array {
+            matches,
+            open: true,
+            rest: Some(rest),
+            ..
+        } => {
+            let start = Term::Num(Number::from(matches.len()));
+            let end = op1(ArrayLength(), Term::Var(x));
+            return Term::Let(
+                *rest,
+                opn(ArraySlice(), vec![Term::Var(x).into(), start.into(), end]),
+                body,
+                Default::default(),
+            )
+            .into();
+        }
         Destruct::Record {
             open: false,
             rest: None,
             ..
         }
+        | Destruct::Array { rest: None, .. }
         | Destruct::Empty => return body,
         _ => panic!("A closed pattern can not have a rest binding"),
     };
@@ -184,6 +248,254 @@ fn destruct_term(x: Ident, pat: &Destruct, body: RichTerm) -> RichTerm {
                 pos,
             )),
         }),
-        _ => body,
+        // The contract of an array pattern doesn't include the contracts of the nested patterns,
+        // so they are generated when desugaring each element.
+        Destruct::Array { matches, .. } => {
+            matches
+                .iter()
+                .enumerate()
+                .fold(body, move |t, (i, (id, pat))| {
+                    let elem = op2(ArrayElemAt(), Term::Var(x), Term::Num(Number::from(i)));
+                    let t = RichTerm::new(Term::LetPattern(*id, pat.clone(), elem, t), pos);
+
+                    if pat.is_empty() {
+                        desugar(t)
+                    } else {
+                        desugar_with_contract(t)
+                    }
+                })
+        }
+        Destruct::Empty => body,
+    }
+}
+
+/// Desugar a match expression with general patterns to a function testing each branch in order.
+/// As for functions, the result can contain `Term::LetPattern` inside.
+pub fn desugar_match(rt: RichTerm) -> RichTerm {
+    match_sharedterm! {rt.term, with {
+        Term::MatchPattern(branches) => {
+            let x = Ident::fresh();
+            // When no branch matches, the argument is given to an empty enum match expression,
+            // which raises a non-exhaustive match error.
+            let no_match = RichTerm::new(
+                Term::App(
+                    RichTerm::new(
+                        Term::Match {
+                            cases: HashMap::new(),
+                            default: None,
+                        },
+                        rt.pos,
+                    ),
+                    Term::Var(x).into(),
+                ),
+                rt.pos,
+            );
+            let body = branches
+                .into_iter()
+                .rev()
+                .fold(no_match, |rest, branch| desugar_branch(x, branch, rest));
+
+            RichTerm::new(Term::Fun(x, body), rt.pos)
+        }
+    } else rt
+    }
+}
+
+/// Desugar a branch of a match expression on the variable `x`, where `rest` is the desugaring of
+/// the following branches.
+fn desugar_branch(x: Ident, branch: MatchBranch, rest: RichTerm) -> RichTerm {
+    let MatchBranch {
+        pattern,
+        guard,
+        body,
+    } = branch;
+    let rest_id = Ident::fresh();
+    let rest_var = || RichTerm::from(Term::Var(rest_id));
+
+    let on_match = match guard {
+        Some(guard) => if_then_else(guard, body, rest_var()),
+        None => body,
+    };
+
+    let test_and_bind = |pat: &BranchPattern, on_match: RichTerm, otherwise: RichTerm| {
+        let bound = bind_branch_pattern(x, pat, on_match);
+
+        match pattern_test(Term::Var(x).into(), pat) {
+            Some(test) => if_then_else(test, bound, otherwise),
+            None => bound,
+        }
+    };
+
+    let result = match pattern {
+        // The guard and the body are shared between the alternatives of an or-pattern, by putting
+        // them in a function of the variables bound by the pattern.
+        BranchPattern::Or(alts) => {
+            let vars = alts
+                .first()
+                .map(BranchPattern::bound_vars)
+                .unwrap_or_default();
+            let k = Ident::fresh();
+            let k_fun = vars.iter().rev().fold(on_match, |t, v| mk_fun!(*v, t));
+            let k_app = vars.iter().fold(RichTerm::from(Term::Var(k)), |t, v| {
+                mk_app!(t, Term::Var(*v))
+            });
+
+            let alts = alts.iter().rev().fold(rest_var(), |otherwise, alt| {
+                test_and_bind(alt, k_app.clone(), otherwise)
+            });
+
+            let_in(k, k_fun, alts)
+        }
+        pattern => test_and_bind(&pattern, on_match, rest_var()),
+    };
+
+    let_in(rest_id, rest, result)
+}
+
+/// Bind the variables of a branch pattern (which is not an or-pattern) to the corresponding parts
+/// of `x` in `body`.
+fn bind_branch_pattern(x: Ident, pat: &BranchPattern, body: RichTerm) -> RichTerm {
+    match pat {
+        BranchPattern::Destruct(Some(id), Destruct::Empty) => let_in(*id, Term::Var(x), body),
+        BranchPattern::Destruct(id, pat) => {
+            Term::LetPattern(*id, pat.clone(), Term::Var(x).into(), body).into()
+        }
+        BranchPattern::Variant(_, arg) if !arg.bound_vars().is_empty() => {
+            let arg_var = Ident::fresh();
+            let_in(
+                arg_var,
+                op1(EnumGetArg(), Term::Var(x)),
+                bind_branch_pattern(arg_var, arg, body),
+            )
+        }
+        BranchPattern::Wildcard
+        | BranchPattern::Enum(_)
+        | BranchPattern::Variant(..)
+        | BranchPattern::Constant(_)
+        | BranchPattern::Or(_) => body,
+    }
+}
+
+/// Generate a boolean expression testing if `value` matches a pattern, or `None` if the pattern
+/// matches any value.
+fn pattern_test(value: RichTerm, pat: &BranchPattern) -> Option<RichTerm> {
+    match pat {
+        BranchPattern::Wildcard => None,
+        BranchPattern::Enum(id) => Some(mk_app!(
+            internals::match_constant(),
+            Term::Enum(Ident::from("Enum")),
+            Term::Enum(*id),
+            value
+        )),
+        BranchPattern::Variant(id, arg) => {
+            let tag_test = mk_app!(internals::match_variant(), Term::Enum(*id), value.clone());
+            let arg_test = pattern_test(op1(EnumGetArg(), value), arg);
+
+            Some(match arg_test {
+                Some(arg_test) => mk_app!(op1(BoolAnd(), tag_test), arg_test),
+                None => tag_test,
+            })
+        }
+        BranchPattern::Constant(c) => {
+            let type_tag = match c.as_ref() {
+                Term::Num(_) => "Num",
+                Term::Str(_) => "Str",
+                Term::Bool(_) => "Bool",
+                _ => "Other",
+            };
+
+            Some(mk_app!(
+                internals::match_constant(),
+                Term::Enum(Ident::from(type_tag)),
+                c.clone(),
+                value
+            ))
+        }
+        BranchPattern::Destruct(_, pat) => destruct_test(value, pat),
+        BranchPattern::Or(alts) => alts
+            .iter()
+            .map(|alt| pattern_test(value.clone(), alt))
+            .reduce(|acc, test| Some(mk_app!(op1(BoolOr(), acc?), test?)))
+            .flatten(),
+    }
+}
+
+/// Generate a boolean expression testing if `value` has the shape of a destructuring pattern, or
+/// `None` if the pattern matches any value. Annotations of the pattern aren't tested: they are
+/// applied as contracts once the branch is selected.
+fn destruct_test(value: RichTerm, pat: &Destruct) -> Option<RichTerm> {
+    let and = |acc: RichTerm, test: RichTerm| mk_app!(op1(BoolAnd(), acc), test);
+    let str_array = |names: Vec<String>| {
+        Term::Array(
+            Array::new(names.into_iter().map(|s| Term::Str(s).into()).collect()),
+            ArrayAttrs::new(),
+        )
+    };
+
+    match pat {
+        Destruct::Record { matches, open, .. } => {
+            let field = |m: &Match| match m {
+                Match::Simple(id, meta) | Match::Assign(id, meta, _) => (*id, meta.value.is_some()),
+            };
+            let fields: Vec<String> = matches.iter().map(|m| field(m).0.to_string()).collect();
+            let required: Vec<String> = matches
+                .iter()
+                .map(field)
+                .filter(|(_, has_default)| !has_default)
+                .map(|(id, _)| id.to_string())
+                .collect();
+
+            let shape = mk_app!(
+                internals::match_record(),
+                str_array(required),
+                str_array(fields),
+                Term::Bool(*open),
+                value.clone()
+            );
+
+            let test = matches
+                .iter()
+                .filter_map(|m| match m {
+                    Match::Assign(id, meta, (_, sub_pat)) => {
+                        let field_value = op1(StaticAccess(*id), value.clone());
+                        let sub_test = destruct_test(field_value, sub_pat)?;
+
+                        // A field with a default value may be absent
+                        Some(if meta.value.is_some() {
+                            if_then_else(
+                                op2(HasField(), Term::Str(id.to_string()), value.clone()),
+                                sub_test,
+                                Term::Bool(true),
+                            )
+                        } else {
+                            sub_test
+                        })
+                    }
+                    Match::Simple(..) => None,
+                })
+                .fold(shape, and);
+
+            Some(test)
+        }
+        Destruct::Array { matches, open, .. } => {
+            let shape = mk_app!(
+                internals::match_array(),
+                Term::Num(Number::from(matches.len())),
+                Term::Bool(*open),
+                value.clone()
+            );
+
+            let test = matches
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (_, sub_pat))| {
+                    let elem = op2(ArrayElemAt(), value.clone(), Term::Num(Number::from(i)));
+                    destruct_test(elem, sub_pat)
+                })
+                .fold(shape, and);
+
+            Some(test)
+        }
+        Destruct::Empty => None,
     }
 }
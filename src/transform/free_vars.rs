@@ -36,6 +36,8 @@ impl CollectFreeVars for RichTerm {
             | Term::Null
             | Term::Bool(_)
             | Term::Num(_)
+            | Term::DateTime(_)
+            | Term::Duration(_)
             | Term::Str(_)
             | Term::Lbl(_)
             | Term::SealingKey(_)
@@ -75,6 +77,24 @@ impl CollectFreeVars for RichTerm {
 
                 free_vars.extend(fresh);
             }
+            Term::LetBlock(bindings, body, attrs) => {
+                let mut fresh = HashSet::new();
+
+                for (_, t) in bindings.iter_mut() {
+                    if attrs.rec {
+                        t.collect_free_vars(&mut fresh);
+                    } else {
+                        t.collect_free_vars(free_vars);
+                    }
+                }
+
+                body.collect_free_vars(&mut fresh);
+                for (id, _) in bindings.iter() {
+                    fresh.remove(id);
+                }
+
+                free_vars.extend(fresh);
+            }
             Term::LetPattern(id, dest_pat, t1, t2) => {
                 let mut fresh = HashSet::new();
 
@@ -96,7 +116,22 @@ impl CollectFreeVars for RichTerm {
                     t.collect_free_vars(free_vars);
                 }
             }
-            Term::Op1(_, t) => t.collect_free_vars(free_vars),
+            Term::MatchPattern(branches) => {
+                for branch in branches {
+                    let mut fresh = HashSet::new();
+
+                    if let Some(guard) = &mut branch.guard {
+                        guard.collect_free_vars(&mut fresh);
+                    }
+                    branch.body.collect_free_vars(&mut fresh);
+                    for id in branch.pattern.bound_vars() {
+                        fresh.remove(&id);
+                    }
+
+                    free_vars.extend(fresh);
+                }
+            }
+            Term::Op1(_, t) | Term::EnumVariant { arg: t, .. } => t.collect_free_vars(free_vars),
             Term::Op2(_, t1, t2) => {
                 t1.collect_free_vars(free_vars);
                 t2.collect_free_vars(free_vars);
@@ -182,6 +217,8 @@ impl CollectFreeVars for Types {
         match &mut self.0 {
             TypeF::Dyn
             | TypeF::Num
+            | TypeF::DateTime
+            | TypeF::Duration
             | TypeF::Bool
             | TypeF::Str
             | TypeF::Sym
@@ -229,9 +266,16 @@ fn bind_pattern(dest_pat: &Destruct, free_vars: &mut HashSet<Ident>) {
                 free_vars.remove(rest);
             }
         }
-        Destruct::Array { matches, .. } => {
-            for m in matches {
-                bind_match(m, free_vars);
+        Destruct::Array { matches, rest, .. } => {
+            for (id, sub_pat) in matches {
+                if let Some(id) = id {
+                    free_vars.remove(id);
+                }
+                bind_pattern(sub_pat, free_vars);
+            }
+
+            if let Some(rest) = rest {
+                free_vars.remove(rest);
             }
         }
         Destruct::Empty => {}
@@ -149,6 +149,15 @@ pub fn transform_one(rt: RichTerm) -> RichTerm {
 
                 with_bindings(Term::Array(ts, attrs), bindings, pos)
             },
+            Term::EnumVariant { tag, arg } if should_share(&arg.term) => {
+                let fresh_var = Ident::fresh();
+                let arg_pos = arg.pos;
+                with_bindings(
+                    Term::EnumVariant { tag, arg: RichTerm::new(Term::Var(fresh_var), arg_pos) },
+                    vec![(fresh_var, arg, BindingType::Normal)],
+                    pos,
+                )
+            },
             Term::MetaValue(meta) if meta.value.as_ref().map(|t| should_share(&t.term)).unwrap_or(false) => {
                     let mut meta = meta;
                     let fresh_var = Ident::fresh();
@@ -176,6 +185,8 @@ pub fn should_share(t: &Term) -> bool {
         Term::Null
         | Term::Bool(_)
         | Term::Num(_)
+        | Term::DateTime(_)
+        | Term::Duration(_)
         | Term::Str(_)
         | Term::Lbl(_)
         | Term::SealingKey(_)
@@ -212,6 +212,8 @@ fn contract_eq_bounded<E: TermEnvironment>(
         (Bool(b1), Bool(b2)) => b1 == b2,
         (Num(n1), Num(n2)) => n1 == n2,
         (Str(s1), Str(s2)) => s1 == s2,
+        (DateTime(dt1), DateTime(dt2)) => dt1 == dt2,
+        (Duration(d1), Duration(d2)) => d1 == d2,
         (Enum(id1), Enum(id2)) => id1 == id2,
         (SealingKey(s1), SealingKey(s2)) => s1 == s2,
         (Sealed(key1, inner1, _), Sealed(key2, inner2, _)) => {
@@ -382,21 +384,23 @@ fn rows_as_map<E: TermEnvironment>(
     map
 }
 
-/// Convert enum rows to a hashset.
+/// Convert enum rows to a hashmap, mapping each tag to the type of its argument, if any.
 ///
 /// Require the rows to be closed (i.e. the last element must be `RowEmpty`), otherwise `None` is
 /// returned. `None` is returned as well if a type encountered is not row type, or if it is a
 /// record row.
-fn rows_as_set(erows: &UnifEnumRows) -> Option<HashSet<Ident>> {
-    let set: Option<HashSet<_>> = erows
+fn erows_as_map<E: TermEnvironment>(
+    erows: &GenericUnifEnumRows<E>,
+) -> Option<HashMap<Ident, Option<&GenericUnifType<E>>>> {
+    let map: Option<HashMap<Ident, _>> = erows
         .iter()
         .map(|item| match item {
-            UnifEnumRowsIteratorItem::Row(id) => Some(*id),
+            GenericUnifEnumRowsIteratorItem::Row(EnumRowF { id, types }) => Some((id, types)),
             _ => None,
         })
         .collect();
 
-    set
+    map
 }
 
 /// Perform the type equality comparison on types. Structurally recurse into type constructors and test
@@ -421,6 +425,8 @@ fn type_eq_bounded<E: TermEnvironment>(
             (TypeF::Wildcard(id1), TypeF::Wildcard(id2)) => id1 == id2,
             (TypeF::Dyn, TypeF::Dyn)
             | (TypeF::Num, TypeF::Num)
+            | (TypeF::DateTime, TypeF::DateTime)
+            | (TypeF::Duration, TypeF::Duration)
             | (TypeF::Bool, TypeF::Bool)
             | (TypeF::Sym, TypeF::Sym)
             | (TypeF::Str, TypeF::Str) => true,
@@ -432,9 +438,28 @@ fn type_eq_bounded<E: TermEnvironment>(
                     && type_eq_bounded(state, t1, env1, t2, env2)
             }
             (TypeF::Enum(uty1), TypeF::Enum(uty2)) => {
-                let rows1 = rows_as_set(uty1);
-                let rows2 = rows_as_set(uty2);
-                rows1.is_some() && rows2.is_some() && rows1 == rows2
+                fn arg_eq_bounded<E: TermEnvironment>(
+                    state: &mut State,
+                    uty1: &Option<&GenericUnifType<E>>,
+                    env1: &E,
+                    uty2: &Option<&GenericUnifType<E>>,
+                    env2: &E,
+                ) -> bool {
+                    match (uty1, uty2) {
+                        (None, None) => true,
+                        (Some(uty1), Some(uty2)) => {
+                            type_eq_bounded(state, *uty1, env1, *uty2, env2)
+                        }
+                        _ => false,
+                    }
+                }
+
+                let map1 = erows_as_map(uty1);
+                let map2 = erows_as_map(uty2);
+
+                map1.zip(map2)
+                    .map(|(m1, m2)| map_eq(arg_eq_bounded, state, &m1, env1, &m2, env2))
+                    .unwrap_or(false)
             }
             (TypeF::Record(uty1), TypeF::Record(uty2)) => {
                 fn type_eq_bounded_wrapper<E: TermEnvironment>(
@@ -486,8 +511,8 @@ fn type_eq_bounded<E: TermEnvironment>(
                         body2.subst_rrows(var2, &GenericUnifRecordRows::Constant(cst_id)),
                     ),
                     VarKind::EnumRows => (
-                        body1.subst_erows(var1, &UnifEnumRows::Constant(cst_id)),
-                        body2.subst_erows(var2, &UnifEnumRows::Constant(cst_id)),
+                        body1.subst_erows(var1, &GenericUnifEnumRows::Constant(cst_id)),
+                        body2.subst_erows(var2, &GenericUnifEnumRows::Constant(cst_id)),
                     ),
                 };
 
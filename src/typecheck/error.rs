@@ -1,7 +1,11 @@
 //! Internal error types for typechecking.
 use super::{reporting, State, UnifType};
 use crate::{
-    error::TypecheckError, identifier::Ident, label::ty_path, position::TermPos, term::RichTerm,
+    error::{TypeOrigin, TypecheckError},
+    identifier::Ident,
+    label::ty_path,
+    position::TermPos,
+    term::RichTerm,
     types::TypeF,
 };
 
@@ -20,6 +24,8 @@ pub enum RowUnifError {
     RowMismatch(Ident, Box<UnifError>),
     /// Tried to unify an enum row and a record row.
     RowKindMismatch(Ident, Option<UnifType>, Option<UnifType>),
+    /// Tried to unify an enum row carrying an argument with an enum row without one.
+    EnumRowArgMismatch(Ident),
     /// A [row constraint][super::RowConstr] was violated.
     UnsatConstr(Ident, Option<UnifType>),
     /// Tried to unify a type constant with another different type.
@@ -50,6 +56,7 @@ impl RowUnifError {
                 UnifError::RowKindMismatch(id, uty1, uty2)
             }
             RowUnifError::RowMismatch(id, err) => UnifError::RowMismatch(id, left, right, err),
+            RowUnifError::EnumRowArgMismatch(id) => UnifError::EnumRowArgMismatch(id, left, right),
             RowUnifError::UnsatConstr(id, uty) => UnifError::RowConflict(id, uty, left, right),
             RowUnifError::WithConst(c, uty) => UnifError::WithConst(c, uty),
             RowUnifError::ConstMismatch(c1, c2) => UnifError::ConstMismatch(c1, c2),
@@ -61,7 +68,9 @@ impl RowUnifError {
 /// Error during the unification of two types.
 #[derive(Debug, PartialEq)]
 pub enum UnifError {
-    /// Tried to unify two incompatible types.
+    /// Tried to unify two incompatible types. When one of the original types was a unification
+    /// variable, the variable is kept instead of its value, so that the origin of this value can
+    /// be reported (the same goes for `RowMismatch`).
     TypeMismatch(UnifType, UnifType),
     /// There are two incompatible definitions for the same row.
     RowMismatch(Ident, UnifType, UnifType, Box<UnifError>),
@@ -69,6 +78,9 @@ pub enum UnifError {
     RowKindMismatch(Ident, Option<UnifType>, Option<UnifType>),
     /// Tried to unify two distinct type constants.
     ConstMismatch(usize, usize),
+    /// Tried to unify two enum rows, but the row of the given identifier carries an argument on
+    /// one side and not on the other.
+    EnumRowArgMismatch(Ident, UnifType, UnifType),
     /// Tried to unify two rows, but an identifier of the LHS was absent from the RHS.
     MissingRow(Ident, UnifType, UnifType),
     /// Tried to unify two rows, but the `Dyn` tail of the RHS was absent from the LHS.
@@ -127,15 +139,17 @@ impl UnifError {
     ) -> TypecheckError {
         match self {
             UnifError::TypeMismatch(ty1, ty2) => TypecheckError::TypeMismatch(
-                reporting::to_type(state.table, state.names, names, ty1),
-                reporting::to_type(state.table, state.names, names, ty2),
+                reporting::to_type(state.table, state.names, names, ty1.clone()),
+                reporting::to_type(state.table, state.names, names, ty2.clone()),
+                origins(state, names, ty1, ty2, pos_opt),
                 pos_opt,
             ),
             UnifError::RowMismatch(ident, uty1, uty2, err) => TypecheckError::RowMismatch(
                 ident,
-                reporting::to_type(state.table, state.names, names, uty1),
-                reporting::to_type(state.table, state.names, names, uty2),
+                reporting::to_type(state.table, state.names, names, uty1.clone()),
+                reporting::to_type(state.table, state.names, names, uty2.clone()),
                 Box::new((*err).into_typecheck_err_(state, names, TermPos::None)),
+                origins(state, names, uty1, uty2, pos_opt),
                 pos_opt,
             ),
             UnifError::RowKindMismatch(id, ty1, ty2) => TypecheckError::RowKindMismatch(
@@ -150,11 +164,13 @@ impl UnifError {
             UnifError::ConstMismatch(c1, c2) => TypecheckError::TypeMismatch(
                 reporting::to_type(state.table, state.names, names, UnifType::Constant(c1)),
                 reporting::to_type(state.table, state.names, names, UnifType::Constant(c2)),
+                Vec::new(),
                 pos_opt,
             ),
             UnifError::WithConst(c, ty) => TypecheckError::TypeMismatch(
                 reporting::to_type(state.table, state.names, names, UnifType::Constant(c)),
                 reporting::to_type(state.table, state.names, names, ty),
+                Vec::new(),
                 pos_opt,
             ),
             UnifError::IncomparableFlatTypes(rt1, rt2) => {
@@ -166,6 +182,12 @@ impl UnifError {
                 reporting::to_type(state.table, state.names, names, uty2),
                 pos_opt,
             ),
+            UnifError::EnumRowArgMismatch(id, uty1, uty2) => TypecheckError::EnumRowArgMismatch(
+                id,
+                reporting::to_type(state.table, state.names, names, uty1),
+                reporting::to_type(state.table, state.names, names, uty2),
+                pos_opt,
+            ),
             UnifError::MissingDynTail(uty1, uty2) => TypecheckError::MissingDynTail(
                 reporting::to_type(state.table, state.names, names, uty1),
                 reporting::to_type(state.table, state.names, names, uty2),
@@ -265,3 +287,29 @@ impl UnifError {
         }
     }
 }
+
+/// Determine the origins of the types of a unification error, for the types which are unification
+/// variables whose value was fixed by the typechecking of a known expression (see
+/// [`super::UnifTable::type_origin`]). An origin inside the expression that failed to typecheck,
+/// at `pos_opt`, is omitted: the type was then fixed by this expression itself, and the origin
+/// would only repeat the primary location of the error.
+fn origins(
+    state: &State,
+    names: &mut reporting::NameReg,
+    expd: UnifType,
+    actual: UnifType,
+    pos_opt: TermPos,
+) -> Vec<TypeOrigin> {
+    [expd, actual]
+        .into_iter()
+        .filter_map(|uty| {
+            let span = state.table.type_origin(&uty).filter(|span| {
+                !matches!(pos_opt.as_opt_ref(), Some(pos) if pos.src_id == span.src_id
+                        && pos.start <= span.start
+                        && span.end <= pos.end)
+            })?;
+            let ty = reporting::to_type(state.table, state.names, names, uty);
+            Some(TypeOrigin { ty, span })
+        })
+        .collect()
+}
@@ -0,0 +1,223 @@
+//! Generalization of the type of let-bound expressions (let-polymorphism).
+//!
+//! When typechecking `let x = bound_exp in body` in strict mode, where `bound_exp` is not
+//! annotated, the type of `bound_exp` is inferred one level deeper than the current one (see
+//! [`UnifTable::enter_level`]). Once this is done, the free unification variables of the inferred
+//! type which still have a deeper level than the current one can't appear in the environment,
+//! and are turned into universally quantified type variables. For example, `fun x => x` is first
+//! given the type `_a -> _a`, which is then generalized to `forall a. a -> a`.
+//!
+//! As in ML languages, only the type of syntactic values is generalized (see
+//! [`is_generalizable`]).
+use super::*;
+
+/// Determine if the type of a let-bound expression may be generalized, that is, if the
+/// expression is a syntactic value: a function, a match expression, an enum tag, or an array or a
+/// record whose elements are themselves syntactic values.
+///
+/// Other values, such as literals, can't have a polymorphic type anyway.
+pub fn is_generalizable(t: &Term) -> bool {
+    match t {
+        Term::Fun(..)
+        | Term::FunPattern(..)
+        | Term::Match { .. }
+        | Term::MatchPattern(_)
+        | Term::Enum(_) => true,
+        Term::EnumVariant { arg, .. } => is_generalizable(arg.as_ref()),
+        Term::Array(ts, _) => ts.iter().all(|t| is_generalizable(t.as_ref())),
+        Term::Record(record) => record.fields.values().all(|t| is_generalizable(t.as_ref())),
+        Term::RecRecord(record, dyn_fields, _) => {
+            dyn_fields.is_empty() && record.fields.values().all(|t| is_generalizable(t.as_ref()))
+        }
+        Term::MetaValue(MetaValue {
+            value: Some(t),
+            types: None,
+            contracts,
+            ..
+        }) if contracts.is_empty() => is_generalizable(t.as_ref()),
+        _ => false,
+    }
+}
+
+/// Generalize the type inferred for a let-bound expression. Must be called after the level
+/// entered before inferring this type has been left.
+///
+/// All the free unification variables of `uty` whose level is strictly greater than the current
+/// one are replaced by type variables, which are quantified at the top of the resulting type in
+/// order of appearance.
+pub fn generalize(table: &UnifTable, uty: UnifType) -> UnifType {
+    let mut gen = Generalizer {
+        table,
+        level: table.level,
+        vars: Vec::new(),
+        type_vars: HashMap::new(),
+        rrows_vars: HashMap::new(),
+        erows_vars: HashMap::new(),
+        used: HashSet::new(),
+        next_name: 0,
+    };
+
+    let uty = uty.into_root(table);
+    gen.collect_type_names(uty.clone());
+    let body = gen.gen_type(uty);
+
+    gen.vars
+        .into_iter()
+        .rev()
+        .fold(body, |body, (var, var_kind)| {
+            UnifType::Concrete(TypeF::Forall {
+                var,
+                var_kind,
+                body: Box::new(body),
+            })
+        })
+}
+
+struct Generalizer<'a> {
+    table: &'a UnifTable,
+    /// Unification variables of a level strictly greater than this one are generalized.
+    level: VarLevel,
+    /// The type variables introduced so far, in order of appearance.
+    vars: Vec<(Ident, VarKind)>,
+    /// The type variables introduced for type unification variables.
+    type_vars: HashMap<VarId, Ident>,
+    /// The type variables introduced for record rows unification variables.
+    rrows_vars: HashMap<VarId, Ident>,
+    /// The type variables introduced for enum rows unification variables.
+    erows_vars: HashMap<VarId, Ident>,
+    /// The type variables already occurring in the type, which can't be reused without risking
+    /// a capture.
+    used: HashSet<Ident>,
+    /// The index of the next candidate name for a type variable.
+    next_name: usize,
+}
+
+impl<'a> Generalizer<'a> {
+    /// Generate a fresh type variable name: `a`, `b`, .., `z`, `a1`, `b1`, etc.
+    fn fresh_name(&mut self) -> Ident {
+        loop {
+            let letter = char::from(b'a' + (self.next_name % 26) as u8);
+            let name = match self.next_name / 26 {
+                0 => Ident::from(letter.to_string()),
+                n => Ident::from(format!("{letter}{n}")),
+            };
+            self.next_name += 1;
+
+            if !self.used.contains(&name) {
+                return name;
+            }
+        }
+    }
+
+    /// Return the type variable corresponding to a unification variable, introducing a new one if
+    /// needed.
+    fn var(&mut self, id: VarId, var_kind: VarKind) -> Ident {
+        let existing = match var_kind {
+            VarKind::Type => self.type_vars.get(&id),
+            VarKind::RecordRows => self.rrows_vars.get(&id),
+            VarKind::EnumRows => self.erows_vars.get(&id),
+        };
+
+        if let Some(var) = existing {
+            return *var;
+        }
+
+        let var = self.fresh_name();
+        self.vars.push((var, var_kind));
+
+        match var_kind {
+            VarKind::Type => self.type_vars.insert(id, var),
+            VarKind::RecordRows => self.rrows_vars.insert(id, var),
+            VarKind::EnumRows => self.erows_vars.insert(id, var),
+        };
+
+        var
+    }
+
+    fn gen_type(&mut self, uty: UnifType) -> UnifType {
+        match uty.into_root(self.table) {
+            UnifType::UnifVar(id) if self.table.type_levels[id] > self.level => {
+                UnifType::Concrete(TypeF::Var(self.var(id, VarKind::Type)))
+            }
+            UnifType::Concrete(t) => UnifType::Concrete(t.map_state(
+                |ty, gen| Box::new(gen.gen_type(*ty)),
+                |rrows, gen| gen.gen_rrows(rrows),
+                |erows, gen| gen.gen_erows(erows),
+                self,
+            )),
+            uty => uty,
+        }
+    }
+
+    fn gen_rrows(&mut self, rrows: UnifRecordRows) -> UnifRecordRows {
+        match rrows.into_root(self.table) {
+            UnifRecordRows::UnifVar(id) if self.table.rrows_levels[id] > self.level => {
+                UnifRecordRows::Concrete(RecordRowsF::TailVar(self.var(id, VarKind::RecordRows)))
+            }
+            UnifRecordRows::Concrete(rrows) => UnifRecordRows::Concrete(rrows.map_state(
+                |ty, gen| Box::new(gen.gen_type(*ty)),
+                |rrows, gen| Box::new(gen.gen_rrows(*rrows)),
+                self,
+            )),
+            rrows => rrows,
+        }
+    }
+
+    fn gen_erows(&mut self, erows: UnifEnumRows) -> UnifEnumRows {
+        match erows.into_root(self.table) {
+            UnifEnumRows::UnifVar(id) if self.table.erows_levels[id] > self.level => {
+                UnifEnumRows::Concrete(EnumRowsF::TailVar(self.var(id, VarKind::EnumRows)))
+            }
+            UnifEnumRows::Concrete(erows) => UnifEnumRows::Concrete(erows.map_state(
+                |ty, gen| Box::new(gen.gen_type(*ty)),
+                |erows, gen| Box::new(gen.gen_erows(*erows)),
+                self,
+            )),
+            erows => erows,
+        }
+    }
+
+    /// Collect the type variables occurring in a type, free or bound.
+    fn collect_type_names(&mut self, uty: UnifType) {
+        if let UnifType::Concrete(t) = uty.into_root(self.table) {
+            if let TypeF::Var(var) | TypeF::Forall { var, .. } = &t {
+                self.used.insert(*var);
+            }
+
+            t.map_state(
+                |ty, gen| gen.collect_type_names(*ty),
+                |rrows, gen| gen.collect_rrows_names(rrows),
+                |erows, gen| gen.collect_erows_names(erows),
+                self,
+            );
+        }
+    }
+
+    fn collect_rrows_names(&mut self, rrows: UnifRecordRows) {
+        if let UnifRecordRows::Concrete(rrows) = rrows.into_root(self.table) {
+            if let RecordRowsF::TailVar(var) = &rrows {
+                self.used.insert(*var);
+            }
+
+            rrows.map_state(
+                |ty, gen| gen.collect_type_names(*ty),
+                |rrows, gen| gen.collect_rrows_names(*rrows),
+                self,
+            );
+        }
+    }
+
+    fn collect_erows_names(&mut self, erows: UnifEnumRows) {
+        if let UnifEnumRows::Concrete(erows) = erows.into_root(self.table) {
+            if let EnumRowsF::TailVar(var) = &erows {
+                self.used.insert(*var);
+            }
+
+            erows.map_state(
+                |ty, gen| gen.collect_type_names(*ty),
+                |erows, gen| gen.collect_erows_names(*erows),
+                self,
+            );
+        }
+    }
+}
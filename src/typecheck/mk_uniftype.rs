@@ -30,7 +30,10 @@ macro_rules! mk_uty_enum_row {
     ( $id:expr $(, $ids:expr )* $(; $tail:expr)?) => {
         $crate::typecheck::UnifEnumRows::Concrete(
             $crate::types::EnumRowsF::Extend {
-                row: Ident::from($id),
+                row: $crate::types::EnumRowF {
+                    id: Ident::from($id),
+                    types: None,
+                },
                 tail: Box::new(mk_uty_enum_row!($( $ids ),* $(; $tail)?))
             }
         )
@@ -113,4 +116,6 @@ generate_builder!(dynamic, Dyn);
 generate_builder!(str, Str);
 generate_builder!(num, Num);
 generate_builder!(bool, Bool);
+generate_builder!(datetime, DateTime);
+generate_builder!(duration, Duration);
 generate_builder!(sym, Sym);
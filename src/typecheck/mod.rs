@@ -22,25 +22,27 @@
 //! Type inference is done via a form of bidirectional typechecking coupled with unification, in
 //! the same spirit as GHC (Haskell), albeit the type system of Nickel is much simpler. The type of
 //! un-annotated let-bound expressions (the type of `bound_exp` in `let x = bound_exp in body`) is
-//! inferred in strict mode, but it is never implicitly generalized. For example, the following
-//! program is rejected:
+//! inferred in strict mode. If `bound_exp` is a syntactic value (a variable, a function, a match
+//! expression, or a literal, an array or a record made of such values), its type is then
+//! generalized, in the style of Hindley-Milner. For example, the following program is accepted:
 //!
 //! ```nickel
-//! # Rejected
-//! let id = fun x => x in seq (id "a") (id 5) : Num
+//! # Accepted
+//! (let id = fun x => x in if id true then id 5 else 0) : Num
 //! ```
 //!
-//! Indeed, `id` is given the type `_a -> _a`, where `_a` is a unification variable, but is not
-//! generalized to `forall a. a -> a`. At the first call site, `_a` is unified with `Str`, and at the second
-//! call site the typechecker complains that `5` is not of type `Str`.
+//! Indeed, `id` is first given the type `_a -> _a`, where `_a` is a unification variable. Because
+//! `_a` doesn't appear anywhere else in the environment, it is then generalized to `forall a. a ->
+//! a`, and each use of `id` instantiates `a` with a fresh unification variable. Generalization
+//! relies on levels: see [`UnifTable::enter_level`] and the [generalize] module.
 //!
-//! This restriction is on purpose, as generalization is not trivial to implement efficiently and
-//! more importantly can interact with other components of the type system and type inference. If
-//! polymorphism is required, the user can simply add annotation:
+//! Other expressions, such as applications, are not generalized (this is the so-called value
+//! restriction of ML languages). Function parameters are never generalized either: polymorphism
+//! over arguments requires an explicit annotation.
 //!
 //! ```nickel
-//! # Accepted
-//! let id : forall a. a -> a = fun x => x in seq (id "a") (id 5) : Num
+//! # Rejected
+//! (let f = fun id => if id true then id 5 else 0 in f (fun x => x)) : Num
 //! ```
 //!
 //! In non-strict mode, the type of let-bound expressions is inferred in a shallow way (see
@@ -53,9 +55,10 @@ use crate::{
     environment::Environment as GenericEnvironment,
     error::TypecheckError,
     identifier::Ident,
-    term::{Contract, MetaValue, RichTerm, StrChunk, Term, TraverseOrder},
+    position::{RawSpan, TermPos},
+    term::{Contract, MetaValue, RichTerm, StrChunk, Term, TraverseOrder, UnaryOp},
     types::{
-        EnumRow, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types, VarKind,
+        EnumRowF, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types, VarKind,
     },
     {mk_uty_arrow, mk_uty_enum, mk_uty_enum_row, mk_uty_record, mk_uty_row},
 };
@@ -74,6 +77,7 @@ pub mod reporting;
 #[macro_use]
 pub mod mk_uniftype;
 pub mod eq;
+pub mod generalize;
 
 use eq::{SimpleTermEnvironment, TermEnvironment};
 use error::*;
@@ -99,14 +103,14 @@ pub enum GenericUnifRecordRows<E: TermEnvironment + Clone> {
     UnifVar(VarId),
 }
 
-/// Unifiable enum rows. Same shape as [`crate::types::EnumRows`] but where each tail may be a
-/// unification variable (or a constant).
-///
-/// Enum rows don't store any type (they are just a sequence of identifiers), so there is no
-/// `GenericUnifEnumRows` taking an additional `E` parameter.
+/// A unifiable enum row.
+pub type GenericUnifEnumRow<E> = EnumRowF<Box<GenericUnifType<E>>>;
+
+/// Unifiable enum rows. Same shape as [`crate::types::EnumRows`] but where the type of the
+/// argument of each row is unifiable, and each tail may be a unification variable (or a constant).
 #[derive(Clone, PartialEq, Debug)]
-pub enum UnifEnumRows {
-    Concrete(EnumRowsF<Box<UnifEnumRows>>),
+pub enum GenericUnifEnumRows<E: TermEnvironment + Clone> {
+    Concrete(EnumRowsF<Box<GenericUnifType<E>>, Box<GenericUnifEnumRows<E>>>),
     Constant(VarId),
     UnifVar(VarId),
 }
@@ -121,7 +125,7 @@ pub enum UnifEnumRows {
 #[derive(Clone, PartialEq, Debug)]
 pub enum GenericUnifType<E: TermEnvironment> {
     /// A concrete type (like `Num` or `Str -> Str`).
-    Concrete(TypeF<Box<GenericUnifType<E>>, GenericUnifRecordRows<E>, UnifEnumRows>),
+    Concrete(TypeF<Box<GenericUnifType<E>>, GenericUnifRecordRows<E>, GenericUnifEnumRows<E>>),
     /// A contract, seen as an opaque type. In order to compute type equality between contracts or
     /// between a contract and a type, we need to carry an additional environment. This is why we
     /// don't reuse the variant from [`crate::types::TypeF`].
@@ -152,14 +156,19 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<RecordRows> for GenericUn
     }
 }
 
-impl std::convert::TryInto<EnumRows> for UnifEnumRows {
+impl<E: TermEnvironment + Clone> std::convert::TryInto<EnumRows> for GenericUnifEnumRows<E> {
     type Error = ();
 
     fn try_into(self) -> Result<EnumRows, ()> {
         match self {
-            UnifEnumRows::Concrete(erows) => {
-                let converted: EnumRowsF<Box<EnumRows>> =
-                    erows.try_map(|erows| Ok(Box::new(UnifEnumRows::try_into(*erows)?)))?;
+            GenericUnifEnumRows::Concrete(erows) => {
+                let converted: EnumRowsF<Box<Types>, Box<EnumRows>> = erows.try_map(
+                    |uty| Ok(Box::new(GenericUnifType::try_into(*uty)?)),
+                    |uerows| {
+                        let erows: EnumRows = (*uerows).try_into()?;
+                        Ok(Box::new(erows))
+                    },
+                )?;
                 Ok(EnumRows(converted))
             }
             _ => Err(()),
@@ -179,7 +188,7 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<Types> for GenericUnifTyp
                         Ok(Box::new(ty))
                     },
                     GenericUnifRecordRows::try_into,
-                    UnifEnumRows::try_into,
+                    GenericUnifEnumRows::try_into,
                 )?;
                 Ok(Types(converted))
             }
@@ -189,21 +198,32 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<Types> for GenericUnifTyp
     }
 }
 
-// As opposed to `UnifType` and `UnifRecordRows` which can contain types and thus contracts, with
-// all the subtleties associated with contract equality checking (see `typecheck::eq` module), we can
-// convert enum rows directly to unifiable enum rows without additional data: instead of
-// implementing a function `from_enum_rows`, we rather implement the more natural trait
-// `From<EnumRows>`.
-impl From<EnumRows> for UnifEnumRows {
-    fn from(erows: EnumRows) -> Self {
-        UnifEnumRows::Concrete(erows.0.map(|erows| Box::new(UnifEnumRows::from(*erows))))
+impl<E: TermEnvironment + Clone> GenericUnifEnumRows<E> {
+    /// Create `GenericUnifEnumRows` from `EnumRows`. As for record rows, the types of the arguments
+    /// may contain contracts, which are represented with an additional term environment (see
+    /// [`GenericUnifRecordRows::from_record_rows`]).
+    pub fn from_enum_rows(erows: EnumRows, env: &E) -> Self {
+        Self::from_enum_rows_(erows, env, &mut Vec::new())
+    }
+
+    /// Same as [`Self::from_enum_rows`], but keep track of the schemas being converted (see
+    /// [`GenericUnifType::from_type`]).
+    fn from_enum_rows_(erows: EnumRows, env: &E, schemas: &mut Vec<Ident>) -> Self {
+        GenericUnifEnumRows::Concrete(erows.0.map_state(
+            |ty, schemas| Box::new(GenericUnifType::from_type_(*ty, env, schemas)),
+            |erows, schemas| Box::new(GenericUnifEnumRows::from_enum_rows_(*erows, env, schemas)),
+            schemas,
+        ))
     }
 }
 
-impl UnifEnumRows {
+impl<E: TermEnvironment> GenericUnifEnumRows<E> {
     /// Return an iterator producing immutable references to individual rows.
-    pub fn iter(&self) -> EnumRowsIterator<UnifEnumRows> {
-        EnumRowsIterator { erows: Some(self) }
+    pub fn iter(&self) -> EnumRowsIterator<GenericUnifType<E>, GenericUnifEnumRows<E>> {
+        EnumRowsIterator {
+            erows: Some(self),
+            ty: std::marker::PhantomData,
+        }
     }
 }
 
@@ -212,11 +232,21 @@ impl<E: TermEnvironment + Clone> GenericUnifRecordRows<E> {
     /// variant [`GenericUnifType::Contract`] which also stores a term environment, required for
     /// checking type equality involving contracts.
     pub fn from_record_rows(rrows: RecordRows, env: &E) -> Self {
-        let f_rrow = |ty: Box<Types>| Box::new(GenericUnifType::from_type(*ty, env));
-        let f_rrows =
-            |rrows: Box<RecordRows>| Box::new(GenericUnifRecordRows::from_record_rows(*rrows, env));
+        Self::from_record_rows_(rrows, env, &mut Vec::new())
+    }
 
-        GenericUnifRecordRows::Concrete(rrows.0.map(f_rrow, f_rrows))
+    /// Same as [`Self::from_record_rows`], but keep track of the schemas being converted (see
+    /// [`GenericUnifType::from_type`]).
+    fn from_record_rows_(rrows: RecordRows, env: &E, schemas: &mut Vec<Ident>) -> Self {
+        GenericUnifRecordRows::Concrete(rrows.0.map_state(
+            |ty, schemas| Box::new(GenericUnifType::from_type_(*ty, env, schemas)),
+            |rrows, schemas| {
+                Box::new(GenericUnifRecordRows::from_record_rows_(
+                    *rrows, env, schemas,
+                ))
+            },
+            schemas,
+        ))
     }
 }
 
@@ -237,8 +267,8 @@ trait SubstRRows<E: TermEnvironment> {
     fn subst_rrows(self, id: &Ident, to: &GenericUnifRecordRows<E>) -> Self;
 }
 
-trait SubstERows {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self;
+trait SubstERows<E: TermEnvironment> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self;
 }
 
 impl<E: TermEnvironment> SubstType<E> for GenericUnifType<E> {
@@ -248,7 +278,7 @@ impl<E: TermEnvironment> SubstType<E> for GenericUnifType<E> {
             GenericUnifType::Concrete(t) => GenericUnifType::Concrete(t.map(
                 |ty| Box::new(ty.subst_type(id, to)),
                 |rrows| rrows.subst_type(id, to),
-                |erows| erows,
+                |erows| erows.subst_type(id, to),
             )),
             _ => self,
         }
@@ -267,13 +297,25 @@ impl<E: TermEnvironment> SubstType<E> for GenericUnifRecordRows<E> {
     }
 }
 
+impl<E: TermEnvironment> SubstType<E> for GenericUnifEnumRows<E> {
+    fn subst_type(self, id: &Ident, to: &GenericUnifType<E>) -> Self {
+        match self {
+            GenericUnifEnumRows::Concrete(erows) => GenericUnifEnumRows::Concrete(erows.map(
+                |ty| Box::new(ty.subst_type(id, to)),
+                |erows| Box::new(erows.subst_type(id, to)),
+            )),
+            _ => self,
+        }
+    }
+}
+
 impl<E: TermEnvironment> SubstRRows<E> for GenericUnifType<E> {
     fn subst_rrows(self, id: &Ident, to: &GenericUnifRecordRows<E>) -> Self {
         match self {
             GenericUnifType::Concrete(t) => GenericUnifType::Concrete(t.map(
                 |ty| Box::new(ty.subst_rrows(id, to)),
                 |rrows| rrows.subst_rrows(id, to),
-                |erows| erows,
+                |erows| erows.subst_rrows(id, to),
             )),
             _ => self,
         }
@@ -295,8 +337,20 @@ impl<E: TermEnvironment> SubstRRows<E> for GenericUnifRecordRows<E> {
     }
 }
 
-impl<E: TermEnvironment> SubstERows for GenericUnifType<E> {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self {
+impl<E: TermEnvironment> SubstRRows<E> for GenericUnifEnumRows<E> {
+    fn subst_rrows(self, id: &Ident, to: &GenericUnifRecordRows<E>) -> Self {
+        match self {
+            GenericUnifEnumRows::Concrete(erows) => GenericUnifEnumRows::Concrete(erows.map(
+                |ty| Box::new(ty.subst_rrows(id, to)),
+                |erows| Box::new(erows.subst_rrows(id, to)),
+            )),
+            _ => self,
+        }
+    }
+}
+
+impl<E: TermEnvironment> SubstERows<E> for GenericUnifType<E> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self {
         match self {
             GenericUnifType::Concrete(t) => GenericUnifType::Concrete(t.map(
                 |ty| Box::new(ty.subst_erows(id, to)),
@@ -308,8 +362,8 @@ impl<E: TermEnvironment> SubstERows for GenericUnifType<E> {
     }
 }
 
-impl<E: TermEnvironment> SubstERows for GenericUnifRecordRows<E> {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self {
+impl<E: TermEnvironment> SubstERows<E> for GenericUnifRecordRows<E> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self {
         match self {
             GenericUnifRecordRows::Concrete(rrows) => GenericUnifRecordRows::Concrete(rrows.map(
                 |ty| Box::new(ty.subst_erows(id, to)),
@@ -320,13 +374,16 @@ impl<E: TermEnvironment> SubstERows for GenericUnifRecordRows<E> {
     }
 }
 
-impl SubstERows for UnifEnumRows {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self {
+impl<E: TermEnvironment> SubstERows<E> for GenericUnifEnumRows<E> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self {
         match self {
-            UnifEnumRows::Concrete(EnumRowsF::TailVar(var_id)) if var_id == *id => to.clone(),
-            UnifEnumRows::Concrete(rrows) => {
-                UnifEnumRows::Concrete(rrows.map(|erows| Box::new(erows.subst_erows(id, to))))
+            GenericUnifEnumRows::Concrete(EnumRowsF::TailVar(var_id)) if var_id == *id => {
+                to.clone()
             }
+            GenericUnifEnumRows::Concrete(erows) => GenericUnifEnumRows::Concrete(erows.map(
+                |ty| Box::new(ty.subst_erows(id, to)),
+                |erows| Box::new(erows.subst_erows(id, to)),
+            )),
             _ => self,
         }
     }
@@ -336,20 +393,110 @@ impl<E: TermEnvironment + Clone> GenericUnifType<E> {
     /// Create a [`GenericUnifType`] from a [`Types`]. Contracts are represented as the separate variant
     /// [`GenericUnifType::Contract`] which also stores a term environment, required for checking type
     /// equality involving contracts.
+    ///
+    /// As an exception, record contracts whose fields are all annotated with a type, such as
+    /// `{port : Num, host | Str}`, are converted to the corresponding record type, here `{port:
+    /// Num, host: Str}`, whether they are written inline or bound to a variable of the term
+    /// environment. We call such contracts schemas. An open schema (`{port : Num, ..}`) is
+    /// converted to a record type with a `Dyn` tail. See [`schema_to_type`].
     pub fn from_type(ty: Types, env: &E) -> Self {
+        Self::from_type_(ty, env, &mut Vec::new())
+    }
+
+    /// Same as [`Self::from_type`], but keep track of the variables bound to the schemas being
+    /// converted, in order to stop on recursive schemas.
+    fn from_type_(ty: Types, env: &E, schemas: &mut Vec<Ident>) -> Self {
         match ty.0 {
-            TypeF::Flat(t) => GenericUnifType::Contract(t, env.clone()),
-            ty => GenericUnifType::Concrete(ty.map(
-                |ty_| Box::new(GenericUnifType::from_type(*ty_, env)),
-                |rrows| GenericUnifRecordRows::from_record_rows(rrows, env),
-                UnifEnumRows::from,
+            TypeF::Flat(t) => schema_to_type(&t, env, schemas)
+                .unwrap_or_else(|| GenericUnifType::Contract(t, env.clone())),
+            ty => GenericUnifType::Concrete(ty.map_state(
+                |ty_, schemas| Box::new(GenericUnifType::from_type_(*ty_, env, schemas)),
+                |rrows, schemas| GenericUnifRecordRows::from_record_rows_(rrows, env, schemas),
+                |erows, schemas| GenericUnifEnumRows::from_enum_rows_(erows, env, schemas),
+                schemas,
             )),
         }
     }
 }
 
+/// Convert a contract to a record type if it is a schema, that is a record literal whose fields
+/// are all annotated with a type and have no definition, or a variable bound to such a record in
+/// the term environment. Return `None` otherwise.
+///
+/// A field is annotated with a type if it has a type annotation (`field : T`), or if it has
+/// exactly one contract annotation (`field | T`). Fields with a value, such as a default value,
+/// and optional fields are not supported, as the record type would then be too restrictive.
+///
+/// `schemas` is the list of the variables whose schema is currently being converted. A recursive
+/// schema isn't converted, but kept as a contract.
+fn schema_to_type<E: TermEnvironment + Clone>(
+    t: &RichTerm,
+    env: &E,
+    schemas: &mut Vec<Ident>,
+) -> Option<GenericUnifType<E>> {
+    let record = match t.as_ref() {
+        Term::Var(id) if !schemas.contains(id) => {
+            return env.get_then(id, |binding| {
+                let (t, env) = binding?;
+                schemas.push(*id);
+                let result = schema_to_type(t, env, schemas);
+                schemas.pop();
+                result
+            });
+        }
+        Term::Record(record) => record,
+        Term::RecRecord(record, dyn_fields, _) if dyn_fields.is_empty() => record,
+        _ => return None,
+    };
+
+    let tail = if record.attrs.open {
+        RecordRowsF::TailDyn
+    } else {
+        RecordRowsF::Empty
+    };
+
+    let mut fields: Vec<_> = record.fields.iter().collect();
+    fields.sort_by_key(|(id, _)| id.label());
+
+    fields
+        .into_iter()
+        .rev()
+        .try_fold(
+            GenericUnifRecordRows::Concrete(tail),
+            |tail, (id, field)| {
+                let ty = match field.as_ref() {
+                    Term::MetaValue(MetaValue {
+                        types: Some(Contract { types, .. }),
+                        value: None,
+                        opt: false,
+                        ..
+                    }) => types,
+                    Term::MetaValue(MetaValue {
+                        types: None,
+                        contracts,
+                        value: None,
+                        opt: false,
+                        ..
+                    }) if contracts.len() == 1 => &contracts[0].types,
+                    _ => return None,
+                };
+
+                Some(GenericUnifRecordRows::Concrete(RecordRowsF::Extend {
+                    row: RecordRowF {
+                        id: *id,
+                        types: Box::new(GenericUnifType::from_type_(ty.clone(), env, schemas)),
+                    },
+                    tail: Box::new(tail),
+                }))
+            },
+        )
+        .map(|rrows| GenericUnifType::Concrete(TypeF::Record(rrows)))
+}
+
 pub type UnifRecordRow = GenericUnifRecordRow<SimpleTermEnvironment>;
 pub type UnifRecordRows = GenericUnifRecordRows<SimpleTermEnvironment>;
+pub type UnifEnumRow = GenericUnifEnumRow<SimpleTermEnvironment>;
+pub type UnifEnumRows = GenericUnifEnumRows<SimpleTermEnvironment>;
 pub type UnifType = GenericUnifType<SimpleTermEnvironment>;
 
 impl UnifRecordRows {
@@ -393,7 +540,10 @@ impl UnifEnumRows {
             },
             UnifEnumRows::Constant(_) => EnumRows(EnumRowsF::Empty),
             UnifEnumRows::Concrete(t) => {
-                let mapped = t.map(|erows| Box::new(erows.into_erows(table)));
+                let mapped = t.map(
+                    |ty| Box::new(ty.into_type(table)),
+                    |erows| Box::new(erows.into_erows(table)),
+                );
                 EnumRows(mapped)
             }
         }
@@ -453,6 +603,23 @@ impl UnifType {
             uty => uty,
         }
     }
+
+    /// Return the identifier of this type if it is a unification variable, or `None` otherwise.
+    fn as_unif_var(&self) -> Option<VarId> {
+        match self {
+            UnifType::UnifVar(var_id) => Some(*var_id),
+            _ => None,
+        }
+    }
+}
+
+/// Return the unification variable `var` if there is one, or its root `uty` otherwise.
+///
+/// Unification errors hold the original unification variables instead of their roots when
+/// possible, so that the origin of the type of these variables can be reported (see
+/// [`UnifTable::type_origin`]).
+fn or_unif_var(var: Option<VarId>, uty: UnifType) -> UnifType {
+    var.map_or(uty, UnifType::UnifVar)
 }
 
 // This implementation assumes that `TypeF::Flat` is not possible. If a [`UnifType`] has been
@@ -470,9 +637,9 @@ impl From<RecordRowsF<Box<UnifType>, Box<UnifRecordRows>>> for UnifRecordRows {
     }
 }
 
-impl From<EnumRowsF<Box<UnifEnumRows>>> for UnifEnumRows {
-    fn from(ty: EnumRowsF<Box<UnifEnumRows>>) -> Self {
-        UnifEnumRows::Concrete(ty)
+impl From<EnumRowsF<Box<UnifType>, Box<UnifEnumRows>>> for UnifEnumRows {
+    fn from(erows: EnumRowsF<Box<UnifType>, Box<UnifEnumRows>>) -> Self {
+        UnifEnumRows::Concrete(erows)
     }
 }
 
@@ -528,40 +695,47 @@ impl<'a, E: TermEnvironment> Iterator
     }
 }
 
-/// Iterator items produced by [`EnumRowsIterator`].
-pub enum UnifEnumRowsIteratorItem<'a> {
+/// Iterator items produced by [`EnumRowsIterator`] on [GenericUnifEnumRows].
+pub enum GenericUnifEnumRowsIteratorItem<'a, E: TermEnvironment> {
     TailVar(&'a Ident),
     TailUnifVar(VarId),
     TailConstant(VarId),
-    Row(&'a EnumRow),
+    Row(EnumRowF<&'a GenericUnifType<E>>),
 }
 
-impl<'a> Iterator for EnumRowsIterator<'a, UnifEnumRows> {
-    type Item = UnifEnumRowsIteratorItem<'a>;
+pub type UnifEnumRowsIteratorItem<'a> = GenericUnifEnumRowsIteratorItem<'a, SimpleTermEnvironment>;
+
+impl<'a, E: TermEnvironment> Iterator
+    for EnumRowsIterator<'a, GenericUnifType<E>, GenericUnifEnumRows<E>>
+{
+    type Item = GenericUnifEnumRowsIteratorItem<'a, E>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.erows.and_then(|next| match next {
-            UnifEnumRows::Concrete(erows) => match erows {
+            GenericUnifEnumRows::Concrete(erows) => match erows {
                 EnumRowsF::Empty => {
                     self.erows = None;
                     None
                 }
                 EnumRowsF::TailVar(id) => {
                     self.erows = None;
-                    Some(UnifEnumRowsIteratorItem::TailVar(id))
+                    Some(GenericUnifEnumRowsIteratorItem::TailVar(id))
                 }
                 EnumRowsF::Extend { row, tail } => {
                     self.erows = Some(tail);
-                    Some(UnifEnumRowsIteratorItem::Row(row))
+                    Some(GenericUnifEnumRowsIteratorItem::Row(EnumRowF {
+                        id: row.id,
+                        types: row.types.as_deref(),
+                    }))
                 }
             },
-            UnifEnumRows::UnifVar(var_id) => {
+            GenericUnifEnumRows::UnifVar(var_id) => {
                 self.erows = None;
-                Some(UnifEnumRowsIteratorItem::TailUnifVar(*var_id))
+                Some(GenericUnifEnumRowsIteratorItem::TailUnifVar(*var_id))
             }
-            UnifEnumRows::Constant(var_id) => {
+            GenericUnifEnumRows::Constant(var_id) => {
                 self.erows = None;
-                Some(UnifEnumRowsIteratorItem::TailConstant(*var_id))
+                Some(GenericUnifEnumRowsIteratorItem::TailConstant(*var_id))
             }
         })
     }
@@ -676,7 +850,7 @@ pub struct State<'a> {
     /// variable which introduced it, if any.
     ///
     /// Used for error reporting.
-    names: &'a mut HashMap<VarId, Ident>,
+    names: &'a mut NameTable,
     /// A mapping from wildcard ID to unification variable.
     wildcard_vars: &'a mut Vec<UnifType>,
 }
@@ -685,7 +859,7 @@ pub struct State<'a> {
 /// It is basically an owned-subset of the typecheking state.
 pub struct Extra {
     pub table: UnifTable,
-    pub names: HashMap<VarId, Ident>,
+    pub names: NameTable,
     pub wildcards: Vec<Types>,
 }
 
@@ -760,6 +934,41 @@ where
     Ok((result, lin))
 }
 
+/// Infer the type of a term in strict mode, as if it was in a statically typed block, and
+/// generalize it. Return an error if the term isn't well-typed in strict mode.
+///
+/// This is used by the REPL to show the most general type of an un-annotated expression.
+pub fn infer_type(
+    t: &RichTerm,
+    initial_ctxt: Context,
+    resolver: &impl ImportResolver,
+) -> Result<Types, TypecheckError> {
+    let (mut table, mut names) = (UnifTable::new(), HashMap::new());
+    let mut wildcard_vars = Vec::new();
+
+    let mut state: State = State {
+        resolver,
+        table: &mut table,
+        constr: &mut RowConstr::new(),
+        names: &mut names,
+        wildcard_vars: &mut wildcard_vars,
+    };
+
+    state.table.enter_level();
+    let ty = state.table.fresh_type_uvar();
+    type_check_(
+        &mut state,
+        initial_ctxt,
+        &mut Linearization::new(()),
+        StubHost::<(), (), Extra>::new(),
+        t,
+        ty.clone(),
+    )?;
+    state.table.leave_level();
+
+    Ok(generalize::generalize(state.table, ty).into_type(state.table))
+}
+
 /// Walk the AST of a term looking for statically typed block to check. Fill the linearization
 /// alongside and store the apparent type of variable inside the typing environment.
 fn walk<L: Linearizer>(
@@ -785,6 +994,8 @@ fn walk<L: Linearizer>(
         | Term::Null
         | Term::Bool(_)
         | Term::Num(_)
+        | Term::DateTime(_)
+        | Term::Duration(_)
         | Term::Str(_)
         | Term::Lbl(_)
         | Term::Enum(_)
@@ -852,6 +1063,34 @@ fn walk<L: Linearizer>(
 
             walk(state, ctxt, lin, linearizer, rt)
         }
+        Term::LetBlock(bindings, rt, attrs) => {
+            let tys_let: Vec<UnifType> = bindings
+                .iter()
+                .map(|(_, re)| binding_type(state, re.as_ref(), &ctxt, false))
+                .collect();
+
+            // The bindings of a non-recursive block only see the enclosing context.
+            let mut bindings_ctxt = ctxt.clone();
+            let term_env = ctxt.term_env.clone();
+
+            // See the `Let` case for why recursive bindings aren't taken into account for
+            // contract equality.
+            for ((x, re), ty_let) in bindings.iter().zip(tys_let.iter()) {
+                ctxt.term_env.0.insert(*x, (re.clone(), term_env.clone()));
+                ctxt.type_env.insert(*x, ty_let.clone());
+                linearizer.retype_ident(lin, x, ty_let.clone());
+            }
+
+            if attrs.rec {
+                bindings_ctxt = ctxt.clone();
+            }
+
+            for (_, re) in bindings.iter() {
+                walk(state, bindings_ctxt.clone(), lin, linearizer.scope(), re)?;
+            }
+
+            walk(state, ctxt, lin, linearizer, rt)
+        }
         Term::LetPattern(x, pat, re, rt) => {
             let ty_let = binding_type(state, re.as_ref(), &ctxt, false);
             walk(state, ctxt.clone(), lin, linearizer.scope(), re)?;
@@ -869,11 +1108,25 @@ fn walk<L: Linearizer>(
             walk(state, ctxt.clone(), lin, linearizer.scope(), e)?;
             walk(state, ctxt, lin, linearizer, t)
         }
+        Term::EnumVariant { arg, .. } => walk(state, ctxt, lin, linearizer, arg),
         Term::Match {cases, default} => {
             cases.values().chain(default.iter()).try_for_each(|case| {
                 walk(state, ctxt.clone(), lin, linearizer.scope(), case)
             })
         }
+        Term::MatchPattern(branches) => {
+            branches.iter().try_for_each(|branch| {
+                let mut ctxt = ctxt.clone();
+                for id in branch.pattern.bound_vars() {
+                    ctxt.type_env.insert(id, mk_uniftype::dynamic());
+                }
+
+                if let Some(guard) = &branch.guard {
+                    walk(state, ctxt.clone(), lin, linearizer.scope(), guard)?;
+                }
+                walk(state, ctxt, lin, linearizer.scope(), &branch.body)
+            })
+        }
         Term::RecRecord(record, dynamic, ..) => {
             for (id, field) in record.fields.iter() {
                 let binding_type = binding_type(
@@ -958,6 +1211,8 @@ fn walk_type<L: Linearizer>(
     match &ty.0 {
        TypeF::Dyn
        | TypeF::Num
+       | TypeF::DateTime
+       | TypeF::Duration
        | TypeF::Bool
        | TypeF::Str
        | TypeF::Sym
@@ -1003,21 +1258,346 @@ fn walk_rrows<L: Linearizer>(
 // TODO: The insertion of values in the type environment is done but everything is
 // typed as `Dyn`.
 fn inject_pat_vars(pat: &Destruct, env: &mut Environment) {
-    if let Destruct::Record { matches, rest, .. } = pat {
-        if let Some(id) = rest {
-            env.insert(*id, UnifType::Concrete(TypeF::Dyn));
-        }
-        matches.iter().for_each(|m| match m {
-            Match::Simple(id, ..) => env.insert(*id, UnifType::Concrete(TypeF::Dyn)),
-            Match::Assign(id, _, (bind_id, pat)) => {
-                let id = bind_id.as_ref().unwrap_or(id);
+    match pat {
+        Destruct::Record { matches, rest, .. } => {
+            if let Some(id) = rest {
                 env.insert(*id, UnifType::Concrete(TypeF::Dyn));
-                if !pat.is_empty() {
-                    inject_pat_vars(pat, env);
+            }
+            matches.iter().for_each(|m| match m {
+                Match::Simple(id, ..) => env.insert(*id, UnifType::Concrete(TypeF::Dyn)),
+                Match::Assign(id, _, (bind_id, pat)) => {
+                    let id = bind_id.as_ref().unwrap_or(id);
+                    env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+                    if !pat.is_empty() {
+                        inject_pat_vars(pat, env);
+                    }
+                }
+            });
+        }
+        Destruct::Array { matches, rest, .. } => {
+            if let Some(id) = rest {
+                env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+            }
+            matches.iter().for_each(|(bind_id, pat)| {
+                if let Some(id) = bind_id {
+                    env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+                }
+                inject_pat_vars(pat, env);
+            });
+        }
+        Destruct::Empty => (),
+    }
+}
+
+/// The enum patterns of the branches of a match expression, which determine the type of the
+/// matched value.
+#[derive(Default)]
+struct EnumPatterns {
+    /// The tags matched by the patterns. A variant pattern also records the type of the argument
+    /// of the variant together with the patterns of this argument.
+    tags: Vec<(Ident, Option<(UnifType, EnumPatterns)>)>,
+    /// Set if some pattern can match any value.
+    catch_all: bool,
+}
+
+impl EnumPatterns {
+    /// Return the type and the patterns of the argument of a variant pattern with the given tag,
+    /// if any.
+    fn variant(&self, tag: &Ident) -> Option<&(UnifType, EnumPatterns)> {
+        self.tags
+            .iter()
+            .find_map(|(id, payload)| payload.as_ref().filter(|_| id == tag))
+    }
+}
+
+/// Check a field access with a default value `record.id_1. ... .id_n or default`, where the record
+/// has the type `record_type` and `res` is the type of the default value, which is also the type
+/// of the whole access.
+///
+/// Each step of the path is typed according to the type of the current value, as far as it is
+/// known at this point: a dictionary gives the type of its elements, and a record gives the type
+/// of the field. If the current value is a closed record without the field, the access always
+/// falls back to the default value, and the rest of the path is ignored. Otherwise, the current
+/// value must be a record with the field.
+fn check_static_access_or(
+    state: &mut State,
+    ctxt: &Context,
+    record_type: UnifType,
+    path: &[Ident],
+    res: &UnifType,
+) -> Result<(), UnifError> {
+    let mut current = record_type;
+
+    for id in path {
+        let field_type = match current.clone().into_root(state.table) {
+            UnifType::Concrete(TypeF::Dict(elt_type)) => Some(*elt_type),
+            UnifType::Concrete(TypeF::Record(rrows)) => {
+                let mut rrows = rrows;
+
+                loop {
+                    match rrows.into_root(state.table) {
+                        UnifRecordRows::Concrete(RecordRowsF::Extend { row, tail }) => {
+                            if row.id == *id {
+                                break Some(*row.types);
+                            }
+
+                            rrows = *tail;
+                        }
+                        UnifRecordRows::Concrete(RecordRowsF::Empty) => return Ok(()),
+                        _ => break None,
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        current = match field_type {
+            Some(ty) => ty,
+            None => {
+                let field_type = state.table.fresh_type_uvar();
+                let rows = state.table.fresh_rrows_uvar();
+                unify(
+                    state,
+                    ctxt,
+                    current,
+                    mk_uty_record!((*id, field_type.clone()); rows),
+                )?;
+                field_type
+            }
+        };
+    }
+
+    unify(state, ctxt, current, res.clone())
+}
+
+/// Unify the type of the argument of a match expression with the type of the constants and of
+/// the destructuring patterns of a pattern. The enum tags of the pattern are collected in
+/// `enum_pats` instead, to be processed by [unify_enum_patterns] once all the branches have been
+/// checked.
+///
+/// The variables bound by the pattern are pushed to `bindings` together with their type: a
+/// variable bound to the whole matched value has the type `arg_type` of the argument, and the
+/// variables bound inside a destructuring pattern have fresh unification variables as types (see
+/// [destruct_pattern_type]).
+fn check_branch_pattern(
+    state: &mut State,
+    ctxt: &Context,
+    pat: &BranchPattern,
+    arg_type: &UnifType,
+    enum_pats: &mut EnumPatterns,
+    bindings: &mut Vec<(Ident, UnifType)>,
+) -> Result<(), UnifError> {
+    match pat {
+        BranchPattern::Wildcard => {
+            enum_pats.catch_all = true;
+            Ok(())
+        }
+        BranchPattern::Destruct(id, Destruct::Empty) => {
+            enum_pats.catch_all = true;
+            bindings.extend(id.map(|id| (id, arg_type.clone())));
+            Ok(())
+        }
+        BranchPattern::Enum(id) => {
+            enum_pats.tags.push((*id, None));
+            Ok(())
+        }
+        BranchPattern::Variant(id, arg) => {
+            if enum_pats.variant(id).is_none() {
+                let payload = (state.table.fresh_type_uvar(), EnumPatterns::default());
+                enum_pats.tags.push((*id, Some(payload)));
+            }
+
+            // unwrap(): we just made sure that there is a variant with this tag
+            let (arg_ty, sub_pats) = enum_pats
+                .tags
+                .iter_mut()
+                .find_map(|(tag, payload)| payload.as_mut().filter(|_| tag == id))
+                .unwrap();
+
+            check_branch_pattern(state, ctxt, arg, arg_ty, sub_pats, bindings)
+        }
+        BranchPattern::Constant(t) => {
+            let const_type = match t.as_ref() {
+                Term::Num(_) => mk_uniftype::num(),
+                Term::Str(_) => mk_uniftype::str(),
+                Term::Bool(_) => mk_uniftype::bool(),
+                _ => mk_uniftype::dynamic(),
+            };
+
+            unify(state, ctxt, arg_type.clone(), const_type)
+        }
+        BranchPattern::Destruct(id, pat) => {
+            bindings.extend(id.map(|id| (id, arg_type.clone())));
+            let pat_type = destruct_pattern_type(state, ctxt, pat, bindings)?;
+            unify(state, ctxt, arg_type.clone(), pat_type)
+        }
+        BranchPattern::Or(alts) => {
+            // All the alternatives bind the same variables, which must have the same types.
+            let mut alt_bindings: Option<Vec<(Ident, UnifType)>> = None;
+
+            for alt in alts {
+                let mut current = Vec::new();
+                check_branch_pattern(state, ctxt, alt, arg_type, enum_pats, &mut current)?;
+
+                match &alt_bindings {
+                    None => alt_bindings = Some(current),
+                    Some(first) => {
+                        for (id, ty) in current {
+                            if let Some((_, first_ty)) = first.iter().find(|(x, _)| *x == id) {
+                                unify(state, ctxt, first_ty.clone(), ty)?;
+                            }
+                        }
+                    }
                 }
             }
+
+            bindings.extend(alt_bindings.unwrap_or_default());
+            Ok(())
+        }
+    }
+}
+
+/// Return the type of the values matched by a destructuring pattern of a match branch, and push
+/// the variables bound by the pattern to `bindings`, with fresh unification variables as types.
+///
+/// A record pattern implies a record type with the fields of the pattern, which is open if the
+/// pattern is open. The fields with a default value may be missing: they don't appear in the
+/// record type, which is then open too. An array pattern implies an array type, whose elements
+/// have the type of all the nested patterns.
+fn destruct_pattern_type(
+    state: &mut State,
+    ctxt: &Context,
+    pat: &Destruct,
+    bindings: &mut Vec<(Ident, UnifType)>,
+) -> Result<UnifType, UnifError> {
+    match pat {
+        Destruct::Record {
+            matches,
+            open,
+            rest,
+            ..
+        } => {
+            let mut rows = Vec::new();
+            let mut has_default = false;
+
+            for m in matches {
+                let (id, meta, ty) = match m {
+                    Match::Simple(id, meta) => {
+                        let ty = state.table.fresh_type_uvar();
+                        bindings.push((*id, ty.clone()));
+                        (id, meta, ty)
+                    }
+                    Match::Assign(id, meta, (bind_id, pat)) => {
+                        let ty = destruct_pattern_type(state, ctxt, pat, bindings)?;
+                        if let Some(bind_id) = bind_id {
+                            bindings.push((*bind_id, ty.clone()));
+                        }
+                        (id, meta, ty)
+                    }
+                };
+
+                if meta.value.is_some() {
+                    has_default = true;
+                } else {
+                    rows.push((*id, ty));
+                }
+            }
+
+            let tail = if *open || has_default {
+                state.table.fresh_rrows_uvar()
+            } else {
+                RecordRowsF::Empty.into()
+            };
+
+            if let Some(rest) = rest {
+                bindings.push((*rest, UnifType::Concrete(TypeF::Record(tail.clone()))));
+            }
+
+            let rrows = rows.into_iter().rev().fold(tail, |tail, (id, ty)| {
+                UnifRecordRows::Concrete(RecordRowsF::Extend {
+                    row: RecordRowF {
+                        id,
+                        types: Box::new(ty),
+                    },
+                    tail: Box::new(tail),
+                })
+            });
+
+            Ok(UnifType::Concrete(TypeF::Record(rrows)))
+        }
+        Destruct::Array { matches, rest, .. } => {
+            let elt_type = state.table.fresh_type_uvar();
+
+            for (bind_id, pat) in matches {
+                let ty = destruct_pattern_type(state, ctxt, pat, bindings)?;
+                unify(state, ctxt, elt_type.clone(), ty)?;
+
+                if let Some(bind_id) = bind_id {
+                    bindings.push((*bind_id, elt_type.clone()));
+                }
+            }
+
+            let array_type = mk_uniftype::array(elt_type);
+
+            if let Some(rest) = rest {
+                bindings.push((*rest, array_type.clone()));
+            }
+
+            Ok(array_type)
+        }
+        Destruct::Empty => Ok(state.table.fresh_type_uvar()),
+    }
+}
+
+/// Unify the type of the argument of a match expression with the enum type determined by the enum
+/// patterns of its branches, if any: the enum type of the matched tags, which is open if some
+/// pattern matches any value. The argument of a variant is typed recursively from the patterns of
+/// this argument. When a tag is matched both as a bare tag and as a variant, the variant takes
+/// precedence.
+fn unify_enum_patterns(
+    state: &mut State,
+    ctxt: &Context,
+    arg_type: UnifType,
+    enum_pats: EnumPatterns,
+) -> Result<(), UnifError> {
+    let EnumPatterns {
+        mut tags,
+        catch_all,
+    } = enum_pats;
+
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    tags.sort_by(|(id1, payload1), (id2, payload2)| {
+        id1.label()
+            .cmp(id2.label())
+            .then(payload2.is_some().cmp(&payload1.is_some()))
+    });
+    tags.dedup_by(|(id1, _), (id2, _)| id1 == id2);
+
+    let mut erows = if catch_all {
+        state.table.fresh_erows_uvar()
+    } else {
+        EnumRowsF::Empty.into()
+    };
+
+    for (id, payload) in tags.into_iter().rev() {
+        let types = match payload {
+            Some((ty, sub_pats)) => {
+                unify_enum_patterns(state, ctxt, ty.clone(), sub_pats)?;
+                Some(Box::new(ty))
+            }
+            None => None,
+        };
+
+        erows = UnifEnumRows::Concrete(EnumRowsF::Extend {
+            row: EnumRowF { id, types },
+            tail: Box::new(erows),
         });
     }
+
+    unify(state, ctxt, arg_type, mk_uty_enum!(; erows))
 }
 
 /// Typecheck a term against a specific type.
@@ -1034,6 +1614,25 @@ fn inject_pat_vars(pat: &Destruct, env: &mut Environment) {
 /// Registers every term with the `linearizer` and makes sure to scope the
 /// liearizer accordingly
 fn type_check_<L: Linearizer>(
+    state: &mut State,
+    ctxt: Context,
+    lin: &mut Linearization<L::Building>,
+    linearizer: L,
+    rt: &RichTerm,
+    ty: UnifType,
+) -> Result<(), TypecheckError> {
+    // The unification variables assigned while checking this term are attributed to it, to be
+    // able to explain errors (see [`UnifTable::type_origin`]).
+    let prev_pos = state.table.enter_term(rt.pos);
+    let result = type_check_term(state, ctxt, lin, linearizer, rt, ty);
+    state.table.leave_term(prev_pos);
+
+    result
+}
+
+/// Typecheck a term against a specific type. This does the actual work of [`type_check_`], which
+/// should be called instead.
+fn type_check_term<L: Linearizer>(
     state: &mut State,
     mut ctxt: Context,
     lin: &mut Linearization<L::Building>,
@@ -1053,6 +1652,10 @@ fn type_check_<L: Linearizer>(
             .map_err(|err| err.into_typecheck_err(state, rt.pos)),
         Term::Num(_) => unify(state, &ctxt, ty, mk_uniftype::num())
             .map_err(|err| err.into_typecheck_err(state, rt.pos)),
+        Term::DateTime(_) => unify(state, &ctxt, ty, mk_uniftype::datetime())
+            .map_err(|err| err.into_typecheck_err(state, rt.pos)),
+        Term::Duration(_) => unify(state, &ctxt, ty, mk_uniftype::duration())
+            .map_err(|err| err.into_typecheck_err(state, rt.pos)),
         Term::Str(_) => unify(state, &ctxt, ty, mk_uniftype::str())
             .map_err(|err| err.into_typecheck_err(state, rt.pos)),
         Term::StrChunks(chunks) => {
@@ -1127,6 +1730,12 @@ fn type_check_<L: Linearizer>(
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
         Term::Let(x, re, rt, attrs) => {
+            let generalize = is_generalizable_binding(state, re.as_ref(), &ctxt);
+
+            if generalize {
+                state.table.enter_level();
+            }
+
             let ty_let = binding_type(state, re.as_ref(), &ctxt, true);
 
             // We don't support recursive binding when checking for contract equality. See the
@@ -1149,9 +1758,108 @@ fn type_check_<L: Linearizer>(
                 ty_let.clone(),
             )?;
 
-            if !attrs.rec {
+            let ty_let = if generalize {
+                state.table.leave_level();
+                let ty_let = generalize::generalize(state.table, ty_let);
+                linearizer.retype_ident(lin, x, ty_let.clone());
+                ty_let
+            } else {
+                ty_let
+            };
+
+            ctxt.type_env.insert(*x, ty_let);
+            type_check_(state, ctxt, lin, linearizer, rt, ty)
+        }
+        Term::LetBlock(bindings, rt, attrs) if !attrs.rec => {
+            // The bindings of a non-recursive block only see the enclosing context. Each binding
+            // is generalized independently, as if the block was a sequence of `let`s.
+            let outer_ctxt = ctxt.clone();
+
+            for (x, re) in bindings {
+                let generalize = is_generalizable_binding(state, re.as_ref(), &outer_ctxt);
+
+                if generalize {
+                    state.table.enter_level();
+                }
+
+                let ty_let = binding_type(state, re.as_ref(), &outer_ctxt, true);
+
+                // We don't support recursive binding when checking for contract equality. See the
+                // `Let` case in `walk`.
+                ctxt.term_env
+                    .0
+                    .insert(*x, (re.clone(), outer_ctxt.term_env.clone()));
+
+                linearizer.retype_ident(lin, x, ty_let.clone());
+                type_check_(
+                    state,
+                    outer_ctxt.clone(),
+                    lin,
+                    linearizer.scope(),
+                    re,
+                    ty_let.clone(),
+                )?;
+
+                let ty_let = if generalize {
+                    state.table.leave_level();
+                    let ty_let = generalize::generalize(state.table, ty_let);
+                    linearizer.retype_ident(lin, x, ty_let.clone());
+                    ty_let
+                } else {
+                    ty_let
+                };
+
                 ctxt.type_env.insert(*x, ty_let);
             }
+
+            type_check_(state, ctxt, lin, linearizer, rt, ty)
+        }
+        Term::LetBlock(bindings, rt, _) => {
+            // The bindings of a recursive block may depend on each other, so they are generalized
+            // as a whole: either all the bindings are generalizable, or none is generalized.
+            let generalize = bindings
+                .iter()
+                .all(|(_, re)| is_generalizable_binding(state, re.as_ref(), &ctxt));
+
+            if generalize {
+                state.table.enter_level();
+            }
+
+            let tys_let: Vec<UnifType> = bindings
+                .iter()
+                .map(|(_, re)| binding_type(state, re.as_ref(), &ctxt, true))
+                .collect();
+
+            // We don't support recursive binding when checking for contract equality. See the
+            // `Let` case in `walk`.
+            let term_env = ctxt.term_env.clone();
+            for ((x, re), ty_let) in bindings.iter().zip(tys_let.iter()) {
+                ctxt.term_env.0.insert(*x, (re.clone(), term_env.clone()));
+                ctxt.type_env.insert(*x, ty_let.clone());
+                linearizer.retype_ident(lin, x, ty_let.clone());
+            }
+
+            for ((_, re), ty_let) in bindings.iter().zip(tys_let.iter()) {
+                type_check_(
+                    state,
+                    ctxt.clone(),
+                    lin,
+                    linearizer.scope(),
+                    re,
+                    ty_let.clone(),
+                )?;
+            }
+
+            if generalize {
+                state.table.leave_level();
+
+                for ((x, _), ty_let) in bindings.iter().zip(tys_let) {
+                    let ty_let = generalize::generalize(state.table, ty_let);
+                    linearizer.retype_ident(lin, x, ty_let.clone());
+                    ctxt.type_env.insert(*x, ty_let);
+                }
+            }
+
             type_check_(state, ctxt, lin, linearizer, rt, ty)
         }
         Term::LetPattern(x, pat, re, rt) => {
@@ -1227,6 +1935,59 @@ fn type_check_<L: Linearizer>(
             unify(state, &ctxt, ty, mk_uty_arrow!(arg_type, return_type))
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::MatchPattern(branches) => {
+            // As for `Term::Match`, a match expression is typed as `a -> b`. Constant patterns
+            // determine `a`, as well as enum tags: if all the patterns are enum tags, `a` is the
+            // enum type of these tags, or an open enum type if some pattern matches any value.
+            // Destructuring patterns constrain `a` to be the record or array type they imply.
+            let arg_type = state.table.fresh_type_uvar();
+            let return_type = state.table.fresh_type_uvar();
+            let mut enum_pats = EnumPatterns::default();
+
+            for branch in branches {
+                let mut bindings = Vec::new();
+                check_branch_pattern(
+                    state,
+                    &ctxt,
+                    &branch.pattern,
+                    &arg_type,
+                    &mut enum_pats,
+                    &mut bindings,
+                )
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+
+                let mut ctxt = ctxt.clone();
+                for (id, ty) in bindings {
+                    ctxt.type_env.insert(id, ty);
+                }
+
+                if let Some(guard) = &branch.guard {
+                    type_check_(
+                        state,
+                        ctxt.clone(),
+                        lin,
+                        linearizer.scope(),
+                        guard,
+                        mk_uniftype::bool(),
+                    )?;
+                }
+
+                type_check_(
+                    state,
+                    ctxt,
+                    lin,
+                    linearizer.scope(),
+                    &branch.body,
+                    return_type.clone(),
+                )?;
+            }
+
+            unify_enum_patterns(state, &ctxt, arg_type.clone(), enum_pats)
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+
+            unify(state, &ctxt, ty, mk_uty_arrow!(arg_type, return_type))
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))
+        }
         Term::Var(x) => {
             let x_ty = ctxt
                 .type_env
@@ -1235,7 +1996,7 @@ fn type_check_<L: Linearizer>(
                 .ok_or(TypecheckError::UnboundIdentifier(*x, *pos))?;
 
             let instantiated = instantiate_foralls(state, x_ty, ForallInst::Ptr);
-            unify(state, &ctxt, ty, instantiated)
+            subsumption(state, &ctxt, instantiated, ty)
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
         Term::Enum(id) => {
@@ -1243,6 +2004,20 @@ fn type_check_<L: Linearizer>(
             unify(state, &ctxt, ty, mk_uty_enum!(*id; row))
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::EnumVariant { tag, arg } => {
+            let ty_arg = state.table.fresh_type_uvar();
+            let row = UnifEnumRows::Concrete(EnumRowsF::Extend {
+                row: EnumRowF {
+                    id: *tag,
+                    types: Some(Box::new(ty_arg.clone())),
+                },
+                tail: Box::new(state.table.fresh_erows_uvar()),
+            });
+
+            unify(state, &ctxt, ty, mk_uty_enum!(; row))
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+            type_check_(state, ctxt, lin, linearizer, arg, ty_arg)
+        }
         // If some fields are defined dynamically, the only potential type that works is `{_ : a}`
         // for some `a`
         Term::RecRecord(record, dynamic, ..) if !dynamic.is_empty() => {
@@ -1331,6 +2106,26 @@ fn type_check_<L: Linearizer>(
                     .map_err(|err| err.into_typecheck_err(state, rt.pos))
             }
         }
+        Term::Op1(UnaryOp::StaticAccessOr(path), t) => {
+            // The type of the access depends on the type of the record, which is thus checked
+            // first. See [check_static_access_or].
+            let ty_record = state.table.fresh_type_uvar();
+            type_check_(
+                state,
+                ctxt.clone(),
+                lin,
+                linearizer.scope(),
+                t,
+                ty_record.clone(),
+            )?;
+
+            let res = state.table.fresh_type_uvar();
+            check_static_access_or(state, &ctxt, ty_record, path, &res)
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+
+            unify(state, &ctxt, ty, mk_uty_arrow!(res.clone(), res))
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))
+        }
         Term::Op1(op, t) => {
             let (ty_arg, ty_res) = get_uop_type(state, op)?;
 
@@ -1470,10 +2265,24 @@ fn binding_type(state: &mut State, t: &Term, ctxt: &Context, strict: bool) -> Un
             replace_wildcards_with_var(state.table, state.wildcard_vars, ty, &ctxt.term_env)
         }
         ApparentType::Approximated(_) if strict => state.table.fresh_type_uvar(),
+        // The type of a variable may have been generalized. It is instantiated, as for any other
+        // use of the variable, and generalized again if needed (see [is_generalizable_binding]).
+        ApparentType::FromEnv(uty) if strict => instantiate_foralls(state, uty, ForallInst::Ptr),
         ty_apt => UnifType::from_apparent_type(ty_apt, &ctxt.term_env),
     }
 }
 
+/// Determine if the type of a let-bound expression is to be generalized in strict mode, that is if
+/// the expression is a syntactic value and its type isn't annotated. See the [generalize] module.
+/// A variable is a value too: binding a variable with a polymorphic type keeps it polymorphic.
+fn is_generalizable_binding(state: &State, t: &Term, ctxt: &Context) -> bool {
+    match apparent_type(t, Some(&ctxt.type_env), Some(state.resolver)) {
+        ApparentType::Approximated(_) => generalize::is_generalizable(t),
+        ApparentType::FromEnv(_) => true,
+        _ => false,
+    }
+}
+
 /// Substitute wildcards in a type for their unification variable.
 fn replace_wildcards_with_var(
     table: &mut UnifTable,
@@ -1498,16 +2307,32 @@ fn replace_wildcards_with_var(
         ))
     }
 
+    fn replace_erows(
+        table: &mut UnifTable,
+        wildcard_vars: &mut Vec<UnifType>,
+        erows: EnumRows,
+        env: &SimpleTermEnvironment,
+    ) -> UnifEnumRows {
+        UnifEnumRows::Concrete(erows.0.map_state(
+            |ty, (table, wildcard_vars)| {
+                Box::new(replace_wildcards_with_var(table, wildcard_vars, *ty, env))
+            },
+            |erows, (table, wildcard_vars)| {
+                Box::new(replace_erows(table, wildcard_vars, *erows, env))
+            },
+            &mut (table, wildcard_vars),
+        ))
+    }
+
     match ty.0 {
         TypeF::Wildcard(i) => get_wildcard_var(table, wildcard_vars, i),
-        TypeF::Flat(t) => UnifType::Contract(t, env.clone()),
+        TypeF::Flat(t) => UnifType::from_type(Types(TypeF::Flat(t)), env),
         _ => UnifType::Concrete(ty.0.map_state(
             |ty, (table, wildcard_vars)| {
                 Box::new(replace_wildcards_with_var(table, wildcard_vars, *ty, env))
             },
             |rrows, (table, wildcard_vars)| replace_rrows(table, wildcard_vars, rrows, env),
-            // Enum rows contain neither wildcards nor contracts
-            |erows, _| UnifEnumRows::from(erows),
+            |erows, (table, wildcard_vars)| replace_erows(table, wildcard_vars, erows, env),
             &mut (table, wildcard_vars),
         )),
     }
@@ -1580,6 +2405,8 @@ pub fn apparent_type(
             apparent_type(v.as_ref(), env, resolver)
         }
         Term::Num(_) => ApparentType::Inferred(Types(TypeF::Num)),
+        Term::DateTime(_) => ApparentType::Inferred(Types(TypeF::DateTime)),
+        Term::Duration(_) => ApparentType::Inferred(Types(TypeF::Duration)),
         Term::Bool(_) => ApparentType::Inferred(Types(TypeF::Bool)),
         Term::SealingKey(_) => ApparentType::Inferred(Types(TypeF::Sym)),
         Term::Str(_) | Term::StrChunks(_) => ApparentType::Inferred(Types(TypeF::Str)),
@@ -1727,36 +2554,41 @@ fn rrows_add(
     }
 }
 
-/// Try to find a specific row (ident) inside enum rows, or add it if permitted.
+/// Try to find a specific row inside enum rows, or add it if permitted.
 ///
-/// If the row is present, this function returns the tail corresponding to the remaining rows coming after
-/// the found one.
+/// If the row is present, this function returns the type of its argument, if any, together with
+/// the tail corresponding to the remaining rows coming after the found one.
 ///
 /// If the row is not present:
 ///
 /// - If the given enum rows are extensible, i.e. they end with a free unification variable, this
-///   function adds a new row (if allowed by [row constraints][RowConstr]). Returns the new tail (a
-///   fresh unification variable).
+///   function adds a new row with the provided argument type `ty` (if allowed by [row
+///   constraints][RowConstr]). Returns `ty` together with the new tail (a fresh unification
+///   variable).
 /// - Otherwise, raise a missing row error.
 fn erows_add(
     state: &mut State,
     id: &Ident,
+    ty: Option<Box<UnifType>>,
     uerows: UnifEnumRows,
-) -> Result<UnifEnumRows, RowUnifError> {
+) -> Result<(Option<Box<UnifType>>, UnifEnumRows), RowUnifError> {
     let uerows = uerows.into_root(state.table);
 
     match uerows {
         UnifEnumRows::Concrete(erows) => match erows {
             EnumRowsF::Empty | EnumRowsF::TailVar(_) => Err(RowUnifError::MissingRow(*id)),
             EnumRowsF::Extend { row, tail } => {
-                if *id == row {
-                    Ok(*tail)
+                if *id == row.id {
+                    Ok((row.types, *tail))
                 } else {
-                    let subrow = erows_add(state, id, *tail)?;
-                    Ok(UnifEnumRows::Concrete(EnumRowsF::Extend {
-                        row,
-                        tail: Box::new(subrow),
-                    }))
+                    let (extracted_type, subrow) = erows_add(state, id, ty, *tail)?;
+                    Ok((
+                        extracted_type,
+                        UnifEnumRows::Concrete(EnumRowsF::Extend {
+                            row,
+                            tail: Box::new(subrow),
+                        }),
+                    ))
                 }
             }
         },
@@ -1771,7 +2603,10 @@ fn erows_add(
             }
             let tail_var_id = state.table.fresh_erows_var_id();
             let new_tail = UnifEnumRows::Concrete(EnumRowsF::Extend {
-                row: *id,
+                row: EnumRowF {
+                    id: *id,
+                    types: ty.clone(),
+                },
                 tail: Box::new(UnifEnumRows::UnifVar(tail_var_id)),
             });
 
@@ -1779,12 +2614,77 @@ fn erows_add(
 
             state.table.assign_erows(uvar, new_tail);
 
-            Ok(UnifEnumRows::UnifVar(tail_var_id))
+            Ok((ty, UnifEnumRows::UnifVar(tail_var_id)))
         }
         UnifEnumRows::Constant(_) => Err(RowUnifError::MissingRow(*id)),
     }
 }
 
+/// Check that an inferred type can be used where another type is expected.
+///
+/// This is the same as unifying `checked` with `inferred`, except for one subsumption rule: a
+/// record type can be used where a dictionary type is expected, as long as it is closed (or its
+/// tail is a free unification variable, which is then closed) and the type of each field can
+/// itself be used where the type of the dictionary elements is expected. That is, `{a: Num, b:
+/// Num}` can be used as a `{_: Num}`, but `{a: Num, b: Str}` or `{a: Num ; Dyn}` can't.
+///
+/// Subsumption is only applied at the top-level of types (and recursively to the fields of the
+/// record), and not for example under an array or a function type. It is used when the type of a
+/// variable is checked against an expected type.
+pub fn subsumption(
+    state: &mut State,
+    ctxt: &Context,
+    inferred: UnifType,
+    checked: UnifType,
+) -> Result<(), UnifError> {
+    let (inferred_var, checked_var) = (inferred.as_unif_var(), checked.as_unif_var());
+    let inferred = inferred.into_root(state.table);
+    let checked = checked.into_root(state.table);
+
+    match (&inferred, &checked) {
+        (UnifType::Concrete(TypeF::Record(rrows)), UnifType::Concrete(TypeF::Dict(ty_elts))) => {
+            let mut rrows = rrows.clone().into_root(state.table);
+
+            loop {
+                match rrows {
+                    UnifRecordRows::Concrete(RecordRowsF::Empty) => return Ok(()),
+                    UnifRecordRows::Concrete(RecordRowsF::Extend { row, tail }) => {
+                        subsumption(state, ctxt, *row.types, (**ty_elts).clone()).map_err(
+                            |err| {
+                                UnifError::RowMismatch(
+                                    row.id,
+                                    or_unif_var(checked_var, checked.clone()),
+                                    or_unif_var(inferred_var, inferred.clone()),
+                                    Box::new(err),
+                                )
+                            },
+                        )?;
+                        rrows = tail.into_root(state.table);
+                    }
+                    UnifRecordRows::UnifVar(id) => {
+                        state
+                            .table
+                            .assign_rrows(id, UnifRecordRows::Concrete(RecordRowsF::Empty));
+                        return Ok(());
+                    }
+                    _ => {
+                        return Err(UnifError::TypeMismatch(
+                            or_unif_var(checked_var, checked),
+                            or_unif_var(inferred_var, inferred),
+                        ))
+                    }
+                }
+            }
+        }
+        _ => unify(
+            state,
+            ctxt,
+            or_unif_var(checked_var, checked),
+            or_unif_var(inferred_var, inferred),
+        ),
+    }
+}
+
 /// Try to unify two types.
 pub fn unify(
     state: &mut State,
@@ -1792,6 +2692,7 @@ pub fn unify(
     t1: UnifType,
     t2: UnifType,
 ) -> Result<(), UnifError> {
+    let (var1, var2) = (t1.as_unif_var(), t2.as_unif_var());
     let t1 = t1.into_root(state.table);
     let t2 = t2.into_root(state.table);
 
@@ -1806,6 +2707,8 @@ pub fn unify(
         (UnifType::Concrete(s1), UnifType::Concrete(s2)) => match (s1, s2) {
             (TypeF::Dyn, TypeF::Dyn)
             | (TypeF::Num, TypeF::Num)
+            | (TypeF::DateTime, TypeF::DateTime)
+            | (TypeF::Duration, TypeF::Duration)
             | (TypeF::Bool, TypeF::Bool)
             | (TypeF::Str, TypeF::Str)
             | (TypeF::Sym, TypeF::Sym) => Ok(()),
@@ -1828,13 +2731,16 @@ pub fn unify(
             }
             (TypeF::Flat(s), TypeF::Flat(t)) => Err(UnifError::IncomparableFlatTypes(s, t)),
             (TypeF::Enum(erows1), TypeF::Enum(erows2)) => {
-                unify_erows(state, erows1.clone(), erows2.clone()).map_err(|err| {
+                unify_erows(state, ctxt, erows1.clone(), erows2.clone()).map_err(|err| {
                     err.into_unif_err(mk_uty_enum!(; erows1), mk_uty_enum!(; erows2))
                 })
             }
             (TypeF::Record(rrows1), TypeF::Record(rrows2)) => {
                 unify_rrows(state, ctxt, rrows1.clone(), rrows2.clone()).map_err(|err| {
-                    err.into_unif_err(mk_uty_record!(; rrows1), mk_uty_record!(; rrows2))
+                    err.into_unif_err(
+                        or_unif_var(var1, mk_uty_record!(; rrows1)),
+                        or_unif_var(var2, mk_uty_record!(; rrows2)),
+                    )
                 })
             }
             (TypeF::Dict(t1), TypeF::Dict(t2)) => unify(state, ctxt, *t1, *t2),
@@ -1881,13 +2787,29 @@ pub fn unify(
                 Err(UnifError::UnboundTypeVariable(ident))
             }
             (ty1, ty2) => Err(UnifError::TypeMismatch(
-                UnifType::Concrete(ty1),
-                UnifType::Concrete(ty2),
+                or_unif_var(var1, UnifType::Concrete(ty1)),
+                or_unif_var(var2, UnifType::Concrete(ty2)),
             )),
         },
         (UnifType::UnifVar(p1), UnifType::UnifVar(p2)) if p1 == p2 => Ok(()),
-        (UnifType::UnifVar(p), uty) | (uty, UnifType::UnifVar(p)) => {
-            state.table.assign_type(p, uty);
+        // When unifying two variables, the second one becomes the root of the first one (see the
+        // next cases). If only the first one has been introduced by a type variable, we do the
+        // opposite instead, so that the name of this type variable is kept in error messages.
+        (UnifType::UnifVar(p1), UnifType::UnifVar(p2))
+            if state.names.contains_key(&(p1, VarKind::Type))
+                && !state.names.contains_key(&(p2, VarKind::Type)) =>
+        {
+            state.table.assign_type(p2, UnifType::UnifVar(p1));
+            Ok(())
+        }
+        // We assign the original variable of the other side, if any, instead of its root, to keep
+        // track of the origin of this root (see [`UnifTable::type_origin`]).
+        (UnifType::UnifVar(p), uty) => {
+            state.table.assign_type(p, or_unif_var(var2, uty));
+            Ok(())
+        }
+        (uty, UnifType::UnifVar(p)) => {
+            state.table.assign_type(p, or_unif_var(var1, uty));
             Ok(())
         }
         (UnifType::Constant(i1), UnifType::Constant(i2)) if i1 == i2 => Ok(()),
@@ -1900,9 +2822,9 @@ pub fn unify(
         {
             Ok(())
         }
-        (uty1 @ UnifType::Contract(..), uty2) | (uty1, uty2 @ UnifType::Contract(..)) => {
-            Err(UnifError::TypeMismatch(uty1, uty2))
-        }
+        (uty1 @ UnifType::Contract(..), uty2) | (uty1, uty2 @ UnifType::Contract(..)) => Err(
+            UnifError::TypeMismatch(or_unif_var(var1, uty1), or_unif_var(var2, uty2)),
+        ),
     }
 }
 
@@ -1991,6 +2913,7 @@ pub fn unify_rrows(
 /// Try to unify two enum row types.
 pub fn unify_erows(
     state: &mut State,
+    ctxt: &Context,
     uerows1: UnifEnumRows,
     uerows2: UnifEnumRows,
 ) -> Result<(), RowUnifError> {
@@ -2004,15 +2927,30 @@ pub fn unify_erows(
                     Err(RowUnifError::UnboundTypeVariable(id))
                 }
                 (EnumRowsF::Empty, EnumRowsF::Empty) => Ok(()),
-                (EnumRowsF::Empty, EnumRowsF::Extend { row: ident, .. }) => {
-                    Err(RowUnifError::ExtraRow(ident))
+                (EnumRowsF::Empty, EnumRowsF::Extend { row, .. }) => {
+                    Err(RowUnifError::ExtraRow(row.id))
                 }
-                (EnumRowsF::Extend { row: ident, .. }, EnumRowsF::Empty) => {
-                    Err(RowUnifError::MissingRow(ident))
+                (EnumRowsF::Extend { row, .. }, EnumRowsF::Empty) => {
+                    Err(RowUnifError::MissingRow(row.id))
                 }
-                (EnumRowsF::Extend { row: id, tail }, erows2 @ EnumRowsF::Extend { .. }) => {
-                    let t2_tail = erows_add(state, &id, UnifEnumRows::Concrete(erows2))?;
-                    unify_erows(state, *tail, t2_tail)
+                (
+                    EnumRowsF::Extend {
+                        row: EnumRowF { id, types },
+                        tail,
+                    },
+                    erows2 @ EnumRowsF::Extend { .. },
+                ) => {
+                    let (ty2, t2_tail) =
+                        erows_add(state, &id, types.clone(), UnifEnumRows::Concrete(erows2))?;
+
+                    match (types, ty2) {
+                        (None, None) => (),
+                        (Some(ty1), Some(ty2)) => unify(state, ctxt, *ty1, *ty2)
+                            .map_err(|err| RowUnifError::RowMismatch(id, Box::new(err)))?,
+                        (_, _) => return Err(RowUnifError::EnumRowArgMismatch(id)),
+                    }
+
+                    unify_erows(state, ctxt, *tail, t2_tail)
                 }
             }
         }
@@ -2060,8 +2998,15 @@ enum ForallInst {
 /// - `ty`: the polymorphic type to instantiate
 /// - `inst`: the type of instantiation, either by a type constant or by a unification variable
 fn instantiate_foralls(state: &mut State, mut ty: UnifType, inst: ForallInst) -> UnifType {
+    let var = ty.as_unif_var();
     ty = ty.into_root(state.table);
 
+    // If there's nothing to instantiate, we return the original unification variable, if any,
+    // which keeps track of the origin of its value (see [`UnifTable::type_origin`]).
+    if !matches!(ty, UnifType::Concrete(TypeF::Forall { .. })) {
+        return or_unif_var(var, ty);
+    }
+
     while let UnifType::Concrete(TypeF::Forall {
         var,
         var_kind,
@@ -2075,7 +3020,7 @@ fn instantiate_foralls(state: &mut State, mut ty: UnifType, inst: ForallInst) ->
                     ForallInst::Constant => UnifType::Constant(fresh_uid),
                     ForallInst::Ptr => UnifType::UnifVar(fresh_uid),
                 };
-                state.names.insert(fresh_uid, var);
+                state.names.insert((fresh_uid, VarKind::Type), var);
                 ty = body.subst_type(&var, &uvar);
             }
             VarKind::RecordRows => {
@@ -2084,7 +3029,7 @@ fn instantiate_foralls(state: &mut State, mut ty: UnifType, inst: ForallInst) ->
                     ForallInst::Constant => UnifRecordRows::Constant(fresh_uid),
                     ForallInst::Ptr => UnifRecordRows::UnifVar(fresh_uid),
                 };
-                state.names.insert(fresh_uid, var);
+                state.names.insert((fresh_uid, VarKind::RecordRows), var);
                 ty = body.subst_rrows(&var, &uvar);
 
                 if inst == ForallInst::Ptr {
@@ -2097,7 +3042,7 @@ fn instantiate_foralls(state: &mut State, mut ty: UnifType, inst: ForallInst) ->
                     ForallInst::Constant => UnifEnumRows::Constant(fresh_uid),
                     ForallInst::Ptr => UnifEnumRows::UnifVar(fresh_uid),
                 };
-                state.names.insert(fresh_uid, var);
+                state.names.insert((fresh_uid, VarKind::EnumRows), var);
                 ty = body.subst_erows(&var, &uvar);
 
                 if inst == ForallInst::Ptr {
@@ -2110,6 +3055,14 @@ fn instantiate_foralls(state: &mut State, mut ty: UnifType, inst: ForallInst) ->
     ty
 }
 
+/// The level of a unification variable. See [`UnifTable::enter_level`].
+pub type VarLevel = usize;
+
+/// A mapping from unification variables or constants to the name of the type variable which
+/// introduced them, if any. Type, record rows and enum rows variables are allocated separately, so
+/// an identifier is only unique together with the kind of the variable.
+pub type NameTable = HashMap<(VarId, VarKind), Ident>;
+
 /// The unification table.
 ///
 /// Map each unification variable to either another type variable or a concrete type it has been
@@ -2118,11 +3071,25 @@ fn instantiate_foralls(state: &mut State, mut ty: UnifType, inst: ForallInst) ->
 ///
 /// The unification table is really three separate tables, corresponding to the different kinds of
 /// types: standard types, record rows, and enum rows.
+///
+/// The table also records the level of each variable, which is used to determine which
+/// variables can be generalized (see [`UnifTable::enter_level`]), and the origin of the
+/// assignment of each type variable, which is used to explain type errors (see
+/// [`UnifTable::type_origin`]).
 #[derive(Default)]
 pub struct UnifTable {
     types: Vec<Option<UnifType>>,
     rrows: Vec<Option<UnifRecordRows>>,
     erows: Vec<Option<UnifEnumRows>>,
+    type_levels: Vec<VarLevel>,
+    rrows_levels: Vec<VarLevel>,
+    erows_levels: Vec<VarLevel>,
+    /// The current level, which is the level of the variables created from now on.
+    level: VarLevel,
+    /// The position of the term whose typechecking caused the assignment of each type variable.
+    type_origins: Vec<TermPos>,
+    /// The position of the term being currently typechecked.
+    pos: TermPos,
 }
 
 impl UnifTable {
@@ -2130,24 +3097,154 @@ impl UnifTable {
         UnifTable::default()
     }
 
+    /// Enter a new level. Unification variables created at this point, such as the ones
+    /// introduced when inferring the type of a let-bound expression, have a level strictly
+    /// greater than the variables created before.
+    ///
+    /// When a variable is assigned a type, the level of the free variables of this type is
+    /// lowered to the level of the assigned variable. Hence, once the current level is left, the
+    /// free variables which still have a greater level don't appear anywhere else than in the
+    /// types produced inside this level, and can be generalized (see [generalize]).
+    pub fn enter_level(&mut self) {
+        self.level += 1;
+    }
+
+    /// Leave the current level, entered by a previous call to [`UnifTable::enter_level`].
+    pub fn leave_level(&mut self) {
+        debug_assert!(self.level > 0);
+        self.level -= 1;
+    }
+
+    /// Record that the term at position `pos` is being typechecked, until the next call to
+    /// [`UnifTable::leave_term`]. The assignments made in the meantime are attributed to this term.
+    /// If `pos` is not defined, which is the case of terms generated by the interpreter, they are
+    /// attributed to the enclosing term instead.
+    ///
+    /// Return the position to restore when leaving the term.
+    pub fn enter_term(&mut self, pos: TermPos) -> TermPos {
+        if pos.is_def() {
+            std::mem::replace(&mut self.pos, pos)
+        } else {
+            self.pos
+        }
+    }
+
+    /// Leave a term entered by a previous call to [`UnifTable::enter_term`].
+    pub fn leave_term(&mut self, prev_pos: TermPos) {
+        self.pos = prev_pos;
+    }
+
     /// Assign a type to a type unification variable.
     pub fn assign_type(&mut self, var: VarId, uty: UnifType) {
         debug_assert!(self.types[var].is_none());
+        self.lower_type_levels(uty.clone(), self.type_levels[var]);
         self.types[var] = Some(uty);
+        self.type_origins[var] = self.pos;
+    }
+
+    /// If `uty` is a unification variable whose root is not a variable, return the position of the
+    /// term which fixed this root, that is, the term whose typechecking caused the last variable
+    /// of the chain to be assigned a type. Return `None` otherwise, or if this position is
+    /// unknown.
+    pub fn type_origin(&self, uty: &UnifType) -> Option<RawSpan> {
+        let mut var_id = match uty {
+            UnifType::UnifVar(var_id) => *var_id,
+            _ => return None,
+        };
+
+        loop {
+            match &self.types[var_id] {
+                Some(UnifType::UnifVar(next)) => var_id = *next,
+                Some(_) => return self.type_origins[var_id].into_opt(),
+                None => return None,
+            }
+        }
     }
 
     /// Assign record rows to a record rows unification variable.
     pub fn assign_rrows(&mut self, var: VarId, rrows: UnifRecordRows) {
         debug_assert!(self.rrows[var].is_none());
+        self.lower_rrows_levels(rrows.clone(), self.rrows_levels[var]);
         self.rrows[var] = Some(rrows);
     }
 
     /// Assign enum rows to an enum rows unification variable.
     pub fn assign_erows(&mut self, var: VarId, erows: UnifEnumRows) {
         debug_assert!(self.erows[var].is_none());
+        self.lower_erows_levels(erows.clone(), self.erows_levels[var]);
         self.erows[var] = Some(erows);
     }
 
+    /// Lower the level of the unification variables occurring in a type to at most `level`.
+    ///
+    /// The level of an assigned variable is never greater than the level of the variables
+    /// occurring in its assignment, so we don't need to go through variables which already have
+    /// a lower level.
+    fn lower_type_levels(&mut self, uty: UnifType, level: VarLevel) {
+        match uty {
+            UnifType::UnifVar(id) if self.type_levels[id] > level => {
+                self.type_levels[id] = level;
+
+                if let Some(uty) = self.types[id].clone() {
+                    self.lower_type_levels(uty, level);
+                }
+            }
+            UnifType::Concrete(t) => {
+                t.map_state(
+                    |ty, table| table.lower_type_levels(*ty, level),
+                    |rrows, table| table.lower_rrows_levels(rrows, level),
+                    |erows, table| table.lower_erows_levels(erows, level),
+                    self,
+                );
+            }
+            _ => (),
+        }
+    }
+
+    /// Lower the level of the unification variables occurring in record rows to at most `level`.
+    /// See [`UnifTable::lower_type_levels`].
+    fn lower_rrows_levels(&mut self, rrows: UnifRecordRows, level: VarLevel) {
+        match rrows {
+            UnifRecordRows::UnifVar(id) if self.rrows_levels[id] > level => {
+                self.rrows_levels[id] = level;
+
+                if let Some(rrows) = self.rrows[id].clone() {
+                    self.lower_rrows_levels(rrows, level);
+                }
+            }
+            UnifRecordRows::Concrete(rrows) => {
+                rrows.map_state(
+                    |ty, table| table.lower_type_levels(*ty, level),
+                    |rrows, table| table.lower_rrows_levels(*rrows, level),
+                    self,
+                );
+            }
+            _ => (),
+        }
+    }
+
+    /// Lower the level of the unification variables occurring in enum rows to at most `level`.
+    /// See [`UnifTable::lower_type_levels`].
+    fn lower_erows_levels(&mut self, erows: UnifEnumRows, level: VarLevel) {
+        match erows {
+            UnifEnumRows::UnifVar(id) if self.erows_levels[id] > level => {
+                self.erows_levels[id] = level;
+
+                if let Some(erows) = self.erows[id].clone() {
+                    self.lower_erows_levels(erows, level);
+                }
+            }
+            UnifEnumRows::Concrete(erows) => {
+                erows.map_state(
+                    |ty, table| table.lower_type_levels(*ty, level),
+                    |erows, table| table.lower_erows_levels(*erows, level),
+                    self,
+                );
+            }
+            _ => (),
+        }
+    }
+
     /// Retrieve the current assignment of a type unification variable.
     pub fn get_type(&self, var: VarId) -> Option<&UnifType> {
         self.types[var].as_ref()
@@ -2168,6 +3265,8 @@ impl UnifTable {
     fn fresh_type_var_id(&mut self) -> VarId {
         let next = self.types.len();
         self.types.push(None);
+        self.type_levels.push(self.level);
+        self.type_origins.push(TermPos::None);
         next
     }
 
@@ -2176,6 +3275,7 @@ impl UnifTable {
     fn fresh_rrows_var_id(&mut self) -> VarId {
         let next = self.rrows.len();
         self.rrows.push(None);
+        self.rrows_levels.push(self.level);
         next
     }
 
@@ -2184,6 +3284,7 @@ impl UnifTable {
     fn fresh_erows_var_id(&mut self) -> VarId {
         let next = self.erows.len();
         self.erows.push(None);
+        self.erows_levels.push(self.level);
         next
     }
 
@@ -2334,25 +3435,27 @@ impl ConstrainFreshRRowsVar for UnifType {
                 ty => ty.constrain_fresh_rrows_var(state, var_id),
             },
             UnifType::Concrete(ty) => match ty {
-                    TypeF::Arrow(uty1, uty2) => {
-                        uty1.constrain_fresh_rrows_var(state, var_id);
-                        uty2.constrain_fresh_rrows_var(state, var_id);
-                    }
-                    TypeF::Forall {body, ..} => body.constrain_fresh_rrows_var(state, var_id),
-                    TypeF::Dyn
-                    | TypeF::Num
-                    | TypeF::Bool
-                    | TypeF::Str
-                    | TypeF::Sym
-                    | TypeF::Flat(_)
-                    | TypeF::Var(_)
-                    // There can be no record rows unification variable inside an enum type
-                    | TypeF::Enum(_) => (),
-                    | TypeF::Wildcard(_) => (),
-                    TypeF::Record(rrows) => rrows.constrain_fresh_rrows_var(state, var_id),
-                    TypeF::Array(uty)
-                    | TypeF::Dict(uty) => uty.constrain_fresh_rrows_var(state, var_id),
-                },
+                TypeF::Arrow(uty1, uty2) => {
+                    uty1.constrain_fresh_rrows_var(state, var_id);
+                    uty2.constrain_fresh_rrows_var(state, var_id);
+                }
+                TypeF::Forall { body, .. } => body.constrain_fresh_rrows_var(state, var_id),
+                TypeF::Dyn
+                | TypeF::Num
+                | TypeF::DateTime
+                | TypeF::Duration
+                | TypeF::Bool
+                | TypeF::Str
+                | TypeF::Sym
+                | TypeF::Flat(_)
+                | TypeF::Var(_)
+                | TypeF::Wildcard(_) => (),
+                TypeF::Enum(erows) => erows.constrain_fresh_rrows_var(state, var_id),
+                TypeF::Record(rrows) => rrows.constrain_fresh_rrows_var(state, var_id),
+                TypeF::Array(uty) | TypeF::Dict(uty) => {
+                    uty.constrain_fresh_rrows_var(state, var_id)
+                }
+            },
             UnifType::Constant(_) | UnifType::Contract(..) => (),
         }
     }
@@ -2390,6 +3493,27 @@ impl ConstrainFreshRRowsVar for UnifRecordRows {
     }
 }
 
+impl ConstrainFreshRRowsVar for UnifEnumRows {
+    fn constrain_fresh_rrows_var(&self, state: &mut State, var_id: VarId) {
+        match self {
+            UnifEnumRows::UnifVar(u) => match state.table.root_erows(*u) {
+                UnifEnumRows::UnifVar(_) => (),
+                erows => erows.constrain_fresh_rrows_var(state, var_id),
+            },
+            UnifEnumRows::Concrete(ty) => match ty {
+                EnumRowsF::Empty | EnumRowsF::TailVar(_) => (),
+                EnumRowsF::Extend { row, tail } => {
+                    if let Some(ty) = &row.types {
+                        ty.constrain_fresh_rrows_var(state, var_id);
+                    }
+                    tail.constrain_fresh_rrows_var(state, var_id);
+                }
+            },
+            UnifEnumRows::Constant(_) => (),
+        }
+    }
+}
+
 impl ConstrainFreshERowsVar for UnifType {
     fn constrain_fresh_erows_var(&self, state: &mut State, var_id: VarId) {
         match self {
@@ -2405,6 +3529,8 @@ impl ConstrainFreshERowsVar for UnifType {
                 TypeF::Forall { body, .. } => body.constrain_fresh_erows_var(state, var_id),
                 TypeF::Dyn
                 | TypeF::Num
+                | TypeF::DateTime
+                | TypeF::Duration
                 | TypeF::Bool
                 | TypeF::Str
                 | TypeF::Sym
@@ -2460,7 +3586,10 @@ impl ConstrainFreshERowsVar for UnifEnumRows {
                 UnifEnumRows::Concrete(ty) => match ty {
                     EnumRowsF::Empty | EnumRowsF::TailVar(_) => (),
                     EnumRowsF::Extend { row, tail } => {
-                        constr.insert(*row);
+                        constr.insert(row.id);
+                        if let Some(ty) = &row.types {
+                            ty.constrain_fresh_erows_var(state, var_id);
+                        }
                         constrain_var(state, constr, tail, var_id);
                     }
                 },
@@ -2533,9 +3662,9 @@ pub fn constr_unify_erows(
 ) -> Result<(), RowUnifError> {
     if let Some(p_constr) = constr.remove(&var_id) {
         match erows {
-            UnifEnumRows::Concrete(EnumRowsF::Extend { row, .. }) if p_constr.contains(row) => {
+            UnifEnumRows::Concrete(EnumRowsF::Extend { row, .. }) if p_constr.contains(&row.id) => {
                 Err(RowUnifError::UnsatConstr(
-                    *row,
+                    row.id,
                     Some(UnifType::Concrete(TypeF::Enum(erows.clone()))),
                 ))
             }
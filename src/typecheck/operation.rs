@@ -22,10 +22,14 @@ pub fn get_uop_type(
                 mk_uty_arrow!(branches.clone(), branches.clone(), branches),
             )
         }
-        // Dyn -> [| `Num, `Bool, `Str, `Enum, `Fun, `Array, `Record, `Lbl, `Other |]
+        // Dyn -> [| `Num, `Bool, `Str, `DateTime, `Duration, `Enum, `Fun, `Array, `Record, `Lbl,
+        //   `Other |]
         UnaryOp::Typeof() => (
             mk_uniftype::dynamic(),
-            mk_uty_enum!("Num", "Bool", "Str", "Enum", "Fun", "Array", "Record", "Lbl", "Other"),
+            mk_uty_enum!(
+                "Num", "Bool", "Str", "DateTime", "Duration", "Enum", "Fun", "Array", "Record",
+                "Lbl", "Other"
+            ),
         ),
         // Bool -> Bool -> Bool
         UnaryOp::BoolAnd() | UnaryOp::BoolOr() => {
@@ -67,6 +71,10 @@ pub fn get_uop_type(
 
             (mk_uty_record!((*id, res.clone()); rows), res)
         }
+        // The type of a field access with a default value depends on the type of the record.
+        UnaryOp::StaticAccessOr(_) => {
+            panic!("the type of a field access with default is computed by type_check_")
+        }
         // forall a b. Array a -> (a -> b) -> Array b
         UnaryOp::ArrayMap() => {
             let a = UnifType::UnifVar(state.table.fresh_type_var_id());
@@ -88,6 +96,74 @@ pub fn get_uop_type(
                 mk_uty_arrow!(f_type, mk_uniftype::array(a)),
             )
         }
+        // forall a. Array a -> Array a
+        UnaryOp::ArrayReverse() => {
+            let ty_array = mk_uniftype::array(state.table.fresh_type_uvar());
+            (ty_array.clone(), ty_array)
+        }
+        // forall a. Array (Array a) -> Array a
+        UnaryOp::ArrayFlatten() => {
+            let ty_array = mk_uniftype::array(state.table.fresh_type_uvar());
+            (mk_uniftype::array(ty_array.clone()), ty_array)
+        }
+        // forall a b. Array b -> (a -> b -> a) -> a -> a
+        UnaryOp::ArrayFoldl() => {
+            let a = state.table.fresh_type_uvar();
+            let b = state.table.fresh_type_uvar();
+
+            let f_type = mk_uty_arrow!(a.clone(), b.clone(), a.clone());
+            (mk_uniftype::array(b), mk_uty_arrow!(f_type, a.clone(), a))
+        }
+        // forall a. Array a -> (a -> Bool) -> Array a
+        UnaryOp::ArrayFilter() => {
+            let a = state.table.fresh_type_uvar();
+            let ty_array = mk_uniftype::array(a.clone());
+
+            (
+                ty_array.clone(),
+                mk_uty_arrow!(mk_uty_arrow!(a, TypeF::Bool), ty_array),
+            )
+        }
+        // forall a. Array a -> (a -> Bool) -> {right: Array a, wrong: Array a}
+        UnaryOp::ArrayPartition() => {
+            let a = state.table.fresh_type_uvar();
+            let ty_array = mk_uniftype::array(a.clone());
+
+            (
+                ty_array.clone(),
+                mk_uty_arrow!(
+                    mk_uty_arrow!(a, TypeF::Bool),
+                    mk_uty_record!(("right", ty_array.clone()), ("wrong", ty_array))
+                ),
+            )
+        }
+        // forall a. Array a -> (a -> a -> [| `Lesser, `Equal, `Greater |]) -> Array a
+        UnaryOp::ArraySort() => {
+            let a = state.table.fresh_type_uvar();
+            let ty_array = mk_uniftype::array(a.clone());
+            let cmp_type = mk_uty_arrow!(a.clone(), a, mk_uty_enum!("Lesser", "Equal", "Greater"));
+
+            (ty_array.clone(), mk_uty_arrow!(cmp_type, ty_array))
+        }
+        // forall a. Array a -> (a -> Dyn) -> Array a
+        UnaryOp::ArraySortBy() | UnaryOp::ArraySortOn() => {
+            let a = state.table.fresh_type_uvar();
+            let ty_array = mk_uniftype::array(a.clone());
+
+            (
+                ty_array.clone(),
+                mk_uty_arrow!(mk_uty_arrow!(a, TypeF::Dyn), ty_array),
+            )
+        }
+        // forall a. Array a -> (a -> Dyn) -> a
+        UnaryOp::ArrayMinBy() | UnaryOp::ArrayMaxBy() => {
+            let a = state.table.fresh_type_uvar();
+
+            (
+                mk_uniftype::array(a.clone()),
+                mk_uty_arrow!(mk_uty_arrow!(a.clone(), TypeF::Dyn), a),
+            )
+        }
         // forall a b. { _ : a} -> (Str -> a -> b) -> { _ : b }
         UnaryOp::RecordMap() => {
             // Assuming f has type Str -> a -> b,
@@ -109,6 +185,12 @@ pub fn get_uop_type(
 
             (fst, mk_uty_arrow!(snd.clone(), snd))
         }
+        // forall a. Str -> a -> a
+        UnaryOp::Trace() => {
+            let ty = UnifType::UnifVar(state.table.fresh_type_var_id());
+
+            (mk_uniftype::str(), mk_uty_arrow!(ty.clone(), ty))
+        }
         // forall a. Array a -> a
         UnaryOp::ArrayHead() => {
             let ty_elt = UnifType::UnifVar(state.table.fresh_type_var_id());
@@ -155,6 +237,22 @@ pub fn get_uop_type(
         UnaryOp::CharCode() => (mk_uniftype::str(), mk_uniftype::num()),
         // Num -> Str
         UnaryOp::CharFromCode() => (mk_uniftype::num(), mk_uniftype::str()),
+        // Str -> DateTime
+        UnaryOp::DateTimeParse() => (mk_uniftype::str(), mk_uniftype::datetime()),
+        // DateTime -> Str
+        UnaryOp::DateTimeToStr() => (mk_uniftype::datetime(), mk_uniftype::str()),
+        // DateTime -> Num
+        UnaryOp::DateTimeToTimestamp() => (mk_uniftype::datetime(), mk_uniftype::num()),
+        // Num -> DateTime
+        UnaryOp::DateTimeFromTimestamp() => (mk_uniftype::num(), mk_uniftype::datetime()),
+        // Str -> Duration
+        UnaryOp::DurationParse() => (mk_uniftype::str(), mk_uniftype::duration()),
+        // Duration -> Str
+        UnaryOp::DurationToStr() => (mk_uniftype::duration(), mk_uniftype::str()),
+        // Duration -> Num
+        UnaryOp::DurationToSeconds() => (mk_uniftype::duration(), mk_uniftype::num()),
+        // Num -> Duration
+        UnaryOp::DurationFromSeconds() => (mk_uniftype::num(), mk_uniftype::duration()),
         // Str -> Str
         UnaryOp::StrUppercase() => (mk_uniftype::str(), mk_uniftype::str()),
         // Str -> Str
@@ -165,6 +263,12 @@ pub fn get_uop_type(
         UnaryOp::ToStr() => (mk_uniftype::dynamic(), mk_uniftype::str()),
         // Str -> Num
         UnaryOp::NumFromStr() => (mk_uniftype::str(), mk_uniftype::num()),
+        // Dyn -> Dyn
+        UnaryOp::EnumGetTag() | UnaryOp::EnumGetArg() => {
+            (mk_uniftype::dynamic(), mk_uniftype::dynamic())
+        }
+        // Dyn -> Bool
+        UnaryOp::EnumIsVariant() => (mk_uniftype::dynamic(), mk_uniftype::bool()),
         // Str -> < | a> for a rigid type variable a
         UnaryOp::EnumFromStr() => (
             mk_uniftype::str(),
@@ -175,27 +279,37 @@ pub fn get_uop_type(
             mk_uniftype::str(),
             mk_uty_arrow!(mk_uniftype::str(), mk_uniftype::bool()),
         ),
-        // Str -> Str -> {matched: Str, index: Num, groups: Array Str}
+        // Str -> Str -> Match
         UnaryOp::StrFind() => (
+            mk_uniftype::str(),
+            mk_uty_arrow!(mk_uniftype::str(), mk_match_type()),
+        ),
+        // Str -> Str -> Array Match
+        UnaryOp::StrFindAll() => (
+            mk_uniftype::str(),
+            mk_uty_arrow!(mk_uniftype::str(), mk_uniftype::array(mk_match_type())),
+        ),
+        // Str -> Str -> (Match -> Str) -> Str
+        UnaryOp::StrReplaceRegexWith() => (
             mk_uniftype::str(),
             mk_uty_arrow!(
                 mk_uniftype::str(),
-                mk_uty_record!(
-                    ("matched", TypeF::Str),
-                    ("index", TypeF::Num),
-                    ("groups", mk_uniftype::array(TypeF::Str))
-                )
+                mk_uty_arrow!(mk_match_type(), mk_uniftype::str()),
+                mk_uniftype::str()
             ),
         ),
         // Str -> Bool
         UnaryOp::StrIsMatchCompiled(_) => (mk_uniftype::str(), mk_uniftype::bool()),
-        // Str -> {matched: Str, index: Num, groups: Array Str}
-        UnaryOp::StrFindCompiled(_) => (
+        // Str -> Match
+        UnaryOp::StrFindCompiled(_) => (mk_uniftype::str(), mk_match_type()),
+        // Str -> Array Match
+        UnaryOp::StrFindAllCompiled(_) => (mk_uniftype::str(), mk_uniftype::array(mk_match_type())),
+        // Str -> (Match -> Str) -> Str
+        UnaryOp::StrReplaceRegexWithCompiled(_) => (
             mk_uniftype::str(),
-            mk_uty_record!(
-                ("matched", TypeF::Str),
-                ("index", TypeF::Num),
-                ("groups", mk_uniftype::array(TypeF::Str))
+            mk_uty_arrow!(
+                mk_uty_arrow!(mk_match_type(), mk_uniftype::str()),
+                mk_uniftype::str()
             ),
         ),
         // Dyn -> Dyn
@@ -210,6 +324,8 @@ pub fn get_uop_type(
             let ty = state.table.fresh_type_uvar();
             (ty.clone(), ty)
         }
+        // This should not happen, as ReplaceValue() is only produced during evaluation.
+        UnaryOp::ReplaceValue() => panic!("cannot typecheck ReplaceValue()"),
         UnaryOp::RecordEmptyWithTail() => (mk_uniftype::dynamic(), mk_uniftype::dynamic()),
     })
 }
@@ -259,6 +375,11 @@ pub fn get_bop_type(
             UnifType::UnifVar(state.table.fresh_type_var_id()),
             mk_uniftype::bool(),
         ),
+        // forall a. a -> a -> <Lesser, Equal, Greater>
+        BinaryOp::Compare() => {
+            let ty = state.table.fresh_type_uvar();
+            (ty.clone(), ty, mk_uty_enum!("Lesser", "Equal", "Greater"))
+        }
         // Num -> Num -> Bool
         BinaryOp::LessThan()
         | BinaryOp::LessOrEq()
@@ -289,6 +410,15 @@ pub fn get_bop_type(
                 mk_uty_arrow!(res.clone(), mk_uniftype::dyn_record(res)),
             )
         }
+        // forall a. Str -> { _ : a } -> a -> { _ : a }
+        BinaryOp::DynUpdate() => {
+            let res = UnifType::UnifVar(state.table.fresh_type_var_id());
+            (
+                mk_uniftype::str(),
+                mk_uniftype::dyn_record(res.clone()),
+                mk_uty_arrow!(res.clone(), mk_uniftype::dyn_record(res)),
+            )
+        }
         // forall a. Str -> { _ : a } -> { _ : a}
         BinaryOp::DynRemove() => {
             let res = UnifType::UnifVar(state.table.fresh_type_var_id());
@@ -322,15 +452,47 @@ pub fn get_bop_type(
                 ty_elt,
             )
         }
+        // Num -> Num -> Array Num
+        BinaryOp::ArrayRange() => (
+            mk_uniftype::num(),
+            mk_uniftype::num(),
+            mk_uniftype::array(TypeF::Num),
+        ),
+        // forall a b. Array a -> Array b -> Array {fst: a, snd: b}
+        BinaryOp::ArrayZip() => {
+            let a = state.table.fresh_type_uvar();
+            let b = state.table.fresh_type_uvar();
+
+            (
+                mk_uniftype::array(a.clone()),
+                mk_uniftype::array(b.clone()),
+                mk_uniftype::array(mk_uty_record!(("fst", a), ("snd", b))),
+            )
+        }
         // Dyn -> Dyn -> Dyn
         BinaryOp::Merge() => (
             mk_uniftype::dynamic(),
             mk_uniftype::dynamic(),
             mk_uniftype::dynamic(),
         ),
-        // <Md5, Sha1, Sha256, Sha512> -> Str -> Str
+        // <Md5, Sha1, Sha256, Sha512, Blake3> -> Str -> Str
         BinaryOp::Hash() => (
-            mk_uty_enum!("Md5", "Sha1", "Sha256", "Sha512"),
+            mk_uty_enum!("Md5", "Sha1", "Sha256", "Sha512", "Blake3"),
+            mk_uniftype::str(),
+            mk_uniftype::str(),
+        ),
+        // forall a. <Md5, Sha1, Sha256, Sha512, Blake3> -> a -> Str
+        BinaryOp::HashValue() => {
+            let ty_input = UnifType::UnifVar(state.table.fresh_type_var_id());
+            (
+                mk_uty_enum!("Md5", "Sha1", "Sha256", "Sha512", "Blake3"),
+                ty_input,
+                mk_uniftype::str(),
+            )
+        }
+        // <Base64, Base64Url, Hex, Url> -> Str -> Str
+        BinaryOp::Encode() | BinaryOp::Decode() => (
+            mk_uty_enum!("Base64", "Base64Url", "Hex", "Url"),
             mk_uniftype::str(),
             mk_uniftype::str(),
         ),
@@ -353,6 +515,26 @@ pub fn get_bop_type(
         BinaryOp::Pow() => (mk_uniftype::num(), mk_uniftype::num(), mk_uniftype::num()),
         // Str -> Str -> Bool
         BinaryOp::StrContains() => (mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::bool()),
+        // Num -> Num -> Str
+        BinaryOp::ToStrRadix() => (mk_uniftype::num(), mk_uniftype::num(), mk_uniftype::str()),
+        // Str -> Dyn -> Str
+        BinaryOp::StrFormat() => (
+            mk_uniftype::str(),
+            mk_uniftype::dynamic(),
+            mk_uniftype::str(),
+        ),
+        // Str -> DateTime -> Str
+        BinaryOp::DateTimeFormat() => (
+            mk_uniftype::str(),
+            mk_uniftype::datetime(),
+            mk_uniftype::str(),
+        ),
+        // DateTime -> Duration -> DateTime
+        BinaryOp::DateTimeAdd() => (
+            mk_uniftype::datetime(),
+            mk_uniftype::duration(),
+            mk_uniftype::datetime(),
+        ),
         // Str -> Str -> Array Str
         BinaryOp::StrSplit() => (
             mk_uniftype::str(),
@@ -374,7 +556,7 @@ pub fn get_bop_type(
 }
 
 pub fn get_nop_type(
-    _state: &mut State,
+    state: &mut State,
     op: &NAryOp,
 ) -> Result<(Vec<UnifType>, UnifType), TypecheckError> {
     Ok(match op {
@@ -383,11 +565,39 @@ pub fn get_nop_type(
             vec![mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::str()],
             mk_uniftype::str(),
         ),
+        // Str -> Num -> Str -> Str
+        NAryOp::StrPadLeft() | NAryOp::StrPadRight() => (
+            vec![mk_uniftype::str(), mk_uniftype::num(), mk_uniftype::str()],
+            mk_uniftype::str(),
+        ),
         // Str -> Num -> Num -> Str
         NAryOp::StrSubstr() => (
             vec![mk_uniftype::str(), mk_uniftype::num(), mk_uniftype::num()],
             mk_uniftype::str(),
         ),
+        // forall a. Array a -> Num -> Num -> Array a
+        NAryOp::ArraySlice() => {
+            let ty_array = mk_uniftype::array(state.table.fresh_type_uvar());
+            (
+                vec![ty_array.clone(), mk_uniftype::num(), mk_uniftype::num()],
+                ty_array,
+            )
+        }
+        // This should not happen, as an array mask primop is only produced during evaluation.
+        NAryOp::ArrayMasked(_) => panic!("cannot typecheck array mask primop"),
+        // forall a. Str -> { _ : a } -> Str -> { _ : a } -> { _ : a }
+        NAryOp::RecordCopyField() => {
+            let ty_record = mk_uniftype::dyn_record(state.table.fresh_type_uvar());
+            (
+                vec![
+                    mk_uniftype::str(),
+                    ty_record.clone(),
+                    mk_uniftype::str(),
+                    ty_record.clone(),
+                ],
+                ty_record,
+            )
+        }
         // Dyn -> Dyn -> Dyn -> Dyn -> Dyn
         NAryOp::RecordSealTail() => (
             vec![
@@ -411,3 +621,14 @@ pub fn get_nop_type(
         NAryOp::MergeContract() => panic!("cannot typecheck MergeContract()"),
     })
 }
+
+/// The type of the result of a regex match: `{matched: Str, index: Num, groups: Array Str, named:
+/// {_: Str}}`.
+fn mk_match_type() -> UnifType {
+    mk_uty_record!(
+        ("matched", TypeF::Str),
+        ("index", TypeF::Num),
+        ("groups", mk_uniftype::array(TypeF::Str)),
+        ("named", mk_uniftype::dyn_record(TypeF::Str))
+    )
+}
@@ -6,7 +6,7 @@ use std::collections::HashSet;
 /// A name registry used to replace unification variables and type constants with human-readable
 /// and distinct names.
 pub struct NameReg {
-    reg: HashMap<usize, Ident>,
+    reg: HashMap<(VarId, VarKind), Ident>,
     taken: HashSet<String>,
     var_count: usize,
     cst_count: usize,
@@ -33,8 +33,8 @@ impl NameReg {
 /// the given counter to generate a new single letter.
 ///
 /// Generated name is clearly not necessarily unique. This is handled by [`select_uniq`].
-fn mk_name(names: &HashMap<usize, Ident>, counter: &mut usize, id: usize) -> String {
-    match names.get(&id) {
+fn mk_name(names: &NameTable, counter: &mut usize, id: VarId, kind: VarKind) -> String {
+    match names.get(&(id, kind)) {
         // First check if that constant or variable was introduced by a forall. If it was, try
         // to use the same name.
         Some(orig) => format!("{}", orig),
@@ -54,7 +54,7 @@ fn mk_name(names: &HashMap<usize, Ident>, counter: &mut usize, id: usize) -> Str
 ///
 /// If the name is already taken, it just iterates by adding a numeric suffix `1`, `2`, .., and so
 /// on until a free name is found. See `var_to_type` and `cst_to_type`.
-fn select_uniq(name_reg: &mut NameReg, mut name: String, id: usize) -> Ident {
+fn select_uniq(name_reg: &mut NameReg, mut name: String, id: VarId, kind: VarKind) -> Ident {
     // To avoid clashing with already picked names, we add a numeric suffix to the picked
     // letter.
     if name_reg.taken.contains(&name) {
@@ -68,30 +68,30 @@ fn select_uniq(name_reg: &mut NameReg, mut name: String, id: usize) -> Ident {
     }
 
     let ident = Ident::from(name);
-    name_reg.reg.insert(id, ident);
+    name_reg.reg.insert((id, kind), ident);
     ident
 }
 
 /// Either retrieve or generate a new fresh name for a unification variable for error reporting,
 /// and wrap it as an identifier. Unification variables are named `_a`, `_b`, .., `_a1`, `_b1`, ..
 /// and so on.
-fn var_name(names: &HashMap<usize, Ident>, name_reg: &mut NameReg, p: usize) -> Ident {
-    name_reg.reg.get(&p).cloned().unwrap_or_else(|| {
+fn var_name(names: &NameTable, name_reg: &mut NameReg, p: VarId, kind: VarKind) -> Ident {
+    name_reg.reg.get(&(p, kind)).cloned().unwrap_or_else(|| {
         // Select a candidate name and add a "_" prefix
-        let name = format!("_{}", mk_name(names, &mut name_reg.var_count, p));
+        let name = format!("_{}", mk_name(names, &mut name_reg.var_count, p, kind));
         // Add a suffix to make it unique if it has already been picked
-        select_uniq(name_reg, name, p)
+        select_uniq(name_reg, name, p, kind)
     })
 }
 
 /// Either retrieve or generate a new fresh name for a constant for error reporting, and wrap it as
 /// type variable. Constant are named `a`, `b`, .., `a1`, `b1`, .. and so on.
-fn cst_name(names: &HashMap<usize, Ident>, name_reg: &mut NameReg, c: usize) -> Ident {
-    name_reg.reg.get(&c).cloned().unwrap_or_else(|| {
+fn cst_name(names: &NameTable, name_reg: &mut NameReg, c: VarId, kind: VarKind) -> Ident {
+    name_reg.reg.get(&(c, kind)).cloned().unwrap_or_else(|| {
         // Select a candidate name
-        let name = mk_name(names, &mut name_reg.cst_count, c);
+        let name = mk_name(names, &mut name_reg.cst_count, c, kind);
         // Add a suffix to make it unique if it has already been picked
-        select_uniq(name_reg, name, c)
+        select_uniq(name_reg, name, c, kind)
     })
 }
 
@@ -105,13 +105,13 @@ fn cst_name(names: &HashMap<usize, Ident>, name_reg: &mut NameReg, c: usize) ->
 /// than having `Dyn` everywhere.
 pub fn to_type(
     table: &UnifTable,
-    reported_names: &HashMap<usize, Ident>,
+    reported_names: &NameTable,
     names: &mut NameReg,
     ty: UnifType,
 ) -> Types {
     fn rrows_to_type(
         table: &UnifTable,
-        reported_names: &HashMap<usize, Ident>,
+        reported_names: &NameTable,
         names: &mut NameReg,
         rrows: UnifRecordRows,
     ) -> RecordRows {
@@ -122,10 +122,14 @@ pub fn to_type(
                 reported_names,
                 names,
                 var_id,
+                VarKind::RecordRows,
+            ))),
+            UnifRecordRows::Constant(c) => RecordRows(RecordRowsF::TailVar(cst_name(
+                reported_names,
+                names,
+                c,
+                VarKind::RecordRows,
             ))),
-            UnifRecordRows::Constant(c) => {
-                RecordRows(RecordRowsF::TailVar(cst_name(reported_names, names, c)))
-            }
             UnifRecordRows::Concrete(t) => {
                 let mapped = t.map_state(
                     |btyp, names| Box::new(to_type(table, reported_names, names, *btyp)),
@@ -139,22 +143,31 @@ pub fn to_type(
 
     fn erows_to_type(
         table: &UnifTable,
-        reported_names: &HashMap<usize, Ident>,
+        reported_names: &NameTable,
         names: &mut NameReg,
         erows: UnifEnumRows,
     ) -> EnumRows {
         let erows = erows.into_root(table);
 
         match erows {
-            UnifEnumRows::UnifVar(var_id) => {
-                EnumRows(EnumRowsF::TailVar(var_name(reported_names, names, var_id)))
-            }
-            UnifEnumRows::Constant(c) => {
-                EnumRows(EnumRowsF::TailVar(cst_name(reported_names, names, c)))
-            }
+            UnifEnumRows::UnifVar(var_id) => EnumRows(EnumRowsF::TailVar(var_name(
+                reported_names,
+                names,
+                var_id,
+                VarKind::EnumRows,
+            ))),
+            UnifEnumRows::Constant(c) => EnumRows(EnumRowsF::TailVar(cst_name(
+                reported_names,
+                names,
+                c,
+                VarKind::EnumRows,
+            ))),
             UnifEnumRows::Concrete(t) => {
-                let mapped =
-                    t.map(|erows| Box::new(erows_to_type(table, reported_names, names, *erows)));
+                let mapped = t.map_state(
+                    |btyp, names| Box::new(to_type(table, reported_names, names, *btyp)),
+                    |erows, names| Box::new(erows_to_type(table, reported_names, names, *erows)),
+                    names,
+                );
                 EnumRows(mapped)
             }
         }
@@ -163,8 +176,18 @@ pub fn to_type(
     let ty = ty.into_root(table);
 
     match ty {
-        UnifType::UnifVar(p) => Types(TypeF::Var(var_name(reported_names, names, p))),
-        UnifType::Constant(c) => Types(TypeF::Var(cst_name(reported_names, names, c))),
+        UnifType::UnifVar(p) => Types(TypeF::Var(var_name(
+            reported_names,
+            names,
+            p,
+            VarKind::Type,
+        ))),
+        UnifType::Constant(c) => Types(TypeF::Var(cst_name(
+            reported_names,
+            names,
+            c,
+            VarKind::Type,
+        ))),
         UnifType::Concrete(t) => {
             let mapped = t.map_state(
                 |btyp, names| Box::new(to_type(table, reported_names, names, *btyp)),
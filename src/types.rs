@@ -39,8 +39,10 @@
 //!
 //! # Enum types
 //!
-//! An enum type is also a row type, but each list element only contains an identifier without an
-//! associated type. It indicates which tag the enum can contain.
+//! An enum type is also a row type, where each list element is a tag, optionally associated to the
+//! type of its argument. It indicates which tags the enum can contain: for example, the type
+//! ``[| `Ok Num, `Error Str, `Unknown |]`` is inhabited by `` `Ok 1``, `` `Error "msg"`` and the bare
+//! tag `` `Unknown``.
 //!
 //! # Contracts
 //!
@@ -54,11 +56,11 @@ use crate::{
     identifier::Ident,
     mk_app, mk_fun,
     term::make as mk_term,
-    term::{record::RecordData, RichTerm, Term, TraverseOrder},
+    term::{record::RecordData, RichTerm, Term, TraverseOrder, UnaryOp},
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{self, Display},
 };
 
@@ -76,14 +78,20 @@ pub struct RecordRowF<Ty> {
     pub types: Ty,
 }
 
-/// An enum row, which is just an identifier. An enum type is a set of identifiers, represented as
-/// a sequence of `EnumRow`s, ending potentially with a type variable tail position.
+/// An enum row, mapping an identifier to the optional type of its argument. An enum type is a set
+/// of such rows, represented as a sequence of `EnumRowF`s, ending potentially with a type variable
+/// tail position. A row without a type corresponds to a bare tag, such as `` `Foo``, while a row
+/// with a type corresponds to a variant applied to an argument, such as `` `Foo 5``.
 ///
-/// `EnumRowF` is the same as `EnumRow` and doesn't have any type parameter. We introduce the alias
-/// nontheless for consistency with other parametrized type definitions. See [`TypeF`] for more
-/// details.
-pub type EnumRowF = Ident;
-pub type EnumRow = EnumRowF;
+/// # Type parameters
+///
+/// As other types with the `F` suffix, this type is parametrized by one or more recursive
+/// unfoldings (here, `Ty` for `TypeF`). See [`TypeF`] for more details.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnumRowF<Ty> {
+    pub id: Ident,
+    pub types: Option<Ty>,
+}
 
 /// Generic sequence of record rows potentially with a type variable or `Dyn` in tail position.
 ///
@@ -111,12 +119,14 @@ pub enum RecordRowsF<Ty, RRows> {
 ///
 /// # Type parameters
 ///
+/// - `Ty` is the recursive unfolding of a Nickel type stored inside one row (the type of the
+///   argument of a variant). In practice, a wrapper around an instantiation of `TypeF`.
 /// - `ERows` is the recursive unfolding of enum rows (the tail of this row sequence). In practice,
 ///   a wrapper around `EnumRowsF`.
 #[derive(Clone, PartialEq, Eq, Debug)]
-pub enum EnumRowsF<ERows> {
+pub enum EnumRowsF<Ty, ERows> {
     Empty,
-    Extend { row: EnumRowF, tail: ERows },
+    Extend { row: EnumRowF<Ty>, tail: ERows },
     TailVar(Ident),
 }
 
@@ -128,7 +138,7 @@ pub enum EnumRowsF<ERows> {
 /// write e.g. `forall a :: Type` or `forall a :: Rows`. But the kind of a variable is required for
 /// the typechecker. It is thus determined during parsing and stored as `VarKind` where type
 /// variables are introduced, that is, on forall quantifiers.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum VarKind {
     Type,
     EnumRows,
@@ -215,6 +225,10 @@ pub enum TypeF<Ty, RRows, ERows> {
     Num,
     /// A boolean.
     Bool,
+    /// A datetime. See [`crate::term::datetime`].
+    DateTime,
+    /// A duration. See [`crate::term::datetime`].
+    Duration,
     /// A string literal.
     Str,
     /// A symbol.
@@ -254,9 +268,11 @@ pub enum TypeF<Ty, RRows, ERows> {
 // `RecordRow` itself potentially contains occurrences of `Types` and `RecordRows`, which need to
 // be boxed. Hence, we don't need to additionally box `RecordRow`.
 
+/// Concrete, recursive definition for an enum row.
+pub type EnumRow = EnumRowF<Box<Types>>;
 /// Concrete, recursive definition for enum rows.
 #[derive(Clone, PartialEq, Debug)]
-pub struct EnumRows(pub EnumRowsF<Box<EnumRows>>);
+pub struct EnumRows(pub EnumRowsF<Box<Types>, Box<EnumRows>>);
 /// Concrete, recursive definition for a record row.
 pub type RecordRow = RecordRowF<Box<Types>>;
 #[derive(Clone, PartialEq, Debug)]
@@ -360,31 +376,41 @@ impl<Ty, RRows> RecordRowsF<Ty, RRows> {
     }
 }
 
-impl<ERows> EnumRowsF<ERows> {
-    /// Map functions over the tail of enum rows. The mutable state ( `S`) is threaded through the
-    /// calls to the mapped function. The function is fallible and may return an error `E`, which
-    /// causes `try_map_state` to return early with the same error.
+impl<Ty, ERows> EnumRowsF<Ty, ERows> {
+    /// Map functions over the children nodes of enum rows, when seen as a tree. The mutable state
+    /// ( `S`) is threaded through the calls to the mapped functions. Functions are fallible and may
+    /// return an error `E`, which causes `try_map_state` to return early with the same error.
     ///
     /// If we put aside the state and the error (see [EnumRowsF::map), this function makes
-    /// `EnumRowsF` a functor. As hinted by the type signature, this function just maps on
-    /// "one-level" of recursion, so to speak. Take the instantiated version `EnumRows`, and
-    /// enum rows of the form ``[| `foo, `bar, `baz |]``. Then, calling `try_map_state(f_erows,
-    /// state)` on these rows will map `f_erows` onto ``[| `bar, `baz |]``.
+    /// `EnumRowsF` a functor (of arity 2). As hinted by the type signature, this function just
+    /// maps on "one-level" of recursion, so to speak. Take the instantiated version `EnumRows`,
+    /// and enum rows of the form ``[| `foo T, `bar, `baz U |]``. Then, calling
+    /// `try_map_state(f_ty, f_erows, state)` on these rows will map `f_ty` onto `T` and `f_erows`
+    /// onto ``[| `bar, `baz U |]``.
     ///
-    /// Note that `f_erows` is just mapped once. Map isn't a recursive operation. It's however a
-    /// building block to express recursive operations: as an example, see [RecordRows::traverse].
-    pub fn try_map_state<ERowsO, FERows, S, E>(
+    /// Note that `f_ty` isn't mapped onto `U` recursively: map isn't a recursive operation. It's
+    /// however a building block to express recursive operations: as an example, see
+    /// [EnumRows::traverse].
+    pub fn try_map_state<TyO, ERowsO, FTy, FERows, S, E>(
         self,
+        mut f_ty: FTy,
         f_erows: FERows,
         state: &mut S,
-    ) -> Result<EnumRowsF<ERowsO>, E>
+    ) -> Result<EnumRowsF<TyO, ERowsO>, E>
     where
+        FTy: FnMut(Ty, &mut S) -> Result<TyO, E>,
         FERows: FnOnce(ERows, &mut S) -> Result<ERowsO, E>,
     {
         match self {
             EnumRowsF::Empty => Ok(EnumRowsF::Empty),
-            EnumRowsF::Extend { row, tail } => Ok(EnumRowsF::Extend {
-                row,
+            EnumRowsF::Extend {
+                row: EnumRowF { id, types },
+                tail,
+            } => Ok(EnumRowsF::Extend {
+                row: EnumRowF {
+                    id,
+                    types: types.map(|ty| f_ty(ty, state)).transpose()?,
+                },
                 tail: f_erows(tail, state)?,
             }),
             EnumRowsF::TailVar(id) => Ok(EnumRowsF::TailVar(id)),
@@ -392,35 +418,51 @@ impl<ERows> EnumRowsF<ERows> {
     }
 
     /// Variant of `try_map_state` without threaded state.
-    pub fn try_map<ERowsO, FERows, E>(self, mut f_erows: FERows) -> Result<EnumRowsF<ERowsO>, E>
+    pub fn try_map<TyO, ERowsO, FTy, FERows, E>(
+        self,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
+    ) -> Result<EnumRowsF<TyO, ERowsO>, E>
     where
+        FTy: FnMut(Ty) -> Result<TyO, E>,
         FERows: FnMut(ERows) -> Result<ERowsO, E>,
     {
+        let f_ty_lifted = |ty: Ty, _: &mut ()| -> Result<TyO, E> { f_ty(ty) };
         let f_erows_lifted = |erows: ERows, _: &mut ()| -> Result<ERowsO, E> { f_erows(erows) };
-        self.try_map_state(f_erows_lifted, &mut ())
+        self.try_map_state(f_ty_lifted, f_erows_lifted, &mut ())
     }
 
     /// Variant of `try_map_state` with infallible functions.
-    pub fn map_state<ERowsO, FERows, S>(
+    pub fn map_state<TyO, ERowsO, FTy, FERows, S>(
         self,
+        mut f_ty: FTy,
         mut f_erows: FERows,
         state: &mut S,
-    ) -> EnumRowsF<ERowsO>
+    ) -> EnumRowsF<TyO, ERowsO>
     where
+        FTy: FnMut(Ty, &mut S) -> TyO,
         FERows: FnMut(ERows, &mut S) -> ERowsO,
     {
+        let f_ty_lifted = |ty: Ty, state: &mut S| -> Result<TyO, ()> { Ok(f_ty(ty, state)) };
         let f_erows_lifted =
             |erows: ERows, state: &mut S| -> Result<ERowsO, ()> { Ok(f_erows(erows, state)) };
-        self.try_map_state(f_erows_lifted, state).unwrap()
+        self.try_map_state(f_ty_lifted, f_erows_lifted, state)
+            .unwrap()
     }
 
     /// Variant of `try_map_state` without threaded state and with infallible functions.
-    pub fn map<ERowsO, FERows>(self, mut f_erows: FERows) -> EnumRowsF<ERowsO>
+    pub fn map<TyO, ERowsO, FTy, FERows>(
+        self,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
+    ) -> EnumRowsF<TyO, ERowsO>
     where
+        FTy: FnMut(Ty) -> TyO,
         FERows: FnMut(ERows) -> ERowsO,
     {
+        let f_ty_lifted = |ty: Ty, _: &mut ()| -> TyO { f_ty(ty) };
         let f_erows_lifted = |erows: ERows, _: &mut ()| -> ERowsO { f_erows(erows) };
-        self.map_state(f_erows_lifted, &mut ())
+        self.map_state(f_ty_lifted, f_erows_lifted, &mut ())
     }
 }
 
@@ -458,6 +500,8 @@ impl<Ty, RRows, ERows> TypeF<Ty, RRows, ERows> {
         match self {
             TypeF::Dyn => Ok(TypeF::Dyn),
             TypeF::Num => Ok(TypeF::Num),
+            TypeF::DateTime => Ok(TypeF::DateTime),
+            TypeF::Duration => Ok(TypeF::Duration),
             TypeF::Bool => Ok(TypeF::Bool),
             TypeF::Str => Ok(TypeF::Str),
             TypeF::Sym => Ok(TypeF::Sym),
@@ -569,6 +613,97 @@ impl RecordRows {
     }
 }
 
+impl EnumRows {
+    fn traverse<FTy, S, E>(
+        self,
+        f: &FTy,
+        state: &mut S,
+        order: TraverseOrder,
+    ) -> Result<EnumRows, E>
+    where
+        FTy: Fn(Types, &mut S) -> Result<Types, E>,
+    {
+        let inner = self.0.try_map_state(
+            |ty, state| Ok(Box::new(ty.traverse(f, state, order)?)),
+            |erows, state| Ok(Box::new(erows.traverse(f, state, order)?)),
+            state,
+        )?;
+
+        Ok(EnumRows(inner))
+    }
+}
+
+impl RecordRows {
+    /// Simultaneously substitute type variables for types in record rows. See
+    /// [`Types::subst_vars`].
+    pub fn subst_vars(
+        self,
+        substs: &HashMap<Ident, Types>,
+    ) -> Result<RecordRows, RowTailSubstError> {
+        match self.0 {
+            RecordRowsF::TailVar(id) if substs.contains_key(&id) => match &substs[&id] {
+                Types(TypeF::Record(rrows)) => Ok(rrows.clone()),
+                Types(TypeF::Var(var)) => Ok(RecordRows(RecordRowsF::TailVar(*var))),
+                Types(TypeF::Dyn) => Ok(RecordRows(RecordRowsF::TailDyn)),
+                _ => Err(RowTailSubstError(id)),
+            },
+            rrows => Ok(RecordRows(rrows.try_map_state(
+                |ty, _| Ok(Box::new(ty.subst_vars(substs)?)),
+                |rrows, _| Ok(Box::new(rrows.subst_vars(substs)?)),
+                &mut (),
+            )?)),
+        }
+    }
+}
+
+impl EnumRows {
+    /// Simultaneously substitute type variables for types in enum rows. See
+    /// [`Types::subst_vars`].
+    pub fn subst_vars(self, substs: &HashMap<Ident, Types>) -> Result<EnumRows, RowTailSubstError> {
+        match self.0 {
+            EnumRowsF::TailVar(id) if substs.contains_key(&id) => match &substs[&id] {
+                Types(TypeF::Enum(erows)) => Ok(erows.clone()),
+                Types(TypeF::Var(var)) => Ok(EnumRows(EnumRowsF::TailVar(*var))),
+                _ => Err(RowTailSubstError(id)),
+            },
+            erows => Ok(EnumRows(erows.try_map_state(
+                |ty, _| Ok(Box::new(ty.subst_vars(substs)?)),
+                |erows, _| Ok(Box::new(erows.subst_vars(substs)?)),
+                &mut (),
+            )?)),
+        }
+    }
+}
+
+/// A type alias, introduced by a type definition `type Name param1 .. paramn = body in term`.
+///
+/// Type aliases are expanded by the parser at each of their uses inside `term`, where an alias
+/// must be applied to exactly `n` types: `Name ty1 .. tyn` is replaced with `body`, where each
+/// `parami` is replaced with `tyi`. The rest of the pipeline, such as the typechecker or the
+/// contract generation, thus only ever sees the expanded types.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeAlias {
+    pub name: Ident,
+    pub params: Vec<Ident>,
+    pub body: Types,
+}
+
+impl TypeAlias {
+    /// Expand an application of the alias to arguments. The number of arguments must be equal to
+    /// the number of parameters.
+    pub fn instantiate(&self, args: Vec<Types>) -> Result<Types, RowTailSubstError> {
+        debug_assert_eq!(self.params.len(), args.len());
+
+        let substs = self.params.iter().copied().zip(args).collect();
+        self.body.clone().subst_vars(&substs)
+    }
+}
+
+/// A type variable in the tail of record rows or enum rows, such as `r` in `{foo : Num ; r}`, was
+/// substituted for a type which isn't a sequence of rows of the same kind.
+#[derive(Clone, Debug)]
+pub struct RowTailSubstError(pub Ident);
+
 #[derive(Clone, Debug)]
 pub struct UnboundTypeVariableError(pub Ident);
 
@@ -631,17 +766,18 @@ impl<'a> Iterator for RecordRowsIterator<'a, Types, RecordRows> {
     }
 }
 
-pub struct EnumRowsIterator<'a, ERows> {
+pub struct EnumRowsIterator<'a, Ty, ERows> {
     pub(crate) erows: Option<&'a ERows>,
+    pub(crate) ty: std::marker::PhantomData<Ty>,
 }
 
-pub enum EnumRowsIteratorItem<'a> {
+pub enum EnumRowsIteratorItem<'a, Ty> {
     TailVar(&'a Ident),
-    Row(&'a EnumRowF),
+    Row(EnumRowF<&'a Ty>),
 }
 
-impl<'a> Iterator for EnumRowsIterator<'a, EnumRows> {
-    type Item = EnumRowsIteratorItem<'a>;
+impl<'a> Iterator for EnumRowsIterator<'a, Types, EnumRows> {
+    type Item = EnumRowsIteratorItem<'a, Types>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.erows.and_then(|next| match next.0 {
@@ -655,7 +791,10 @@ impl<'a> Iterator for EnumRowsIterator<'a, EnumRows> {
             }
             EnumRowsF::Extend { ref row, ref tail } => {
                 self.erows = Some(tail);
-                Some(EnumRowsIteratorItem::Row(row))
+                Some(EnumRowsIteratorItem::Row(EnumRowF {
+                    id: row.id,
+                    types: row.types.as_deref(),
+                }))
             }
         })
     }
@@ -678,7 +817,12 @@ fn get_var_contract(
 }
 
 impl EnumRows {
-    fn subcontract(&self) -> Result<RichTerm, UnboundTypeVariableError> {
+    fn subcontract(
+        &self,
+        h: HashMap<Ident, (RichTerm, RichTerm)>,
+        pol: bool,
+        sy: &mut i32,
+    ) -> Result<RichTerm, UnboundTypeVariableError> {
         use crate::stdlib::contract;
 
         let mut cases = HashMap::new();
@@ -688,8 +832,29 @@ impl EnumRows {
 
         for row in self.iter() {
             match row {
-                EnumRowsIteratorItem::Row(id) => {
-                    cases.insert(*id, mk_term::var(value_arg));
+                EnumRowsIteratorItem::Row(EnumRowF { id, types: None }) => {
+                    cases.insert(
+                        id,
+                        mk_app!(
+                            contract::enum_tag(),
+                            mk_term::var(label_arg),
+                            mk_term::var(value_arg)
+                        ),
+                    );
+                }
+                EnumRowsIteratorItem::Row(EnumRowF {
+                    id,
+                    types: Some(ty),
+                }) => {
+                    cases.insert(
+                        id,
+                        mk_app!(
+                            contract::enum_variant(),
+                            ty.subcontract(h.clone(), pol, sy)?,
+                            mk_term::var(label_arg),
+                            mk_term::var(value_arg)
+                        ),
+                    );
                 }
                 EnumRowsIteratorItem::TailVar(_) => {
                     has_tail = true;
@@ -700,40 +865,44 @@ impl EnumRows {
 
         // If the enum type has a tail, the tail must be a universally quantified variable,
         // and this means that the tag can be anything.
-        let case_body = if has_tail {
+        let default = if has_tail {
             mk_term::var(value_arg)
-        }
-        // Otherwise, we build a match with all the tags as cases, which just returns the
-        // original argument, and a default case that blames.
+        } else {
+            mk_app!(contract::enum_fail(), mk_term::var(label_arg))
+        };
+
+        // We build a match on the tag of the value with all the tags of the type as cases, which
+        // check that the value is applied to an argument if and only if the row has a type, and
+        // apply the contract of the argument. The default case blames, or returns the original
+        // value if the enum type is open.
         //
-        // For example, for an enum type [| `foo, `bar, `baz |], the `case` function looks
-        // like:
+        // For example, for an enum type [| `foo, `bar Num |], the `case` function looks like:
         //
         // ```
         // fun l x =>
         //   match {
-        //     `foo => x,
-        //     `bar => x,
-        //     `baz => x,
+        //     `foo => $enum_tag l x,
+        //     `bar => $enum_variant $num l x,
         //     _ => $enum_fail l
-        //   } x
+        //   } (%enum_get_tag% x)
         // ```
-        else {
-            mk_app!(
-                Term::Match {
-                    cases,
-                    default: Some(mk_app!(contract::enum_fail(), mk_term::var(label_arg))),
-                },
-                mk_term::var(value_arg)
-            )
-        };
+        let case_body = mk_app!(
+            Term::Match {
+                cases,
+                default: Some(default),
+            },
+            mk_term::op1(UnaryOp::EnumGetTag(), mk_term::var(value_arg))
+        );
         let case = mk_fun!(label_arg, value_arg, case_body);
 
         Ok(mk_app!(contract::enums(), case))
     }
 
-    pub fn iter(&self) -> EnumRowsIterator<EnumRows> {
-        EnumRowsIterator { erows: Some(self) }
+    pub fn iter(&self) -> EnumRowsIterator<Types, EnumRows> {
+        EnumRowsIterator {
+            erows: Some(self),
+            ty: std::marker::PhantomData,
+        }
     }
 }
 
@@ -820,6 +989,101 @@ impl Types {
         self.subcontract(HashMap::new(), true, &mut sy)
     }
 
+    /// Simultaneously substitute type variables for types. Substituted variables which are bound
+    /// by an inner `forall` are left untouched in the body of the `forall`, and a variable bound
+    /// by a `forall` is renamed if it occurs in a substituted type, so that it doesn't capture it.
+    ///
+    /// Variables in the tail of rows are substituted as well, in which case the corresponding type
+    /// must be a record type (respectively an enum type), whose rows are spliced in place of the
+    /// tail, a type variable, or `Dyn` for record rows. Otherwise, a [`RowTailSubstError`] is
+    /// returned.
+    pub fn subst_vars(self, substs: &HashMap<Ident, Types>) -> Result<Types, RowTailSubstError> {
+        match self.0 {
+            TypeF::Var(id) => Ok(substs.get(&id).cloned().unwrap_or(Types(TypeF::Var(id)))),
+            TypeF::Forall {
+                var,
+                var_kind,
+                body,
+            } => {
+                let mut substs = substs.clone();
+                substs.remove(&var);
+
+                let captured: HashSet<Ident> =
+                    substs.values().flat_map(Types::type_var_names).collect();
+                let (var, body) = if captured.contains(&var) {
+                    let in_body = body.type_var_names();
+                    let mut fresh = var;
+                    while captured.contains(&fresh) || in_body.contains(&fresh) {
+                        fresh = Ident::new_with_pos(format!("{}'", fresh), var.pos);
+                    }
+
+                    let renaming = HashMap::from([(var, Types(TypeF::Var(fresh)))]);
+                    (fresh, body.subst_vars(&renaming)?)
+                } else {
+                    (var, *body)
+                };
+
+                Ok(Types(TypeF::Forall {
+                    var,
+                    var_kind,
+                    body: Box::new(body.subst_vars(&substs)?),
+                }))
+            }
+            ty => Ok(Types(ty.try_map_state(
+                |ty, _| Ok(Box::new(ty.subst_vars(substs)?)),
+                |rrows, _| rrows.subst_vars(substs),
+                |erows, _| erows.subst_vars(substs),
+                &mut (),
+            )?)),
+        }
+    }
+
+    /// Return the names of all the type variables occurring in a type, free or bound, including
+    /// in the tail of rows.
+    fn type_var_names(&self) -> HashSet<Ident> {
+        fn tail_vars(ty: &Types) -> Option<Ident> {
+            match &ty.0 {
+                TypeF::Record(rrows) => {
+                    let mut rrows = rrows;
+                    loop {
+                        match &rrows.0 {
+                            RecordRowsF::Extend { tail, .. } => rrows = tail,
+                            RecordRowsF::TailVar(id) => break Some(*id),
+                            _ => break None,
+                        }
+                    }
+                }
+                TypeF::Enum(erows) => {
+                    let mut erows = erows;
+                    loop {
+                        match &erows.0 {
+                            EnumRowsF::Extend { tail, .. } => erows = tail,
+                            EnumRowsF::TailVar(id) => break Some(*id),
+                            EnumRowsF::Empty => break None,
+                        }
+                    }
+                }
+                _ => None,
+            }
+        }
+
+        let mut names = HashSet::new();
+        let _: Result<Types, ()> = self.clone().traverse(
+            &|ty: Types, names: &mut HashSet<Ident>| {
+                match &ty.0 {
+                    TypeF::Var(id) | TypeF::Forall { var: id, .. } => {
+                        names.insert(*id);
+                    }
+                    _ => names.extend(tail_vars(&ty)),
+                }
+                Ok(ty)
+            },
+            &mut names,
+            TraverseOrder::TopDown,
+        );
+        names
+    }
+
     /// Returns true if this type is a function type, false otherwise.
     pub fn is_function_type(&self) -> bool {
         match self {
@@ -849,6 +1113,8 @@ impl Types {
         let ctr = match self.0 {
             TypeF::Dyn => contract::dynamic(),
             TypeF::Num => contract::num(),
+            TypeF::DateTime => contract::datetime(),
+            TypeF::Duration => contract::duration(),
             TypeF::Bool => contract::bool(),
             TypeF::Str => contract::string(),
             //TODO: optimization: have a specialized contract for `Array Dyn`, to avoid mapping an
@@ -881,7 +1147,7 @@ impl Types {
                 *sy += 1;
                 body.subcontract(h, pol, sy)?
             }
-            TypeF::Enum(ref erows) => erows.subcontract()?,
+            TypeF::Enum(ref erows) => erows.subcontract(h, pol, sy)?,
             TypeF::Record(ref rrows) => rrows.subcontract(h, pol, sy)?,
             TypeF::Dict(ref ty) => {
                 mk_app!(contract::dyn_record(), ty.subcontract(h, pol, sy)?)
@@ -923,7 +1189,7 @@ impl Types {
                 let inner = f(self, state)?.0.try_map_state(
                     |ty, state| Ok(Box::new(ty.traverse(f, state, order)?)),
                     |rrows, state| rrows.traverse(f, state, order),
-                    |erows, _| Ok(erows),
+                    |erows, state| erows.traverse(f, state, order),
                     state,
                 )?;
 
@@ -933,7 +1199,7 @@ impl Types {
                 let traversed_depth_first = self.0.try_map_state(
                     |ty, state| Ok(Box::new(ty.traverse(f, state, order)?)),
                     |rrows, state| rrows.traverse(f, state, order),
-                    |erows, _| Ok(erows),
+                    |erows, state| erows.traverse(f, state, order),
                     state,
                 )?;
 
@@ -965,7 +1231,15 @@ impl Display for EnumRows {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
             EnumRowsF::Extend { ref row, ref tail } => {
-                write!(f, "`{}", row)?;
+                write!(f, "`{}", row.id)?;
+
+                if let Some(ty) = &row.types {
+                    if ty.fmt_is_atom() {
+                        write!(f, " {}", ty)?;
+                    } else {
+                        write!(f, " ({})", ty)?;
+                    }
+                }
 
                 match tail.0 {
                     EnumRowsF::Extend { .. } => write!(f, ", {}", tail),
@@ -983,6 +1257,8 @@ impl Display for Types {
         match &self.0 {
             TypeF::Dyn => write!(f, "Dyn"),
             TypeF::Num => write!(f, "Num"),
+            TypeF::DateTime => write!(f, "DateTime"),
+            TypeF::Duration => write!(f, "Duration"),
             TypeF::Bool => write!(f, "Bool"),
             TypeF::Str => write!(f, "Str"),
             TypeF::Array(ty) => {
@@ -1074,6 +1350,8 @@ mod test {
 
         assert_format_eq("[|`a, `b, `c, `d|]");
         assert_format_eq("forall r. [|`tag1, `tag2, `tag3 ; r|]");
+        assert_format_eq("[|`Ok Num, `Error {msg: Str}, `None|]");
+        assert_format_eq("forall r. [|`Some (Array Num), `Pair [|`a, `b|] ; r|]");
 
         assert_format_eq("Array Num");
         assert_format_eq("Array (Array Num)");
@@ -11,6 +11,18 @@ fn div_by_zero() {
     );
 }
 
+#[test]
+fn large_exponents() {
+    assert_matches!(
+        eval("num.pow 3 100000000"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("pow")
+    );
+    assert_matches!(
+        eval("string.to_num \"1e999999999\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+}
+
 #[test]
 fn comparisons() {
     assert_matches!(
@@ -62,3 +74,159 @@ fn string_chunks() {
         Err(Error::EvalError(EvalError::TypeError(..)))
     );
 }
+
+#[test]
+fn datetimes() {
+    assert_matches!(
+        eval("datetime.parse \"2022-11-03\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("duration.parse \"P1M\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("datetime.add (duration.days 1) (datetime.parse \"9999-12-31T12:00:00Z\")"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("(\"2022-11-03T10:45:00Z\" | DateTime)"),
+        Err(Error::EvalError(EvalError::BlameError(..)))
+    );
+}
+
+#[test]
+fn decoding() {
+    assert_matches!(
+        eval("string.decode `Base64 \"a$==\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.decode `Hex \"ff\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.decode `Url \"100%\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+}
+
+#[test]
+fn formatting() {
+    assert_matches!(
+        eval("string.format \"{} {}\" [1]"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.format \"{name}\" [1]"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.format \"{:x}\" [1.5]"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.format \"{}\" [[1]]"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.pad_left 3 \"ab\" \"x\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.pad_left (-1) \" \" \"x\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("string.pad_left 99999999999 \" \" \"x\""),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+}
+
+#[test]
+fn array_ops() {
+    assert_matches!(
+        eval("array.slice 1 4 [1, 2, 3]"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("array.slice 2 1 [1, 2, 3]"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("array.range 0 1.5"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("%array_filter% [1, 2] (fun x => 1)"),
+        Err(Error::EvalError(EvalError::TypeError(..)))
+    );
+    assert_matches!(
+        eval("%array_sort% [1, 2] (fun x y => `Foo)"),
+        Err(Error::EvalError(EvalError::Other(..)))
+    );
+    assert_matches!(
+        eval("%array_flatten% [[1], 2]"),
+        Err(Error::EvalError(EvalError::TypeError(..)))
+    );
+}
+
+#[test]
+fn compare() {
+    assert_matches!(
+        eval("builtin.compare 1 \"a\""),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("compare")
+    );
+    assert_matches!(
+        eval("builtin.compare [1, 2] [1, \"a\"]"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("compare")
+    );
+    assert_matches!(
+        eval("builtin.compare {f = fun x => x} {f = fun x => x}"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("compare")
+    );
+    assert_matches!(
+        eval("array.min_by (fun x => x) []"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg == "min_by: empty array"
+    );
+    assert_matches!(
+        eval("array.max_by (fun x => x) []"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg == "max_by: empty array"
+    );
+    assert_matches!(
+        eval("array.sort_on (fun x => x) [1, \"a\"]"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("compare")
+    );
+}
+
+#[test]
+fn hash_value() {
+    assert_matches!(
+        eval("builtin.hash_value `Sha256 {f = fun x => x}"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("hash_value")
+    );
+    assert_matches!(
+        eval("%hash_value% `Crc32 1"),
+        Err(Error::EvalError(EvalError::TypeError(..)))
+    );
+}
+
+#[test]
+fn non_exhaustive_match() {
+    assert_matches!(
+        eval("match { 1 => 2, {a} => a } 5"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+    assert_matches!(
+        eval("match { [x] if x > 0 => x } [0]"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+    assert_matches!(
+        eval("match { `foo => 1, `bar => 2 } `baz"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+    assert_matches!(
+        eval("match { `Ok 1 => 1, `Err x => x } (`Ok 2)"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+}
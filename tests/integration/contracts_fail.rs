@@ -60,6 +60,14 @@ fn enum_simple() {
     assert_raise_blame!("`foo | [| |]");
 }
 
+#[test]
+fn enum_variants() {
+    assert_raise_blame!("let x | [| `Ok Num, `Err Str |] = `Ok \"a\" in %deep_seq% x x");
+    assert_raise_blame!("`Ok | [| `Ok Num |]");
+    assert_raise_blame!("`Ok 1 | [| `Ok |]");
+    assert_raise_blame!("`Other 1 | [| `Ok Num |]");
+}
+
 #[test]
 fn metavalue_contract_default_fail() {
     assert_raise_blame!("{val | default | Num = true}.val");
@@ -0,0 +1,105 @@
+use nickel_lang::debugger::{Breakpoint, Debugger};
+use nickel_lang::term::{number::Number, Term};
+use nickel_lang_utilities::TestProgram;
+use std::cell::RefCell;
+use std::io::{BufReader, Cursor, Write};
+use std::rc::Rc;
+
+/// A writer whose content can still be read once it has been handed over to the debugger.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn debug(source: &str, breakpoints: &[&str], commands: &str) -> (Term, String) {
+    let mut prog = TestProgram::new_from_source(BufReader::new(source.as_bytes()), "debugged")
+        .expect("program shouldn't fail");
+    let output = SharedBuffer::default();
+    let debugger = Debugger::new(
+        Box::new(Cursor::new(String::from(commands))),
+        Box::new(output.clone()),
+        breakpoints.iter().map(|bp| bp.parse().unwrap()).collect(),
+    );
+    prog.set_eval_hooks(Some(Box::new(debugger)));
+
+    let result = prog.eval_full().map(Term::from).unwrap();
+    let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+    (result, output)
+}
+
+#[test]
+fn parse_breakpoints() {
+    assert_eq!(
+        "main.ncl:3".parse::<Breakpoint>(),
+        Ok(Breakpoint::Position {
+            file: String::from("main.ncl"),
+            line: 3
+        })
+    );
+    assert_eq!(
+        "a.b".parse::<Breakpoint>(),
+        Ok(Breakpoint::Field(vec!["a".into(), "b".into()]))
+    );
+    assert!("main.ncl:x".parse::<Breakpoint>().is_err());
+    assert!("a..b".parse::<Breakpoint>().is_err());
+}
+
+#[test]
+fn stops_at_first_step() {
+    let (result, output) = debug("1 + 1", &[], "help\nc\n");
+    assert_eq!(result, Term::Num(Number::from(2)));
+    assert!(output.starts_with("step 1 at"));
+    assert!(output.contains("Available commands"));
+}
+
+#[test]
+fn field_breakpoint() {
+    let source = "let f = fun x => x + 1 in\n{\n  a = f 2,\n  b = { c = a * 3 },\n}";
+    let (result, output) = debug(source, &["b.c"], "env\nbt\nc\n");
+    assert!(matches!(result, Term::Record(..)));
+    assert!(output.contains("breakpoint #0 hit: b.c"));
+    assert!(output.contains("debugged:4:13"));
+    assert!(output.contains("f ["));
+    assert!(output.contains("field `c`"));
+}
+
+#[test]
+fn position_breakpoint_and_thunks() {
+    let source = "let x = 1 + 1 in\nlet y = x * 2 in\ny + 1";
+    let (result, output) = debug(source, &["debugged:3"], "p x\np y\nq\n");
+    assert_eq!(result, Term::Num(Number::from(5)));
+    assert!(output.contains("x [suspended]"));
+    assert!(output.contains("y [suspended]"));
+}
+
+#[test]
+fn breakpoints_match_whole_components() {
+    let source = "let x = 1 + 1 in\n{ b = { c = x * 3 } }";
+
+    // `ebugged` is only a suffix of the name of the source `debugged`
+    let (_, output) = debug(source, &["ebugged:2"], "q\n");
+    assert!(!output.contains("breakpoint #0 hit"));
+    let (_, output) = debug(source, &["./debugged:2"], "q\n");
+    assert!(output.contains("breakpoint #0 hit"));
+
+    // `c` is only a suffix of the path of the field `b.c`
+    let (_, output) = debug(source, &["c"], "q\n");
+    assert!(!output.contains("breakpoint #0 hit"));
+    let (_, output) = debug(source, &["b.c"], "q\n");
+    assert!(output.contains("breakpoint #0 hit: b.c"));
+}
+
+#[test]
+fn quit_on_end_of_input() {
+    let (result, output) = debug("[1, 2, 3]", &[], "s 2\n");
+    assert!(matches!(result, Term::Array(..)));
+    assert!(output.contains("step 3 at"));
+}
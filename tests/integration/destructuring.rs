@@ -88,3 +88,74 @@ fn typecontract_fail() {
 fn fun() {
     assert_eq!(eval_file("destructuring/fun.ncl"), Ok(Term::Bool(true)));
 }
+
+#[test]
+fn array() {
+    assert_eq!(eval_file("destructuring/array.ncl"), Ok(Term::Bool(true)));
+}
+
+#[test]
+fn array_rest() {
+    assert_eq!(
+        eval_file("destructuring/array_rest.ncl"),
+        Ok(Term::Bool(true))
+    );
+}
+
+#[test]
+fn array_open() {
+    assert_eq!(
+        eval_file("destructuring/array_open.ncl"),
+        Ok(Term::Bool(true))
+    );
+}
+
+#[test]
+fn array_nested() {
+    assert_eq!(
+        eval_file("destructuring/array_nested.ncl"),
+        Ok(Term::Bool(true))
+    );
+}
+
+#[test]
+fn array_fun() {
+    assert_eq!(
+        eval_file("destructuring/array_fun.ncl"),
+        Ok(Term::Bool(true))
+    );
+}
+
+#[test]
+fn array_typed() {
+    assert_eq!(
+        eval_file("destructuring/array_typed.ncl"),
+        Ok(Term::Bool(true))
+    );
+}
+
+#[test]
+fn array_length_fail() {
+    assert_matches!(
+        eval_file("destructuring/array_length_fail.ncl"),
+        Err(Error::EvalError(EvalError::BlameError(label, ..)))
+            if label.tag == "expected an array of 2 elements, got 3"
+    );
+}
+
+#[test]
+fn array_open_fail() {
+    assert_matches!(
+        eval_file("destructuring/array_open_fail.ncl"),
+        Err(Error::EvalError(EvalError::BlameError(label, ..)))
+            if label.tag == "expected an array of at least 2 elements, got 1"
+    );
+}
+
+#[test]
+fn array_not_array_fail() {
+    assert_matches!(
+        eval_file("destructuring/array_not_array_fail.ncl"),
+        Err(Error::EvalError(EvalError::BlameError(..)))
+    );
+}
@@ -145,6 +145,18 @@ fn destruct_fun() {
     ));
 }
 
+#[test]
+fn destruct_array() {
+    assert!(check_stat_vars(
+        "{
+          a = let [b, {c}, ..d] = null in b + c + d + a,
+          b = (fun [a, e@[f, ..]] => a + e + f + c) b,
+          c = [],
+        }",
+        HashMap::from([("a", vec!["a"]), ("b", vec!["b", "c"]), ("c", vec![]),])
+    ));
+}
+
 #[test]
 fn nested_records() {
     assert!(check_stat_vars(
@@ -182,3 +194,15 @@ fn recursive_let() {
         ])
     ));
 }
+
+#[test]
+fn let_block() {
+    assert!(check_stat_vars(
+        "{
+          a = let b = a, c = b in b + c,
+          b = let rec a = b + c, c = a in a + c,
+          c = let rec x = y, y = x in x
+        }",
+        HashMap::from([("a", vec!["a", "b"]), ("b", vec!["b"]), ("c", vec![])])
+    ));
+}
@@ -1,6 +1,7 @@
 use assert_matches::assert_matches;
+use nickel_lang::cache::ImportPolicy;
 use nickel_lang::error::{Error, EvalError, ImportError, TypecheckError};
-use nickel_lang::term::Term;
+use nickel_lang::term::{number::Number, Term};
 use nickel_lang_utilities::TestProgram;
 use std::io::BufReader;
 use std::path::PathBuf;
@@ -21,7 +22,7 @@ fn nested() {
         "should_be = 3",
     )
     .unwrap();
-    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(3.)));
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(3))));
 }
 
 #[test]
@@ -31,7 +32,7 @@ fn root_path() {
         "should_be = 44",
     )
     .unwrap();
-    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(44.)));
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(44))));
 }
 
 #[test]
@@ -41,7 +42,7 @@ fn multi_imports() {
         "should_be = 5",
     )
     .unwrap();
-    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(5.)));
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(5))));
 }
 
 #[test]
@@ -163,3 +164,98 @@ fn import_unexpected_token_buried_fail() {
         Err(Error::ImportError(ImportError::ParseErrors(..)))
     );
 }
+
+fn imports_dir() -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/integration/imports");
+    path
+}
+
+#[test]
+fn import_policy_roots() {
+    let mut prog = TestProgram::new_from_file(imports_dir().join("root_path/import.ncl")).unwrap();
+    prog.set_import_policy(ImportPolicy {
+        roots: vec![imports_dir()],
+        ..Default::default()
+    });
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(44))));
+
+    let mut prog = TestProgram::new_from_file(imports_dir().join("root_path/import.ncl")).unwrap();
+    prog.set_import_policy(ImportPolicy {
+        roots: vec![imports_dir().join("root_path")],
+        ..Default::default()
+    });
+    assert_matches!(
+        prog.eval(),
+        Err(Error::ImportError(ImportError::Denied(..)))
+    );
+}
+
+#[test]
+fn import_policy_deny_absolute() {
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(mk_import("two.ncl").as_bytes()),
+        "should_fail",
+    )
+    .unwrap();
+    prog.set_import_policy(ImportPolicy {
+        deny_absolute: true,
+        ..Default::default()
+    });
+    assert_matches!(
+        prog.eval(),
+        Err(Error::ImportError(ImportError::Denied(..)))
+    );
+}
+
+#[test]
+fn import_policy_nickel_only() {
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(format!("({}).foo", mk_import("data.json")).as_bytes()),
+        "should_fail",
+    )
+    .unwrap();
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(1))));
+
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(format!("({}).foo", mk_import("data.json")).as_bytes()),
+        "should_fail",
+    )
+    .unwrap();
+    prog.set_import_policy(ImportPolicy {
+        nickel_only: true,
+        ..Default::default()
+    });
+    assert_matches!(
+        prog.eval(),
+        Err(Error::ImportError(ImportError::Denied(..)))
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn import_policy_symlink_escape() {
+    let root = std::env::temp_dir().join(format!("nickel-import-policy-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let link = root.join("link.ncl");
+    let _ = std::fs::remove_file(&link);
+    std::os::unix::fs::symlink(imports_dir().join("two.ncl"), &link).unwrap();
+    std::fs::write(root.join("main.ncl"), "import \"link.ncl\"").unwrap();
+
+    let mut prog = TestProgram::new_from_file(root.join("main.ncl")).unwrap();
+    prog.set_import_policy(ImportPolicy {
+        roots: vec![root.clone()],
+        ..Default::default()
+    });
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(2))));
+
+    let mut prog = TestProgram::new_from_file(root.join("main.ncl")).unwrap();
+    prog.set_import_policy(ImportPolicy {
+        roots: vec![root.clone()],
+        deny_symlink_escape: true,
+        ..Default::default()
+    });
+    let result = prog.eval();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_matches!(result, Err(Error::ImportError(ImportError::Denied(..))));
+}
@@ -1,5 +1,6 @@
 mod basics_fail;
 mod contracts_fail;
+mod debugger;
 mod destructuring;
 mod eq_fail;
 mod examples;
@@ -10,9 +11,12 @@ mod merge_fail;
 mod parse_fail;
 mod pass;
 mod pretty;
+mod profiler;
 mod query;
 mod records_fail;
+mod stats;
 mod stdlib_arrays_fail;
 mod stdlib_typecheck;
+mod trace;
 mod typecheck_fail;
 mod unbound_type_variables;
@@ -20,3 +20,60 @@ fn unexpected_token_buried() {
         Err(Error::ParseErrors(..))
     );
 }
+
+#[test]
+fn or_pattern_different_bindings() {
+    assert_matches!(
+        eval("match { [x] | [x, y] => x } [1]"),
+        Err(Error::ParseErrors(..))
+    );
+}
+
+#[test]
+fn let_block_duplicate_bindings() {
+    assert_matches!(eval("let a = 1, a = 2 in a"), Err(Error::ParseErrors(..)));
+    assert_matches!(
+        eval("let rec a = 1, {a} = {a = 2} in a"),
+        Err(Error::ParseErrors(..))
+    );
+}
+
+#[test]
+fn recursive_let_block_pattern() {
+    assert_matches!(
+        eval("let rec {a} = {a = 1}, b = 2 in b"),
+        Err(Error::ParseErrors(..))
+    );
+}
+
+#[test]
+fn dynamic_access_with_default() {
+    assert_matches!(
+        eval("let x = \"a\" in {a = 1}.\"%{x}\" or 0"),
+        Err(Error::ParseErrors(..))
+    );
+}
+
+#[test]
+fn type_alias_arity_mismatch() {
+    assert_matches!(
+        eval("type Pair a b = {fst: a, snd: b} in {fst = 1, snd = 2} | Pair Num"),
+        Err(Error::ParseErrors(..))
+    );
+    assert_matches!(
+        eval("type Port = Num in 80 | Port Num"),
+        Err(Error::ParseErrors(..))
+    );
+}
+
+#[test]
+fn type_alias_invalid_row_tail() {
+    assert_matches!(
+        eval("type R r = {a: Num ; r} in {a = 1} | R Num"),
+        Err(Error::ParseErrors(..))
+    );
+    assert_matches!(
+        eval("type E r = [| `a ; r |] in `a | E {b: Num}"),
+        Err(Error::ParseErrors(..))
+    );
+}
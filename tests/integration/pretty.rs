@@ -1,3 +1,5 @@
+use codespan::Files;
+use nickel_lang::parser::{grammar, lexer};
 use nickel_lang::pretty::*;
 use nickel_lang::term::{RichTerm, StrChunk, Term};
 use nickel_lang_utilities::parse;
@@ -138,6 +140,36 @@ fn overriding() {
     check_file("overriding.ncl");
 }
 
+#[test]
+fn numbers() {
+    check_file("numbers.ncl");
+
+    // Literals are printed in their original form when the sources are available, and in decimal
+    // notation otherwise.
+    for (literal, decimal) in [
+        ("0xFF00", "65280"),
+        ("0o644", "420"),
+        ("0b101", "5"),
+        ("-0x10", "-16"),
+        ("1_000_000", "1000000"),
+        ("0.50", "0.5"),
+    ] {
+        let mut files = Files::new();
+        let file_id = files.add("<test>", String::from(literal));
+        let rt = grammar::TermParser::new()
+            .parse_term(file_id, lexer::Lexer::new(literal))
+            .unwrap();
+
+        let allocator = SourceAllocator::new(&files);
+        let mut ret = Vec::new();
+        let doc: DocBuilder<_, ()> = rt.clone().pretty(&allocator);
+        doc.render(80, &mut ret).unwrap();
+
+        assert_eq!(String::from_utf8(ret).unwrap().replace(' ', ""), literal);
+        assert_eq!(pretty(&rt).replace(' ', ""), decimal);
+    }
+}
+
 #[test]
 fn str_vs_strchunks() {
     assert_eq!(
@@ -0,0 +1,106 @@
+use nickel_lang::profiler::{Profile, Profiler};
+use nickel_lang_utilities::TestProgram;
+use std::cell::RefCell;
+use std::io::BufReader;
+use std::rc::Rc;
+
+fn profile(source: &str) -> Profile {
+    let mut prog = TestProgram::new_from_source(BufReader::new(source.as_bytes()), "profiled")
+        .expect("program shouldn't fail");
+    let profile = Rc::new(RefCell::new(Profile::default()));
+    prog.set_eval_hooks(Some(Box::new(Profiler::new(profile.clone()))));
+    prog.eval_full().unwrap();
+    prog.set_eval_hooks(None);
+
+    let result = profile.borrow().clone();
+    result
+}
+
+#[test]
+fn cost_per_field() {
+    let profile = profile(
+        "{
+          cheap = 1,
+          expensive = array.foldl (fun acc x => acc + x) 0 (array.generate (fun x => x) 50),
+        }",
+    );
+
+    let cheap = &profile.fields["cheap"];
+    let expensive = &profile.fields["expensive"];
+    assert!(expensive.reductions > cheap.reductions);
+    assert_eq!(
+        profile.reductions,
+        profile
+            .fields
+            .values()
+            .map(|cost| cost.reductions)
+            .sum::<u64>()
+    );
+    assert_eq!(profile.sorted_fields()[0].0, "expensive");
+}
+
+#[test]
+fn contract_applications() {
+    let profile = profile(
+        "let Port = fun l x => if builtin.is_num x && x > 0 then x else contract.blame l in
+        {
+          port | Port = 80,
+          ports | Array Port = [1, 2, 3],
+        }",
+    );
+
+    let port = profile
+        .contracts
+        .values()
+        .find(|cost| cost.name == "Port")
+        .expect("the Port contract should be profiled");
+    assert_eq!(port.applications, 4);
+    assert!(port.reductions > 0);
+    assert!(port.location.starts_with("profiled:1:"));
+    assert!(profile.fields["port"].contract_applications >= 1);
+}
+
+#[test]
+fn cache_hits() {
+    let profile = profile("let x = 1 + 1 in {a = x, b = x, c = x}");
+
+    assert!(profile.cache_hits >= 2);
+    assert!(profile.cache_misses >= 1);
+}
+
+#[test]
+fn folded_stacks() {
+    let profile = profile("let Pos = fun l x => x in {a = {b | Pos = 1 + 1}}");
+    let mut output = Vec::new();
+    profile.write_folded(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.lines().any(|line| line.starts_with("a;b ")));
+    assert!(output
+        .lines()
+        .any(|line| line.starts_with("a;b;contract Pos ")));
+    assert_eq!(
+        output
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+            .sum::<u64>(),
+        profile.reductions
+    );
+}
+
+#[test]
+fn last_step_timed() {
+    let mut prog = TestProgram::new_from_source(BufReader::new("1 + 1".as_bytes()), "profiled")
+        .expect("program shouldn't fail");
+    let profile = Rc::new(RefCell::new(Profile::default()));
+    prog.set_eval_hooks(Some(Box::new(Profiler::new(profile.clone()))));
+    prog.eval_full().unwrap();
+
+    let before = profile.borrow().time;
+    // Uninstalling the profiler attributes the duration of the last step.
+    prog.set_eval_hooks(None);
+    let profile = profile.borrow();
+
+    assert!(profile.time > before);
+    assert_eq!(profile.fields["<toplevel>"].time, profile.time);
+}
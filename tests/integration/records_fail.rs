@@ -81,3 +81,47 @@ fn missing_field() {
         Err(Error::EvalError(EvalError::MissingFieldDef(..)))
     )
 }
+
+#[test]
+fn record_stdlib() {
+    assert_matches!(
+        eval("(record.set_in [\"a\"] \"a\" {a | Num = 1}).a == 1"),
+        Err(Error::EvalError(EvalError::BlameError(..)))
+    );
+    assert_matches!(
+        eval("(record.update_in [\"a\", \"b\"] (fun x => x) ({a | {b | Num}} & {a.b = 1})).a.b"),
+        Ok(_)
+    );
+    assert_matches!(
+        eval("(record.update_in [\"a\", \"b\"] (fun x => \"a\") ({a | {b | Num}} & {a.b = 1})).a.b == 1"),
+        Err(Error::EvalError(EvalError::BlameError(..)))
+    );
+    assert_matches!(
+        eval("(record.update_in [\"a\", \"b\"] (fun x => x) {a = {}}).a.b"),
+        Err(Error::EvalError(EvalError::FieldMissing(..)))
+    );
+    assert_matches!(
+        eval("record.from_array [{field = \"a\", value = 1}, {field = \"a\", value = 2}]"),
+        Err(Error::EvalError(EvalError::Other(msg, ..))) if msg.starts_with("record_copy_field")
+    );
+    assert_matches!(
+        eval("record.from_array [{field = 1, value = 1}]"),
+        Err(Error::EvalError(EvalError::TypeError(..)))
+    );
+}
+
+#[test]
+fn access_with_default() {
+    assert_matches!(
+        eval("{a = 1}.a.b or 2"),
+        Err(Error::EvalError(EvalError::TypeError(..)))
+    );
+    assert_matches!(
+        eval("({a | Num} & {a = \"a\"}).a or 1"),
+        Err(Error::EvalError(EvalError::BlameError(..)))
+    );
+    assert_matches!(
+        eval("({a.b | Str | optional} & {a.b = 1}).a.b or \"b\""),
+        Err(Error::EvalError(EvalError::BlameError(..)))
+    );
+}
@@ -0,0 +1,54 @@
+use nickel_lang_utilities::TestProgram;
+use std::io::BufReader;
+use std::time::Duration;
+
+fn program(source: &str) -> TestProgram {
+    TestProgram::new_from_source(BufReader::new(source.as_bytes()), "stats")
+        .expect("program shouldn't fail")
+}
+
+#[test]
+fn eval_stats() {
+    let mut prog = program("let f = fun x => x + 1 in {a = f 1, b = array.map f [1, 2, 3]}");
+    prog.eval_full().unwrap();
+    let stats = prog.stats();
+
+    // The main file, plus the modules of the standard library.
+    assert!(stats.files_loaded > 1);
+    assert!(stats.terms_transformed >= 1);
+    assert!(stats.thunks_created > 0);
+    assert!(stats.peak_stack_size > 0);
+    assert!(stats.eval > Duration::ZERO);
+    assert!(stats.stdlib > Duration::ZERO);
+    assert_eq!(
+        stats.total(),
+        stats.parse + stats.typecheck + stats.transform + stats.stdlib + stats.eval
+    );
+}
+
+#[test]
+fn typecheck_stats() {
+    let mut prog = program("{a : Num = 1}");
+    prog.typecheck().unwrap();
+    let stats = prog.stats();
+
+    assert!(stats.files_loaded > 1);
+    assert!(stats.typecheck > Duration::ZERO);
+    assert_eq!(stats.eval, Duration::ZERO);
+    assert_eq!(stats.thunks_created, 0);
+}
+
+#[test]
+fn cached_phases() {
+    let mut prog = program("1 + 1");
+    prog.eval().unwrap();
+    let first = prog.stats();
+    prog.eval().unwrap();
+    let second = prog.stats();
+
+    // Sources are only parsed and transformed once.
+    assert_eq!(first.files_loaded, second.files_loaded);
+    assert_eq!(first.terms_transformed, second.terms_transformed);
+    assert_eq!(first.parse, second.parse);
+    assert!(second.eval > first.eval);
+}
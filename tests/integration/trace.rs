@@ -0,0 +1,44 @@
+use nickel_lang::eval::trace::TraceEvent;
+use nickel_lang::term::{number::Number, Term};
+use nickel_lang_utilities::TestProgram;
+use std::cell::RefCell;
+use std::io::BufReader;
+use std::rc::Rc;
+
+fn eval_traced(source: &str) -> (Result<Term, nickel_lang::error::Error>, Vec<TraceEvent>) {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let mut prog = TestProgram::new_from_source(BufReader::new(source.as_bytes()), "trace")
+        .expect("program shouldn't fail");
+    let sink_events = events.clone();
+    prog.set_trace_sink(Box::new(move |event| sink_events.borrow_mut().push(event)));
+
+    let result = prog.eval_full().map(Term::from);
+    let events = events.borrow().clone();
+    (result, events)
+}
+
+#[test]
+fn trace_returns_value() {
+    let (result, events) = eval_traced("builtin.trace \"hello\" (1 + 1)");
+    assert_eq!(result, Ok(Term::Num(Number::from(2))));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].message, "hello");
+    assert_eq!(events[0].location.as_deref(), Some("trace:1:15"));
+}
+
+#[test]
+fn trace_is_lazy() {
+    let (result, events) =
+        eval_traced("let x = builtin.trace \"unused\" 1 in builtin.trace \"used\" 2");
+    assert_eq!(result, Ok(Term::Num(Number::from(2))));
+    let messages: Vec<_> = events.into_iter().map(|event| event.message).collect();
+    assert_eq!(messages, vec![String::from("used")]);
+}
+
+#[test]
+fn trace_typechecks() {
+    let (result, events) =
+        eval_traced("(builtin.trace \"typed\" 1 + builtin.trace \"message\" 2) : Num");
+    assert_eq!(result, Ok(Term::Num(Number::from(3))));
+    assert_eq!(events.len(), 2);
+}
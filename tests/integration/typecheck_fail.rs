@@ -1,8 +1,9 @@
 use assert_matches::assert_matches;
 use codespan::Files;
 use nickel_lang::cache::resolvers::DummyResolver;
-use nickel_lang::error::TypecheckError;
+use nickel_lang::error::{TypeOrigin, TypecheckError};
 use nickel_lang::parser::{grammar, lexer};
+use nickel_lang::position::TermPos;
 use nickel_lang::term::RichTerm;
 use nickel_lang::types::{TypeF, Types};
 use nickel_lang::{typecheck, typecheck::Context};
@@ -105,6 +106,34 @@ fn enum_complex() {
     );
 }
 
+#[test]
+fn enum_variants() {
+    assert_typecheck_fails!("`Ok \"a\" : [| `Ok Num |]");
+    assert_typecheck_fails!("(match { `Ok x => x, `Err e => 0 }) : [| `Ok Num |] -> Num");
+    assert_typecheck_fails!(
+        "(match { `Ok x => x + 1, `Err e => e }) : [| `Ok Num, `Err Str |] -> Num"
+    );
+    assert_matches!(
+        type_check_expr("`Ok : [| `Ok Num |]"),
+        Err(TypecheckError::EnumRowArgMismatch(..))
+    );
+    assert_matches!(
+        type_check_expr("`Ok 1 : [| `Ok |]"),
+        Err(TypecheckError::EnumRowArgMismatch(..))
+    );
+}
+
+#[test]
+fn match_patterns() {
+    assert_typecheck_fails!("match { 1 => \"one\", _ => 2 } 1 : Str");
+    assert_typecheck_fails!("match { \"a\" => 1, _ => 0 } 1 : Num");
+    assert_typecheck_fails!("match { x if x => 1, _ => 0 } 1 : Num");
+    assert_typecheck_fails!("(match { `bla => 1, `ble if true => 2 }) : [| `bla |] -> Num");
+    assert_typecheck_fails!("match { {a, ..} => a + 1, _ => 0 } {b = 1} : Num");
+    assert_typecheck_fails!("match { {a} => a + 1, _ => 0 } {a = \"a\"} : Num");
+    assert_typecheck_fails!("match { [x, y] => x + y, _ => 0 } [1, \"a\"] : Num");
+}
+
 #[test]
 fn static_record_simple() {
     assert_typecheck_fails!("{bla = true} : {bla : Num}");
@@ -131,6 +160,15 @@ fn dynamic_record_simple() {
     );
 }
 
+#[test]
+fn access_with_default() {
+    assert_typecheck_fails!("({a = 1}.a or \"a\") : Num");
+    assert_typecheck_fails!("({a = {b = 1}}.a.b or 0) : Str");
+    assert_typecheck_fails!("({a = 1}.a.b or 0) : Num");
+    assert_typecheck_fails!("(let r = {a = \"a\"} in r.a or 0) : Num");
+    assert_typecheck_fails!("let r : {_ : Str} = {a = \"a\"} in (r.b or 0 : Num)");
+}
+
 #[test]
 fn simple_array() {
     assert_typecheck_fails!("[1, 2, false] : Array Num");
@@ -255,6 +293,25 @@ fn recursive_let() {
     );
 }
 
+#[test]
+fn let_block() {
+    assert_matches!(
+        type_check_expr(
+            "let rec f : Num -> Num = fun x => g x, g : Str -> Num = fun x => f x in null"
+        ),
+        Err(TypecheckError::TypeMismatch(..))
+    );
+    assert_matches!(
+        type_check_expr("(let a = 1, b = \"a\" in a + b) : Num"),
+        Err(TypecheckError::TypeMismatch(..))
+    );
+    // The bindings of a non-recursive block don't see each other
+    assert_matches!(
+        type_check_expr("(let a = 1, b = a in b) : Num"),
+        Err(TypecheckError::UnboundIdentifier(..))
+    );
+}
+
 #[test]
 fn fails_only_with_wildcard() {
     // Without a wildcard annotation, this type checks
@@ -289,6 +346,7 @@ g"#
         Err(TypecheckError::TypeMismatch(
             Types(TypeF::Arrow(_, _)),
             Types(TypeF::Dyn),
+            _,
             _
         ))
     );
@@ -306,7 +364,154 @@ fn locally_different_flat_types() {
         Err(TypecheckError::TypeMismatch(
             Types(TypeF::Flat(..)),
             Types(TypeF::Flat(..)),
+            _,
             _
         ))
     );
 }
+
+#[test]
+fn let_polymorphism() {
+    // Function parameters are not generalized
+    assert_matches!(
+        type_check_expr("(let f = fun id => if id true then id 5 else 0 in f (fun x => x)) : Num"),
+        Err(TypecheckError::TypeMismatch(..))
+    );
+    // Applications are not generalized
+    assert_matches!(
+        type_check_expr(
+            "(let id = (fun f => f) (fun x => x) in if id true then id 5 else 0) : Num"
+        ),
+        Err(TypecheckError::ArrowTypeMismatch(..))
+    );
+    // Variables of the environment are not generalized
+    assert_matches!(
+        type_check_expr("(fun x => let f = fun y => x in if f 0 then f 0 + 1 else 0) : _"),
+        Err(TypecheckError::ArrowTypeMismatch(..))
+    );
+    // Binding a monomorphic variable doesn't generalize its type
+    assert_matches!(
+        type_check_expr("(fun h => let g = h in if g true then g 1 else 0) : (Num -> Num) -> Num"),
+        Err(TypecheckError::ArrowTypeMismatch(..))
+    );
+    // A recursive block is generalized as a whole, and a non-generalizable binding prevents the
+    // generalization of the other ones
+    assert_matches!(
+        type_check_expr("(let rec id = fun x => x, k = 1 in if id true then id k else 0) : Num"),
+        Err(TypecheckError::ArrowTypeMismatch(..))
+    );
+}
+
+#[test]
+fn record_to_dict() {
+    // The fields must all have the type of the dictionary elements
+    assert_matches!(
+        type_check_expr("(let r = {a = 1, b = \"a\"} in (r : {_: Num})) : _"),
+        Err(TypecheckError::RowMismatch(..))
+    );
+    // Open records can't be used as dictionaries
+    assert_typecheck_fails!("(fun r => (r : {_: Num})) : {a: Num ; Dyn} -> {_: Num}");
+    assert_typecheck_fails!("(fun r => (r : {_: Num})) : forall t. {a: Num ; t} -> {_: Num}");
+    // A dictionary can't be used as a record
+    assert_typecheck_fails!("(fun d => (d : {a: Num})) : {_: Num} -> {a: Num}");
+}
+
+#[test]
+fn record_contracts_as_types() {
+    assert_matches!(
+        type_check_expr(
+            "let Server = {port | Num} in
+             (let server : Server = {port = \"80\"} in server.port) : Num"
+        ),
+        Err(TypecheckError::RowMismatch(..))
+    );
+    assert_matches!(
+        type_check_expr(
+            "let Server = {port | Num} in
+             (let server : Server = {port = 80} in server.host) : Dyn"
+        ),
+        Err(TypecheckError::MissingRow(..))
+    );
+    assert_matches!(
+        type_check_expr("({port = 80, host = \"localhost\"} : {port | Num}) : _"),
+        Err(TypecheckError::ExtraRow(..))
+    );
+    // Contracts with a default value are still opaque types
+    assert_matches!(
+        type_check_expr("({port = 80} : {port | default = 80 | Num}) : _"),
+        Err(TypecheckError::TypeMismatch(..))
+    );
+}
+
+#[test]
+fn type_aliases() {
+    assert_matches!(
+        type_check_expr(
+            "type Service a = {name: Str, tags: Array a} in
+             (let service : Service Num = {name = \"web\", tags = [\"http\"]} in service) : _"
+        ),
+        Err(TypecheckError::RowMismatch(..))
+    );
+    assert_typecheck_fails!("type Id a = a in (1 : Id Str)");
+    // The bound variable `b` of the definition must not capture the argument `b`.
+    assert_typecheck_fails!("type F a = forall b. a -> b in ((fun x => x) : forall b. F b)");
+}
+
+#[test]
+fn type_mismatch_origins() {
+    // The type of `x` is fixed to `Str` by its first use
+    let err = type_check_expr("(fun x => let y = x ++ \"a\" in x + 1) : _").unwrap_err();
+    assert_matches!(
+        &err,
+        TypecheckError::TypeMismatch(Types(TypeF::Num), Types(TypeF::Str), origins, _)
+            if matches!(
+                origins.as_slice(),
+                [TypeOrigin { ty: Types(TypeF::Str), span }] if span.start.to_usize() == 18
+            )
+    );
+
+    // The domain of `f` is fixed to `Bool` by the argument of its first application
+    let err = type_check_expr("(fun f => let a = f true in f 0) : _").unwrap_err();
+    assert_matches!(
+        &err,
+        TypecheckError::TypeMismatch(Types(TypeF::Bool), Types(TypeF::Num), origins, _)
+            if matches!(
+                origins.as_slice(),
+                [TypeOrigin { ty: Types(TypeF::Bool), span }] if span.start.to_usize() == 20
+            )
+    );
+
+    // The type of the field is fixed by the erroring expression `r.a` itself, which is thus not
+    // reported as an origin, while the origin of the first use differs from the error span
+    let err = type_check_expr("(let r = {a = \"s\"} in r.a + 1) : Num").unwrap_err();
+    assert_matches!(&err, TypecheckError::TypeMismatch(_, _, origins, _) if origins.is_empty());
+    let err = type_check_expr("(fun r => let s = r.a ++ \"b\" in r.a + 1) : _").unwrap_err();
+    assert_matches!(
+        &err,
+        TypecheckError::TypeMismatch(_, _, origins, TermPos::Original(pos))
+            if matches!(
+                origins.as_slice(),
+                [TypeOrigin { span, .. }] if span.start.to_usize() == 18 && pos.start.to_usize() == 32
+            )
+    );
+
+    // Types coming from annotations have no origin
+    let err = type_check_expr("(1 : Str) : _").unwrap_err();
+    assert_matches!(&err, TypecheckError::TypeMismatch(_, _, origins, _) if origins.is_empty());
+}
+
+#[test]
+fn type_variable_names_in_errors() {
+    // Type variables and row type variables are numbered separately: they must not be mixed up
+    assert_matches!(
+        type_check_expr(
+            "(let f : forall a r. {foo: a ; r} -> {foo: a ; r} = fun x => {foo = 1} in 1) : Num"
+        ),
+        Err(TypecheckError::RowMismatch(_, _, _, err, _, _))
+            if matches!(
+                err.as_ref(),
+                TypecheckError::TypeMismatch(Types(TypeF::Var(var)), _, _, _)
+                    if var.label() == "a"
+            )
+    );
+}